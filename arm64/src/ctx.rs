@@ -20,11 +20,16 @@ impl EncodeContext {
 
         let mut local_symbols = HashMap::new();
         let mut new_relocations = Vec::new();
+        let mut table_entries = Vec::new();
         for ins in code {
             match ins {
                 Ins::LocalSymbol(id) => {
                     local_symbols.insert(*id, self.raw.len());
                 },
+                Ins::JumpTableEntry(table, target) => {
+                    table_entries.push((*table, *target, self.raw.len()));
+                    self.raw.extend(0u32.to_le_bytes());
+                },
                 _ => {
                     let res = ins.encode();
                     self.raw.extend(res.get().to_le_bytes());
@@ -50,6 +55,13 @@ impl EncodeContext {
                         curr |= val << 5;
                         self.raw[reloc.1..reloc.1 + 4].copy_from_slice(&curr.to_le_bytes());
                     },
+                    InsRelocMode::Adr21 => {
+                        let mut curr = u32::from_le_bytes(self.raw[reloc.1..reloc.1 + 4].try_into().unwrap());
+                        let val = (addr as i32 - reloc.1 as i32) as u32;
+                        curr |= (val & 3) << 29;
+                        curr |= ((val >> 2) & ((1<<19) - 1)) << 5;
+                        self.raw[reloc.1..reloc.1 + 4].copy_from_slice(&curr.to_le_bytes());
+                    },
                     InsRelocMode::Page21 => unreachable!(),
                     InsRelocMode::PageOff12 => unreachable!(),
                 }
@@ -58,6 +70,12 @@ impl EncodeContext {
             }
         }
 
+        for (table, target, offset) in table_entries {
+            let table = *local_symbols.get(&table).expect("Local symbol not definied");
+            let target = *local_symbols.get(&target).expect("Local symbol not definied");
+            self.raw[offset..offset + 4].copy_from_slice(&((target as i32 - table as i32) as u32).to_le_bytes());
+        }
+
         (addr, self.raw.len() - addr)
     }
 
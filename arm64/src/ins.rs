@@ -57,7 +57,8 @@ pub enum InsRelocMode {
     Branch26,
     Branch19Shift5,
     Page21,
-    PageOff12
+    PageOff12,
+    Adr21
 }
 
 pub struct InsReloc {
@@ -204,6 +205,12 @@ pub enum Ins {
         dest: Reg,
         val: u32, shift: u32
     },
+    /// Moves a 16 bit immediate into the bits of the register at the shift, keeping the rest
+    MovK {
+        size: SizeFlag,
+        dest: Reg,
+        val: u32, shift: u32
+    },
     Stur {
         size: SizeFlag,
         src: Reg,
@@ -289,7 +296,19 @@ pub enum Ins {
         cond: Condition,
         true_reg: Reg,
         inc_reg: Reg
-    }
+    },
+    AdrLocalSymbol(LocalSymbolID, Reg),
+    /// Loads the sign extended word at base + (index << 2)
+    LdrswScaled {
+        dest: Reg,
+        base: Reg,
+        index: Reg
+    },
+    Br(Reg),
+    /// Supervisor call, with the immediate available to the handler
+    Svc(u16),
+    /// Not an instruction, but the 32 bit offset of the second symbol from the first, as placed in a jump table starting at the first
+    JumpTableEntry(LocalSymbolID, LocalSymbolID)
 }
 
 fn orr(size: SizeFlag, shift_mode: ShiftMode, src1: Reg, src2: Reg, shift: u32, dest: Reg) -> Res {
@@ -297,7 +316,7 @@ fn orr(size: SizeFlag, shift_mode: ShiftMode, src1: Reg, src2: Reg, shift: u32,
 }
 
 fn dp(size: SizeFlag, mode: IndexMode, src1: Reg, src2: Reg, base: Reg, offset: i32, load: u32) -> Res {
-    Res::val(((size as u32) << 31) | (0b010100 << 25) | ((mode as u32) << 23) | (load << 22) | (((offset / size.divisor()) & 1023) << 15) as u32 | (src2.u32() << 10) | (base.u32() << 5) | (src1.u32() << 0))
+    Res::val(((size as u32) << 31) | (0b010100 << 25) | ((mode as u32) << 23) | (load << 22) | (((offset / size.divisor()) & 127) << 15) as u32 | (src2.u32() << 10) | (base.u32() << 5) | (src1.u32() << 0))
 }
 
 fn arith_imm(opcode: u32, size: SizeFlag, src: Reg, dest: Reg, val: u32, shift: ImmShift) -> Res {
@@ -312,6 +331,7 @@ impl Ins {
     pub fn encode(&self) -> InsEncodeResult {
         match *self {
             Ins::LocalSymbol(_) => unreachable!(),
+            Ins::JumpTableEntry(_, _) => unreachable!(),
 
             // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ORR--immediate---Bitwise-OR--immediate--?lang=en
             Ins::OrrImm { size, shift_mode, src1, src2, dest, shift } => orr(size, shift_mode, src1, src2, shift, dest),
@@ -348,6 +368,9 @@ impl Ins {
             // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/MOVZ--Move-wide-with-zero-?lang=en
             Ins::MovZ { size, dest, val, shift } => Res::val(((size as u32) << 31) | (0b10100101 << 23) | ((shift / 16) << 21) | (val << 5) | (dest.u32() << 0)),
 
+            // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/MOVK--Move-wide-with-keep-?lang=en
+            Ins::MovK { size, dest, val, shift } => Res::val(((size as u32) << 31) | (0b11100101 << 23) | ((shift / 16) << 21) | (val << 5) | dest.u32()),
+
             // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/STUR--Store-Register--unscaled--?lang=en
             Ins::Stur { size, src, base, offset } => ldstr_ur(0b10111000000, size, src, base, offset),
 
@@ -395,6 +418,18 @@ impl Ins {
 
             // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/CSINC--Conditional-Select-Increment-?lang=en
             Ins::CSInc { size, dest, cond, true_reg, inc_reg } => Res::val(((size as u32) << 31) | (0b0011010100 << 21) | (inc_reg.u32() << 16) | ((cond as u32) << 12) | (0b01 << 10) | ((true_reg.u32()) << 5) | dest.u32()),
+
+            // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/ADR--Form-PC-relative-address-?lang=en
+            Ins::AdrLocalSymbol(symbol, dest) => Res::val((0b10000 << 24) | dest.u32()).reloc(InsRelocMode::Adr21, RelocationType::LocalFunctionSymbol(symbol)),
+
+            // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/LDRSW--register---Load-Register-Signed-Word--register--?lang=en
+            Ins::LdrswScaled { dest, base, index } => Res::val((0b10111000101 << 21) | (index.u32() << 16) | (0b011 << 13) | (1 << 12) | (0b10 << 10) | (base.u32() << 5) | dest.u32()),
+
+            // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/BR--Branch-to-Register-?lang=en
+            Ins::Br(reg) => Res::val((0b1101011000011111000000 << 10) | (reg.u32() << 5)),
+
+            // https://developer.arm.com/documentation/ddi0596/2021-12/Base-Instructions/SVC--Supervisor-Call-?lang=en
+            Ins::Svc(imm) => Res::val((0b11010100000 << 21) | ((imm as u32) << 5) | 0b00001),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use crate::{LocalIndex, ValueType};
+
+/// The architecture an inline assembly block is written for. A block can only be translated by the backend for its architecture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AsmArch {
+    X8664,
+    Arm64
+}
+
+impl AsmArch {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AsmArch::X8664 => "x86_64",
+            AsmArch::Arm64 => "arm64"
+        }
+    }
+}
+
+/// An operand of an assembly instruction. Registers are kept as they were written, and are only given meaning by the
+/// backend for the architecture of the block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmOperand {
+    Reg(String),
+    Imm(i64),
+    /// `[base + index * scale + offset]`, where the index is optional
+    Mem(String, Option<(String, u8)>, i64)
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmIns {
+    mnemonic: String,
+    operands: Vec<AsmOperand>
+}
+
+impl AsmIns {
+    pub fn new<T: Into<String>>(mnemonic: T, operands: Vec<AsmOperand>) -> AsmIns {
+        AsmIns {
+            mnemonic: mnemonic.into(),
+            operands
+        }
+    }
+
+    pub fn mnemonic(&self) -> &str {
+        &self.mnemonic
+    }
+
+    pub fn operands(&self) -> &Vec<AsmOperand> {
+        &self.operands
+    }
+
+    pub fn operand_count(&self) -> usize {
+        self.operands.len()
+    }
+}
+
+/// Binds a register to a local, which must be a value of the given type
+#[derive(Debug, Clone)]
+pub struct AsmBinding {
+    reg: String,
+    local: LocalIndex,
+    value_type: ValueType
+}
+
+impl AsmBinding {
+    pub fn new<T: Into<String>>(reg: T, local: LocalIndex, value_type: ValueType) -> AsmBinding {
+        AsmBinding {
+            reg: reg.into(),
+            local, value_type
+        }
+    }
+
+    pub fn reg(&self) -> &str {
+        &self.reg
+    }
+
+    pub fn local(&self) -> LocalIndex {
+        self.local
+    }
+
+    pub fn value_type(&self) -> &ValueType {
+        &self.value_type
+    }
+}
+
+/// A block of machine code for a single architecture. Each input local is loaded into its register before the code runs,
+/// and each output register is stored to its local after it has finished.
+#[derive(Debug, Clone)]
+pub struct AsmBlock {
+    arch: AsmArch,
+    inputs: Vec<AsmBinding>,
+    outputs: Vec<AsmBinding>,
+    code: Vec<AsmIns>
+}
+
+impl AsmBlock {
+    pub fn new(arch: AsmArch, inputs: Vec<AsmBinding>, outputs: Vec<AsmBinding>, code: Vec<AsmIns>) -> AsmBlock {
+        AsmBlock {
+            arch, inputs, outputs, code
+        }
+    }
+
+    pub fn arch(&self) -> AsmArch {
+        self.arch
+    }
+
+    pub fn inputs(&self) -> &Vec<AsmBinding> {
+        &self.inputs
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn outputs(&self) -> &Vec<AsmBinding> {
+        &self.outputs
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn code(&self) -> &Vec<AsmIns> {
+        &self.code
+    }
+
+    pub fn code_count(&self) -> usize {
+        self.code.len()
+    }
+}
+
+impl std::fmt::Display for AsmOperand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmOperand::Reg(reg) => f.write_str(reg),
+            AsmOperand::Imm(imm) => f.write_fmt(format_args!("{}", imm)),
+            AsmOperand::Mem(base, index, offset) => {
+                f.write_fmt(format_args!("[{}", base))?;
+                if let Some((index, scale)) = index {
+                    f.write_fmt(format_args!(" + {} * {}", index, scale))?;
+                }
+                match offset {
+                    0 => {},
+                    offset if *offset < 0 => f.write_fmt(format_args!(" - {}", -(*offset as i128)))?,
+                    offset => f.write_fmt(format_args!(" + {}", offset))?
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AsmIns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.mnemonic)?;
+        for (o, operand) in self.operands.iter().enumerate() {
+            f.write_str(if o == 0 { " " } else { ", " })?;
+            operand.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
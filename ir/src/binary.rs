@@ -0,0 +1,979 @@
+use crate::*;
+
+/// The first bytes of every IR object
+const MAGIC: [u8; 4] = *b"\x7fNLO";
+
+/// Changed whenever the encoding changes, objects written with any other version are rejected rather than misread
+pub const BINARY_VERSION: u16 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum BinaryError {
+    NotAnObject,
+    UnsupportedVersion(u16),
+    UnexpectedEnd,
+    InvalidTag(u8),
+    InvalidString,
+    InvalidIndex,
+    /// A type in the object has the same name as one in the unit, but is declared differently
+    TypeMismatch(String),
+    /// A function in the object has the same name as one in the unit, but a different signature
+    SignatureMismatch(String),
+    /// A function is defined, rather than extern, in both the object and the unit
+    DuplicateFunction(String)
+}
+
+impl std::fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryError::NotAnObject => f.write_str("Not an IR object"),
+            BinaryError::UnsupportedVersion(version) => f.write_fmt(format_args!("Object is version {}, but only version {} is supported", version, BINARY_VERSION)),
+            BinaryError::UnexpectedEnd => f.write_str("Object ends unexpectedly"),
+            BinaryError::InvalidTag(tag) => f.write_fmt(format_args!("Invalid tag {}", tag)),
+            BinaryError::InvalidString => f.write_str("String is not valid UTF-8"),
+            BinaryError::InvalidIndex => f.write_str("Index is out of range"),
+            BinaryError::TypeMismatch(name) => f.write_fmt(format_args!("Type {} is declared differently in each unit", name)),
+            BinaryError::SignatureMismatch(name) => f.write_fmt(format_args!("Function {} has a different signature in each unit", name)),
+            BinaryError::DuplicateFunction(name) => f.write_fmt(format_args!("Function {} is defined in both units", name)),
+        }
+    }
+}
+
+struct Writer<'a> {
+    bytes: Vec<u8>,
+    unit: &'a TranslationUnit
+}
+
+impl Writer<'_> {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Unsigned LEB128, so that the small numbers which make up most of a unit take a single byte
+    fn uint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.uint(value as u64);
+    }
+
+    fn string(&mut self, string: &str) {
+        self.usize(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    fn value_type(&mut self, vt: &ValueType) {
+        match vt {
+            ValueType::U8 => self.u8(0),
+            ValueType::I8 => self.u8(1),
+            ValueType::U16 => self.u8(2),
+            ValueType::I16 => self.u8(3),
+            ValueType::U32 => self.u8(4),
+            ValueType::I32 => self.u8(5),
+            ValueType::U64 => self.u8(6),
+            ValueType::I64 => self.u8(7),
+            ValueType::UPtr => self.u8(8),
+            ValueType::IPtr => self.u8(9),
+            ValueType::Bool => self.u8(10),
+            ValueType::Ref(st) => {
+                self.u8(11);
+                self.storable_type(st);
+            },
+            ValueType::Index(st) => {
+                self.u8(12);
+                self.storable_type(st);
+            },
+        }
+    }
+
+    fn value_types(&mut self, types: &[ValueType]) {
+        self.usize(types.len());
+        for vt in types {
+            self.value_type(vt);
+        }
+    }
+
+    fn storable_type(&mut self, st: &StorableType) {
+        match st {
+            StorableType::Compound(ct) => {
+                self.u8(0);
+                let idx = self.unit.compound_types().iter().position(|other| other == ct).expect("Type is not in the unit");
+                self.usize(idx);
+            },
+            StorableType::Value(vt) => {
+                self.u8(1);
+                self.value_type(vt);
+            },
+            StorableType::Slice(st) => {
+                self.u8(2);
+                self.storable_type(st);
+            },
+            StorableType::SliceData(st) => {
+                self.u8(3);
+                self.storable_type(st);
+            },
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::U8(v) => { self.u8(0); self.uint(*v as u64); },
+            Value::I8(v) => { self.u8(1); self.uint(*v as u64); },
+            Value::U16(v) => { self.u8(2); self.uint(*v as u64); },
+            Value::I16(v) => { self.u8(3); self.uint(*v as u64); },
+            Value::U32(v) => { self.u8(4); self.uint(*v as u64); },
+            Value::I32(v) => { self.u8(5); self.uint(*v as u64); },
+            Value::U64(v) => { self.u8(6); self.uint(*v); },
+            Value::I64(v) => { self.u8(7); self.uint(*v as u64); },
+            Value::UPtr(v) => { self.u8(8); self.uint(*v as u64); },
+            Value::IPtr(v) => { self.u8(9); self.uint(*v as u64); },
+            Value::Bool(v) => { self.u8(10); self.u8(*v as u8); },
+            Value::Ref(global) => { self.u8(11); self.usize(global.idx()); },
+        }
+    }
+
+    fn storable_value(&mut self, value: &StorableValue) {
+        match value {
+            StorableValue::Compound(CompoundValue::Struct(s)) => {
+                self.u8(0);
+                self.usize(s.props().len());
+                for prop in s.props() {
+                    self.storable_value(prop.value());
+                }
+            },
+            StorableValue::Value(value) => {
+                self.u8(1);
+                self.value(value);
+            },
+            StorableValue::Slice(global, start, length) => {
+                self.u8(2);
+                self.usize(global.idx());
+                self.usize(*start);
+                self.usize(*length);
+            },
+            StorableValue::SliceData(values) => {
+                self.u8(3);
+                self.usize(values.len());
+                for value in values {
+                    self.storable_value(value);
+                }
+            },
+        }
+    }
+
+    fn path(&mut self, path: &ValuePath) {
+        match path.origin() {
+            ValuePathOrigin::Local(idx, st) => {
+                self.u8(0);
+                self.usize(idx.idx());
+                self.storable_type(st);
+            },
+            ValuePathOrigin::Global(idx, st) => {
+                self.u8(1);
+                self.usize(idx.idx());
+                self.storable_type(st);
+            },
+            ValuePathOrigin::Deref(st) => {
+                self.u8(2);
+                self.storable_type(st);
+            },
+        }
+
+        self.usize(path.component_count());
+        for component in path.components() {
+            match component {
+                ValuePathComponent::Slice(st) => {
+                    self.u8(0);
+                    self.storable_type(st);
+                },
+                ValuePathComponent::Property(idx, ct, st) => {
+                    self.u8(1);
+                    self.usize(idx.idx());
+                    self.storable_type(&StorableType::Compound(ct.clone()));
+                    self.storable_type(st);
+                },
+                ValuePathComponent::Length => self.u8(2),
+            }
+        }
+    }
+
+    fn block(&mut self, code: &[Ins]) {
+        self.usize(code.len());
+        for ins in code {
+            self.ins(ins);
+        }
+    }
+
+    fn asm_operand(&mut self, operand: &AsmOperand) {
+        match operand {
+            AsmOperand::Reg(reg) => {
+                self.u8(0);
+                self.string(reg);
+            },
+            AsmOperand::Imm(imm) => {
+                self.u8(1);
+                self.uint(*imm as u64);
+            },
+            AsmOperand::Mem(base, index, offset) => {
+                self.u8(2);
+                self.string(base);
+                match index {
+                    Some((index, scale)) => {
+                        self.u8(1);
+                        self.string(index);
+                        self.u8(*scale);
+                    },
+                    None => self.u8(0)
+                }
+                self.uint(*offset as u64);
+            },
+        }
+    }
+
+    fn asm_bindings(&mut self, bindings: &[AsmBinding]) {
+        self.usize(bindings.len());
+        for binding in bindings {
+            self.string(binding.reg());
+            self.usize(binding.local().idx());
+            self.value_type(binding.value_type());
+        }
+    }
+
+    fn ins(&mut self, ins: &Ins) {
+        match ins {
+            Ins::PushPath(path, vt) => { self.u8(0); self.path(path); self.value_type(vt); },
+            Ins::Push(vt) => { self.u8(1); self.value_type(vt); },
+            Ins::Pop(vt) => { self.u8(2); self.value_type(vt); },
+            Ins::PopLocal(idx, vt) => { self.u8(3); self.usize(idx.idx()); self.value_type(vt); },
+            Ins::Index(st) => { self.u8(4); self.storable_type(st); },
+            Ins::New(st) => { self.u8(5); self.storable_type(st); },
+            Ins::NewSlice(st) => { self.u8(6); self.storable_type(st); },
+            Ins::Free(st) => { self.u8(7); self.storable_type(st); },
+            Ins::FreeSlice(st) => { self.u8(8); self.storable_type(st); },
+            Ins::Convert(from, to) => { self.u8(9); self.value_type(from); self.value_type(to); },
+            Ins::Call(idx) => { self.u8(10); self.usize(idx.idx()); },
+            Ins::Ret => self.u8(11),
+            Ins::Inc(vt, value) => { self.u8(12); self.value_type(vt); self.uint(*value); },
+            Ins::Dec(vt, value) => { self.u8(13); self.value_type(vt); self.uint(*value); },
+            Ins::Add(vt) => { self.u8(14); self.value_type(vt); },
+            Ins::Mul(vt) => { self.u8(15); self.value_type(vt); },
+            Ins::Div(vt) => { self.u8(16); self.value_type(vt); },
+            Ins::Sub(vt) => { self.u8(17); self.value_type(vt); },
+            Ins::Neg(vt) => { self.u8(18); self.value_type(vt); },
+            Ins::Eq(vt) => { self.u8(19); self.value_type(vt); },
+            Ins::Ne(vt) => { self.u8(20); self.value_type(vt); },
+            Ins::Lt(vt) => { self.u8(21); self.value_type(vt); },
+            Ins::Le(vt) => { self.u8(22); self.value_type(vt); },
+            Ins::Gt(vt) => { self.u8(23); self.value_type(vt); },
+            Ins::Ge(vt) => { self.u8(24); self.value_type(vt); },
+            Ins::BoolAnd => self.u8(25),
+            Ins::BoolOr => self.u8(26),
+            Ins::Loop(code, cond, inc) => {
+                self.u8(27);
+                self.block(code);
+                self.block(cond);
+                self.block(inc);
+            },
+            Ins::If(true_then, cond) => {
+                self.u8(28);
+                self.block(true_then);
+                self.block(cond);
+            },
+            Ins::IfElse(vt, true_then, false_then, cond) => {
+                self.u8(29);
+                match vt {
+                    Some(vt) => {
+                        self.u8(1);
+                        self.value_type(vt);
+                    },
+                    None => self.u8(0)
+                }
+                self.block(true_then);
+                self.block(false_then);
+                self.block(cond);
+            },
+            Ins::Switch(vt, cases, default, value) => {
+                self.u8(30);
+                self.value_type(vt);
+                self.usize(cases.len());
+                for (values, code) in cases {
+                    self.usize(values.len());
+                    for value in values {
+                        self.uint(*value);
+                    }
+                    self.block(code);
+                }
+                self.block(default);
+                self.block(value);
+            },
+            Ins::Asm(block) => {
+                self.u8(31);
+                self.u8(match block.arch() {
+                    AsmArch::X8664 => 0,
+                    AsmArch::Arm64 => 1
+                });
+                self.asm_bindings(block.inputs());
+                self.asm_bindings(block.outputs());
+                self.usize(block.code_count());
+                for ins in block.code() {
+                    self.string(ins.mnemonic());
+                    self.usize(ins.operand_count());
+                    for operand in ins.operands() {
+                        self.asm_operand(operand);
+                    }
+                }
+            },
+            Ins::Break(depth) => { self.u8(32); self.usize(*depth); },
+            Ins::Continue(depth) => { self.u8(33); self.usize(*depth); },
+            Ins::PushLiteral(vt, value) => { self.u8(34); self.value_type(vt); self.uint(*value); },
+            Ins::Drop => self.u8(35),
+        }
+    }
+
+    fn attr(&mut self, attr: &FunctionAttr) {
+        match attr {
+            FunctionAttr::Entry => self.u8(0),
+            FunctionAttr::Alloc => self.u8(1),
+            FunctionAttr::AllocSlice => self.u8(2),
+            FunctionAttr::Free => self.u8(3),
+            FunctionAttr::FreeSlice => self.u8(4),
+            FunctionAttr::ExternLocation(location) => {
+                self.u8(5);
+                self.string(location);
+            },
+            FunctionAttr::Panic => self.u8(6),
+            FunctionAttr::Fallible => self.u8(7),
+            FunctionAttr::Test => self.u8(8),
+            FunctionAttr::Inline => self.u8(9),
+            FunctionAttr::Export => self.u8(10),
+        }
+    }
+
+    fn unit(&mut self) {
+        self.bytes.extend_from_slice(&MAGIC);
+        self.bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+
+        let unit = self.unit;
+        self.usize(unit.compound_types().len());
+        for ct in unit.compound_types() {
+            self.string(ct.name());
+            match ct.content() {
+                CompoundContent::Struct(s) => {
+                    self.u8(match s.repr() {
+                        StructRepr::Default => 0,
+                        StructRepr::C => 1,
+                        StructRepr::Packed => 2
+                    });
+                    self.usize(s.min_align().unwrap_or(0));
+                    self.usize(s.props().len());
+                    for prop in s.props() {
+                        self.string(prop.name());
+                        self.storable_type(prop.prop_type());
+                    }
+                }
+            }
+        }
+
+        self.usize(unit.global_count());
+        for global in unit.globals() {
+            match global.name() {
+                Some(name) => {
+                    self.u8(1);
+                    self.string(name);
+                },
+                None => self.u8(0)
+            }
+            self.u8(global.is_read_only() as u8);
+            self.storable_type(global.global_type());
+            match global.default() {
+                Some(default) => {
+                    self.u8(1);
+                    self.storable_value(default);
+                },
+                None => self.u8(0)
+            }
+        }
+
+        // Every function is declared before any code, so that calls can be resolved while the code is read
+        self.usize(unit.function_count());
+        for func in unit.functions() {
+            self.string(func.name());
+            self.value_types(func.signature().params());
+            self.value_types(func.signature().returns());
+            match func.method_data() {
+                Some(method_data) => {
+                    self.u8(if method_data.is_static() { 2 } else { 1 });
+                    self.storable_type(&StorableType::Compound(method_data.compound_type()));
+                },
+                None => self.u8(0)
+            }
+            self.usize(func.attr_count());
+            for attr in func.attrs() {
+                self.attr(attr);
+            }
+            self.u8(func.is_extern() as u8);
+        }
+
+        for func in unit.functions() {
+            if let Some(code) = func.code_opt() {
+                self.usize(func.local_count());
+                for local in func.locals() {
+                    self.storable_type(local.local_type());
+                }
+                self.block(code);
+            }
+        }
+    }
+}
+
+/// Reads an object into a unit, mapping every index in the object to where it ended up in the unit
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    compound_types: Vec<CompoundTypeRef>,
+    global_offset: usize,
+    functions: Vec<FunctionIndex>
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, BinaryError> {
+        match self.bytes.get(self.offset) {
+            Some(byte) => {
+                self.offset += 1;
+                Ok(*byte)
+            },
+            None => Err(BinaryError::UnexpectedEnd)
+        }
+    }
+
+    fn uint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 { return Err(BinaryError::InvalidTag(byte)); }
+
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 { return Ok(value); }
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize, BinaryError> {
+        Ok(self.uint()? as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, BinaryError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(BinaryError::InvalidTag(tag))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, BinaryError> {
+        let len = self.usize()?;
+        let bytes = match self.bytes.get(self.offset..self.offset.saturating_add(len)) {
+            Some(bytes) => bytes,
+            None => return Err(BinaryError::UnexpectedEnd)
+        };
+        self.offset += len;
+
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(BinaryError::InvalidString)
+        }
+    }
+
+    fn value_type(&mut self) -> Result<ValueType, BinaryError> {
+        Ok(match self.u8()? {
+            0 => ValueType::U8,
+            1 => ValueType::I8,
+            2 => ValueType::U16,
+            3 => ValueType::I16,
+            4 => ValueType::U32,
+            5 => ValueType::I32,
+            6 => ValueType::U64,
+            7 => ValueType::I64,
+            8 => ValueType::UPtr,
+            9 => ValueType::IPtr,
+            10 => ValueType::Bool,
+            11 => ValueType::Ref(Box::new(self.storable_type()?)),
+            12 => ValueType::Index(Box::new(self.storable_type()?)),
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn value_types(&mut self) -> Result<Vec<ValueType>, BinaryError> {
+        let count = self.usize()?;
+        let mut types = Vec::new();
+        for _ in 0..count {
+            types.push(self.value_type()?);
+        }
+
+        Ok(types)
+    }
+
+    fn compound_type(&mut self) -> Result<CompoundTypeRef, BinaryError> {
+        match self.storable_type()? {
+            StorableType::Compound(ct) => Ok(ct),
+            _ => Err(BinaryError::InvalidTag(0))
+        }
+    }
+
+    fn storable_type(&mut self) -> Result<StorableType, BinaryError> {
+        Ok(match self.u8()? {
+            0 => {
+                let idx = self.usize()?;
+                match self.compound_types.get(idx) {
+                    Some(ct) => StorableType::Compound(ct.clone()),
+                    None => return Err(BinaryError::InvalidIndex)
+                }
+            },
+            1 => StorableType::Value(self.value_type()?),
+            2 => StorableType::Slice(Box::new(self.storable_type()?)),
+            3 => StorableType::SliceData(Box::new(self.storable_type()?)),
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn global(&mut self) -> Result<GlobalIndex, BinaryError> {
+        Ok(GlobalIndex::new(self.global_offset + self.usize()?))
+    }
+
+    fn value(&mut self) -> Result<Value, BinaryError> {
+        Ok(match self.u8()? {
+            0 => Value::U8(self.uint()? as u8),
+            1 => Value::I8(self.uint()? as i8),
+            2 => Value::U16(self.uint()? as u16),
+            3 => Value::I16(self.uint()? as i16),
+            4 => Value::U32(self.uint()? as u32),
+            5 => Value::I32(self.uint()? as i32),
+            6 => Value::U64(self.uint()?),
+            7 => Value::I64(self.uint()? as i64),
+            8 => Value::UPtr(self.uint()? as usize),
+            9 => Value::IPtr(self.uint()? as isize),
+            10 => Value::Bool(self.bool()?),
+            11 => Value::Ref(self.global()?),
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn storable_value(&mut self) -> Result<StorableValue, BinaryError> {
+        Ok(match self.u8()? {
+            0 => {
+                let count = self.usize()?;
+                let mut props = Vec::new();
+                for _ in 0..count {
+                    props.push(StructPropertyValue::new(self.storable_value()?));
+                }
+                StorableValue::Compound(CompoundValue::Struct(StructValue::new(props)))
+            },
+            1 => StorableValue::Value(self.value()?),
+            2 => StorableValue::Slice(self.global()?, self.usize()?, self.usize()?),
+            3 => {
+                let count = self.usize()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.storable_value()?);
+                }
+                StorableValue::SliceData(values)
+            },
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn path(&mut self) -> Result<ValuePath, BinaryError> {
+        let origin = match self.u8()? {
+            0 => ValuePathOrigin::Local(LocalIndex::new(self.usize()?), self.storable_type()?),
+            1 => ValuePathOrigin::Global(self.global()?, self.storable_type()?),
+            2 => ValuePathOrigin::Deref(self.storable_type()?),
+            tag => return Err(BinaryError::InvalidTag(tag))
+        };
+
+        let count = self.usize()?;
+        let mut components = Vec::new();
+        for _ in 0..count {
+            components.push(match self.u8()? {
+                0 => ValuePathComponent::Slice(self.storable_type()?),
+                1 => ValuePathComponent::Property(PropertyIndex::new(self.usize()?), self.compound_type()?, self.storable_type()?),
+                2 => ValuePathComponent::Length,
+                tag => return Err(BinaryError::InvalidTag(tag))
+            });
+        }
+
+        Ok(ValuePath::new(origin, components))
+    }
+
+    fn block(&mut self) -> Result<Vec<Ins>, BinaryError> {
+        let count = self.usize()?;
+        let mut code = Vec::new();
+        for _ in 0..count {
+            code.push(self.ins()?);
+        }
+
+        Ok(code)
+    }
+
+    fn asm_operand(&mut self) -> Result<AsmOperand, BinaryError> {
+        Ok(match self.u8()? {
+            0 => AsmOperand::Reg(self.string()?),
+            1 => AsmOperand::Imm(self.uint()? as i64),
+            2 => {
+                let base = self.string()?;
+                let index = match self.bool()? {
+                    true => Some((self.string()?, self.u8()?)),
+                    false => None
+                };
+                AsmOperand::Mem(base, index, self.uint()? as i64)
+            },
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn asm_bindings(&mut self) -> Result<Vec<AsmBinding>, BinaryError> {
+        let count = self.usize()?;
+        let mut bindings = Vec::new();
+        for _ in 0..count {
+            bindings.push(AsmBinding::new(self.string()?, LocalIndex::new(self.usize()?), self.value_type()?));
+        }
+
+        Ok(bindings)
+    }
+
+    fn ins(&mut self) -> Result<Ins, BinaryError> {
+        Ok(match self.u8()? {
+            0 => Ins::PushPath(self.path()?, self.value_type()?),
+            1 => Ins::Push(self.value_type()?),
+            2 => Ins::Pop(self.value_type()?),
+            3 => Ins::PopLocal(LocalIndex::new(self.usize()?), self.value_type()?),
+            4 => Ins::Index(self.storable_type()?),
+            5 => Ins::New(self.storable_type()?),
+            6 => Ins::NewSlice(self.storable_type()?),
+            7 => Ins::Free(self.storable_type()?),
+            8 => Ins::FreeSlice(self.storable_type()?),
+            9 => Ins::Convert(self.value_type()?, self.value_type()?),
+            10 => {
+                let idx = self.usize()?;
+                match self.functions.get(idx) {
+                    Some(idx) => Ins::Call(*idx),
+                    None => return Err(BinaryError::InvalidIndex)
+                }
+            },
+            11 => Ins::Ret,
+            12 => Ins::Inc(self.value_type()?, self.uint()?),
+            13 => Ins::Dec(self.value_type()?, self.uint()?),
+            14 => Ins::Add(self.value_type()?),
+            15 => Ins::Mul(self.value_type()?),
+            16 => Ins::Div(self.value_type()?),
+            17 => Ins::Sub(self.value_type()?),
+            18 => Ins::Neg(self.value_type()?),
+            19 => Ins::Eq(self.value_type()?),
+            20 => Ins::Ne(self.value_type()?),
+            21 => Ins::Lt(self.value_type()?),
+            22 => Ins::Le(self.value_type()?),
+            23 => Ins::Gt(self.value_type()?),
+            24 => Ins::Ge(self.value_type()?),
+            25 => Ins::BoolAnd,
+            26 => Ins::BoolOr,
+            27 => Ins::Loop(self.block()?, self.block()?, self.block()?),
+            28 => Ins::If(self.block()?, self.block()?),
+            29 => {
+                let vt = match self.bool()? {
+                    true => Some(self.value_type()?),
+                    false => None
+                };
+                Ins::IfElse(vt, self.block()?, self.block()?, self.block()?)
+            },
+            30 => {
+                let vt = self.value_type()?;
+                let count = self.usize()?;
+                let mut cases = Vec::new();
+                for _ in 0..count {
+                    let value_count = self.usize()?;
+                    let mut values = Vec::new();
+                    for _ in 0..value_count {
+                        values.push(self.uint()?);
+                    }
+                    cases.push((values, self.block()?));
+                }
+                Ins::Switch(vt, cases, self.block()?, self.block()?)
+            },
+            31 => {
+                let arch = match self.u8()? {
+                    0 => AsmArch::X8664,
+                    1 => AsmArch::Arm64,
+                    tag => return Err(BinaryError::InvalidTag(tag))
+                };
+                let inputs = self.asm_bindings()?;
+                let outputs = self.asm_bindings()?;
+
+                let count = self.usize()?;
+                let mut code = Vec::new();
+                for _ in 0..count {
+                    let mnemonic = self.string()?;
+                    let operand_count = self.usize()?;
+                    let mut operands = Vec::new();
+                    for _ in 0..operand_count {
+                        operands.push(self.asm_operand()?);
+                    }
+                    code.push(AsmIns::new(mnemonic, operands));
+                }
+                Ins::Asm(AsmBlock::new(arch, inputs, outputs, code))
+            },
+            32 => Ins::Break(self.usize()?),
+            33 => Ins::Continue(self.usize()?),
+            34 => Ins::PushLiteral(self.value_type()?, self.uint()?),
+            35 => Ins::Drop,
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn attr(&mut self) -> Result<FunctionAttr, BinaryError> {
+        Ok(match self.u8()? {
+            0 => FunctionAttr::Entry,
+            1 => FunctionAttr::Alloc,
+            2 => FunctionAttr::AllocSlice,
+            3 => FunctionAttr::Free,
+            4 => FunctionAttr::FreeSlice,
+            5 => FunctionAttr::ExternLocation(self.string()?),
+            6 => FunctionAttr::Panic,
+            7 => FunctionAttr::Fallible,
+            8 => FunctionAttr::Test,
+            9 => FunctionAttr::Inline,
+            10 => FunctionAttr::Export,
+            tag => return Err(BinaryError::InvalidTag(tag))
+        })
+    }
+
+    fn compound_types(&mut self, unit: &mut TranslationUnit) -> Result<(), BinaryError> {
+        let count = self.usize()?;
+        for _ in 0..count {
+            let name = self.string()?;
+
+            let mut content = StructContent::new();
+            content.set_repr(match self.u8()? {
+                0 => StructRepr::Default,
+                1 => StructRepr::C,
+                2 => StructRepr::Packed,
+                tag => return Err(BinaryError::InvalidTag(tag))
+            });
+            match self.usize()? {
+                0 => {},
+                align if align.is_power_of_two() && align <= MAX_ALIGN => content.set_min_align(Some(align)),
+                _ => return Err(BinaryError::InvalidIndex)
+            }
+
+            let prop_count = self.usize()?;
+            for _ in 0..prop_count {
+                content.push_prop(StructProperty::new(self.string()?, self.storable_type()?));
+            }
+
+            let ct = match unit.find_type(&name) {
+                Some(ct) => match ct.content() {
+                    CompoundContent::Struct(s) => {
+                        let same = s.repr() == content.repr() && s.min_align() == content.min_align()
+                            && s.props().len() == content.props().len()
+                            && s.props().iter().zip(content.props()).all(|(a, b)| a.name() == b.name() && a.prop_type() == b.prop_type());
+                        if !same { return Err(BinaryError::TypeMismatch(name)); }
+                        ct
+                    }
+                },
+                None => {
+                    let ct = CompoundType::new(name, CompoundContent::Struct(content));
+                    unit.add_type(ct.clone());
+                    ct
+                }
+            };
+            self.compound_types.push(ct);
+        }
+
+        Ok(())
+    }
+
+    /// Globals are never shared between units, so are all added
+    fn globals(&mut self, unit: &mut TranslationUnit) -> Result<(), BinaryError> {
+        self.global_offset = unit.global_count();
+
+        let count = self.usize()?;
+        for _ in 0..count {
+            let name = match self.bool()? {
+                true => Some(self.string()?),
+                false => None
+            };
+            let read_only = self.bool()?;
+            let global_type = self.storable_type()?;
+            let default = match self.bool()? {
+                true => Some(self.storable_value()?),
+                false => None
+            };
+
+            unit.add_global(match (default, read_only) {
+                (Some(default), true) => Global::new_read_only(name, global_type, default),
+                (Some(default), false) => Global::new_default(name, global_type, default),
+                (None, false) => Global::new(name, global_type),
+                (None, true) => return Err(BinaryError::InvalidTag(1))
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Functions are matched by name, and by the type they are a method of, so that an extern function in one unit is
+    /// resolved by the definition in the other. Gives the index of every function whose code is in the object.
+    fn functions(&mut self, unit: &mut TranslationUnit) -> Result<Vec<FunctionIndex>, BinaryError> {
+        let mut defined = Vec::new();
+
+        let count = self.usize()?;
+        for _ in 0..count {
+            let name = self.string()?;
+            let signature = Signature::new(self.value_types()?, self.value_types()?);
+            let method_data = match self.u8()? {
+                0 => None,
+                1 => Some(MethodData::new_virtual(self.compound_type()?)),
+                2 => Some(MethodData::new_static(self.compound_type()?)),
+                tag => return Err(BinaryError::InvalidTag(tag))
+            };
+
+            let attr_count = self.usize()?;
+            let mut attrs = Vec::new();
+            for _ in 0..attr_count {
+                attrs.push(self.attr()?);
+            }
+            let is_extern = self.bool()?;
+
+            let existing = match &method_data {
+                Some(method_data) => unit.find_method_index(method_data.compound_type(), &name),
+                None => unit.find_function_index(&name)
+            };
+
+            if let Some(idx) = existing {
+                let is_static = match &method_data {
+                    Some(method_data) => method_data.is_static(),
+                    None => false
+                };
+
+                let other = unit.get_function(idx).unwrap();
+                if other.signature().params() != signature.params() || other.signature().returns() != signature.returns()
+                    || other.is_static() != is_static {
+                    return Err(BinaryError::SignatureMismatch(name));
+                }
+
+                match (other.is_extern(), is_extern) {
+                    (false, false) => return Err(BinaryError::DuplicateFunction(name)),
+                    (_, true) => {
+                        self.functions.push(idx);
+                        continue;
+                    },
+                    (true, false) => {}
+                }
+            }
+
+            let mut func = match method_data {
+                Some(method_data) => Function::new_method(name, signature, method_data),
+                None => Function::new(name, signature)
+            };
+            if is_extern { func.set_extern(); }
+            for attr in attrs {
+                func.push_attr(attr);
+            }
+
+            // The definition takes the place of the extern, so nothing which already calls it needs to change
+            let idx = match existing {
+                Some(idx) => {
+                    *unit.get_function_mut(idx).unwrap() = func;
+                    idx
+                },
+                None => unit.add_function(func)
+            };
+
+            self.functions.push(idx);
+            if !is_extern { defined.push(idx); }
+        }
+
+        Ok(defined)
+    }
+
+    fn unit(&mut self, unit: &mut TranslationUnit) -> Result<(), BinaryError> {
+        if self.bytes.get(0..4) != Some(&MAGIC[..]) { return Err(BinaryError::NotAnObject); }
+        self.offset = 4;
+
+        let version = u16::from_le_bytes([self.u8()?, self.u8()?]);
+        if version != BINARY_VERSION { return Err(BinaryError::UnsupportedVersion(version)); }
+
+        self.compound_types(unit)?;
+        self.globals(unit)?;
+
+        for idx in self.functions(unit)? {
+            let local_count = self.usize()?;
+            let mut locals = Vec::new();
+            for _ in 0..local_count {
+                locals.push(Local::new(self.storable_type()?));
+            }
+            let code = self.block()?;
+
+            let func = unit.get_function_mut(idx).unwrap();
+            for local in locals {
+                func.push_local(local);
+            }
+            for ins in code {
+                func.push(ins);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TranslationUnit {
+    /// Encodes the whole unit as an IR object, which can be read back with `from_binary` or merged into another unit with
+    /// `merge_binary`. The object starts with a magic number and `BINARY_VERSION`, followed by the compound types,
+    /// globals and function declarations of the unit, and then the code of each function which is not
+    /// extern. Anything which refers to another item does so by its index in the object.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut writer = Writer {
+            bytes: Vec::new(),
+            unit: self
+        };
+        writer.unit();
+
+        writer.bytes
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<TranslationUnit, BinaryError> {
+        let mut unit = TranslationUnit::new();
+        unit.merge_binary(bytes)?;
+        Ok(unit)
+    }
+
+    /// Adds everything in an IR object to this unit, like a linker would. Types with the same name are the same type, and
+    /// functions with the same name are the same function, so an extern function on one side is resolved by the
+    /// definition on the other. Fails if a type or function is declared differently on each side, or if a function is
+    /// defined on both. The unit may be left partly merged if this fails.
+    pub fn merge_binary(&mut self, bytes: &[u8]) -> Result<(), BinaryError> {
+        let mut reader = Reader {
+            bytes,
+            offset: 0,
+            compound_types: Vec::new(),
+            global_offset: 0,
+            functions: Vec::new()
+        };
+
+        reader.unit(self)
+    }
+
+    /// Merges a copy of another unit into this one, as `merge_binary` does
+    pub fn merge(&mut self, other: &TranslationUnit) -> Result<(), BinaryError> {
+        self.merge_binary(&other.to_binary())
+    }
+}
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::fmt::Write;
 
-use crate::{Function, Ins, StorableType, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
+use crate::{CompoundContent, CompoundValue, Function, FunctionAttr, Ins, Signature, StorableType, StorableValue, StructRepr, TranslationUnit, Value, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
 
 impl std::fmt::Display for ValueType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -25,7 +25,11 @@ impl std::fmt::Display for ValueType {
 impl std::fmt::Display for StorableType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            StorableType::Compound(compound) => f.write_fmt(format_args!("#comp({:?})", compound.name())),
+            StorableType::Compound(compound) => {
+                f.write_str("#comp(")?;
+                write_string(f, compound.name().as_bytes())?;
+                f.write_char(')')
+            },
             StorableType::Value(vt) => vt.fmt(f),
             StorableType::Slice(st) => f.write_fmt(format_args!("#slice({})", st)),
             StorableType::SliceData(st) => f.write_fmt(format_args!("#slicedata({})", st)),
@@ -38,7 +42,7 @@ impl std::fmt::Display for ValuePathOrigin {
         match self {
             ValuePathOrigin::Local(local_index, _) => f.write_fmt(format_args!("#lcl({})", local_index)),
             ValuePathOrigin::Global(global_index, _) => f.write_fmt(format_args!("#glbl({})", global_index)),
-            ValuePathOrigin::Deref(_) => f.write_str("deref"),
+            ValuePathOrigin::Deref(st) => f.write_fmt(format_args!("deref({})", st)),
         }
     }
 }
@@ -71,6 +75,7 @@ impl std::fmt::Display for Ins {
             Ins::PushPath(path, vt) => f.write_fmt(format_args!("pushpath %{} {}", vt, path)),
             Ins::Push(vt) => f.write_fmt(format_args!("push %{}", vt)),
             Ins::Pop(vt) => f.write_fmt(format_args!("pop %{}", vt)),
+            Ins::PopLocal(idx, vt) => f.write_fmt(format_args!("poplocal %{} #lcl({})", vt, idx)),
             Ins::Index(vt) => f.write_fmt(format_args!("index %{}", vt)),
             Ins::New(st) => f.write_fmt(format_args!("new {}", st)),
             Ins::NewSlice(st) => f.write_fmt(format_args!("newslice {}", st)),
@@ -127,8 +132,12 @@ impl std::fmt::Display for Ins {
                 f.write_str("\n\t}")?;
                 Ok(())
             },
-            Ins::IfElse(true_then, false_then, cond) => {
-                f.write_str("if\n\tthen {")?;
+            Ins::IfElse(vt, true_then, false_then, cond) => {
+                f.write_str("if")?;
+                if let Some(vt) = vt {
+                    f.write_fmt(format_args!(" %{}", vt))?;
+                }
+                f.write_str("\n\tthen {")?;
                 for ins in true_then {
                     f.write_str("\n\t\t")?;
                     f.write_str(&format!("{}", ins).replace('\n', "\n\t\t"))?;
@@ -146,25 +155,235 @@ impl std::fmt::Display for Ins {
                 f.write_str("\n\t}")?;
                 Ok(())
             },
+            Ins::Switch(vt, cases, default, value) => {
+                f.write_fmt(format_args!("switch %{}", vt))?;
+                for (values, code) in cases {
+                    f.write_str("\n\tcase ")?;
+                    f.write_str(&values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", "))?;
+                    f.write_str(" {")?;
+                    for ins in code {
+                        f.write_str("\n\t\t")?;
+                        f.write_str(&format!("{}", ins).replace('\n', "\n\t\t"))?;
+                    }
+                    f.write_str("\n\t}")?;
+                }
+                f.write_str("\n\tdefault {")?;
+                for ins in default {
+                    f.write_str("\n\t\t")?;
+                    f.write_str(&format!("{}", ins).replace('\n', "\n\t\t"))?;
+                }
+                f.write_str("\n\t}\n\tvalue {")?;
+                for ins in value {
+                    f.write_str("\n\t\t")?;
+                    f.write_str(&format!("{}", ins).replace('\n', "\n\t\t"))?;
+                }
+                f.write_str("\n\t}")?;
+                Ok(())
+            },
+            Ins::Asm(block) => {
+                f.write_fmt(format_args!("asm {}", block.arch().name()))?;
+                for input in block.inputs() {
+                    f.write_fmt(format_args!("\n\tin {} #lcl({}) %{}", input.reg(), input.local(), input.value_type()))?;
+                }
+                for output in block.outputs() {
+                    f.write_fmt(format_args!("\n\tout {} #lcl({}) %{}", output.reg(), output.local(), output.value_type()))?;
+                }
+                f.write_str("\n\tcode {")?;
+                for ins in block.code() {
+                    f.write_fmt(format_args!("\n\t\t{};", ins))?;
+                }
+                f.write_str("\n\t}")?;
+                Ok(())
+            },
             Ins::Break(depth) => f.write_fmt(format_args!("break {}", depth)),
-            Ins::Continue(depth) => f.write_fmt(format_args!("break {}", depth)),
+            Ins::Continue(depth) => f.write_fmt(format_args!("continue {}", depth)),
             Ins::PushLiteral(vt, val) => f.write_fmt(format_args!("pushlit %{} {}", vt, val)),
             Ins::Drop => f.write_str("drop"),
         }
     }
 }
 
+/// Writes a string in quotes, escaping it so that it can be read back by `parse_unit`. Bytes which are not valid UTF-8 cannot
+/// be read back, so are written as the replacement character.
+fn write_string(f: &mut std::fmt::Formatter<'_>, bytes: &[u8]) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c {
+            '\\' => f.write_str("\\\\")?,
+            '"' => f.write_str("\\\"")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => f.write_fmt(format_args!("\\u{{{:x}}}", c as u32))?,
+            c => f.write_char(c)?
+        }
+    }
+    f.write_char('"')
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("({})", self.params().iter().map(|vt| vt.to_string()).collect::<Vec<_>>().join(", ")))?;
+        if self.return_count() != 0 {
+            f.write_fmt(format_args!(" -> ({})", self.returns().iter().map(|vt| vt.to_string()).collect::<Vec<_>>().join(", ")))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for FunctionAttr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FunctionAttr::Entry => f.write_str("@entry"),
+            FunctionAttr::Alloc => f.write_str("@alloc"),
+            FunctionAttr::AllocSlice => f.write_str("@alloc_slice"),
+            FunctionAttr::Free => f.write_str("@free"),
+            FunctionAttr::FreeSlice => f.write_str("@free_slice"),
+            FunctionAttr::ExternLocation(location) => {
+                f.write_str("@extern(")?;
+                write_string(f, location.as_bytes())?;
+                f.write_char(')')
+            },
+            FunctionAttr::Panic => f.write_str("@panic"),
+            FunctionAttr::Fallible => f.write_str("@fallible"),
+            FunctionAttr::Test => f.write_str("@test"),
+            FunctionAttr::Inline => f.write_str("@inline"),
+            FunctionAttr::Export => f.write_str("@export"),
+        }
+    }
+}
+
+/// Writes everything after the index of a function, so that a unit can write the index of each
+fn write_function(f: &mut std::fmt::Formatter<'_>, func: &Function) -> std::fmt::Result {
+    write_string(f, func.name().as_bytes())?;
+    f.write_fmt(format_args!(" {}", func.signature()))?;
+
+    if let Some(method_data) = func.method_data() {
+        f.write_str(if method_data.is_static() { " @static(" } else { " @method(" })?;
+        write_string(f, method_data.compound_type().name().as_bytes())?;
+        f.write_char(')')?;
+    }
+
+    for attr in func.attrs() {
+        f.write_fmt(format_args!(" {}", attr))?;
+    }
+
+    if let Some(code) = func.code_opt() {
+        f.write_str(" {")?;
+        for (l, local) in func.locals().iter().enumerate() {
+            f.write_fmt(format_args!("\n\tlocal {}: {}", l, local.local_type()))?;
+        }
+        for ins in code {
+            f.write_str("\n\t")?;
+            f.write_str(&format!("{}", ins).replace('\n', "\n\t"))?;
+        }
+        f.write_str("\n}")?;
+    } else {
+        f.write_str(" extern")?;
+    }
+
+    Ok(())
+}
+
 impl std::fmt::Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(code) = self.code_opt() {
-            f.write_char('{')?;
-            for ins in code {
-                f.write_str("\n\t")?;
-                f.write_str(&format!("{}", ins).replace('\n', "\n\t"))?;
+        f.write_str("func ")?;
+        write_function(f, self)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::U8(v) => f.write_fmt(format_args!("u8 {}", v)),
+            Value::I8(v) => f.write_fmt(format_args!("i8 {}", v)),
+            Value::U16(v) => f.write_fmt(format_args!("u16 {}", v)),
+            Value::I16(v) => f.write_fmt(format_args!("i16 {}", v)),
+            Value::U32(v) => f.write_fmt(format_args!("u32 {}", v)),
+            Value::I32(v) => f.write_fmt(format_args!("i32 {}", v)),
+            Value::U64(v) => f.write_fmt(format_args!("u64 {}", v)),
+            Value::I64(v) => f.write_fmt(format_args!("i64 {}", v)),
+            Value::UPtr(v) => f.write_fmt(format_args!("uptr {}", v)),
+            Value::IPtr(v) => f.write_fmt(format_args!("iptr {}", v)),
+            Value::Bool(v) => f.write_fmt(format_args!("bool {}", v)),
+            Value::Ref(global) => f.write_fmt(format_args!("ref #glbl({})", global)),
+        }
+    }
+}
+
+impl std::fmt::Display for StorableValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorableValue::Compound(CompoundValue::Struct(s)) => {
+                f.write_fmt(format_args!("struct {{{}}}", s.props().iter().map(|prop| prop.value().to_string()).collect::<Vec<_>>().join(", ")))
+            },
+            StorableValue::Value(value) => value.fmt(f),
+            StorableValue::Slice(global, start, length) => f.write_fmt(format_args!("slice(#glbl({}), {}, {})", global, start, length)),
+            StorableValue::SliceData(values) => {
+                // Strings are by far the most common data, so are written as one where possible
+                let bytes = values.iter().map(|value| match value {
+                    StorableValue::Value(Value::U8(byte)) => Some(*byte),
+                    _ => None
+                }).collect::<Option<Vec<_>>>();
+
+                match bytes {
+                    Some(bytes) if !values.is_empty() && std::str::from_utf8(&bytes).is_ok() => {
+                        f.write_str("data ")?;
+                        write_string(f, &bytes)
+                    },
+                    _ => f.write_fmt(format_args!("data [{}]", values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", ")))
+                }
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for TranslationUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ct in self.compound_types() {
+            f.write_str("struct ")?;
+            write_string(f, ct.name().as_bytes())?;
+            match ct.content() {
+                CompoundContent::Struct(s) => {
+                    match s.repr() {
+                        StructRepr::Default => {},
+                        StructRepr::C => f.write_str(" @repr(c)")?,
+                        StructRepr::Packed => f.write_str(" @repr(packed)")?,
+                    }
+                    if let Some(align) = s.min_align() {
+                        f.write_fmt(format_args!(" @align({})", align))?;
+                    }
+
+                    f.write_str(" {")?;
+                    for (p, prop) in s.props().iter().enumerate() {
+                        f.write_str(if p == 0 { "\n\t" } else { ",\n\t" })?;
+                        write_string(f, prop.name().as_bytes())?;
+                        f.write_fmt(format_args!(": {}", prop.prop_type()))?;
+                    }
+                    f.write_str("\n}\n")?;
+                }
+            }
+        }
+
+        for (g, global) in self.globals().iter().enumerate() {
+            f.write_fmt(format_args!("global {}:", g))?;
+            if let Some(name) = global.name() {
+                write_string(f, name.as_bytes())?;
             }
-            f.write_str("\n}")?;
-        } else {
-            f.write_str("extern")?;
+            if global.is_read_only() {
+                f.write_str(" readonly")?;
+            }
+            f.write_fmt(format_args!(" {}", global.global_type()))?;
+            if let Some(default) = global.default() {
+                f.write_fmt(format_args!(" = {}", default))?;
+            }
+            f.write_char('\n')?;
+        }
+
+        for (idx, func) in self.functions().iter().enumerate() {
+            f.write_fmt(format_args!("func {}:", idx))?;
+            write_function(f, func)?;
+            f.write_char('\n')?;
         }
 
         Ok(())
@@ -1,6 +1,6 @@
 use crate::StorableType;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct GlobalIndex(usize);
 
 impl GlobalIndex {
@@ -23,7 +23,8 @@ impl std::fmt::Display for GlobalIndex {
 pub struct Global {
     name: Option<String>,
     global_type: StorableType,
-    default: Option<StorableValue>
+    default: Option<StorableValue>,
+    read_only: bool
 }
 
 impl Global {
@@ -34,7 +35,8 @@ impl Global {
                 None => None
             },
             global_type,
-            default: None
+            default: None,
+            read_only: false
         }
     }
 
@@ -45,7 +47,21 @@ impl Global {
                 None => None
             },
             global_type,
-            default: Some(default)
+            default: Some(default),
+            read_only: false
+        }
+    }
+
+    /// Creates a global which is never written to, so may be placed in read only memory by the target
+    pub fn new_read_only<T: Into<String>>(name: Option<T>, global_type: StorableType, default: StorableValue) -> Global {
+        Global {
+            name: match name {
+                Some(x) => Some(x.into()),
+                None => None
+            },
+            global_type,
+            default: Some(default),
+            read_only: true
         }
     }
 
@@ -63,6 +79,10 @@ impl Global {
     pub fn global_type(&self) -> &StorableType {
         &self.global_type
     }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
 }
 
 #[derive(Debug)]
@@ -104,7 +124,7 @@ pub enum CompoundValue {
     Struct(StructValue)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     U8(u8), I8(i8),
     U16(u16), I16(i16),
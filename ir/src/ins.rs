@@ -1,20 +1,20 @@
-use crate::{CompoundTypeRef, GlobalIndex, PropertyIndex, StorableType, ValueType, unit::*};
+use crate::{AsmBlock, CompoundTypeRef, GlobalIndex, PropertyIndex, StorableType, ValueType, unit::*};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ValuePathOrigin {
     Local(LocalIndex, StorableType),
     Global(GlobalIndex, StorableType),
     Deref(StorableType)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ValuePathComponent {
     Slice(StorableType),
     Property(PropertyIndex, CompoundTypeRef, StorableType),
     Length
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ValuePath {
     origin: ValuePathOrigin,
     components: Vec<ValuePathComponent>
@@ -51,7 +51,7 @@ impl ValuePath {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Ins {
     /// Push the given path to the path stack, popping from the stack as needed to get references / indices etc
     PushPath(ValuePath, ValueType),
@@ -60,6 +60,33 @@ pub enum Ins {
     /// Pop a path from the path stack, and pop the value it should point to
     Pop(ValueType),
 
+    /// Pop a value from the stack and store it directly into the given local, which must be a value of the same type.
+    /// Unlike Pop, this does not need a path beneath the value, so it can be used to store each of the values returned
+    /// by a call with multiple returns.
+    /// # Examples
+    /// ```
+    /// use ir;
+    /// let mut unit = ir::TranslationUnit::new();
+    /// 
+    /// let div_mod = unit.add_function(ir::Function::new_extern("div_mod", ir::Signature::new(vec![ ], vec![ ir::ValueType::I32, ir::ValueType::I32 ])));
+    /// 
+    /// let mut func = ir::Function::new("remainder", ir::Signature::new(vec![ ], vec![ ir::ValueType::I32 ]));
+    /// let quotient = func.push_local(ir::Local::new(ir::StorableType::Value(ir::ValueType::I32)));
+    /// let remainder = func.push_local(ir::Local::new(ir::StorableType::Value(ir::ValueType::I32)));
+    /// 
+    /// func.push(ir::Ins::Call(div_mod));
+    /// func.push(ir::Ins::PopLocal(remainder, ir::ValueType::I32));
+    /// func.push(ir::Ins::PopLocal(quotient, ir::ValueType::I32));
+    /// 
+    /// func.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(remainder, ir::StorableType::Value(ir::ValueType::I32))), ir::ValueType::I32));
+    /// func.push(ir::Ins::Push(ir::ValueType::I32));
+    /// func.push(ir::Ins::Ret);
+    /// 
+    /// unit.add_function(func);
+    /// assert!(unit.validate().is_ok());
+    /// ```
+    PopLocal(LocalIndex, ValueType),
+
     /// Pop a uptr and push an Index in it's place
     Index(StorableType),
 
@@ -330,16 +357,18 @@ pub enum Ins {
     /// ```
     If(Vec<Ins>, Vec<Ins>),
 
-    /// true_then, false_then, condition
-    /// Executes it's first set of children if the popped item is nonzero, otherwise executes it's second set.
-    /// The instruction must start with one (bool) item on the stack, and both branches must end with 0.
+    /// value type, true_then, false_then, condition
+    /// Executes it's first set of children if the condition is nonzero, otherwise executes it's second set.
+    /// The condition and both branches run on a stack of their own, so anything already on the stack is left alone. The condition must
+    /// end with one (bool) item. Without a value type the branches must both end with 0 items, otherwise they must both end with one
+    /// item of that type, which is left on the stack once the IfElse is done. This allows it to be used as an expression.
     /// # Examples
     /// ```
     /// use ir;
     /// let mut func = ir::Function::new("if_1_else", ir::Signature::new(vec![ ], vec![ ]));
     /// 
     /// func.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 1));
-    /// func.push(ir::Ins::IfElse(vec![
+    /// func.push(ir::Ins::IfElse(None, vec![
     ///     // Do something
     /// ], vec![
     ///     // Do something else
@@ -348,7 +377,67 @@ pub enum Ins {
     /// ]));
     /// func.push(ir::Ins::Ret);
     /// ```
-    IfElse(Vec<Ins>, Vec<Ins>, Vec<Ins>),
+    /// 
+    /// ```
+    /// use ir;
+    /// let mut func = ir::Function::new("if_1_else_value", ir::Signature::new(vec![ ], vec![ ir::ValueType::I32 ]));
+    /// 
+    /// func.push(ir::Ins::IfElse(Some(ir::ValueType::I32), vec![
+    ///     ir::Ins::PushLiteral(ir::ValueType::I32, 1),
+    /// ], vec![
+    ///     ir::Ins::PushLiteral(ir::ValueType::I32, 2),
+    /// ], vec![
+    ///     ir::Ins::PushLiteral(ir::ValueType::Bool, 1),
+    /// ]));
+    /// func.push(ir::Ins::Ret);
+    /// ```
+    IfElse(Option<ValueType>, Vec<Ins>, Vec<Ins>, Vec<Ins>),
+
+    /// value type, cases, default, value
+    /// Executes the code of the case listing the integer the value evaluates to, or the default if no case lists it.
+    /// The instruction must start with an empty stack, the value must end with one item of the given type, and every case and the
+    /// default must end with an empty stack. Case values are the bits of the value, so negative values of signed types are sign
+    /// extended, and each may only be listed once across all of the cases.
+    /// # Examples
+    /// ```
+    /// use ir;
+    /// let mut func = ir::Function::new("switch_on_2", ir::Signature::new(vec![ ], vec![ ]));
+    ///
+    /// func.push(ir::Ins::Switch(ir::ValueType::I32, vec![
+    ///     (vec![ 1, 2 ], vec![
+    ///         // Do something for 1 or 2
+    ///     ]),
+    ///     (vec![ 3 ], vec![
+    ///         // Do something for 3
+    ///     ]),
+    /// ], vec![
+    ///     // Do something for anything else
+    /// ], vec![
+    ///     ir::Ins::PushLiteral(ir::ValueType::I32, 2),
+    /// ]));
+    /// func.push(ir::Ins::Ret);
+    /// ```
+    Switch(ValueType, Vec<(Vec<u64>, Vec<Ins>)>, Vec<Ins>, Vec<Ins>),
+
+    /// Runs a block of machine code, which only the backend for its architecture can translate. The instruction must run with
+    /// an empty stack. The code may use any register other than the stack and frame pointers, and any callee saved registers
+    /// it uses are saved by the function.
+    /// # Examples
+    /// ```
+    /// use ir;
+    /// let mut func = ir::Function::new("exit_with", ir::Signature::new(vec![ ir::ValueType::I64 ], vec![ ]));
+    /// let code = ir::LocalIndex::new(0);
+    /// func.push_local(ir::Local::new(ir::StorableType::Value(ir::ValueType::I64)));
+    ///
+    /// func.push(ir::Ins::Asm(ir::AsmBlock::new(ir::AsmArch::X8664, vec![
+    ///     ir::AsmBinding::new("rdi", code, ir::ValueType::I64)
+    /// ], vec![], vec![
+    ///     ir::AsmIns::new("mov", vec![ ir::AsmOperand::Reg("rax".to_string()), ir::AsmOperand::Imm(60) ]),
+    ///     ir::AsmIns::new("syscall", vec![]),
+    /// ])));
+    /// func.push(ir::Ins::Ret);
+    /// ```
+    Asm(AsmBlock),
 
     /// Breaks out of the loop at the depth above the current instruction given. The depth must refer to a Loop, and must run with an empty stack
     /// # Examples
@@ -419,4 +508,69 @@ pub enum Ins {
     /// func.push(ir::Ins::Ret);
     /// ```
     Drop
-}
\ No newline at end of file
+}
+
+impl Ins {
+    /// Whether the instruction only affects the stack and can never fault, so it is safe to run even if its result is never used,
+    /// such as when both branches of an IfElse are run to select between their values without branching
+    pub fn is_side_effect_free(&self) -> bool {
+        match self {
+            // Reading a local or global is fine, but reading through a reference could fault
+            Ins::PushPath(path, _) => path.component_count() == 0 && !matches!(path.origin(), ValuePathOrigin::Deref(_)),
+            Ins::Push(_) | Ins::PushLiteral(_, _) | Ins::Convert(_, _) |
+            Ins::Inc(_, _) | Ins::Dec(_, _) | Ins::Add(_) | Ins::Mul(_) | Ins::Sub(_) | Ins::Neg(_) |
+            Ins::Eq(_) | Ins::Ne(_) | Ins::Lt(_) | Ins::Le(_) | Ins::Gt(_) | Ins::Ge(_) |
+            Ins::BoolAnd | Ins::BoolOr => true,
+            // Division faults on zero
+            _ => false
+        }
+    }
+}
+
+/// The smallest value listed by the cases of a Switch and the number of values from it up to the largest, if they are close enough
+/// together to be dispatched through a table indexed by `value - min` rather than by comparing the value against each in turn
+pub fn switch_table_range(vt: &ValueType, cases: &[(Vec<u64>, Vec<Ins>)]) -> Option<(u64, usize)> {
+    let count = cases.iter().map(|(values, _)| values.len()).sum::<usize>();
+    if count < 3 { return None; }
+
+    let as_wide = |value: u64| if vt.is_signed() { value as i64 as i128 } else { value as i128 };
+
+    let min = cases.iter().flat_map(|(values, _)| values.iter()).map(|value| as_wide(*value)).min()?;
+    let max = cases.iter().flat_map(|(values, _)| values.iter()).map(|value| as_wide(*value)).max()?;
+
+    // At least a quarter of the table must lead somewhere other than the default
+    let len = max - min + 1;
+    if len > count as i128 * 4 || len > 1024 { return None; }
+
+    Some((min as u64, len as usize))
+}
+
+/// Every inline assembly block in the code, including those nested in the blocks of other instructions
+pub fn asm_blocks(code: &[Ins]) -> Vec<&AsmBlock> {
+    let mut blocks = Vec::new();
+
+    for ins in code {
+        match ins {
+            Ins::Asm(block) => blocks.push(block),
+            Ins::Loop(a, b, c) | Ins::IfElse(_, a, b, c) => {
+                blocks.extend(asm_blocks(a));
+                blocks.extend(asm_blocks(b));
+                blocks.extend(asm_blocks(c));
+            },
+            Ins::If(a, b) => {
+                blocks.extend(asm_blocks(a));
+                blocks.extend(asm_blocks(b));
+            },
+            Ins::Switch(_, cases, default, value) => {
+                for (_, case) in cases {
+                    blocks.extend(asm_blocks(case));
+                }
+                blocks.extend(asm_blocks(default));
+                blocks.extend(asm_blocks(value));
+            },
+            _ => {}
+        }
+    }
+
+    blocks
+}
@@ -0,0 +1,690 @@
+use std::io::Write;
+
+use crate::*;
+
+/// How deeply calls may be nested before the program is stopped, as each call is interpreted on the stack of the host
+const MAX_CALL_DEPTH: usize = 512;
+
+/// The most elements a single slice may be allocated with
+const MAX_SLICE_LEN: u64 = 1 << 24;
+
+#[derive(Debug, PartialEq)]
+pub enum InterpError {
+    /// No function in the unit is marked as the entry
+    NoEntry,
+    /// An extern function was called which the host does not provide
+    UnknownExtern(String),
+    /// Assembly can only be run by the backend for its architecture
+    Asm,
+    NullDereference,
+    /// A reference was used after the object it refers to was freed
+    UseAfterFree,
+    /// A reference was freed which is not to a live object on the heap
+    InvalidFree,
+    /// The index, and the length of the slice it was used on
+    IndexOutOfBounds(u64, usize),
+    DivideByZero,
+    CallDepthExceeded,
+    SliceTooLong(u64),
+    /// The host failed to carry out an extern function
+    Host(String),
+    /// The code does something which validation does not allow, such as pushing a path where a value is needed
+    InvalidCode
+}
+
+impl std::fmt::Display for InterpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpError::NoEntry => f.write_str("No entry function"),
+            InterpError::UnknownExtern(name) => f.write_fmt(format_args!("Extern function {} is not provided by the host", name)),
+            InterpError::Asm => f.write_str("Assembly cannot be interpreted"),
+            InterpError::NullDereference => f.write_str("Null reference dereferenced"),
+            InterpError::UseAfterFree => f.write_str("Object used after it was freed"),
+            InterpError::InvalidFree => f.write_str("Freed an object which is not on the heap, or was already freed"),
+            InterpError::IndexOutOfBounds(index, len) => f.write_fmt(format_args!("Index {} is out of bounds for a slice of length {}", index, len)),
+            InterpError::DivideByZero => f.write_str("Division by zero"),
+            InterpError::CallDepthExceeded => f.write_fmt(format_args!("Calls nested more than {} deep", MAX_CALL_DEPTH)),
+            InterpError::SliceTooLong(len) => f.write_fmt(format_args!("Slice of length {} is too long to allocate", len)),
+            InterpError::Host(message) => f.write_str(message),
+            InterpError::InvalidCode => f.write_str("Code is not valid"),
+        }
+    }
+}
+
+/// How a call finished, either returning its values or by asking for the whole program to exit with a code
+#[derive(Debug, PartialEq)]
+pub enum CallResult {
+    Return(Vec<u64>),
+    Exit(i32)
+}
+
+/// Provides the extern functions of a unit while it is interpreted. Values are passed as their bits, signed values being
+/// sign extended to 64 bits, and the host must return as many values as the function's signature does.
+pub trait InterpHost {
+    fn call(&mut self, name: &str, args: &[u64]) -> Result<CallResult, InterpError>;
+}
+
+/// Provides the externs std uses on every target, `putchar` writing the low byte of its argument to the output and `exit`
+/// exitting with its argument
+pub struct StdHost<W: Write> {
+    out: W
+}
+
+impl<W: Write> StdHost<W> {
+    pub fn new(out: W) -> StdHost<W> {
+        StdHost {
+            out
+        }
+    }
+
+    pub fn out(&self) -> &W {
+        &self.out
+    }
+}
+
+impl<W: Write> InterpHost for StdHost<W> {
+    fn call(&mut self, name: &str, args: &[u64]) -> Result<CallResult, InterpError> {
+        match (name, args) {
+            ("putchar", [chr]) => match self.out.write_all(&[*chr as u8]) {
+                Ok(_) => Ok(CallResult::Return(Vec::new())),
+                Err(e) => Err(InterpError::Host(format!("{}", e)))
+            },
+            ("exit", [code]) => {
+                if let Err(e) = self.out.flush() {
+                    return Err(InterpError::Host(format!("{}", e)));
+                }
+                Ok(CallResult::Exit(*code as i32))
+            },
+            _ => Err(InterpError::UnknownExtern(name.to_string()))
+        }
+    }
+}
+
+/// Storage for a value of a storable type, whether in a local, a global, or an object on the heap
+#[derive(Debug, Clone)]
+enum Cell {
+    Value(u64),
+    Struct(Vec<Cell>),
+    /// The object holding the data, the index of the first element in it, and the length
+    Slice(u64, usize, usize),
+    SliceData(Vec<Cell>)
+}
+
+/// Where a path starts, objects being numbered from one so that zero is the null reference
+#[derive(Debug)]
+enum Root {
+    Local(usize),
+    Object(u64)
+}
+
+/// A resolved path, which steps through properties and slice elements from its root, and may be to the length of a slice
+#[derive(Debug)]
+struct Place {
+    root: Root,
+    steps: Vec<usize>,
+    length: bool
+}
+
+#[derive(Debug)]
+enum Entry {
+    Value(u64),
+    Path(Place)
+}
+
+/// How a block finished
+enum Flow {
+    Next,
+    Break(BlockMoveDepth),
+    Continue(BlockMoveDepth),
+    Return(Vec<u64>),
+    Exit(i32)
+}
+
+impl Flow {
+    /// The flow once it leaves the block it was made in, which must not be the block a break or continue moves to
+    fn leave(self) -> Result<Flow, InterpError> {
+        match self {
+            Flow::Break(0) | Flow::Continue(0) => Err(InterpError::InvalidCode),
+            Flow::Break(depth) => Ok(Flow::Break(depth - 1)),
+            Flow::Continue(depth) => Ok(Flow::Continue(depth - 1)),
+            flow => Ok(flow)
+        }
+    }
+}
+
+struct Frame<'a> {
+    function: &'a Function,
+    locals: Vec<Cell>
+}
+
+/// Runs the functions of a unit directly, without translating it for any target. Globals are set up when the
+/// interpreter is made, and objects made with `New` and `NewSlice` are kept until they are freed, so the state is
+/// shared by every call made with the same interpreter. uptr and iptr are 64 bits wide.
+pub struct Interpreter<'a> {
+    unit: &'a TranslationUnit,
+    host: &'a mut dyn InterpHost,
+    /// The globals, followed by every object which has been allocated, which are None once freed
+    objects: Vec<Option<Cell>>,
+    depth: usize
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(unit: &'a TranslationUnit, host: &'a mut dyn InterpHost) -> Interpreter<'a> {
+        let objects = unit.globals().iter().map(|global| match global.default() {
+            Some(value) => Some(stored_cell(value)),
+            None => Some(default_cell(global.global_type()))
+        }).collect();
+
+        Interpreter {
+            unit,
+            host,
+            objects,
+            depth: 0
+        }
+    }
+
+    /// Calls the entry function, giving the value it returns, or the code the program exitted with
+    pub fn run_entry(&mut self) -> Result<i32, InterpError> {
+        let entry = match self.unit.functions().iter().position(|function| function.is_entry()) {
+            Some(x) => FunctionIndex::new(x),
+            None => return Err(InterpError::NoEntry)
+        };
+
+        match self.call(entry, &[])? {
+            CallResult::Return(values) => match values.as_slice() {
+                [code] => Ok(*code as i32),
+                _ => Err(InterpError::InvalidCode)
+            },
+            CallResult::Exit(code) => Ok(code)
+        }
+    }
+
+    /// Calls the function with the given arguments, passing it to the host if it is extern
+    pub fn call(&mut self, idx: FunctionIndex, args: &[u64]) -> Result<CallResult, InterpError> {
+        let unit = self.unit;
+        let function = match unit.get_function(idx) {
+            Some(x) => x,
+            None => return Err(InterpError::InvalidCode)
+        };
+
+        if args.len() != function.signature().param_count() {
+            return Err(InterpError::InvalidCode);
+        }
+
+        if function.is_extern() {
+            return self.host.call(function.name(), args);
+        }
+
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(InterpError::CallDepthExceeded);
+        }
+
+        // The first locals hold the params
+        let mut frame = Frame {
+            function,
+            locals: function.locals().iter().map(|local| default_cell(local.local_type())).collect()
+        };
+        for (local, arg) in frame.locals.iter_mut().zip(args) {
+            *local = Cell::Value(*arg);
+        }
+
+        self.depth += 1;
+        let flow = self.run_block(function.code(), &mut frame, &mut Vec::new());
+        self.depth -= 1;
+
+        match flow? {
+            Flow::Return(values) => Ok(CallResult::Return(values)),
+            Flow::Exit(code) => Ok(CallResult::Exit(code)),
+            _ => Err(InterpError::InvalidCode)
+        }
+    }
+
+    /// The number of objects on the heap which have not been freed
+    pub fn live_object_count(&self) -> usize {
+        self.objects[self.unit.global_count()..].iter().filter(|object| object.is_some()).count()
+    }
+
+    fn run_block(&mut self, code: &'a [Ins], frame: &mut Frame<'a>, stack: &mut Vec<Entry>) -> Result<Flow, InterpError> {
+        for ins in code {
+            match self.run(ins, frame, stack)? {
+                Flow::Next => {},
+                flow => return Ok(flow)
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    /// Runs a block which leaves a single value on the stack, such as a condition, giving that value
+    fn evaluate(&mut self, code: &'a [Ins], frame: &mut Frame<'a>) -> Result<Result<u64, Flow>, InterpError> {
+        let mut stack = Vec::new();
+        match self.run_block(code, frame, &mut stack)? {
+            Flow::Next => Ok(Ok(pop(&mut stack)?)),
+            flow => Ok(Err(flow))
+        }
+    }
+
+    fn run(&mut self, ins: &'a Ins, frame: &mut Frame<'a>, stack: &mut Vec<Entry>) -> Result<Flow, InterpError> {
+        match ins {
+            Ins::PushPath(path, _) => {
+                let place = self.resolve(path, frame, stack)?;
+                stack.push(Entry::Path(place));
+            },
+            Ins::Push(_) => {
+                let place = pop_path(stack)?;
+                let value = match (self.cell(&place, frame)?, place.length) {
+                    (Cell::Value(value), false) => *value,
+                    (Cell::Slice(_, _, len), true) => *len as u64,
+                    _ => return Err(InterpError::InvalidCode)
+                };
+                stack.push(Entry::Value(value));
+            },
+            Ins::Pop(_) => {
+                let value = pop(stack)?;
+                let place = pop_path(stack)?;
+                if place.length { return Err(InterpError::InvalidCode) }
+
+                match self.cell_mut(&place, frame)? {
+                    Cell::Value(x) => *x = value,
+                    _ => return Err(InterpError::InvalidCode)
+                }
+            },
+            Ins::PopLocal(idx, _) => {
+                let value = pop(stack)?;
+                match frame.locals.get_mut(idx.idx()) {
+                    Some(local) => *local = Cell::Value(value),
+                    None => return Err(InterpError::InvalidCode)
+                }
+            },
+            // An index is held as the uptr it was made from
+            Ins::Index(_) => {
+                top(stack)?;
+            },
+            Ins::New(object_type) => {
+                let object = self.alloc(default_cell(object_type));
+                stack.push(Entry::Value(object));
+            },
+            Ins::NewSlice(slice_type) => {
+                let len = pop(stack)?;
+                if len > MAX_SLICE_LEN { return Err(InterpError::SliceTooLong(len)) }
+
+                let data = self.alloc(Cell::SliceData(vec![default_cell(slice_type); len as usize]));
+                let slice = self.alloc(Cell::Slice(data, 0, len as usize));
+                stack.push(Entry::Value(slice));
+            },
+            Ins::Free(_) => {
+                let object = pop(stack)?;
+                self.free(object)?;
+            },
+            Ins::FreeSlice(_) => {
+                let slice = pop(stack)?;
+                if slice != 0 {
+                    // The data is freed along with the slice, as NewSlice allocates them together
+                    let data = match self.object(slice)? {
+                        Cell::Slice(data, _, _) => *data,
+                        _ => return Err(InterpError::InvalidCode)
+                    };
+                    self.free(data)?;
+                    self.free(slice)?;
+                }
+            },
+            Ins::Convert(_, to) => {
+                let value = top(stack)?;
+                *value = match to {
+                    ValueType::Bool => (*value != 0) as u64,
+                    _ => wrap(to, *value)
+                };
+            },
+            Ins::Call(idx) => {
+                let count = match self.unit.get_function(*idx) {
+                    Some(function) => function.signature().param_count(),
+                    None => return Err(InterpError::InvalidCode)
+                };
+                let args = pop_values(stack, count)?;
+
+                match self.call(*idx, &args)? {
+                    CallResult::Return(values) => stack.extend(values.into_iter().map(Entry::Value)),
+                    CallResult::Exit(code) => return Ok(Flow::Exit(code))
+                }
+            },
+            Ins::Ret => {
+                let values = pop_values(stack, frame.function.signature().return_count())?;
+                return Ok(Flow::Return(values));
+            },
+            Ins::Inc(vt, amount) => {
+                let value = top(stack)?;
+                *value = wrap(vt, value.wrapping_add(*amount));
+            },
+            Ins::Dec(vt, amount) => {
+                let value = top(stack)?;
+                *value = wrap(vt, value.wrapping_sub(*amount));
+            },
+            Ins::Neg(vt) => {
+                let value = top(stack)?;
+                *value = wrap(vt, value.wrapping_neg());
+            },
+            Ins::Add(vt) | Ins::Sub(vt) | Ins::Mul(vt) | Ins::Div(vt) => {
+                let b = pop(stack)?;
+                let a = top(stack)?;
+                *a = wrap(vt, match ins {
+                    Ins::Add(_) => a.wrapping_add(b),
+                    Ins::Sub(_) => a.wrapping_sub(b),
+                    Ins::Mul(_) => a.wrapping_mul(b),
+                    _ if b == 0 => return Err(InterpError::DivideByZero),
+                    _ if vt.is_signed() => (*a as i64).wrapping_div(b as i64) as u64,
+                    _ => *a / b
+                });
+            },
+            Ins::Eq(vt) | Ins::Ne(vt) | Ins::Lt(vt) | Ins::Le(vt) | Ins::Gt(vt) | Ins::Ge(vt) => {
+                let b = pop(stack)?;
+                let a = pop(stack)?;
+                let ordering = match vt.is_signed() {
+                    true => (a as i64).cmp(&(b as i64)),
+                    false => a.cmp(&b)
+                };
+
+                let result = match ins {
+                    Ins::Eq(_) => ordering.is_eq(),
+                    Ins::Ne(_) => ordering.is_ne(),
+                    Ins::Lt(_) => ordering.is_lt(),
+                    Ins::Le(_) => ordering.is_le(),
+                    Ins::Gt(_) => ordering.is_gt(),
+                    _ => ordering.is_ge()
+                };
+                stack.push(Entry::Value(result as u64));
+            },
+            Ins::BoolAnd | Ins::BoolOr => {
+                let b = pop(stack)? != 0;
+                let a = top(stack)?;
+                *a = match ins {
+                    Ins::BoolAnd => (*a != 0 && b) as u64,
+                    _ => (*a != 0 || b) as u64
+                };
+            },
+            Ins::Loop(code, cond, inc) => loop {
+                match self.evaluate(cond, frame)? {
+                    Ok(0) => break,
+                    Ok(_) => {},
+                    Err(flow) => return Ok(flow)
+                }
+
+                match self.run_block(code, frame, stack)? {
+                    Flow::Next | Flow::Continue(0) => {},
+                    Flow::Break(0) => break,
+                    flow => return flow.leave()
+                }
+
+                match self.run_block(inc, frame, stack)? {
+                    Flow::Next => {},
+                    flow => return Ok(flow)
+                }
+            },
+            Ins::If(code, cond) => {
+                match self.evaluate(cond, frame)? {
+                    Ok(0) => {},
+                    Ok(_) => match self.run_block(code, frame, stack)? {
+                        Flow::Next => {},
+                        flow => return flow.leave()
+                    },
+                    Err(flow) => return Ok(flow)
+                }
+            },
+            Ins::IfElse(_, true_then, else_then, cond) => {
+                let code = match self.evaluate(cond, frame)? {
+                    Ok(0) => else_then,
+                    Ok(_) => true_then,
+                    Err(flow) => return Ok(flow)
+                };
+
+                // The branch runs on a stack of its own, and may leave a value
+                let mut branch_stack = Vec::new();
+                match self.run_block(code, frame, &mut branch_stack)? {
+                    Flow::Next => {},
+                    flow => return flow.leave()
+                }
+
+                if let Some(value) = branch_stack.pop() {
+                    stack.push(value);
+                }
+            },
+            Ins::Switch(_, cases, default, value) => {
+                let value = match self.evaluate(value, frame)? {
+                    Ok(x) => x,
+                    Err(flow) => return Ok(flow)
+                };
+
+                let code = match cases.iter().find(|(values, _)| values.contains(&value)) {
+                    Some((_, code)) => code,
+                    None => default
+                };
+
+                match self.run_block(code, frame, stack)? {
+                    Flow::Next => {},
+                    flow => return flow.leave()
+                }
+            },
+            Ins::Asm(_) => return Err(InterpError::Asm),
+            Ins::Break(depth) => return Ok(Flow::Break(*depth)),
+            Ins::Continue(depth) => return Ok(Flow::Continue(*depth)),
+            Ins::PushLiteral(vt, value) => stack.push(Entry::Value(wrap(vt, *value))),
+            Ins::Drop => {
+                if stack.pop().is_none() { return Err(InterpError::InvalidCode) }
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    /// Pops the reference and indices the path needs, checking that each index is in bounds of its slice
+    fn resolve(&self, path: &ValuePath, frame: &Frame, stack: &mut Vec<Entry>) -> Result<Place, InterpError> {
+        let mut place = Place {
+            root: match path.origin() {
+                ValuePathOrigin::Local(idx, _) => Root::Local(idx.idx()),
+                ValuePathOrigin::Global(idx, _) => Root::Object(idx.idx() as u64 + 1),
+                ValuePathOrigin::Deref(_) => {
+                    let object = pop(stack)?;
+                    self.object(object)?;
+                    Root::Object(object)
+                }
+            },
+            steps: Vec::new(),
+            length: false
+        };
+
+        for component in path.components() {
+            match component {
+                ValuePathComponent::Property(idx, _, _) => place.steps.push(idx.idx()),
+                ValuePathComponent::Length => place.length = true,
+                ValuePathComponent::Slice(_) => {
+                    let index = pop(stack)?;
+                    let (data, start, len) = match self.cell(&place, frame)? {
+                        Cell::Slice(data, start, len) => (*data, *start, *len),
+                        _ => return Err(InterpError::InvalidCode)
+                    };
+
+                    if index >= len as u64 {
+                        return Err(InterpError::IndexOutOfBounds(index, len));
+                    }
+
+                    self.object(data)?;
+                    place.root = Root::Object(data);
+                    place.steps = vec![ start + index as usize ];
+                }
+            }
+        }
+
+        Ok(place)
+    }
+
+    fn object(&self, object: u64) -> Result<&Cell, InterpError> {
+        if object == 0 { return Err(InterpError::NullDereference) }
+
+        match self.objects.get(object as usize - 1) {
+            Some(Some(cell)) => Ok(cell),
+            Some(None) => Err(InterpError::UseAfterFree),
+            None => Err(InterpError::InvalidCode)
+        }
+    }
+
+    fn cell<'b>(&'b self, place: &Place, frame: &'b Frame) -> Result<&'b Cell, InterpError> {
+        let mut cell = match place.root {
+            Root::Local(idx) => match frame.locals.get(idx) {
+                Some(x) => x,
+                None => return Err(InterpError::InvalidCode)
+            },
+            Root::Object(object) => self.object(object)?
+        };
+
+        for step in &place.steps {
+            cell = match cell {
+                Cell::Struct(cells) | Cell::SliceData(cells) => match cells.get(*step) {
+                    Some(x) => x,
+                    None => return Err(InterpError::InvalidCode)
+                },
+                _ => return Err(InterpError::InvalidCode)
+            };
+        }
+
+        Ok(cell)
+    }
+
+    fn cell_mut<'b>(&'b mut self, place: &Place, frame: &'b mut Frame) -> Result<&'b mut Cell, InterpError> {
+        let mut cell = match place.root {
+            Root::Local(idx) => match frame.locals.get_mut(idx) {
+                Some(x) => x,
+                None => return Err(InterpError::InvalidCode)
+            },
+            Root::Object(object) => {
+                self.object(object)?;
+                match self.objects.get_mut(object as usize - 1) {
+                    Some(Some(x)) => x,
+                    _ => return Err(InterpError::InvalidCode)
+                }
+            }
+        };
+
+        for step in &place.steps {
+            cell = match cell {
+                Cell::Struct(cells) | Cell::SliceData(cells) => match cells.get_mut(*step) {
+                    Some(x) => x,
+                    None => return Err(InterpError::InvalidCode)
+                },
+                _ => return Err(InterpError::InvalidCode)
+            };
+        }
+
+        Ok(cell)
+    }
+
+    fn alloc(&mut self, cell: Cell) -> u64 {
+        self.objects.push(Some(cell));
+        self.objects.len() as u64
+    }
+
+    /// Frees the object, unless it is null. Globals are not on the heap, so can not be freed.
+    fn free(&mut self, object: u64) -> Result<(), InterpError> {
+        if object == 0 { return Ok(()) }
+        if object as usize <= self.unit.global_count() { return Err(InterpError::InvalidFree) }
+
+        match self.objects.get_mut(object as usize - 1) {
+            Some(x @ Some(_)) => {
+                *x = None;
+                Ok(())
+            },
+            _ => Err(InterpError::InvalidFree)
+        }
+    }
+}
+
+impl TranslationUnit {
+    /// Runs the unit from its entry function, giving the code it exits with
+    pub fn interpret(&self, host: &mut dyn InterpHost) -> Result<i32, InterpError> {
+        Interpreter::new(self, host).run_entry()
+    }
+}
+
+fn pop(stack: &mut Vec<Entry>) -> Result<u64, InterpError> {
+    match stack.pop() {
+        Some(Entry::Value(value)) => Ok(value),
+        _ => Err(InterpError::InvalidCode)
+    }
+}
+
+fn pop_path(stack: &mut Vec<Entry>) -> Result<Place, InterpError> {
+    match stack.pop() {
+        Some(Entry::Path(place)) => Ok(place),
+        _ => Err(InterpError::InvalidCode)
+    }
+}
+
+fn top(stack: &mut [Entry]) -> Result<&mut u64, InterpError> {
+    match stack.last_mut() {
+        Some(Entry::Value(value)) => Ok(value),
+        _ => Err(InterpError::InvalidCode)
+    }
+}
+
+/// Pops the given number of values, giving them in the order they were pushed
+fn pop_values(stack: &mut Vec<Entry>, count: usize) -> Result<Vec<u64>, InterpError> {
+    if stack.len() < count { return Err(InterpError::InvalidCode) }
+
+    let mut values = Vec::new();
+    for entry in stack.split_off(stack.len() - count) {
+        match entry {
+            Entry::Value(value) => values.push(value),
+            Entry::Path(_) => return Err(InterpError::InvalidCode)
+        }
+    }
+
+    Ok(values)
+}
+
+/// Truncates the bits to the width of the type, then sign or zero extends them back to 64 bits, which is how every value
+/// is held
+pub(crate) fn wrap(vt: &ValueType, value: u64) -> u64 {
+    let shift = match vt {
+        ValueType::U8 | ValueType::I8 | ValueType::Bool => 56,
+        ValueType::U16 | ValueType::I16 => 48,
+        ValueType::U32 | ValueType::I32 => 32,
+        _ => return value
+    };
+
+    match vt.is_signed() {
+        true => ((value << shift) as i64 >> shift) as u64,
+        false => (value << shift) >> shift
+    }
+}
+
+fn default_cell(storable_type: &StorableType) -> Cell {
+    match storable_type {
+        StorableType::Value(_) => Cell::Value(0),
+        StorableType::Compound(ct) => match ct.content() {
+            CompoundContent::Struct(s) => Cell::Struct(s.props().iter().map(|prop| default_cell(prop.prop_type())).collect())
+        },
+        StorableType::Slice(_) => Cell::Slice(0, 0, 0),
+        StorableType::SliceData(_) => Cell::SliceData(Vec::new())
+    }
+}
+
+/// The cell a global starts with, references to globals being to their object
+fn stored_cell(value: &StorableValue) -> Cell {
+    match value {
+        StorableValue::Value(value) => Cell::Value(match value {
+            Value::U8(x) => *x as u64,
+            Value::I8(x) => *x as i64 as u64,
+            Value::U16(x) => *x as u64,
+            Value::I16(x) => *x as i64 as u64,
+            Value::U32(x) => *x as u64,
+            Value::I32(x) => *x as i64 as u64,
+            Value::U64(x) => *x,
+            Value::I64(x) => *x as u64,
+            Value::UPtr(x) => *x as u64,
+            Value::IPtr(x) => *x as i64 as u64,
+            Value::Bool(x) => *x as u64,
+            Value::Ref(global) => global.idx() as u64 + 1
+        }),
+        StorableValue::Compound(CompoundValue::Struct(s)) => Cell::Struct(s.props().iter().map(|prop| stored_cell(prop.value())).collect()),
+        StorableValue::Slice(global, start, len) => Cell::Slice(global.idx() as u64 + 1, *start, *len),
+        StorableValue::SliceData(values) => Cell::SliceData(values.iter().map(stored_cell).collect())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+use crate::{CompoundContent, CompoundType, PropertyIndex, StorableType, StructContent, StructRepr, ValueType};
+
+/// Rounds `offset` up to the next multiple of `align`, which must be a power of two
+fn align_to(offset: usize, align: usize) -> usize {
+    (offset + align - 1) & !(align - 1)
+}
+
+impl ValueType {
+    /// The size of the value in memory, on a target whose pointers are `ptr_size` bytes
+    pub fn size(&self, ptr_size: usize) -> usize {
+        match self {
+            ValueType::U8 | ValueType::I8 | ValueType::Bool => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 => 4,
+            ValueType::U64 | ValueType::I64 => 8,
+            ValueType::UPtr | ValueType::IPtr | ValueType::Ref(_) | ValueType::Index(_) => ptr_size,
+        }
+    }
+
+    /// Values are naturally aligned, so this is always the same as the size
+    pub fn align(&self, ptr_size: usize) -> usize {
+        self.size(ptr_size)
+    }
+}
+
+impl StorableType {
+    pub fn size(&self, ptr_size: usize) -> usize {
+        match self {
+            StorableType::Compound(ct) => ct.size(ptr_size),
+            StorableType::Value(vt) => vt.size(ptr_size),
+            StorableType::Slice(_) => ptr_size * 2,
+            StorableType::SliceData(_) => panic!("Cannot compute raw size of SliceData type"),
+        }
+    }
+
+    pub fn align(&self, ptr_size: usize) -> usize {
+        match self {
+            StorableType::Compound(ct) => ct.align(ptr_size),
+            StorableType::Value(vt) => vt.align(ptr_size),
+            StorableType::Slice(_) => ptr_size,
+            StorableType::SliceData(st) => st.align(ptr_size),
+        }
+    }
+}
+
+/// Where each property of a struct is placed, along with the size and alignment of the struct as a whole
+struct StructLayout {
+    offsets: Vec<usize>,
+    size: usize,
+    align: usize
+}
+
+impl StructContent {
+    fn layout(&self, ptr_size: usize) -> StructLayout {
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        let mut align = 1;
+
+        for prop in self.props() {
+            let prop_align = match self.repr() {
+                StructRepr::C => prop.prop_type().align(ptr_size),
+                StructRepr::Default | StructRepr::Packed => 1
+            };
+
+            offset = align_to(offset, prop_align);
+            offsets.push(offset);
+            offset += prop.prop_type().size(ptr_size);
+            align = align.max(prop_align);
+        }
+
+        if let Some(min_align) = self.min_align() {
+            align = align.max(min_align);
+        }
+
+        StructLayout {
+            offsets,
+            size: align_to(offset, align),
+            align
+        }
+    }
+}
+
+impl CompoundType {
+    fn layout(&self, ptr_size: usize) -> StructLayout {
+        match self.content() {
+            CompoundContent::Struct(s) => s.layout(ptr_size)
+        }
+    }
+
+    /// The size of the type in memory, including any padding at its end, on a target whose pointers are `ptr_size` bytes
+    pub fn size(&self, ptr_size: usize) -> usize {
+        self.layout(ptr_size).size
+    }
+
+    pub fn align(&self, ptr_size: usize) -> usize {
+        self.layout(ptr_size).align
+    }
+
+    /// The offset of every property from the start of the type, in the order they are declared
+    pub fn prop_offsets(&self, ptr_size: usize) -> Vec<usize> {
+        self.layout(ptr_size).offsets
+    }
+
+    pub fn prop_offset(&self, idx: PropertyIndex, ptr_size: usize) -> usize {
+        self.layout(ptr_size).offsets[idx.idx()]
+    }
+}
\ No newline at end of file
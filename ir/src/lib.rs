@@ -4,6 +4,13 @@ mod validate;
 mod types;
 mod display;
 mod global;
+mod asm;
+mod layout;
+mod text;
+mod binary;
+mod interp;
+pub mod opt;
+pub mod ssa;
 
 #[cfg(test)]
 mod tests;
@@ -13,4 +20,8 @@ pub use unit::*;
 pub use types::*;
 pub use display::*;
 pub use global::*;
-pub use validate::*;
\ No newline at end of file
+pub use validate::*;
+pub use asm::*;
+pub use text::*;
+pub use binary::*;
+pub use interp::*;
\ No newline at end of file
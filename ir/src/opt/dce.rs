@@ -0,0 +1,263 @@
+use crate::{CompoundValue, FunctionIndex, Global, GlobalIndex, Ins, StorableValue, StructPropertyValue, StructValue, TranslationUnit, Value, ValuePath, ValuePathOrigin};
+
+use super::{Pass, blocks, blocks_mut};
+
+/// Removes code after a return, break or continue, and every function and global which cannot be reached from the entry
+/// point, exported functions or named globals, giving those left new indices in the same order. Unnamed read only globals
+/// with the same value are merged first, so that the same string in many places is only stored once. A unit without an
+/// entry point is a library, which keeps all of its functions, as any of them may be used once it is linked.
+pub struct DeadCode;
+
+impl Pass for DeadCode {
+    fn name(&self) -> &str {
+        "dead-code"
+    }
+
+    fn run(&mut self, unit: &mut TranslationUnit) -> bool {
+        let mut changed = false;
+
+        for f in 0..unit.function_count() {
+            let function = unit.get_function_mut(FunctionIndex::new(f)).unwrap();
+            if function.is_extern() { continue; }
+
+            changed |= remove_unreachable(function.code_mut());
+        }
+
+        let canonical = merge_globals(unit);
+        let Reach { functions, globals, .. } = Reach::find(unit, &canonical);
+
+        if functions.iter().all(|reached| *reached) && globals.iter().all(|reached| *reached) {
+            return changed;
+        }
+
+        // The index of each function and global which is kept, once those before it are removed
+        let function_map = new_indices(&functions);
+        let global_map = new_indices(&globals);
+        let global_map = canonical.iter().map(|g| global_map[*g]).collect::<Vec<_>>();
+
+        let mut f = 0;
+        unit.functions_mut().retain(|_| { f += 1; functions[f - 1] });
+        for function in unit.functions_mut() {
+            if function.is_extern() { continue; }
+            remap_block(function.code_mut(), &function_map, &global_map);
+        }
+
+        let old_globals = std::mem::take(unit.globals_mut());
+        *unit.globals_mut() = old_globals.into_iter().enumerate()
+            .filter(|(g, _)| globals[*g])
+            .map(|(_, global)| remap_global(&global, &global_map))
+            .collect();
+
+        true
+    }
+}
+
+/// Removes the code after each return, break or continue in the block and the blocks in it, which can never run,
+/// returning whether anything was removed
+fn remove_unreachable(code: &mut Vec<Ins>) -> bool {
+    let mut changed = false;
+
+    if let Some(end) = code.iter().position(|ins| matches!(ins, Ins::Ret | Ins::Break(_) | Ins::Continue(_))) {
+        changed = end + 1 < code.len();
+        code.truncate(end + 1);
+    }
+
+    for ins in code.iter_mut() {
+        for block in blocks_mut(ins) {
+            changed |= remove_unreachable(block);
+        }
+    }
+
+    changed
+}
+
+/// Gives the index of the global each global is merged into, which is the first unnamed read only global with the same
+/// type and value, counting references to globals which are merged as the same
+fn merge_globals(unit: &TranslationUnit) -> Vec<usize> {
+    let globals = unit.globals();
+    let mut canonical = (0..globals.len()).collect::<Vec<_>>();
+
+    // Merging a global may make those which refer to it the same, such as the slice of the bytes of a string
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for g in 0..globals.len() {
+            if canonical[g] != g || !is_mergeable(&globals[g]) { continue; }
+
+            let same = (0..g).find(|h| {
+                canonical[*h] == *h && is_mergeable(&globals[*h]) &&
+                globals[*h].global_type() == globals[g].global_type() &&
+                same_value(globals[*h].default().unwrap(), globals[g].default().unwrap(), &canonical)
+            });
+
+            if let Some(h) = same {
+                for target in canonical.iter_mut() {
+                    if *target == g { *target = h; }
+                }
+                changed = true;
+            }
+        }
+    }
+
+    canonical
+}
+
+/// Whether the global may be merged with another, which it can be if nothing may write to it or refer to it by name
+fn is_mergeable(global: &Global) -> bool {
+    global.is_read_only() && global.name().is_none() && global.default().is_some()
+}
+
+fn same_value(a: &StorableValue, b: &StorableValue, canonical: &[usize]) -> bool {
+    match (a, b) {
+        (StorableValue::Compound(CompoundValue::Struct(a)), StorableValue::Compound(CompoundValue::Struct(b))) =>
+            a.props().len() == b.props().len() &&
+            a.props().iter().zip(b.props()).all(|(a, b)| same_value(a.value(), b.value(), canonical)),
+        (StorableValue::Value(Value::Ref(a)), StorableValue::Value(Value::Ref(b))) => canonical[a.idx()] == canonical[b.idx()],
+        (StorableValue::Value(a), StorableValue::Value(b)) => a == b,
+        (StorableValue::Slice(a, a_start, a_len), StorableValue::Slice(b, b_start, b_len)) =>
+            canonical[a.idx()] == canonical[b.idx()] && a_start == b_start && a_len == b_len,
+        (StorableValue::SliceData(a), StorableValue::SliceData(b)) =>
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b, canonical)),
+        _ => false
+    }
+}
+
+/// Which functions and globals can be reached, where each global is counted as the one it is merged into
+struct Reach<'a> {
+    unit: &'a TranslationUnit,
+    canonical: &'a [usize],
+    functions: Vec<bool>,
+    globals: Vec<bool>
+}
+
+impl<'a> Reach<'a> {
+    fn find(unit: &'a TranslationUnit, canonical: &'a [usize]) -> Reach<'a> {
+        let mut reach = Reach {
+            unit, canonical,
+            functions: vec![ false; unit.function_count() ],
+            globals: vec![ false; unit.global_count() ]
+        };
+
+        // Code outside the unit may call exported functions and refer to named globals, as they are given symbols
+        let is_library = !unit.functions().iter().any(|function| function.is_entry());
+        for (f, function) in unit.functions().iter().enumerate() {
+            if is_library || function.is_entry() || function.is_export() {
+                reach.function(f);
+            }
+        }
+
+        for (g, global) in unit.globals().iter().enumerate() {
+            if global.name().is_some() {
+                reach.global(g);
+            }
+        }
+
+        reach
+    }
+
+    fn function(&mut self, f: usize) {
+        if self.functions[f] { return; }
+        self.functions[f] = true;
+
+        if let Some(code) = self.unit.functions()[f].code_opt() {
+            self.block(code);
+        }
+    }
+
+    fn function_opt(&mut self, idx: Option<FunctionIndex>) {
+        if let Some(idx) = idx {
+            self.function(idx.idx());
+        }
+    }
+
+    fn global(&mut self, g: usize) {
+        let g = self.canonical[g];
+        if self.globals[g] { return; }
+        self.globals[g] = true;
+
+        if let Some(default) = self.unit.globals()[g].default() {
+            self.value(default);
+        }
+    }
+
+    fn value(&mut self, value: &StorableValue) {
+        match value {
+            StorableValue::Compound(CompoundValue::Struct(value)) => for prop in value.props() {
+                self.value(prop.value());
+            },
+            StorableValue::Value(Value::Ref(idx)) | StorableValue::Slice(idx, _, _) => self.global(idx.idx()),
+            StorableValue::Value(_) => {},
+            StorableValue::SliceData(values) => for value in values {
+                self.value(value);
+            }
+        }
+    }
+
+    fn block(&mut self, code: &[Ins]) {
+        for ins in code {
+            match ins {
+                Ins::Call(idx) => self.function(idx.idx()),
+                Ins::PushPath(path, _) => if let ValuePathOrigin::Global(idx, _) = path.origin() {
+                    self.global(idx.idx());
+                },
+                // The target calls the functions which allocate and free for each instruction which does
+                Ins::New(_) => self.function_opt(self.unit.find_alloc()),
+                Ins::NewSlice(_) => self.function_opt(self.unit.find_alloc_slice()),
+                Ins::Free(_) => self.function_opt(self.unit.find_free()),
+                Ins::FreeSlice(_) => self.function_opt(self.unit.find_free_slice()),
+                _ => {}
+            }
+
+            for block in blocks(ins) {
+                self.block(block);
+            }
+        }
+    }
+}
+
+/// The new index of each item which is kept, once those before it which are not are removed
+fn new_indices(kept: &[bool]) -> Vec<usize> {
+    let mut next = 0;
+    kept.iter().map(|kept| {
+        next += *kept as usize;
+        next.saturating_sub(1)
+    }).collect()
+}
+
+fn remap_block(code: &mut Vec<Ins>, function_map: &[usize], global_map: &[usize]) {
+    for ins in code.iter_mut() {
+        match ins {
+            Ins::Call(idx) => *idx = FunctionIndex::new(function_map[idx.idx()]),
+            Ins::PushPath(path, _) => if let ValuePathOrigin::Global(idx, st) = path.origin() {
+                let origin = ValuePathOrigin::Global(GlobalIndex::new(global_map[idx.idx()]), st.clone());
+                *path = ValuePath::new(origin, path.components().clone());
+            },
+            _ => {}
+        }
+
+        for block in blocks_mut(ins) {
+            remap_block(block, function_map, global_map);
+        }
+    }
+}
+
+fn remap_global(global: &Global, global_map: &[usize]) -> Global {
+    match (global.default(), global.is_read_only()) {
+        (Some(default), true) => Global::new_read_only(global.name(), global.global_type().clone(), remap_value(default, global_map)),
+        (Some(default), false) => Global::new_default(global.name(), global.global_type().clone(), remap_value(default, global_map)),
+        (None, _) => Global::new(global.name(), global.global_type().clone())
+    }
+}
+
+fn remap_value(value: &StorableValue, global_map: &[usize]) -> StorableValue {
+    match value {
+        StorableValue::Compound(CompoundValue::Struct(value)) => StorableValue::Compound(CompoundValue::Struct(StructValue::new(
+            value.props().iter().map(|prop| StructPropertyValue::new(remap_value(prop.value(), global_map))).collect()
+        ))),
+        StorableValue::Value(Value::Ref(idx)) => StorableValue::Value(Value::Ref(GlobalIndex::new(global_map[idx.idx()]))),
+        StorableValue::Value(value) => StorableValue::Value(value.clone()),
+        StorableValue::Slice(idx, start, len) => StorableValue::Slice(GlobalIndex::new(global_map[idx.idx()]), *start, *len),
+        StorableValue::SliceData(values) => StorableValue::SliceData(values.iter().map(|value| remap_value(value, global_map)).collect())
+    }
+}
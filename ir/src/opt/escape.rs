@@ -0,0 +1,180 @@
+use crate::{CompoundContent, Function, FunctionIndex, Ins, Local, LocalIndex, PropertyIndex, StorableType, TranslationUnit, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
+
+use super::{Pass, blocks, blocks_mut, contains_asm};
+
+/// Puts objects which never escape the function that makes them on the stack. An object escapes unless the only
+/// reference to it is kept in one local, which is only ever used to reach into the object or free it, so the reference is
+/// never stored to a global or field, passed to a call or returned. That local is given the type of the object instead,
+/// which the target keeps in the frame, each `New` which made it is replaced by zeroing the local, and each `Free` of it
+/// is removed. Objects holding references or slices are left on the heap, as a collector could not see those in a frame.
+pub struct StackAllocate;
+
+impl Pass for StackAllocate {
+    fn name(&self) -> &str {
+        "stack-allocate"
+    }
+
+    fn run(&mut self, unit: &mut TranslationUnit) -> bool {
+        let mut changed = false;
+
+        for f in 0..unit.function_count() {
+            let function = unit.get_function_mut(FunctionIndex::new(f)).unwrap();
+            if function.is_extern() || contains_asm(function.code()) { continue; }
+
+            let mut objects = candidates(function);
+            check_block(function.code(), &mut objects);
+            if objects.iter().all(Option::is_none) { continue; }
+
+            let mut code = std::mem::take(function.code_mut());
+            rewrite_block(&mut code, &objects);
+            *function.code_mut() = code;
+
+            for (l, object) in objects.into_iter().enumerate() {
+                if let Some(object) = object {
+                    function.locals_mut()[l] = Local::new(object);
+                }
+            }
+
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+/// The type of the object each local may refer to, for those which are not params and may refer to an object which can
+/// be kept on the stack
+fn candidates(function: &Function) -> Vec<Option<StorableType>> {
+    function.locals().iter().enumerate().map(|(l, local)| match local.local_type() {
+        StorableType::Value(ValueType::Ref(object)) if l >= function.signature().param_count() && is_plain(object) =>
+            Some(object.as_ref().clone()),
+        _ => None
+    }).collect()
+}
+
+/// Whether the type is a struct of numbers and bools, or of structs which are, so it can be zeroed one value at a time
+fn is_plain(st: &StorableType) -> bool {
+    match st {
+        StorableType::Compound(ct) => match ct.content() {
+            CompoundContent::Struct(content) => content.props().iter().all(|prop| match prop.prop_type() {
+                StorableType::Value(vt) => vt.is_num(),
+                prop_type => is_plain(prop_type)
+            })
+        },
+        _ => false
+    }
+}
+
+/// The local a path refers to the whole of, if it does
+fn whole_local(path: &ValuePath) -> Option<LocalIndex> {
+    match path.origin() {
+        ValuePathOrigin::Local(idx, _) if path.component_count() == 0 => Some(*idx),
+        _ => None
+    }
+}
+
+/// The local each instruction stores a new object in, when the instruction starts one of the two ways of doing so
+fn new_into(code: &[Ins]) -> Option<(LocalIndex, usize)> {
+    match code {
+        [ Ins::PushPath(path, _), Ins::New(_), Ins::Pop(_), .. ] => whole_local(path).map(|idx| (idx, 3)),
+        [ Ins::New(_), Ins::PopLocal(idx, _), .. ] => Some((*idx, 2)),
+        _ => None
+    }
+}
+
+/// Stops any local used in the block in a way other than storing a new object in it, reaching into the object it refers
+/// to or freeing that object from being a candidate
+fn check_block(code: &[Ins], objects: &mut [Option<StorableType>]) {
+    let mut i = 0;
+    while i < code.len() {
+        if let Some((_, len)) = new_into(&code[i..]) {
+            i += len;
+            continue;
+        }
+
+        match &code[i..] {
+            // The reference is used straight away, either by the path which follows or by freeing it
+            [ Ins::PushPath(path, _), Ins::Push(_), Ins::PushPath(next, _), .. ]
+                if whole_local(path).is_some() && matches!(next.origin(), ValuePathOrigin::Deref(_)) => i += 2,
+            [ Ins::PushPath(path, _), Ins::Push(_), Ins::Free(_), .. ] if whole_local(path).is_some() => i += 3,
+            [ ins, .. ] => {
+                match ins {
+                    Ins::PushPath(path, _) => if let ValuePathOrigin::Local(idx, _) = path.origin() {
+                        objects[idx.idx()] = None;
+                    },
+                    Ins::PopLocal(idx, _) => objects[idx.idx()] = None,
+                    _ => {}
+                }
+
+                for block in blocks(ins) {
+                    check_block(block, objects);
+                }
+
+                i += 1;
+            },
+            [] => unreachable!()
+        }
+    }
+}
+
+/// Replaces each use of a local whose object is kept on the stack with the use of the object in the local
+fn rewrite_block(code: &mut Vec<Ins>, objects: &[Option<StorableType>]) {
+    let old = std::mem::take(code);
+    let mut i = 0;
+    while i < old.len() {
+        if let Some((idx, len)) = new_into(&old[i..]) {
+            if let Some(object) = &objects[idx.idx()] {
+                zero(idx, object, code);
+                i += len;
+                continue;
+            }
+        }
+
+        let object = match &old[i..] {
+            [ Ins::PushPath(path, _), Ins::Push(_), .. ] => whole_local(path).and_then(|idx| {
+                objects[idx.idx()].as_ref().map(|object| (idx, object))
+            }),
+            _ => None
+        };
+
+        match (object, &old[i..]) {
+            (Some((idx, object)), [ _, _, Ins::PushPath(next, vt), .. ]) => {
+                let origin = ValuePathOrigin::Local(idx, object.clone());
+                code.push(Ins::PushPath(ValuePath::new(origin, next.components().clone()), vt.clone()));
+                i += 3;
+            },
+            (Some(_), [ _, _, Ins::Free(_), .. ]) => i += 3,
+            _ => {
+                let mut ins = old[i].clone();
+                for block in blocks_mut(&mut ins) {
+                    rewrite_block(block, objects);
+                }
+                code.push(ins);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Sets each number and bool in the object kept in the local to zero, as it would be in a new object
+fn zero(idx: LocalIndex, object: &StorableType, code: &mut Vec<Ins>) {
+    zero_values(&ValuePathOrigin::Local(idx, object.clone()), object, &mut Vec::new(), code);
+}
+
+fn zero_values(origin: &ValuePathOrigin, st: &StorableType, components: &mut Vec<ValuePathComponent>, code: &mut Vec<Ins>) {
+    match st {
+        StorableType::Compound(ct) => match ct.content() {
+            CompoundContent::Struct(content) => for (p, prop) in content.props().iter().enumerate() {
+                components.push(ValuePathComponent::Property(PropertyIndex::new(p), ct.clone(), prop.prop_type().clone()));
+                zero_values(origin, prop.prop_type(), components, code);
+                components.pop();
+            }
+        },
+        StorableType::Value(vt) => {
+            code.push(Ins::PushPath(ValuePath::new(origin.clone(), components.clone()), vt.clone()));
+            code.push(Ins::PushLiteral(vt.clone(), 0));
+            code.push(Ins::Pop(vt.clone()));
+        },
+        _ => unreachable!("Only plain objects are kept on the stack")
+    }
+}
@@ -0,0 +1,274 @@
+use crate::interp::wrap;
+use crate::{Function, FunctionIndex, Ins, LocalIndex, StorableType, TranslationUnit, ValuePath, ValuePathOrigin, ValueType};
+
+use super::{Pass, blocks, blocks_mut};
+
+/// Folds arithmetic, comparisons and conversions of literals into the literal they give, and replaces each read of a
+/// local with the literal it holds, where the local is assigned that literal once, before any of the reads
+pub struct ConstantFold {
+    ptr_size: usize
+}
+
+impl ConstantFold {
+    /// Folds for a target whose pointers are `ptr_size` bytes, which `uptr` and `iptr` wrap to
+    pub fn new(ptr_size: usize) -> ConstantFold {
+        ConstantFold {
+            ptr_size
+        }
+    }
+}
+
+impl Pass for ConstantFold {
+    fn name(&self) -> &str {
+        "constant-fold"
+    }
+
+    fn run(&mut self, unit: &mut TranslationUnit) -> bool {
+        let mut changed = false;
+
+        for f in 0..unit.function_count() {
+            let function = unit.get_function_mut(FunctionIndex::new(f)).unwrap();
+            if function.is_extern() { continue; }
+
+            changed |= propagate_locals(function, self.ptr_size);
+            changed |= fold_block(function.code_mut(), self.ptr_size);
+        }
+
+        changed
+    }
+}
+
+/// Wraps a value to the width of its type, where pointer sized types are `ptr_size` bytes
+fn wrap_to(vt: &ValueType, value: u64, ptr_size: usize) -> u64 {
+    match vt {
+        ValueType::UPtr if ptr_size == 4 => wrap(&ValueType::U32, value),
+        ValueType::IPtr if ptr_size == 4 => wrap(&ValueType::I32, value),
+        _ => wrap(vt, value)
+    }
+}
+
+/// Folds the block and every block nested in it, returning whether anything was folded
+fn fold_block(code: &mut Vec<Ins>, ptr_size: usize) -> bool {
+    let mut changed = false;
+
+    for ins in code.iter_mut() {
+        for block in blocks_mut(ins) {
+            changed |= fold_block(block, ptr_size);
+        }
+    }
+
+    let mut i = 0;
+    while i < code.len() {
+        match fold_at(code, i, ptr_size) {
+            // The literal may be an operand of the instruction after it, so that is looked at next
+            Some((start, literal)) => {
+                code.splice(start..=i, std::iter::once(literal));
+                i = start + 1;
+                changed = true;
+            },
+            None => i += 1
+        }
+    }
+
+    changed
+}
+
+/// The value of the literal at the index, in the form it is held in, if the instruction there is a literal
+fn literal_at(code: &[Ins], idx: usize, ptr_size: usize) -> Option<u64> {
+    match code.get(idx) {
+        Some(Ins::PushLiteral(vt, value)) => Some(wrap_to(vt, *value, ptr_size)),
+        _ => None
+    }
+}
+
+/// If the instruction at the index only operates on literals just before it, gives the index of the first of them and the
+/// literal the instructions can be replaced with
+fn fold_at(code: &[Ins], i: usize, ptr_size: usize) -> Option<(usize, Ins)> {
+    match &code[i] {
+        Ins::Inc(vt, _) | Ins::Dec(vt, _) | Ins::Neg(vt) | Ins::Convert(_, vt) => {
+            let a = literal_at(code, i.checked_sub(1)?, ptr_size)?;
+            let result = match &code[i] {
+                Ins::Inc(_, amount) => a.wrapping_add(*amount),
+                Ins::Dec(_, amount) => a.wrapping_sub(*amount),
+                Ins::Neg(_) => a.wrapping_neg(),
+                _ => match vt {
+                    ValueType::Bool => (a != 0) as u64,
+                    x if x.is_num() => a,
+                    _ => return None
+                }
+            };
+
+            Some((i - 1, Ins::PushLiteral(vt.clone(), wrap_to(vt, result, ptr_size))))
+        },
+        Ins::Add(vt) | Ins::Sub(vt) | Ins::Mul(vt) | Ins::Div(vt) => {
+            let b = literal_at(code, i.checked_sub(1)?, ptr_size)?;
+            let a = literal_at(code, i.checked_sub(2)?, ptr_size)?;
+            let result = match &code[i] {
+                Ins::Add(_) => a.wrapping_add(b),
+                Ins::Sub(_) => a.wrapping_sub(b),
+                Ins::Mul(_) => a.wrapping_mul(b),
+                // Dividing by zero, or overflowing a signed division, is left to fault as it would have
+                _ if b == 0 => return None,
+                _ if vt.is_signed() && b == u64::MAX => return None,
+                _ if vt.is_signed() => (a as i64).wrapping_div(b as i64) as u64,
+                _ => a / b
+            };
+
+            Some((i - 2, Ins::PushLiteral(vt.clone(), wrap_to(vt, result, ptr_size))))
+        },
+        Ins::Eq(vt) | Ins::Ne(vt) | Ins::Lt(vt) | Ins::Le(vt) | Ins::Gt(vt) | Ins::Ge(vt) => {
+            let b = literal_at(code, i.checked_sub(1)?, ptr_size)?;
+            let a = literal_at(code, i.checked_sub(2)?, ptr_size)?;
+            let ordering = match vt.is_signed() {
+                true => (a as i64).cmp(&(b as i64)),
+                false => a.cmp(&b)
+            };
+
+            let result = match &code[i] {
+                Ins::Eq(_) => ordering.is_eq(),
+                Ins::Ne(_) => ordering.is_ne(),
+                Ins::Lt(_) => ordering.is_lt(),
+                Ins::Le(_) => ordering.is_le(),
+                Ins::Gt(_) => ordering.is_gt(),
+                _ => ordering.is_ge()
+            };
+
+            Some((i - 2, Ins::PushLiteral(ValueType::Bool, result as u64)))
+        },
+        Ins::BoolAnd | Ins::BoolOr => {
+            let b = literal_at(code, i.checked_sub(1)?, ptr_size)? != 0;
+            let a = literal_at(code, i.checked_sub(2)?, ptr_size)? != 0;
+            let result = match &code[i] {
+                Ins::BoolAnd => a && b,
+                _ => a || b
+            };
+
+            Some((i - 2, Ins::PushLiteral(ValueType::Bool, result as u64)))
+        },
+        _ => None
+    }
+}
+
+/// How a local is used throughout a function
+#[derive(Default)]
+struct LocalUses {
+    /// Each literal assigned to the local in the function's own code, rather than a block, with the index of the assignment
+    literals: Vec<(usize, u64)>,
+    /// The index of the instruction in the function's own code which each read is in, or is
+    reads: Vec<usize>,
+    /// Whether the local is used in any other way, such as being assigned something other than a literal
+    other: bool
+}
+
+/// The local a path leads to, if it leads straight to one
+fn local_of(path: &ValuePath) -> Option<LocalIndex> {
+    match path.origin() {
+        ValuePathOrigin::Local(idx, _) if path.component_count() == 0 => Some(*idx),
+        _ => None
+    }
+}
+
+/// Finds how each local is used in a block. The index is the index of the instruction in the function's own code which
+/// holds the block, or None if the block is the function's own code.
+fn find_uses(code: &[Ins], top: Option<usize>, uses: &mut Vec<LocalUses>, ptr_size: usize) {
+    let mut i = 0;
+    while i < code.len() {
+        let at = top.unwrap_or(i);
+
+        match (&code[i], code.get(i + 1), code.get(i + 2)) {
+            (Ins::PushPath(path, _), Some(Ins::Push(_)), _) if local_of(path).is_some() => {
+                uses[local_of(path).unwrap().idx()].reads.push(at);
+                i += 2;
+                continue;
+            },
+            (Ins::PushPath(path, _), Some(Ins::PushLiteral(vt, value)), Some(Ins::Pop(_))) if top.is_none() && local_of(path).is_some() => {
+                uses[local_of(path).unwrap().idx()].literals.push((at, wrap_to(vt, *value, ptr_size)));
+                i += 3;
+                continue;
+            },
+            (Ins::PushLiteral(vt, value), Some(Ins::PopLocal(idx, _)), _) if top.is_none() => {
+                uses[idx.idx()].literals.push((at, wrap_to(vt, *value, ptr_size)));
+                i += 2;
+                continue;
+            },
+            (Ins::PushPath(path, _), _, _) => if let ValuePathOrigin::Local(idx, _) = path.origin() {
+                uses[idx.idx()].other = true;
+            },
+            (Ins::PopLocal(idx, _), _, _) => uses[idx.idx()].other = true,
+            (Ins::Asm(block), _, _) => for binding in block.inputs().iter().chain(block.outputs().iter()) {
+                uses[binding.local().idx()].other = true;
+            },
+            (ins, _, _) => for block in blocks(ins) {
+                find_uses(block, Some(at), uses, ptr_size);
+            }
+        }
+
+        i += 1;
+    }
+}
+
+/// Replaces each read of a local in the block, and the blocks nested in it, with the literal it holds
+fn replace_reads(code: &mut Vec<Ins>, literals: &[Option<u64>]) {
+    for ins in code.iter_mut() {
+        for block in blocks_mut(ins) {
+            replace_reads(block, literals);
+        }
+    }
+
+    let mut i = 0;
+    while i + 1 < code.len() {
+        let literal = match (&code[i], &code[i + 1]) {
+            (Ins::PushPath(path, _), Ins::Push(vt)) => match local_of(path) {
+                Some(idx) => literals[idx.idx()].map(|value| Ins::PushLiteral(vt.clone(), value)),
+                None => None
+            },
+            _ => None
+        };
+
+        if let Some(literal) = literal {
+            code.splice(i..i + 2, std::iter::once(literal));
+        }
+        i += 1;
+    }
+}
+
+/// Removes the assignment of each local which is replaced by a literal, which are all in the function's own code
+fn remove_assignments(code: &mut Vec<Ins>, literals: &[Option<u64>]) {
+    let mut i = 0;
+    while i < code.len() {
+        let len = match (&code[i], code.get(i + 1), code.get(i + 2)) {
+            (Ins::PushPath(path, _), Some(Ins::PushLiteral(_, _)), Some(Ins::Pop(_))) if local_of(path).is_some_and(|idx| literals[idx.idx()].is_some()) => 3,
+            (Ins::PushLiteral(_, _), Some(Ins::PopLocal(idx, _)), _) if literals[idx.idx()].is_some() => 2,
+            _ => 0
+        };
+
+        if len == 0 {
+            i += 1;
+        } else {
+            code.drain(i..i + len);
+        }
+    }
+}
+
+/// Replaces the reads of each local which is assigned a literal once in the function's own code, where every read comes
+/// after it, with that literal. Params are assigned by the caller, so are left alone. Returns whether anything was replaced.
+fn propagate_locals(function: &mut Function, ptr_size: usize) -> bool {
+    let mut uses = Vec::new();
+    uses.resize_with(function.local_count(), LocalUses::default);
+    find_uses(function.code(), None, &mut uses, ptr_size);
+
+    let literals = uses.iter().enumerate().map(|(l, uses)| {
+        let is_value = matches!(function.locals()[l].local_type(), StorableType::Value(_));
+        match uses.literals.as_slice() {
+            [(at, value)] if is_value && !uses.other && l >= function.signature().param_count() &&
+                !uses.reads.is_empty() && uses.reads.iter().all(|read| read > at) => Some(*value),
+            _ => None
+        }
+    }).collect::<Vec<_>>();
+
+    if literals.iter().all(Option::is_none) { return false; }
+
+    replace_reads(function.code_mut(), &literals);
+    remove_assignments(function.code_mut(), &literals);
+    true
+}
\ No newline at end of file
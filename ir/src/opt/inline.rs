@@ -0,0 +1,223 @@
+use crate::{Function, FunctionIndex, Ins, Local, LocalIndex, StorableType, TranslationUnit, ValuePath, ValuePathOrigin, ValueType};
+
+use super::{Pass, blocks, blocks_mut, contains_asm};
+
+/// How many instructions, counting those in blocks, a function may have to be inlined without being marked to be
+pub const INLINE_SIZE: usize = 16;
+
+/// Puts the code of small functions, and those marked to be inlined, in place of each call to them. The arguments are
+/// popped into locals of the caller which stand in for the callee's, and the code is run in a loop which each return
+/// breaks out of, having popped the returned values into locals to be pushed once the loop is done.
+pub struct Inline {
+    max_size: usize
+}
+
+impl Inline {
+    /// Inlines functions with at most the given number of instructions, or any number if they are marked to be
+    pub fn new(max_size: usize) -> Inline {
+        Inline {
+            max_size
+        }
+    }
+}
+
+impl Pass for Inline {
+    fn name(&self) -> &str {
+        "inline"
+    }
+
+    fn run(&mut self, unit: &mut TranslationUnit) -> bool {
+        let callees = unit.functions().iter().map(|function| match can_inline(function, self.max_size) {
+            true => Some(Callee::new(function)),
+            false => None
+        }).collect::<Vec<_>>();
+
+        let mut changed = false;
+        for f in 0..unit.function_count() {
+            let function = unit.get_function_mut(FunctionIndex::new(f)).unwrap();
+            if function.is_extern() { continue; }
+
+            let mut code = std::mem::take(function.code_mut());
+            changed |= inline_block(&mut code, FunctionIndex::new(f), function, &callees);
+            *function.code_mut() = code;
+        }
+
+        changed
+    }
+}
+
+/// What is needed of a function to put its code in place of a call to it
+struct Callee {
+    locals: Vec<StorableType>,
+    params: Vec<ValueType>,
+    returns: Vec<ValueType>,
+    code: Vec<Ins>,
+    /// Whether the only return is the last instruction, so there is nothing to break out of
+    returns_once: bool
+}
+
+impl Callee {
+    fn new(function: &Function) -> Callee {
+        Callee {
+            locals: function.locals().iter().map(|local| local.local_type().clone()).collect(),
+            params: function.signature().params().clone(),
+            returns: function.signature().returns().clone(),
+            code: function.code().clone(),
+            returns_once: count_rets(function.code()) == 1 && matches!(function.code().last(), Some(Ins::Ret))
+        }
+    }
+}
+
+/// The number of instructions in the code, including those in blocks
+fn size(code: &[Ins]) -> usize {
+    code.iter().map(|ins| 1 + blocks(ins).into_iter().map(|block| size(block)).sum::<usize>()).sum()
+}
+
+fn count_rets(code: &[Ins]) -> usize {
+    code.iter().map(|ins| match ins {
+        Ins::Ret => 1,
+        _ => blocks(ins).into_iter().map(|block| count_rets(block)).sum()
+    }).sum()
+}
+
+/// Whether the function can be inlined. Its locals are reset to zero at each inlined call, as they would be for each
+/// call, so each must be a number or bool.
+fn can_inline(function: &Function, max_size: usize) -> bool {
+    let code = match function.code_opt() {
+        Some(code) => code,
+        None => return false
+    };
+
+    let locals_are_nums = function.locals()[function.signature().param_count()..].iter()
+        .all(|local| matches!(local.local_type(), StorableType::Value(vt) if vt.is_num()));
+
+    locals_are_nums && !contains_asm(code) && (function.is_inline() || size(code) <= max_size)
+}
+
+/// Puts the code of each callee in place of the calls to it in the block, and the blocks nested in it, returning whether
+/// anything was inlined. A function is never inlined into itself, so recursion cannot grow it without end.
+fn inline_block(code: &mut Vec<Ins>, idx: FunctionIndex, function: &mut Function, callees: &[Option<Callee>]) -> bool {
+    let mut changed = false;
+
+    for ins in code.iter_mut() {
+        for block in blocks_mut(ins) {
+            changed |= inline_block(block, idx, function, callees);
+        }
+    }
+
+    let mut i = 0;
+    while i < code.len() {
+        let callee = match &code[i] {
+            Ins::Call(callee) if *callee != idx => callees.get(callee.idx()).and_then(Option::as_ref),
+            _ => None
+        };
+
+        match callee {
+            // Calls in the inlined code are left for the next run, which will have inlined into the callee too
+            Some(callee) => {
+                let inlined = inline_call(callee, function);
+                let len = inlined.len();
+                code.splice(i..=i, inlined);
+                i += len;
+                changed = true;
+            },
+            None => i += 1
+        }
+    }
+
+    changed
+}
+
+/// Gives the code which replaces a call to the callee, adding the locals it needs to the function
+fn inline_call(callee: &Callee, function: &mut Function) -> Vec<Ins> {
+    let base = function.local_count();
+    for local_type in &callee.locals {
+        function.push_local(Local::new(local_type.clone()));
+    }
+
+    let returns = callee.returns.iter().map(|vt| {
+        (function.push_local(Local::new(StorableType::Value(vt.clone()))), vt.clone())
+    }).collect::<Vec<_>>();
+
+    // The arguments are on the stack with the last on top, so are popped into the params from the last
+    let mut code = Vec::new();
+    for (p, vt) in callee.params.iter().enumerate().rev() {
+        code.push(Ins::PopLocal(LocalIndex::new(base + p), vt.clone()));
+    }
+
+    let mut body = Vec::new();
+    for (l, local_type) in callee.locals.iter().enumerate().skip(callee.params.len()) {
+        if let StorableType::Value(vt) = local_type {
+            body.push(Ins::PushLiteral(vt.clone(), 0));
+            body.push(Ins::PopLocal(LocalIndex::new(base + l), vt.clone()));
+        }
+    }
+    body.extend(remap(&callee.code, base, &returns, 0, !callee.returns_once));
+
+    if !callee.returns_once {
+        body = vec![ Ins::Loop(body, vec![ Ins::PushLiteral(ValueType::Bool, 1) ], Vec::new()) ];
+    }
+
+    // An IfElse runs its branches on a stack of their own, so the code can start with an empty stack whatever the caller
+    // has on it
+    code.push(Ins::IfElse(None, body, Vec::new(), vec![ Ins::PushLiteral(ValueType::Bool, 1) ]));
+
+    for (local, vt) in returns {
+        code.push(Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(local, StorableType::Value(vt.clone()))), vt.clone()));
+        code.push(Ins::Push(vt));
+    }
+
+    code
+}
+
+/// Copies the callee's code with its locals moved to the given base, and each return replaced by popping the values into
+/// the return locals. Where the code is wrapped in a loop, the return then breaks out of it, from within the given number
+/// of blocks a break counts.
+fn remap(code: &[Ins], base: usize, returns: &[(LocalIndex, ValueType)], depth: usize, wrapped: bool) -> Vec<Ins> {
+    let mut remapped = Vec::new();
+
+    for ins in code {
+        match ins {
+            Ins::Ret => {
+                for (local, vt) in returns.iter().rev() {
+                    remapped.push(Ins::PopLocal(*local, vt.clone()));
+                }
+                if wrapped {
+                    remapped.push(Ins::Break(depth));
+                }
+            },
+            Ins::PushPath(path, vt) => {
+                let origin = match path.origin() {
+                    ValuePathOrigin::Local(idx, st) => ValuePathOrigin::Local(LocalIndex::new(base + idx.idx()), st.clone()),
+                    origin => origin.clone()
+                };
+                remapped.push(Ins::PushPath(ValuePath::new(origin, path.components().clone()), vt.clone()));
+            },
+            Ins::PopLocal(idx, vt) => remapped.push(Ins::PopLocal(LocalIndex::new(base + idx.idx()), vt.clone())),
+            Ins::Loop(code, cond, inc) => remapped.push(Ins::Loop(
+                remap(code, base, returns, depth + 1, wrapped),
+                remap(cond, base, returns, depth, wrapped),
+                remap(inc, base, returns, depth, wrapped)
+            )),
+            Ins::If(code, cond) => remapped.push(Ins::If(
+                remap(code, base, returns, depth + 1, wrapped),
+                remap(cond, base, returns, depth, wrapped)
+            )),
+            Ins::IfElse(vt, true_then, else_then, cond) => remapped.push(Ins::IfElse(
+                vt.clone(),
+                remap(true_then, base, returns, depth + 1, wrapped),
+                remap(else_then, base, returns, depth + 1, wrapped),
+                remap(cond, base, returns, depth, wrapped)
+            )),
+            Ins::Switch(vt, cases, default, value) => remapped.push(Ins::Switch(
+                vt.clone(),
+                cases.iter().map(|(values, code)| (values.clone(), remap(code, base, returns, depth + 1, wrapped))).collect(),
+                remap(default, base, returns, depth + 1, wrapped),
+                remap(value, base, returns, depth, wrapped)
+            )),
+            ins => remapped.push(ins.clone())
+        }
+    }
+
+    remapped
+}
\ No newline at end of file
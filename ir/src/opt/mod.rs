@@ -0,0 +1,109 @@
+mod dce;
+mod escape;
+mod fold;
+mod inline;
+
+pub use dce::*;
+pub use escape::*;
+pub use fold::*;
+pub use inline::*;
+
+use crate::{Ins, TranslationUnit};
+
+/// The most times every pass is run over a unit, as each may give the others more to do
+const MAX_ROUNDS: usize = 8;
+
+/// A rewrite of a unit into one which does the same thing with less work
+pub trait Pass {
+    fn name(&self) -> &str;
+
+    /// Runs the pass over a valid unit, leaving it valid, and returns whether it was changed
+    fn run(&mut self, unit: &mut TranslationUnit) -> bool;
+}
+
+/// Runs passes over a unit in the order they were added
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>
+}
+
+impl PassManager {
+    pub fn new() -> PassManager {
+        PassManager {
+            passes: Vec::new()
+        }
+    }
+
+    /// The passes run at an optimisation level, where 0 runs none, 1 folds constants, puts objects which do not escape
+    /// on the stack and removes dead code, and 2 inlines functions too. Constants are folded for a target whose pointers
+    /// are `ptr_size` bytes.
+    pub fn for_level(level: u8, ptr_size: usize) -> PassManager {
+        let mut manager = PassManager::new();
+        if level >= 2 {
+            manager.add_pass(Box::new(Inline::new(INLINE_SIZE)));
+        }
+        if level >= 1 {
+            manager.add_pass(Box::new(ConstantFold::new(ptr_size)));
+            manager.add_pass(Box::new(StackAllocate));
+            manager.add_pass(Box::new(DeadCode));
+        }
+        manager
+    }
+
+    pub fn add_pass(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &Vec<Box<dyn Pass>> {
+        &self.passes
+    }
+
+    pub fn pass_count(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Runs every pass in turn, then runs them all again for as long as any of them changes the unit, up to a limit.
+    /// Returns whether the unit was changed.
+    pub fn run(&mut self, unit: &mut TranslationUnit) -> bool {
+        let mut changed = false;
+
+        for _ in 0..MAX_ROUNDS {
+            let mut round_changed = false;
+            for pass in &mut self.passes {
+                round_changed |= pass.run(unit);
+            }
+
+            if !round_changed { break; }
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+/// The blocks an instruction holds, numbered as they are in a validation location
+pub(crate) fn blocks(ins: &Ins) -> Vec<&Vec<Ins>> {
+    match ins {
+        Ins::Loop(code, cond, inc) => vec![ code, cond, inc ],
+        Ins::If(code, cond) => vec![ code, cond ],
+        Ins::IfElse(_, true_then, else_then, cond) => vec![ true_then, else_then, cond ],
+        Ins::Switch(_, cases, default, value) => cases.iter().map(|(_, code)| code).chain(vec![ default, value ]).collect(),
+        _ => Vec::new()
+    }
+}
+
+/// The blocks an instruction holds, numbered as they are in a validation location
+pub(crate) fn blocks_mut(ins: &mut Ins) -> Vec<&mut Vec<Ins>> {
+    match ins {
+        Ins::Loop(code, cond, inc) => vec![ code, cond, inc ],
+        Ins::If(code, cond) => vec![ code, cond ],
+        Ins::IfElse(_, true_then, else_then, cond) => vec![ true_then, else_then, cond ],
+        Ins::Switch(_, cases, default, value) => cases.iter_mut().map(|(_, code)| code).chain(vec![ default, value ]).collect(),
+        _ => Vec::new()
+    }
+}
+
+/// Whether the code contains an asm block, whose bindings name locals in a way which passes do not follow
+pub(crate) fn contains_asm(code: &[Ins]) -> bool {
+    code.iter().any(|ins| matches!(ins, Ins::Asm(_)) || blocks(ins).into_iter().any(|block| contains_asm(block)))
+}
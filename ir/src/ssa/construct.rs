@@ -0,0 +1,479 @@
+use crate::{Function, Ins, LocalIndex, StorableType, TranslationUnit, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
+
+use super::{Block, BlockIndex, Inst, Op, Phi, Reg, SsaFunction, Terminator};
+
+/// What the stack holds at a point in the code
+#[derive(Clone)]
+enum Entry {
+    Value(Reg),
+    /// A path to the whole of a local kept in registers
+    Local(LocalIndex),
+    /// A path, with the values it popped
+    Path(ValuePath, Vec<Reg>)
+}
+
+/// The stack, and the register each local kept in registers has, at a point in the code
+#[derive(Clone)]
+struct State {
+    stack: Vec<Entry>,
+    locals: Vec<Option<Reg>>
+}
+
+/// A block as it is being built, which gets its terminator once control is known to leave it
+struct PartialBlock {
+    phis: Vec<Phi>,
+    insts: Vec<Inst>,
+    terminator: Option<Terminator>,
+    /// The state at the start of the block, made of its phis, once control is known to reach it
+    entry: Option<State>
+}
+
+/// Follows the structured code, keeping the register each value on the stack and each local is in. Every block control
+/// can move to gets a phi for each value and local, which are left out once all the ways into the block are known if
+/// they are always given the same register.
+struct Builder<'a> {
+    unit: &'a TranslationUnit,
+    function: &'a Function,
+    blocks: Vec<PartialBlock>,
+    regs: Vec<ValueType>,
+    /// The block being added to and the state at the end of it, or none if the code being followed cannot be reached
+    current: Option<(BlockIndex, State)>,
+    /// For each block a break or continue can count, the blocks they move to, if it is a loop
+    targets: Vec<Option<(BlockIndex, BlockIndex)>>
+}
+
+impl SsaFunction {
+    /// Puts the code of a valid function which is not extern in SSA form
+    pub fn new(function: &Function, unit: &TranslationUnit) -> SsaFunction {
+        let mut bound = vec![ false; function.local_count() ];
+        find_bound_locals(function.code(), &mut bound);
+
+        let mut builder = Builder {
+            unit, function,
+            blocks: Vec::new(),
+            regs: Vec::new(),
+            current: None,
+            targets: Vec::new()
+        };
+
+        let entry = builder.new_block();
+        let mut state = State {
+            stack: Vec::new(),
+            locals: vec![ None; function.local_count() ]
+        };
+
+        // Locals start as the params they are given, or zero
+        for (l, local) in function.locals().iter().enumerate() {
+            if let (StorableType::Value(vt), false) = (local.local_type(), bound[l]) {
+                let reg = builder.new_reg(vt.clone());
+                let op = match l < function.signature().param_count() {
+                    true => Op::Param(l),
+                    false => Op::Ins(Ins::PushLiteral(vt.clone(), 0))
+                };
+                builder.blocks[entry.idx()].insts.push(Inst::new(op, Vec::new(), vec![ reg ]));
+                state.locals[l] = Some(reg);
+            }
+        }
+
+        builder.current = Some((entry, state));
+        builder.code(function.code());
+
+        // A function which returns nothing may reach the end of its code
+        if let Some((block, _)) = builder.current.take() {
+            builder.blocks[block.idx()].terminator = Some(Terminator::Return(Vec::new()));
+        }
+
+        builder.finish()
+    }
+}
+
+/// Marks each local an asm block binds, which has to stay in memory
+fn find_bound_locals(code: &[Ins], bound: &mut [bool]) {
+    for ins in code {
+        if let Ins::Asm(block) = ins {
+            for binding in block.inputs().iter().chain(block.outputs().iter()) {
+                bound[binding.local().idx()] = true;
+            }
+        }
+
+        for block in crate::opt::blocks(ins) {
+            find_bound_locals(block, bound);
+        }
+    }
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> BlockIndex {
+        self.blocks.push(PartialBlock {
+            phis: Vec::new(),
+            insts: Vec::new(),
+            terminator: None,
+            entry: None
+        });
+        BlockIndex::new(self.blocks.len() - 1)
+    }
+
+    fn new_reg(&mut self, value_type: ValueType) -> Reg {
+        self.regs.push(value_type);
+        Reg::new(self.regs.len() - 1)
+    }
+
+    fn state(&mut self) -> &mut State {
+        &mut self.current.as_mut().expect("Code is reachable").1
+    }
+
+    fn push(&mut self, reg: Reg) {
+        self.state().stack.push(Entry::Value(reg));
+    }
+
+    fn pop(&mut self) -> Reg {
+        match self.state().stack.pop() {
+            Some(Entry::Value(reg)) => reg,
+            _ => panic!("Expected a value on the stack of a valid function")
+        }
+    }
+
+    /// Pops the given number of values, giving them in the order they were pushed
+    fn pop_n(&mut self, n: usize) -> Vec<Reg> {
+        let mut values = (0..n).map(|_| self.pop()).collect::<Vec<_>>();
+        values.reverse();
+        values
+    }
+
+    fn emit(&mut self, op: Op, args: Vec<Reg>, dests: Vec<Reg>) {
+        let block = self.current.as_ref().expect("Code is reachable").0;
+        self.blocks[block.idx()].insts.push(Inst::new(op, args, dests));
+    }
+
+    /// Ends the current block, which control cannot then fall out of
+    fn terminate(&mut self, terminator: Terminator) {
+        let (block, state) = self.current.take().expect("Code is reachable");
+        for target in terminator.successors() {
+            self.add_edge(block, &state, target);
+        }
+        self.blocks[block.idx()].terminator = Some(terminator);
+    }
+
+    /// Ends the current block by moving to the target, if the end of the current block can be reached
+    fn jump(&mut self, target: BlockIndex) {
+        if self.current.is_some() {
+            self.terminate(Terminator::Jump(target));
+        }
+    }
+
+    /// Gives each phi of the target its register for control coming from the block, first making the phis if this is
+    /// the first way into the target
+    fn add_edge(&mut self, from: BlockIndex, state: &State, target: BlockIndex) {
+        if self.blocks[target.idx()].entry.is_none() {
+            let mut entry = state.clone();
+            let mut phis = Vec::new();
+
+            let values = entry.stack.iter_mut().filter_map(|entry| match entry {
+                Entry::Value(reg) => Some(reg),
+                _ => None
+            });
+            for reg in values.chain(entry.locals.iter_mut().flatten()) {
+                self.regs.push(self.regs[reg.idx()].clone());
+                *reg = Reg::new(self.regs.len() - 1);
+                phis.push(Phi::new(*reg, Vec::new()));
+            }
+
+            self.blocks[target.idx()].phis = phis;
+            self.blocks[target.idx()].entry = Some(entry);
+        }
+
+        let values = state.stack.iter().filter_map(|entry| match entry {
+            Entry::Value(reg) => Some(*reg),
+            _ => None
+        });
+        let args = values.chain(state.locals.iter().flatten().copied()).collect::<Vec<_>>();
+        for (phi, arg) in self.blocks[target.idx()].phis.iter_mut().zip(args) {
+            phi.args.push((from, arg));
+        }
+    }
+
+    /// Continues in the block, if control can reach it
+    fn enter(&mut self, block: BlockIndex) {
+        self.current = self.blocks[block.idx()].entry.clone().map(|state| (block, state));
+    }
+
+    /// Follows the code of a block a break or continue counts, which moves to the given blocks if it is a loop
+    fn nested(&mut self, code: &[Ins], loop_targets: Option<(BlockIndex, BlockIndex)>) {
+        self.targets.push(loop_targets);
+        self.code(code);
+        self.targets.pop();
+    }
+
+    /// Follows code which runs on a stack of its own, giving back the stack it left
+    fn own_stack(&mut self, code: &[Ins], loop_targets: Option<(BlockIndex, BlockIndex)>, nested: bool) -> Option<Vec<Entry>> {
+        if let Some((_, state)) = &mut self.current {
+            state.stack.clear();
+        }
+
+        match nested {
+            true => self.nested(code, loop_targets),
+            false => self.code(code)
+        }
+
+        self.current.as_mut().map(|(_, state)| std::mem::take(&mut state.stack))
+    }
+
+    fn code(&mut self, code: &[Ins]) {
+        for ins in code {
+            // Nothing after a return, break or continue can run
+            if self.current.is_none() { return; }
+            self.ins(ins);
+        }
+    }
+
+    fn ins(&mut self, ins: &Ins) {
+        match ins {
+            Ins::PushPath(path, _) => {
+                let entry = match path.origin() {
+                    ValuePathOrigin::Local(idx, _) if self.state().locals[idx.idx()].is_some() => Entry::Local(*idx),
+                    origin => {
+                        let popped = path.components().iter().filter(|component| matches!(component, ValuePathComponent::Slice(_))).count() +
+                            matches!(origin, ValuePathOrigin::Deref(_)) as usize;
+                        Entry::Path(path.clone(), self.pop_n(popped))
+                    }
+                };
+                self.state().stack.push(entry);
+            },
+            Ins::Push(vt) => match self.state().stack.pop() {
+                Some(Entry::Local(idx)) => {
+                    let reg = self.state().locals[idx.idx()].unwrap();
+                    self.push(reg);
+                },
+                Some(Entry::Path(path, args)) => {
+                    let reg = self.new_reg(vt.clone());
+                    self.emit(Op::Load(path, vt.clone()), args, vec![ reg ]);
+                    self.push(reg);
+                },
+                _ => panic!("Expected a path on the stack of a valid function")
+            },
+            Ins::Pop(vt) => {
+                let value = self.pop();
+                match self.state().stack.pop() {
+                    Some(Entry::Local(idx)) => self.state().locals[idx.idx()] = Some(value),
+                    Some(Entry::Path(path, mut args)) => {
+                        args.push(value);
+                        self.emit(Op::Store(path, vt.clone()), args, Vec::new());
+                    },
+                    _ => panic!("Expected a path on the stack of a valid function")
+                }
+            },
+            Ins::PopLocal(idx, vt) => {
+                let value = self.pop();
+                match self.state().locals[idx.idx()] {
+                    Some(_) => self.state().locals[idx.idx()] = Some(value),
+                    None => {
+                        let path = ValuePath::new_origin_only(ValuePathOrigin::Local(*idx, StorableType::Value(vt.clone())));
+                        self.emit(Op::Store(path, vt.clone()), vec![ value ], Vec::new());
+                    }
+                }
+            },
+            Ins::Drop => { self.state().stack.pop(); },
+            Ins::Ret => {
+                let values = self.pop_n(self.function.signature().return_count());
+                self.terminate(Terminator::Return(values));
+            },
+            Ins::Break(depth) | Ins::Continue(depth) => {
+                let (continue_target, break_target) = self.targets[self.targets.len() - 1 - depth].expect("Break or continue of a loop");
+                let target = if matches!(ins, Ins::Break(_)) { break_target } else { continue_target };
+                self.terminate(Terminator::Jump(target));
+            },
+            Ins::Loop(code, cond, inc) => {
+                let (header, body, next, exit) = (self.new_block(), self.new_block(), self.new_block(), self.new_block());
+                self.jump(header);
+
+                self.enter(header);
+                self.code(cond);
+                let cond = self.pop();
+                self.terminate(Terminator::Branch(cond, body, exit));
+
+                self.enter(body);
+                self.nested(code, Some((next, exit)));
+                self.jump(next);
+
+                self.enter(next);
+                self.code(inc);
+                self.jump(header);
+
+                self.enter(exit);
+            },
+            Ins::If(code, cond) => {
+                let (then, after) = (self.new_block(), self.new_block());
+
+                self.code(cond);
+                let cond = self.pop();
+                self.terminate(Terminator::Branch(cond, then, after));
+
+                self.enter(then);
+                self.nested(code, None);
+                self.jump(after);
+
+                self.enter(after);
+            },
+            Ins::IfElse(_, true_then, else_then, cond) => {
+                let (true_block, else_block, after) = (self.new_block(), self.new_block(), self.new_block());
+                let outer = std::mem::take(&mut self.state().stack);
+
+                let cond = self.own_stack(cond, None, false);
+                if let Some(mut cond) = cond {
+                    let cond = match cond.pop() {
+                        Some(Entry::Value(reg)) => reg,
+                        _ => panic!("Expected a value on the stack of a valid function")
+                    };
+                    self.terminate(Terminator::Branch(cond, true_block, else_block));
+                }
+
+                for (block, code) in [ (true_block, true_then), (else_block, else_then) ] {
+                    self.enter(block);
+                    if let Some(value) = self.own_stack(code, None, true) {
+                        let mut stack = outer.clone();
+                        stack.extend(value);
+                        self.state().stack = stack;
+                        self.jump(after);
+                    }
+                }
+
+                self.enter(after);
+            },
+            Ins::Switch(vt, cases, default, value) => {
+                let case_blocks = cases.iter().map(|_| self.new_block()).collect::<Vec<_>>();
+                let (default_block, after) = (self.new_block(), self.new_block());
+
+                self.code(value);
+                let value = self.pop();
+                let targets = cases.iter().zip(&case_blocks).map(|((values, _), block)| (values.clone(), *block)).collect();
+                self.terminate(Terminator::Switch(vt.clone(), value, targets, default_block));
+
+                let codes = cases.iter().map(|(_, code)| code).chain(std::iter::once(default));
+                for (block, code) in case_blocks.into_iter().chain(std::iter::once(default_block)).zip(codes) {
+                    self.enter(block);
+                    self.nested(code, None);
+                    self.jump(after);
+                }
+
+                self.enter(after);
+            },
+            Ins::Asm(_) => self.emit(Op::Ins(ins.clone()), Vec::new(), Vec::new()),
+            _ => {
+                let (popped, pushed) = effect(ins, self.unit);
+                let args = self.pop_n(popped);
+                let dests = pushed.into_iter().map(|vt| self.new_reg(vt)).collect::<Vec<_>>();
+                self.emit(Op::Ins(ins.clone()), args, dests.clone());
+                for dest in dests {
+                    self.push(dest);
+                }
+            }
+        }
+    }
+
+    /// Leaves out the blocks control cannot reach and the phis which are always given the same register, which is used in
+    /// their place
+    fn finish(self) -> SsaFunction {
+        let mut new_index = Vec::new();
+        let mut next = 0;
+        for block in &self.blocks {
+            new_index.push(BlockIndex::new(next));
+            next += block.terminator.is_some() as usize;
+        }
+
+        let mut blocks = self.blocks.into_iter().filter_map(|PartialBlock { phis, insts, terminator, .. }| {
+            terminator.map(|terminator| Block::new(phis, insts, terminator))
+        }).collect::<Vec<_>>();
+
+        for block in &mut blocks {
+            for phi in &mut block.phis {
+                for (from, _) in &mut phi.args {
+                    *from = new_index[from.idx()];
+                }
+            }
+            for target in block.terminator.targets_mut() {
+                *target = new_index[target.idx()];
+            }
+        }
+
+        let mut ssa = SsaFunction {
+            blocks,
+            regs: self.regs
+        };
+        remove_trivial_phis(&mut ssa);
+        ssa
+    }
+}
+
+/// How many values the instruction pops, and the types of those it pushes
+fn effect(ins: &Ins, unit: &TranslationUnit) -> (usize, Vec<ValueType>) {
+    match ins {
+        Ins::PushLiteral(vt, _) => (0, vec![ vt.clone() ]),
+        Ins::Index(st) => (1, vec![ ValueType::Index(Box::new(st.clone())) ]),
+        Ins::New(st) => (0, vec![ ValueType::Ref(Box::new(st.clone())) ]),
+        Ins::NewSlice(st) => (1, vec![ ValueType::Ref(Box::new(StorableType::Slice(Box::new(st.clone())))) ]),
+        Ins::Free(_) | Ins::FreeSlice(_) => (1, Vec::new()),
+        Ins::Convert(_, to) => (1, vec![ to.clone() ]),
+        Ins::Call(idx) => {
+            let signature = unit.get_function(*idx).expect("Function of a valid call").signature();
+            (signature.param_count(), signature.returns().clone())
+        },
+        Ins::Inc(vt, _) | Ins::Dec(vt, _) | Ins::Neg(vt) => (1, vec![ vt.clone() ]),
+        Ins::Add(vt) | Ins::Sub(vt) | Ins::Mul(vt) | Ins::Div(vt) => (2, vec![ vt.clone() ]),
+        Ins::Eq(_) | Ins::Ne(_) | Ins::Lt(_) | Ins::Le(_) | Ins::Gt(_) | Ins::Ge(_) | Ins::BoolAnd | Ins::BoolOr => (2, vec![ ValueType::Bool ]),
+        _ => unreachable!("Instruction which moves control or uses a path")
+    }
+}
+
+/// Removes each phi which is given only one register other than its own, using that register in its place, until none
+/// are left. A loop may keep a phi alive only through another phi, so removing one may make others trivial too.
+fn remove_trivial_phis(ssa: &mut SsaFunction) {
+    let mut replaced = (0..ssa.regs.len()).map(Reg::new).collect::<Vec<_>>();
+    let resolve = |replaced: &[Reg], mut reg: Reg| {
+        while replaced[reg.idx()] != reg {
+            reg = replaced[reg.idx()];
+        }
+        reg
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for block in &mut ssa.blocks {
+            block.phis.retain(|phi| {
+                let mut same = None;
+                for (_, arg) in &phi.args {
+                    let arg = resolve(&replaced, *arg);
+                    if arg == phi.dest || Some(arg) == same { continue; }
+                    if same.is_some() { return true; }
+                    same = Some(arg);
+                }
+
+                match same {
+                    Some(same) => {
+                        replaced[phi.dest.idx()] = same;
+                        changed = true;
+                        false
+                    },
+                    None => true
+                }
+            });
+        }
+    }
+
+    for block in &mut ssa.blocks {
+        for phi in &mut block.phis {
+            for (_, arg) in &mut phi.args {
+                *arg = resolve(&replaced, *arg);
+            }
+        }
+        for inst in &mut block.insts {
+            for arg in &mut inst.args {
+                *arg = resolve(&replaced, *arg);
+            }
+        }
+        for reg in block.terminator.regs_mut() {
+            *reg = resolve(&replaced, *reg);
+        }
+    }
+}
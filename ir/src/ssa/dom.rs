@@ -0,0 +1,131 @@
+use super::{BlockIndex, SsaFunction};
+
+/// Which blocks dominate which, where a block dominates another if every path from the entry to the other passes through
+/// it. Found with the iterative algorithm of Cooper, Harvey and Kennedy, which walks the blocks in reverse postorder until
+/// the immediate dominator of each stops changing.
+pub struct Dominators {
+    idoms: Vec<Option<BlockIndex>>,
+    /// The place of each reachable block in reverse postorder
+    order: Vec<Option<usize>>,
+    children: Vec<Vec<BlockIndex>>
+}
+
+impl Dominators {
+    pub fn new(ssa: &SsaFunction) -> Dominators {
+        let rpo = ssa.reverse_postorder();
+        let preds = ssa.predecessors();
+
+        let mut order = vec![ None; ssa.block_count() ];
+        for (i, block) in rpo.iter().enumerate() {
+            order[block.idx()] = Some(i);
+        }
+
+        let mut idoms = vec![ None; ssa.block_count() ];
+        idoms[ssa.entry().idx()] = Some(ssa.entry());
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in rpo.iter().skip(1) {
+                let mut new_idom = None;
+                for pred in &preds[block.idx()] {
+                    if idoms[pred.idx()].is_none() { continue; }
+
+                    new_idom = Some(match new_idom {
+                        None => *pred,
+                        Some(other) => intersect(&idoms, &order, *pred, other)
+                    });
+                }
+
+                if new_idom.is_some() && idoms[block.idx()] != new_idom {
+                    idoms[block.idx()] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        // The entry has no immediate dominator, though it stood in as its own while they were found
+        idoms[ssa.entry().idx()] = None;
+
+        let mut children = vec![ Vec::new(); ssa.block_count() ];
+        for block in &rpo {
+            if let Some(idom) = idoms[block.idx()] {
+                children[idom.idx()].push(*block);
+            }
+        }
+
+        Dominators {
+            idoms, order, children
+        }
+    }
+
+    /// The closest block which dominates the given one other than itself, which only the entry and blocks which cannot be
+    /// reached have none of
+    pub fn idom(&self, block: BlockIndex) -> Option<BlockIndex> {
+        self.idoms[block.idx()]
+    }
+
+    /// The blocks the given one is the immediate dominator of, in reverse postorder
+    pub fn children(&self, block: BlockIndex) -> &Vec<BlockIndex> {
+        &self.children[block.idx()]
+    }
+
+    /// Whether every path from the entry to b passes through a, which is true when they are the same block
+    pub fn dominates(&self, a: BlockIndex, b: BlockIndex) -> bool {
+        if self.order[b.idx()].is_none() { return false; }
+
+        let mut block = Some(b);
+        while let Some(curr) = block {
+            if curr == a { return true; }
+            block = self.idoms[curr.idx()];
+        }
+        false
+    }
+
+    pub fn is_reachable(&self, block: BlockIndex) -> bool {
+        self.order[block.idx()].is_some()
+    }
+
+    /// The place of the block in reverse postorder, if it can be reached
+    pub fn order(&self, block: BlockIndex) -> Option<usize> {
+        self.order[block.idx()]
+    }
+
+    /// The dominance frontier of each block, which is each block it does not strictly dominate but which a block it
+    /// dominates can move to. These are where a value written in the block may meet another, so need a phi.
+    pub fn frontiers(&self, ssa: &SsaFunction) -> Vec<Vec<BlockIndex>> {
+        let mut frontiers = vec![ Vec::new(); ssa.block_count() ];
+
+        for (b, preds) in ssa.predecessors().into_iter().enumerate() {
+            let block = BlockIndex::new(b);
+            if preds.len() < 2 || !self.is_reachable(block) { continue; }
+
+            for pred in preds {
+                let mut runner = Some(pred);
+                while let Some(curr) = runner {
+                    if Some(curr) == self.idom(block) || !self.is_reachable(curr) { break; }
+                    if !frontiers[curr.idx()].contains(&block) {
+                        frontiers[curr.idx()].push(block);
+                    }
+                    runner = self.idom(curr);
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+/// The closest common dominator of two blocks, found by walking up from whichever is later in reverse postorder
+fn intersect(idoms: &[Option<BlockIndex>], order: &[Option<usize>], mut a: BlockIndex, mut b: BlockIndex) -> BlockIndex {
+    while a != b {
+        while order[a.idx()] > order[b.idx()] {
+            a = idoms[a.idx()].unwrap();
+        }
+        while order[b.idx()] > order[a.idx()] {
+            b = idoms[b.idx()].unwrap();
+        }
+    }
+    a
+}
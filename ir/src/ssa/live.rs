@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+
+use super::{BlockIndex, Reg, SsaFunction};
+
+/// The registers live at the start and end of each block, which are those that may still be read before they are
+/// written again. A phi's register is written at the start of its block, and each of its args is read at the end of the
+/// block control comes from, so is only live out of that block.
+pub struct Liveness {
+    live_in: Vec<BTreeSet<Reg>>,
+    live_out: Vec<BTreeSet<Reg>>
+}
+
+impl Liveness {
+    pub fn new(ssa: &SsaFunction) -> Liveness {
+        let count = ssa.block_count();
+
+        // What each block reads before writing, and what it writes, not counting its phis
+        let mut uses = vec![ BTreeSet::new(); count ];
+        let mut defs = vec![ BTreeSet::new(); count ];
+        for (b, block) in ssa.blocks().iter().enumerate() {
+            for inst in block.insts() {
+                for arg in inst.args() {
+                    if !defs[b].contains(arg) { uses[b].insert(*arg); }
+                }
+                defs[b].extend(inst.dests().iter().copied());
+            }
+            for arg in block.terminator().args() {
+                if !defs[b].contains(&arg) { uses[b].insert(arg); }
+            }
+        }
+
+        let mut live_in = vec![ BTreeSet::new(); count ];
+        let mut live_out = vec![ BTreeSet::<Reg>::new(); count ];
+
+        // Going through the blocks backwards lets most of what is live flow through in one round
+        let mut order = ssa.reverse_postorder();
+        order.reverse();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in &order {
+                let b = block.idx();
+                let mut out = BTreeSet::new();
+                for succ in ssa.block(*block).successors() {
+                    let succ_block = ssa.block(succ);
+                    out.extend(live_in[succ.idx()].iter().filter(|reg| !succ_block.phis().iter().any(|phi| phi.dest() == **reg)));
+                    out.extend(succ_block.phis().iter().filter_map(|phi| phi.arg_from(*block)));
+                }
+
+                let mut inn = uses[b].clone();
+                inn.extend(out.iter().filter(|reg| !defs[b].contains(reg)));
+                inn.extend(ssa.block(*block).phis().iter().map(|phi| phi.dest()));
+
+                if out != live_out[b] || inn != live_in[b] {
+                    live_out[b] = out;
+                    live_in[b] = inn;
+                    changed = true;
+                }
+            }
+        }
+
+        Liveness {
+            live_in, live_out
+        }
+    }
+
+    /// The registers live at the start of the block, including those its phis write
+    pub fn live_in(&self, block: BlockIndex) -> &BTreeSet<Reg> {
+        &self.live_in[block.idx()]
+    }
+
+    /// The registers live at the end of the block, including the args it gives the phis of the blocks it moves to
+    pub fn live_out(&self, block: BlockIndex) -> &BTreeSet<Reg> {
+        &self.live_out[block.idx()]
+    }
+
+    pub fn is_live_in(&self, block: BlockIndex, reg: Reg) -> bool {
+        self.live_in[block.idx()].contains(&reg)
+    }
+
+    pub fn is_live_out(&self, block: BlockIndex, reg: Reg) -> bool {
+        self.live_out[block.idx()].contains(&reg)
+    }
+}
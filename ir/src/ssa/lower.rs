@@ -0,0 +1,248 @@
+use crate::{Function, Ins, Local, LocalIndex, StorableType, ValuePath, ValuePathOrigin, ValueType};
+
+use super::{BlockIndex, Dominators, Op, Reg, SsaFunction, Terminator};
+
+/// What a break or continue counts on the way out to the block it moves to
+#[derive(Clone, Copy)]
+enum Enclosing {
+    /// A loop which continuing moves to the start of the block
+    LoopHeadedBy(BlockIndex),
+    /// A loop run once, which breaking out of moves to the block after it
+    BlockFollowedBy(BlockIndex),
+    /// A branch, which can be counted but not moved to
+    Branch
+}
+
+/// Gives back structured code for a graph which can be reached only through the start of each loop, as any made from
+/// structured code can. This follows Ramsey's "Beyond Relooper": each block is placed in the code of the block which
+/// immediately dominates it, a block control can move to from more than one other is put after a loop which is run once
+/// and broken out of to get to it, and a loop is put around each block a later one moves back to.
+struct Lowering<'a> {
+    ssa: &'a SsaFunction,
+    dominators: Dominators,
+    /// How many of the ways into each block come from an earlier block, rather than moving back to the start of a loop
+    forward_preds: Vec<usize>,
+    is_loop_header: Vec<bool>,
+    /// The local each register is kept in, if it is read
+    locals: Vec<Option<LocalIndex>>
+}
+
+impl SsaFunction {
+    /// Replaces the code of the function this was made from with code doing the same, keeping each register in a local
+    /// of its own, which is added to the function. Each phi is given its value by popping the args into the locals of the
+    /// phis of a block just before moving to it, all at once, so that phis reading each other see the values from before.
+    pub fn write_to(&self, function: &mut Function) {
+        let dominators = Dominators::new(self);
+
+        let mut forward_preds = vec![ 0; self.block_count() ];
+        let mut is_loop_header = vec![ false; self.block_count() ];
+        for (b, preds) in self.predecessors().into_iter().enumerate() {
+            for pred in preds {
+                match dominators.order(pred) >= dominators.order(BlockIndex::new(b)) {
+                    true => is_loop_header[b] = true,
+                    false => forward_preds[b] += 1
+                }
+            }
+        }
+
+        let mut read = vec![ false; self.reg_count() ];
+        for block in self.blocks() {
+            for phi in block.phis() {
+                for (_, arg) in phi.args() {
+                    read[arg.idx()] = true;
+                }
+            }
+            for inst in block.insts() {
+                for arg in inst.args() {
+                    read[arg.idx()] = true;
+                }
+            }
+            for arg in block.terminator().args() {
+                read[arg.idx()] = true;
+            }
+        }
+
+        // A param is never written by the code given back, so the registers of params can be read from their locals
+        let mut locals = vec![ None; self.reg_count() ];
+        for block in self.blocks() {
+            for inst in block.insts() {
+                if let Op::Param(idx) = inst.op() {
+                    locals[inst.dests()[0].idx()] = Some(LocalIndex::new(*idx));
+                }
+            }
+        }
+
+        for (r, is_read) in read.into_iter().enumerate() {
+            if is_read && locals[r].is_none() {
+                locals[r] = Some(function.push_local(Local::new(StorableType::Value(self.regs[r].clone()))));
+            }
+        }
+
+        let lowering = Lowering {
+            ssa: self,
+            dominators, forward_preds, is_loop_header, locals
+        };
+
+        *function.code_mut() = lowering.tree(self.entry(), &mut Vec::new());
+    }
+}
+
+impl<'a> Lowering<'a> {
+    fn is_merge(&self, block: BlockIndex) -> bool {
+        self.forward_preds[block.idx()] > 1
+    }
+
+    fn read(&self, reg: Reg, code: &mut Vec<Ins>) {
+        let vt = self.ssa.reg_type(reg).clone();
+        let local = self.locals[reg.idx()].expect("Local of a register which is read");
+        code.push(Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(local, StorableType::Value(vt.clone()))), vt.clone()));
+        code.push(Ins::Push(vt));
+    }
+
+    /// Pops the value on top of the stack into the register's local, or drops it if the register is never read
+    fn write(&self, reg: Reg, code: &mut Vec<Ins>) {
+        match self.locals[reg.idx()] {
+            Some(local) => code.push(Ins::PopLocal(local, self.ssa.reg_type(reg).clone())),
+            None => code.push(Ins::Drop)
+        }
+    }
+
+    /// The code of the block and those it immediately dominates
+    fn tree(&self, block: BlockIndex, enclosing: &mut Vec<Enclosing>) -> Vec<Ins> {
+        // Those reached from more than one block are put after the code of this one, the last in reverse postorder outermost
+        let mut merges = self.dominators.children(block).iter().copied().filter(|child| self.is_merge(*child)).collect::<Vec<_>>();
+        merges.reverse();
+
+        match self.is_loop_header[block.idx()] {
+            true => {
+                enclosing.push(Enclosing::LoopHeadedBy(block));
+                let code = self.within(block, &merges, enclosing);
+                enclosing.pop();
+                vec![ run_once(code) ]
+            },
+            false => self.within(block, &merges, enclosing)
+        }
+    }
+
+    /// The code of the block, followed by that of the blocks it dominates which are reached from more than one block
+    fn within(&self, block: BlockIndex, merges: &[BlockIndex], enclosing: &mut Vec<Enclosing>) -> Vec<Ins> {
+        match merges.split_first() {
+            Some((merge, rest)) => {
+                enclosing.push(Enclosing::BlockFollowedBy(*merge));
+                let inner = self.within(block, rest, enclosing);
+                enclosing.pop();
+
+                let mut code = vec![ run_once(inner) ];
+                code.extend(self.tree(*merge, enclosing));
+                code
+            },
+            None => {
+                let mut code = Vec::new();
+                self.insts(block, &mut code);
+                self.terminator(block, enclosing, &mut code);
+                code
+            }
+        }
+    }
+
+    fn insts(&self, block: BlockIndex, code: &mut Vec<Ins>) {
+        for inst in self.ssa.block(block).insts() {
+            match inst.op() {
+                // The register is read from the param's local
+                Op::Param(_) => {},
+                Op::Ins(ins) => {
+                    for arg in inst.args() {
+                        self.read(*arg, code);
+                    }
+                    code.push(ins.clone());
+                    for dest in inst.dests().iter().rev() {
+                        self.write(*dest, code);
+                    }
+                },
+                Op::Load(path, vt) => {
+                    for arg in inst.args() {
+                        self.read(*arg, code);
+                    }
+                    code.push(Ins::PushPath(path.clone(), vt.clone()));
+                    code.push(Ins::Push(vt.clone()));
+                    self.write(inst.dests()[0], code);
+                },
+                Op::Store(path, vt) => {
+                    let (value, popped) = inst.args().split_last().expect("Value to store");
+                    for arg in popped {
+                        self.read(*arg, code);
+                    }
+                    code.push(Ins::PushPath(path.clone(), vt.clone()));
+                    self.read(*value, code);
+                    code.push(Ins::Pop(vt.clone()));
+                }
+            }
+        }
+    }
+
+    fn terminator(&self, block: BlockIndex, enclosing: &mut Vec<Enclosing>, code: &mut Vec<Ins>) {
+        match self.ssa.block(block).terminator() {
+            Terminator::Jump(target) => code.extend(self.branch(block, *target, enclosing)),
+            Terminator::Branch(cond, true_target, false_target) => {
+                enclosing.push(Enclosing::Branch);
+                let true_then = self.branch(block, *true_target, enclosing);
+                let else_then = self.branch(block, *false_target, enclosing);
+                enclosing.pop();
+
+                let mut cond_code = Vec::new();
+                self.read(*cond, &mut cond_code);
+                code.push(Ins::IfElse(None, true_then, else_then, cond_code));
+            },
+            Terminator::Switch(vt, value, cases, default) => {
+                enclosing.push(Enclosing::Branch);
+                let cases = cases.iter().map(|(values, target)| (values.clone(), self.branch(block, *target, enclosing))).collect();
+                let default = self.branch(block, *default, enclosing);
+                enclosing.pop();
+
+                let mut value_code = Vec::new();
+                self.read(*value, &mut value_code);
+                code.push(Ins::Switch(vt.clone(), cases, default, value_code));
+            },
+            Terminator::Return(values) => {
+                for value in values {
+                    self.read(*value, code);
+                }
+                code.push(Ins::Ret);
+            }
+        }
+    }
+
+    /// Gives the phis of the target their args from the block, then moves to it
+    fn branch(&self, from: BlockIndex, target: BlockIndex, enclosing: &mut Vec<Enclosing>) -> Vec<Ins> {
+        let phis = self.ssa.block(target).phis();
+        let mut code = Vec::new();
+        for phi in phis {
+            self.read(phi.arg_from(from).expect("Phi arg for each way into its block"), &mut code);
+        }
+        for phi in phis.iter().rev() {
+            self.write(phi.dest(), &mut code);
+        }
+
+        let depth = |wanted: &dyn Fn(&Enclosing) -> bool| {
+            enclosing.iter().rev().position(wanted).expect("Block to move to encloses the branch")
+        };
+
+        if self.dominators.order(target) <= self.dominators.order(from) {
+            code.push(Ins::Continue(depth(&|e| matches!(e, Enclosing::LoopHeadedBy(b) if *b == target))));
+        } else if self.is_merge(target) {
+            code.push(Ins::Break(depth(&|e| matches!(e, Enclosing::BlockFollowedBy(b) if *b == target))));
+        } else {
+            code.extend(self.tree(target, enclosing));
+        }
+
+        code
+    }
+}
+
+/// A loop which runs the code once, unless it continues, and which the code can break out of
+fn run_once(mut code: Vec<Ins>) -> Ins {
+    if crate::falls_through(&code) {
+        code.push(Ins::Break(0));
+    }
+    Ins::Loop(code, vec![ Ins::PushLiteral(ValueType::Bool, 1) ], Vec::new())
+}
@@ -0,0 +1,381 @@
+//! A form of a function's code made of basic blocks, where each value is kept in a virtual register which is written
+//! once, and phis choose between the registers of the blocks control can come from. This makes what the code does with
+//! each value plain to see, for optimisation passes and register allocators, which the stack of the structured code
+//! hides. A function is put in this form with `SsaFunction::new`, and its code is given back with `SsaFunction::write_to`.
+
+mod construct;
+mod dom;
+mod live;
+mod lower;
+
+pub use dom::*;
+pub use live::*;
+
+use std::fmt::Write;
+
+use crate::{Ins, ValuePath, ValueType};
+
+/// A virtual register, which is written by exactly one instruction or phi
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Reg(usize);
+
+impl Reg {
+    pub fn new(value: usize) -> Reg {
+        Reg(value)
+    }
+
+    pub fn idx(&self) -> usize {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Reg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("%{}", self.0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockIndex(usize);
+
+impl BlockIndex {
+    pub fn new(value: usize) -> BlockIndex {
+        BlockIndex(value)
+    }
+
+    pub fn idx(&self) -> usize {
+        self.0
+    }
+}
+
+impl std::fmt::Display for BlockIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.0))
+    }
+}
+
+/// Gives its register the value of the argument for the block control came from
+#[derive(Debug, Clone)]
+pub struct Phi {
+    dest: Reg,
+    args: Vec<(BlockIndex, Reg)>
+}
+
+impl Phi {
+    pub fn new(dest: Reg, args: Vec<(BlockIndex, Reg)>) -> Phi {
+        Phi {
+            dest, args
+        }
+    }
+
+    pub fn dest(&self) -> Reg {
+        self.dest
+    }
+
+    pub fn args(&self) -> &Vec<(BlockIndex, Reg)> {
+        &self.args
+    }
+
+    pub fn args_mut(&mut self) -> &mut Vec<(BlockIndex, Reg)> {
+        &mut self.args
+    }
+
+    /// The register given when control comes from the block
+    pub fn arg_from(&self, block: BlockIndex) -> Option<Reg> {
+        self.args.iter().find(|(from, _)| *from == block).map(|(_, reg)| *reg)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    /// The value of the param at the index
+    Param(usize),
+    /// An instruction which neither moves control nor follows a path, which takes its args in the order it would pop them
+    /// from the stack, last first, and gives its dests in the order it would push them
+    Ins(Ins),
+    /// Reads the value the path points to. The args are the values the path pops, in the order they were pushed.
+    Load(ValuePath, ValueType),
+    /// Writes the last arg to where the path points. The args before it are the values the path pops.
+    Store(ValuePath, ValueType)
+}
+
+#[derive(Debug, Clone)]
+pub struct Inst {
+    op: Op,
+    args: Vec<Reg>,
+    dests: Vec<Reg>
+}
+
+impl Inst {
+    pub fn new(op: Op, args: Vec<Reg>, dests: Vec<Reg>) -> Inst {
+        Inst {
+            op, args, dests
+        }
+    }
+
+    pub fn op(&self) -> &Op {
+        &self.op
+    }
+
+    pub fn args(&self) -> &Vec<Reg> {
+        &self.args
+    }
+
+    pub fn args_mut(&mut self) -> &mut Vec<Reg> {
+        &mut self.args
+    }
+
+    pub fn dests(&self) -> &Vec<Reg> {
+        &self.dests
+    }
+}
+
+/// How control leaves a block
+#[derive(Debug, Clone)]
+pub enum Terminator {
+    Jump(BlockIndex),
+    /// Condition, true target, false target
+    Branch(Reg, BlockIndex, BlockIndex),
+    /// Value type, value, the target of each set of cases, default target
+    Switch(ValueType, Reg, Vec<(Vec<u64>, BlockIndex)>, BlockIndex),
+    /// Returns the values in order
+    Return(Vec<Reg>)
+}
+
+impl Terminator {
+    /// The blocks control may move to, once for each way it can get there
+    pub fn successors(&self) -> Vec<BlockIndex> {
+        match self {
+            Terminator::Jump(target) => vec![ *target ],
+            Terminator::Branch(_, true_target, false_target) => vec![ *true_target, *false_target ],
+            Terminator::Switch(_, _, cases, default) => cases.iter().map(|(_, target)| *target).chain(std::iter::once(*default)).collect(),
+            Terminator::Return(_) => Vec::new()
+        }
+    }
+
+    pub fn args(&self) -> Vec<Reg> {
+        match self {
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch(cond, _, _) => vec![ *cond ],
+            Terminator::Switch(_, value, _, _) => vec![ *value ],
+            Terminator::Return(values) => values.clone()
+        }
+    }
+
+    fn regs_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Terminator::Jump(_) => Vec::new(),
+            Terminator::Branch(cond, _, _) => vec![ cond ],
+            Terminator::Switch(_, value, _, _) => vec![ value ],
+            Terminator::Return(values) => values.iter_mut().collect()
+        }
+    }
+
+    fn targets_mut(&mut self) -> Vec<&mut BlockIndex> {
+        match self {
+            Terminator::Jump(target) => vec![ target ],
+            Terminator::Branch(_, true_target, false_target) => vec![ true_target, false_target ],
+            Terminator::Switch(_, _, cases, default) => cases.iter_mut().map(|(_, target)| target).chain(std::iter::once(default)).collect(),
+            Terminator::Return(_) => Vec::new()
+        }
+    }
+}
+
+/// Code which control enters at the start and leaves at the end, once its phis have chosen their values
+#[derive(Debug, Clone)]
+pub struct Block {
+    phis: Vec<Phi>,
+    insts: Vec<Inst>,
+    terminator: Terminator
+}
+
+impl Block {
+    pub fn new(phis: Vec<Phi>, insts: Vec<Inst>, terminator: Terminator) -> Block {
+        Block {
+            phis, insts, terminator
+        }
+    }
+
+    pub fn phis(&self) -> &Vec<Phi> {
+        &self.phis
+    }
+
+    pub fn phis_mut(&mut self) -> &mut Vec<Phi> {
+        &mut self.phis
+    }
+
+    pub fn insts(&self) -> &Vec<Inst> {
+        &self.insts
+    }
+
+    pub fn insts_mut(&mut self) -> &mut Vec<Inst> {
+        &mut self.insts
+    }
+
+    pub fn terminator(&self) -> &Terminator {
+        &self.terminator
+    }
+
+    pub fn terminator_mut(&mut self) -> &mut Terminator {
+        &mut self.terminator
+    }
+
+    pub fn successors(&self) -> Vec<BlockIndex> {
+        self.terminator.successors()
+    }
+}
+
+/// The code of a function as a graph of blocks, starting at the first. Locals holding a number, bool or reference are kept
+/// in registers, and any other local, or one an asm block binds, is read and written through paths as it was before.
+#[derive(Debug, Clone)]
+pub struct SsaFunction {
+    blocks: Vec<Block>,
+    regs: Vec<ValueType>
+}
+
+impl SsaFunction {
+    pub fn entry(&self) -> BlockIndex {
+        BlockIndex::new(0)
+    }
+
+    pub fn blocks(&self) -> &Vec<Block> {
+        &self.blocks
+    }
+
+    pub fn block(&self, idx: BlockIndex) -> &Block {
+        &self.blocks[idx.idx()]
+    }
+
+    pub fn block_mut(&mut self, idx: BlockIndex) -> &mut Block {
+        &mut self.blocks[idx.idx()]
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Adds a register of the given type, which must then be written by one instruction or phi
+    pub fn new_reg(&mut self, value_type: ValueType) -> Reg {
+        self.regs.push(value_type);
+        Reg::new(self.regs.len() - 1)
+    }
+
+    pub fn reg_type(&self, reg: Reg) -> &ValueType {
+        &self.regs[reg.idx()]
+    }
+
+    pub fn reg_count(&self) -> usize {
+        self.regs.len()
+    }
+
+    /// The blocks control may come to each block from, once for each way it can get there
+    pub fn predecessors(&self) -> Vec<Vec<BlockIndex>> {
+        let mut preds = vec![ Vec::new(); self.blocks.len() ];
+        for (b, block) in self.blocks.iter().enumerate() {
+            for succ in block.successors() {
+                preds[succ.idx()].push(BlockIndex::new(b));
+            }
+        }
+        preds
+    }
+
+    /// The blocks which can be reached from the entry, each after those which come before it on every path to it other
+    /// than through a loop
+    pub fn reverse_postorder(&self) -> Vec<BlockIndex> {
+        let mut visited = vec![ false; self.blocks.len() ];
+        let mut order = Vec::new();
+
+        // Each entry is a block and how many of its successors have been visited
+        let mut stack = vec![ (self.entry(), 0) ];
+        visited[0] = true;
+        while let Some((block, next)) = stack.pop() {
+            let succs = self.block(block).successors();
+            match succs.get(next) {
+                Some(succ) => {
+                    stack.push((block, next + 1));
+                    if !visited[succ.idx()] {
+                        visited[succ.idx()] = true;
+                        stack.push((*succ, 0));
+                    }
+                },
+                None => order.push(block)
+            }
+        }
+
+        order.reverse();
+        order
+    }
+}
+
+impl std::fmt::Display for Inst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (d, dest) in self.dests.iter().enumerate() {
+            f.write_str(if d == 0 { "" } else { ", " })?;
+            dest.fmt(f)?;
+        }
+        if !self.dests.is_empty() {
+            f.write_str(" = ")?;
+        }
+
+        match &self.op {
+            Op::Param(idx) => f.write_fmt(format_args!("param {}", idx))?,
+            Op::Ins(ins) => ins.fmt(f)?,
+            Op::Load(path, vt) => f.write_fmt(format_args!("load %{} {}", vt, path))?,
+            Op::Store(path, vt) => f.write_fmt(format_args!("store %{} {}", vt, path))?
+        }
+
+        for arg in &self.args {
+            f.write_fmt(format_args!(" {}", arg))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Terminator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Terminator::Jump(target) => f.write_fmt(format_args!("jump {}", target)),
+            Terminator::Branch(cond, true_target, false_target) => f.write_fmt(format_args!("branch {} {} {}", cond, true_target, false_target)),
+            Terminator::Switch(vt, value, cases, default) => {
+                f.write_fmt(format_args!("switch %{} {}", vt, value))?;
+                for (values, target) in cases {
+                    let values = values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+                    f.write_fmt(format_args!(" [{}] {}", values.join(", "), target))?;
+                }
+                f.write_fmt(format_args!(" default {}", default))
+            },
+            Terminator::Return(values) => {
+                f.write_str("ret")?;
+                for value in values {
+                    f.write_fmt(format_args!(" {}", value))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SsaFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (b, block) in self.blocks.iter().enumerate() {
+            f.write_fmt(format_args!("block {}:\n", b))?;
+
+            for phi in &block.phis {
+                f.write_fmt(format_args!("\t{} = phi", phi.dest))?;
+                for (from, arg) in &phi.args {
+                    f.write_fmt(format_args!(" {}:{}", from, arg))?;
+                }
+                f.write_char('\n')?;
+            }
+
+            for inst in &block.insts {
+                f.write_fmt(format_args!("\t{}\n", inst))?;
+            }
+
+            f.write_fmt(format_args!("\t{}\n", block.terminator))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+
+const LIBRARY: &str = "struct \"Point\" @repr(c) {
+	\"x\": i32,
+	\"y\": i32
+}
+global 0: #slicedata(u8) = data \"lib\"
+global 1:\"name\" readonly #slice(u8) = slice(#glbl(0), 0, 3)
+func 0:\"sum\" (ref(#comp(\"Point\"))) -> (i32) @method(\"Point\") {
+	local 0: ref(#comp(\"Point\"))
+	pushpath %ref(#comp(\"Point\")) #lcl(0)
+	push %ref(#comp(\"Point\"))
+	pushpath %i32 deref(#comp(\"Point\"))/prop(0)
+	push %i32
+	pushpath %ref(#comp(\"Point\")) #lcl(0)
+	push %ref(#comp(\"Point\"))
+	pushpath %i32 deref(#comp(\"Point\"))/prop(1)
+	push %i32
+	add %i32
+	ret
+}
+func 1:\"next_id\" (u32) -> (u32) {
+	local 0: u32
+	pushpath %u32 #lcl(0)
+	push %u32
+	inc %u32 1
+	ret
+}
+func 2:\"putchar\" (u32) @extern(\"putchar\") extern
+";
+
+/// The kind of unit built from a file which imports the library without linking it, so only has its declarations
+const PROGRAM: &str = "struct \"Point\" @repr(c) {
+	\"x\": i32,
+	\"y\": i32
+}
+global 0: i8 = i8 -3
+global 1: ref(i8) = ref #glbl(0)
+func 0:\"sum\" (ref(#comp(\"Point\"))) -> (i32) @method(\"Point\") extern
+func 1:\"next_id\" (u32) -> (u32) extern
+func 2:\"main\" () -> (i32) @entry {
+	local 0: ref(#comp(\"Point\"))
+	pushpath %ref(#comp(\"Point\")) #lcl(0)
+	push %ref(#comp(\"Point\"))
+	call #fn(0)
+	pushlit %u32 4
+	call #fn(1)
+	drop
+	ret
+}
+";
+
+/// Normal test - Verify that a unit is read back from its binary encoding unchanged
+#[test]
+fn binary_round_trip() {
+    for source in [LIBRARY, PROGRAM, super::ir_text::UNIT] {
+        let unit = parse_unit(source).unwrap();
+        let read = TranslationUnit::from_binary(&unit.to_binary()).unwrap();
+        assert_eq!(read.to_string(), source);
+    }
+}
+
+/// Normal test - Verify that merging resolves extern functions by name, shares types of the same name, and renumbers the
+/// globals and functions of the object
+#[test]
+fn binary_merge() {
+    let mut unit = parse_unit(PROGRAM).unwrap();
+    unit.merge_binary(&parse_unit(LIBRARY).unwrap().to_binary()).unwrap();
+    assert!(unit.validate().is_ok());
+
+    assert_eq!(unit.compound_types().len(), 1);
+    assert_eq!(unit.global_count(), 4);
+    assert_eq!(unit.function_count(), 4);
+
+    // The definitions take the place of the externs, and everything else is added after what was already there
+    assert!(!unit.get_function(FunctionIndex::new(0)).unwrap().is_extern());
+    assert!(!unit.get_function(FunctionIndex::new(1)).unwrap().is_extern());
+    assert_eq!(unit.get_function(FunctionIndex::new(3)).unwrap().name(), "putchar");
+    assert!(matches!(unit.get_global(GlobalIndex::new(3)).unwrap().default(), Some(StorableValue::Slice(global, 0, 3)) if global.idx() == 2));
+
+    // Merging the other way around gives the same functions, whichever side they were defined on
+    let mut unit = parse_unit(LIBRARY).unwrap();
+    unit.merge(&parse_unit(PROGRAM).unwrap()).unwrap();
+    assert!(unit.validate().is_ok());
+    assert_eq!(unit.function_count(), 4);
+    assert_eq!(unit.functions().iter().filter(|func| func.is_extern()).count(), 1);
+}
+
+/// Erroneous test - Verify that objects which are not valid, or which disagree with the unit, are rejected
+#[test]
+fn binary_errors() {
+    let library = parse_unit(LIBRARY).unwrap();
+    let bytes = library.to_binary();
+
+    assert_eq!(TranslationUnit::from_binary(b"\x7fELF").err(), Some(BinaryError::NotAnObject));
+    assert_eq!(TranslationUnit::from_binary(&bytes[..bytes.len() - 1]).err(), Some(BinaryError::UnexpectedEnd));
+
+    let mut newer = bytes.clone();
+    newer[4] = 2;
+    assert_eq!(TranslationUnit::from_binary(&newer).err(), Some(BinaryError::UnsupportedVersion(2)));
+
+    let mut unit = parse_unit(LIBRARY).unwrap();
+    assert_eq!(unit.merge(&library).err(), Some(BinaryError::DuplicateFunction("sum".to_string())));
+
+    let mut unit = parse_unit("struct \"Point\" {\n\t\"x\": i32,\n\t\"y\": i32\n}").unwrap();
+    assert_eq!(unit.merge_binary(&bytes).err(), Some(BinaryError::TypeMismatch("Point".to_string())));
+
+    let mut unit = parse_unit("func \"putchar\" (u8) extern").unwrap();
+    assert_eq!(unit.merge_binary(&bytes).err(), Some(BinaryError::SignatureMismatch("putchar".to_string())));
+}
\ No newline at end of file
@@ -106,7 +106,10 @@ fn loop_not_a_bool_cond() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackIncorrectType);
+    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::new(
+        ValidationLocation::Function(FunctionIndex::new(0), vec![ 3 ]),
+        ValidationErrorKind::StackIncorrectType(ValueType::Bool, ValueType::I32)
+    ));
 }
 
 /// Erroneous test - Verify that a program which attempts to loop with a non-empty stack is considered invalid
@@ -165,7 +168,7 @@ fn loop_not_empty_stack() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackDepthNotZero);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackDepthNotZero);
 }
 
 /// Erroneous test - Verify that a program which attempts to loop within it's body with a non-empty stack is considered invalid
@@ -224,7 +227,7 @@ fn loop_not_empty_stack_after_body() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackDepthNotZero);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackDepthNotZero);
 }
 
 /// Erroneous test - Verify that a program with more than one value in a loop condition is considered invalid
@@ -282,7 +285,7 @@ fn loop_not_one_after_condition() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackDepthNotOne);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackDepthNotOne);
 }
 
 /// Erroneous test - Verify that a program which does not terminate with a return is considered invalid
@@ -337,7 +340,7 @@ fn loop_missing_ret() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::NoFinalReturn);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::NoFinalReturn);
 }
 
 /// Normal / boundary test - Verify that a valid program containing nested if/if-else statements are considered correct
@@ -354,7 +357,7 @@ fn if_correct() {
         let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
         let param2 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
 
-        func.push(Ins::IfElse(vec![ // True
+        func.push(Ins::IfElse(None, vec![ // True
             Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
             Ins::Push(ValueType::I32),
 
@@ -415,7 +418,7 @@ fn if_no_return() {
         let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
         let param2 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
 
-        func.push(Ins::IfElse(vec![ // True
+        func.push(Ins::IfElse(None, vec![ // True
             Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
             Ins::Push(ValueType::I32),
 
@@ -457,7 +460,7 @@ fn if_no_return() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::NoFinalReturn);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::NoFinalReturn);
 }
 
 /// Erroneous - Verify that entering an if statement with a non-empty stack is erroneous
@@ -474,7 +477,7 @@ fn if_non_empty_stack() {
         let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
         let param2 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
 
-        func.push(Ins::IfElse(vec![ // True
+        func.push(Ins::IfElse(None, vec![ // True
             Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
             Ins::Push(ValueType::I32),
 
@@ -510,5 +513,199 @@ fn if_non_empty_stack() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackDepthNotZero);
-}
\ No newline at end of file
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackDepthNotZero);
+}
+/// Normal test - Verify that an if-else leaving a value of the same type from both branches can be used in an expression
+#[test]
+fn if_else_value_correct() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("pick", Signature::new(vec![
+            ValueType::I32,
+        ], vec![
+            ValueType::I32,
+        ]));
+        
+        let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
+
+        func.push(Ins::PushLiteral(ValueType::I32, 1));
+        func.push(Ins::IfElse(Some(ValueType::I32), vec![ // True
+            Ins::PushLiteral(ValueType::I32, 2),
+        ], vec![ // False
+            Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
+            Ins::Push(ValueType::I32),
+        ], vec![ // Condition
+            Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
+            Ins::Push(ValueType::I32),
+
+            Ins::PushLiteral(ValueType::I32, 1),
+
+            Ins::Eq(ValueType::I32)
+        ]));
+        func.push(Ins::Add(ValueType::I32));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    unit.validate().expect("Invalid IR");
+}
+
+/// Erroneous test - Verify that an if-else with a branch which leaves a value of a type other than the one it declares is erroneous
+#[test]
+fn if_else_value_mismatch() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("pick", Signature::new(vec![], vec![
+            ValueType::I32,
+        ]));
+
+        func.push(Ins::IfElse(Some(ValueType::I32), vec![ // True
+            Ins::PushLiteral(ValueType::I32, 2),
+        ], vec![ // False
+            Ins::PushLiteral(ValueType::I64, 3),
+        ], vec![ // Condition
+            Ins::PushLiteral(ValueType::Bool, 1),
+        ]));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::BranchValueMismatch(Some(ValueType::I32), Some(ValueType::I64)));
+}
+
+/// Erroneous test - Verify that an if-else which declares no value is erroneous if its branches leave one
+#[test]
+fn if_else_value_undeclared() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("pick", Signature::new(vec![], vec![
+            ValueType::I32,
+        ]));
+
+        func.push(Ins::IfElse(None, vec![ // True
+            Ins::PushLiteral(ValueType::I32, 2),
+        ], vec![ // False
+            Ins::PushLiteral(ValueType::I32, 3),
+        ], vec![ // Condition
+            Ins::PushLiteral(ValueType::Bool, 1),
+        ]));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::BranchValueMismatch(None, Some(ValueType::I32)));
+}
+
+/// Normal test - Verify that a switch whose cases all return is considered valid by the checker
+#[test]
+fn switch_correct() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("classify", Signature::new(vec![
+            ValueType::I32,
+        ], vec![
+            ValueType::I32,
+        ]));
+        
+        let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
+
+        func.push(Ins::Switch(ValueType::I32, vec![
+            (vec![ 1, 2 ], vec![
+                Ins::PushLiteral(ValueType::I32, 10),
+                Ins::Ret,
+            ]),
+            (vec![ -1i64 as u64 ], vec![
+                Ins::PushLiteral(ValueType::I32, 20),
+                Ins::Ret,
+            ]),
+        ], vec![ // Default
+            Ins::PushLiteral(ValueType::I32, 30),
+            Ins::Ret,
+        ], vec![ // Value
+            Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(param1, StorableType::Value(ValueType::I32))), ValueType::I32),
+            Ins::Push(ValueType::I32),
+        ]));
+
+        func
+    });
+
+    unit.validate().expect("Invalid IR");
+}
+
+/// Erroneous test - Verify that a switch listing the same value in two cases is erroneous
+#[test]
+fn switch_duplicate_case() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("classify", Signature::new(vec![], vec![]));
+
+        func.push(Ins::Switch(ValueType::I32, vec![
+            (vec![ 1, 2 ], vec![]),
+            (vec![ 2 ], vec![]),
+        ], vec![ // Default
+        ], vec![ // Value
+            Ins::PushLiteral(ValueType::I32, 2),
+        ]));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::DuplicateCase(2));
+}
+
+/// Correct test - Verify that an assembly block bound to locals of the right types is correct
+#[test]
+fn asm_correct() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("add_five", Signature::new(vec![
+            ValueType::I64,
+        ], vec![
+            ValueType::I64,
+        ]));
+
+        let param1 = func.push_local(Local::new(StorableType::Value(ValueType::I64)));
+        let result = func.push_local(Local::new(StorableType::Value(ValueType::I64)));
+
+        func.push(Ins::Asm(AsmBlock::new(AsmArch::X8664, vec![
+            AsmBinding::new("rax", param1, ValueType::I64),
+        ], vec![
+            AsmBinding::new("rax", result, ValueType::I64),
+        ], vec![
+            AsmIns::new("add", vec![ AsmOperand::Reg("rax".to_string()), AsmOperand::Imm(5) ]),
+        ])));
+        func.push(Ins::PushPath(ValuePath::new_origin_only(ValuePathOrigin::Local(result, StorableType::Value(ValueType::I64))), ValueType::I64));
+        func.push(Ins::Push(ValueType::I64));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    unit.validate().expect("Invalid IR");
+}
+
+/// Erroneous test - Verify that binding a register to a local of a different type is erroneous
+#[test]
+fn asm_binding_incorrect_type() {
+    let mut unit = TranslationUnit::new();
+    unit.add_function({
+        let mut func = Function::new("add_five", Signature::new(vec![], vec![]));
+
+        let local = func.push_local(Local::new(StorableType::Value(ValueType::I32)));
+
+        func.push(Ins::Asm(AsmBlock::new(AsmArch::X8664, vec![
+            AsmBinding::new("rax", local, ValueType::I64),
+        ], vec![], vec![
+            AsmIns::new("add", vec![ AsmOperand::Reg("rax".to_string()), AsmOperand::Imm(5) ]),
+        ])));
+        func.push(Ins::Ret);
+
+        func
+    });
+
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::LocalIncorrectType(StorableType::Value(ValueType::I64), StorableType::Value(ValueType::I32)));
+}
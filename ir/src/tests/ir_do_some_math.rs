@@ -80,7 +80,7 @@ fn too_few_locals() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::LocalUnderflow);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::LocalUnderflow);
 }
 
 /// Erroneous test - Verify that a program where an attempt to access a local with an incorrect given type is considered invalid
@@ -122,7 +122,7 @@ fn incorrect_local_type_access() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::LocalIncorrectType);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::LocalIncorrectType(StorableType::Value(ValueType::I64), StorableType::Value(ValueType::I32)));
 }
 
 /// Erroneous / boundary test - Verify that a program where an attempt to access a local which does not exist is considered invalid
@@ -164,7 +164,7 @@ fn out_of_bounds_local_access() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::LocalDoesNotExist);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::LocalDoesNotExist(LocalIndex::new(2)));
 }
 
 /// Erroneous / boundary test - Verify that a program with one too few return values compared to it's signature is considered invalid
@@ -206,7 +206,7 @@ fn too_small_return() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackUnderflow);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackUnderflow);
 }
 
 /// Erroneous / boundary test - Verify that a program with one too many return values compared to it's signature is considered invalid
@@ -246,7 +246,7 @@ fn too_big_return() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackDepthNotZero);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackDepthNotZero);
 }
 
 /// Erroneous test - Verify that a program which attempts to take a an item off the stack with the incorrect type is considered invalid
@@ -288,7 +288,10 @@ fn invalid_stack_access() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackIncorrectType);
+    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::new(
+        ValidationLocation::Function(FunctionIndex::new(0), vec![ 3 ]),
+        ValidationErrorKind::StackIncorrectType(ValueType::I64, ValueType::I32)
+    ));
 }
 
 /// Erroneous test - Verify that a program which attempts to operate on more items than there are on the stack is considered invalid
@@ -330,5 +333,5 @@ fn stack_underflow() {
         func
     });
 
-    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::StackUnderflow);
+    assert_eq!(unit.validate().expect_err("Not incorrect").kind(), &ValidationErrorKind::StackUnderflow);
 }
\ No newline at end of file
@@ -0,0 +1,261 @@
+use crate::*;
+
+pub(super) const MATH: &str = "func 0:\"div_mod\" (i32, i32) -> (i32, i32) {
+	local 0: i32
+	local 1: i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushpath %i32 #lcl(1)
+	push %i32
+	div %i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushpath %i32 #lcl(1)
+	push %i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushpath %i32 #lcl(1)
+	push %i32
+	div %i32
+	mul %i32
+	sub %i32
+	ret
+}
+func 1:\"wrap\" (u8) -> (u8) {
+	local 0: u8
+	pushpath %u8 #lcl(0)
+	push %u8
+	inc %u8 250
+	ret
+}
+func 2:\"widen\" (i8) -> (u32, i64, bool) {
+	local 0: i8
+	pushpath %i8 #lcl(0)
+	push %i8
+	conv i8, u32
+	pushpath %i8 #lcl(0)
+	push %i8
+	conv i8, i64
+	pushpath %i8 #lcl(0)
+	push %i8
+	pushlit %i8 0
+	lt %i8
+	ret
+}
+";
+
+pub(super) const CONTROL_FLOW: &str = "func 0:\"putchar\" (u32) extern
+func 1:\"exit\" (i32) extern
+func 2:\"main\" () -> (i32) @entry {
+	local 0: u32
+	loop
+		code {
+			if
+				then {
+					continue 1
+				}
+				cond {
+					pushpath %u32 #lcl(0)
+					push %u32
+					pushlit %u32 1
+					eq %u32
+				}
+			if
+				then {
+					break 1
+				}
+				cond {
+					pushpath %u32 #lcl(0)
+					push %u32
+					pushlit %u32 4
+					eq %u32
+				}
+			pushpath %u32 #lcl(0)
+			push %u32
+			pushlit %u32 48
+			add %u32
+			call #fn(0)
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+			pushpath %u32 #lcl(0)
+			pushpath %u32 #lcl(0)
+			push %u32
+			inc %u32 1
+			pop %u32
+		}
+	switch %u32
+		case 3, 4 {
+			pushlit %u32 33
+			call #fn(0)
+		}
+		default {
+		}
+		value {
+			pushpath %u32 #lcl(0)
+			push %u32
+		}
+	if
+		then {
+			pushlit %i32 3
+			call #fn(1)
+		}
+		cond {
+			pushpath %u32 #lcl(0)
+			push %u32
+			pushlit %u32 5
+			eq %u32
+		}
+	if %i32
+		then {
+			pushlit %i32 7
+		}
+		else {
+			pushlit %i32 9
+		}
+		cond {
+			pushlit %bool 0
+		}
+	ret
+}
+";
+
+pub(super) const HEAP: &str = "struct \"Pair\" {
+	\"a\": u32,
+	\"items\": ref(#slice(u16))
+}
+global 0: #slicedata(u8) = data \"hey\"
+global 1: #slice(u8) = slice(#glbl(0), 1, 2)
+global 2: ref(#comp(\"Pair\"))
+func 0:\"main\" () -> (i32) @entry {
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	new #comp(\"Pair\")
+	pop %ref(#comp(\"Pair\"))
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	pushpath %ref(#slice(u16)) deref(#comp(\"Pair\"))/prop(1)
+	pushlit %uptr 3
+	newslice u16
+	pop %ref(#slice(u16))
+	pushlit %uptr 2
+	index %u16
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	pushpath %ref(#slice(u16)) deref(#comp(\"Pair\"))/prop(1)
+	push %ref(#slice(u16))
+	pushpath %u16 deref(#slice(u16))/slice
+	pushlit %u16 65535
+	inc %u16 2
+	pop %u16
+	pushlit %uptr 2
+	index %u16
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	pushpath %ref(#slice(u16)) deref(#comp(\"Pair\"))/prop(1)
+	push %ref(#slice(u16))
+	pushpath %u16 deref(#slice(u16))/slice
+	push %u16
+	conv u16, i32
+	pushlit %uptr 1
+	index %u8
+	pushpath %u8 #glbl(1)/slice
+	push %u8
+	conv u8, i32
+	add %i32
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	pushpath %ref(#slice(u16)) deref(#comp(\"Pair\"))/prop(1)
+	push %ref(#slice(u16))
+	pushpath %uptr deref(#slice(u16))/length
+	push %uptr
+	conv uptr, i32
+	add %i32
+	ret
+}
+func 1:\"release\" () {
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	pushpath %ref(#slice(u16)) deref(#comp(\"Pair\"))/prop(1)
+	push %ref(#slice(u16))
+	freeslice u16
+	pushpath %ref(#comp(\"Pair\")) #glbl(2)
+	push %ref(#comp(\"Pair\"))
+	free #comp(\"Pair\")
+	ret
+}
+";
+
+/// Normal test - Verify that arithmetic wraps to the width of its type, that division and comparison follow the sign of
+/// the type, and that every return of a call is given in order
+#[test]
+fn interp_math() {
+    let unit = parse_unit(MATH).unwrap();
+    assert!(unit.validate().is_ok());
+
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+
+    assert_eq!(interp.call(FunctionIndex::new(0), &[-7i64 as u64, 2]), Ok(CallResult::Return(vec![ -3i64 as u64, -1i64 as u64 ])));
+    assert_eq!(interp.call(FunctionIndex::new(1), &[10]), Ok(CallResult::Return(vec![ 4 ])));
+    assert_eq!(interp.call(FunctionIndex::new(2), &[-1i64 as u64]), Ok(CallResult::Return(vec![ 0xffffffff, u64::MAX, 1 ])));
+}
+
+/// Normal test - Verify that continue and break move to the loop at their depth, that switches and ifs run the right
+/// code, and that exit stops the program with its code
+#[test]
+fn interp_control_flow() {
+    let unit = parse_unit(CONTROL_FLOW).unwrap();
+    assert!(unit.validate().is_ok());
+
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(unit.interpret(&mut host), Ok(9));
+    assert_eq!(host.out(), b"023!");
+
+    // Breaking out of the loop later changes what is printed, and leads to the exit
+    let unit = parse_unit(&CONTROL_FLOW.replace("pushlit %u32 4\n", "pushlit %u32 5\n")).unwrap();
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(unit.interpret(&mut host), Ok(3));
+    assert_eq!(host.out(), b"0234");
+}
+
+/// Normal test - Verify that objects and slices on the heap can be written and read through references, alongside
+/// slices of globals, and that they stay alive between calls until they are freed
+#[test]
+fn interp_heap() {
+    let unit = parse_unit(HEAP).unwrap();
+    assert!(unit.validate().is_ok());
+
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+
+    // The element wraps around to 1, the global slice starts at 'e' so its second element is 'y', and there are 3 elements
+    assert_eq!(interp.run_entry(), Ok(1 + 121 + 3));
+    assert_eq!(interp.live_object_count(), 3);
+
+    assert_eq!(interp.call(FunctionIndex::new(1), &[]), Ok(CallResult::Return(Vec::new())));
+    assert_eq!(interp.live_object_count(), 0);
+    assert_eq!(interp.call(FunctionIndex::new(1), &[]), Err(InterpError::UseAfterFree));
+}
+
+/// Erroneous test - Verify that faults which a target would crash on, and code which cannot be interpreted, are reported
+/// as errors
+#[test]
+fn interp_errors() {
+    let run = |code: &str| {
+        let unit = parse_unit(&format!("func \"mystery\" () extern\nfunc \"main\" () -> (i32) @entry {{\n\tlocal ref(u8)\n{}\n\tret\n}}", code)).unwrap();
+        assert!(unit.validate().is_ok());
+        unit.interpret(&mut StdHost::new(Vec::new()))
+    };
+
+    assert_eq!(run("pushlit %i32 1\npushlit %i32 0\ndiv %i32"), Err(InterpError::DivideByZero));
+    assert_eq!(run("pushlit %uptr 3\nindex %u8\npushlit %uptr 3\nnewslice u8\npushpath %u8 deref(#slice(u8))/slice\npush %u8\nconv u8, i32"), Err(InterpError::IndexOutOfBounds(3, 3)));
+    assert_eq!(run("pushpath %ref(u8) #lcl(0)\npush %ref(u8)\npushpath %u8 deref(u8)\npush %u8\nconv u8, i32"), Err(InterpError::NullDereference));
+    assert_eq!(run("pushpath %ref(u8) #lcl(0)\nnew u8\npop %ref(u8)\npushpath %ref(u8) #lcl(0)\npush %ref(u8)\nfree u8\npushpath %ref(u8) #lcl(0)\npush %ref(u8)\nfree u8\npushlit %i32 0"), Err(InterpError::InvalidFree));
+    assert_eq!(run("call #fn(0)\npushlit %i32 0"), Err(InterpError::UnknownExtern("mystery".to_string())));
+    assert_eq!(run("asm x86_64 code {\n\tnop;\n}\npushlit %i32 0"), Err(InterpError::Asm));
+
+    let unit = parse_unit("func \"f\" () {\n\tret\n}").unwrap();
+    assert_eq!(unit.interpret(&mut StdHost::new(Vec::new())), Err(InterpError::NoEntry));
+}
\ No newline at end of file
@@ -0,0 +1,63 @@
+use crate::*;
+
+fn header(repr: StructRepr, min_align: Option<usize>) -> CompoundTypeRef {
+    CompoundType::new("Header", CompoundContent::Struct({
+        let mut content = StructContent::new();
+        content.set_repr(repr);
+        content.set_min_align(min_align);
+        content.push_prop(StructProperty::new("tag", StorableType::Value(ValueType::U8)));
+        content.push_prop(StructProperty::new("data", StorableType::Slice(Box::new(StorableType::Value(ValueType::U8)))));
+        content.push_prop(StructProperty::new("len", StorableType::Value(ValueType::U16)));
+        content
+    }))
+}
+
+/// Normal test - Verify that properties are placed without padding unless the struct asks otherwise
+#[test]
+fn default_layout() {
+    let ct = header(StructRepr::Default, None);
+    assert_eq!(ct.prop_offsets(8), vec![0, 1, 17]);
+    assert_eq!(ct.size(8), 19);
+    assert_eq!(ct.align(8), 1);
+
+    let ct = header(StructRepr::Packed, None);
+    assert_eq!(ct.prop_offsets(4), vec![0, 1, 9]);
+    assert_eq!(ct.size(4), 11);
+    assert_eq!(ct.align(4), 1);
+}
+
+/// Normal test - Verify that repr(C) structs are padded as a C compiler would pad them, for either pointer width
+#[test]
+fn c_layout() {
+    let ct = header(StructRepr::C, None);
+    assert_eq!(ct.prop_offsets(8), vec![0, 8, 24]);
+    assert_eq!(ct.size(8), 32);
+    assert_eq!(ct.align(8), 8);
+
+    assert_eq!(ct.prop_offset(PropertyIndex::new(2), 4), 12);
+    assert_eq!(ct.size(4), 16);
+    assert_eq!(ct.align(4), 4);
+
+    let outer = CompoundType::new("Outer", CompoundContent::Struct({
+        let mut content = StructContent::new();
+        content.set_repr(StructRepr::C);
+        content.push_prop(StructProperty::new("flag", StorableType::Value(ValueType::Bool)));
+        content.push_prop(StructProperty::new("header", StorableType::Compound(ct.clone())));
+        content
+    }));
+    assert_eq!(outer.prop_offsets(8), vec![0, 8]);
+    assert_eq!(outer.size(8), 40);
+}
+
+/// Normal test - Verify that a minimum alignment raises the alignment and rounds up the size, but never lowers either
+#[test]
+fn min_align_layout() {
+    let ct = header(StructRepr::Packed, Some(8));
+    assert_eq!(ct.prop_offsets(8), vec![0, 1, 17]);
+    assert_eq!(ct.size(8), 24);
+    assert_eq!(ct.align(8), 8);
+
+    let ct = header(StructRepr::C, Some(2));
+    assert_eq!(ct.size(8), 32);
+    assert_eq!(ct.align(8), 8);
+}
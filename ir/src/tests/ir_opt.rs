@@ -0,0 +1,532 @@
+use crate::*;
+use crate::opt::*;
+
+/// Optimises the unit at the given level for a 64 bit target, checking that it is still valid afterwards
+fn optimise(source: &str, level: u8) -> TranslationUnit {
+    optimise_for(source, level, 8)
+}
+
+/// Optimises the unit at the given level for a target whose pointers are `ptr_size` bytes
+fn optimise_for(source: &str, level: u8, ptr_size: usize) -> TranslationUnit {
+    let mut unit = parse_unit(source).unwrap();
+    assert!(unit.validate().is_ok());
+
+    PassManager::for_level(level, ptr_size).run(&mut unit);
+    assert!(unit.validate().is_ok());
+    unit
+}
+
+/// Normal test - Verify that arithmetic, comparisons and conversions of literals are folded into the literal they give,
+/// wrapping to the width of their type, and that division by zero is left to fault
+#[test]
+fn opt_fold() {
+    let unit = optimise("func 0:\"f\" () -> (i32, bool, u8, i32) {
+	pushlit %i32 2
+	pushlit %i32 3
+	mul %i32
+	inc %i32 4
+	pushlit %i32 5
+	sub %i32
+	pushlit %i32 1
+	pushlit %i32 2
+	lt %i32
+	pushlit %bool 1
+	and
+	pushlit %i32 300
+	conv i32, u8
+	pushlit %i32 1
+	pushlit %i32 0
+	div %i32
+	ret
+}", 1);
+
+    assert_eq!(unit.to_string(), "func 0:\"f\" () -> (i32, bool, u8, i32) {
+	pushlit %i32 5
+	pushlit %bool 1
+	pushlit %u8 44
+	pushlit %i32 1
+	pushlit %i32 0
+	div %i32
+	ret
+}
+");
+
+    // Nothing is changed at level 0
+    let unit = optimise("func 0:\"f\" () -> (i32) {\n\tpushlit %i32 1\n\tneg %i32\n\tret\n}", 0);
+    assert_eq!(unit.to_string(), "func 0:\"f\" () -> (i32) {\n\tpushlit %i32 1\n\tneg %i32\n\tret\n}\n");
+    let unit = optimise("func 0:\"f\" () -> (i32) {\n\tpushlit %i32 1\n\tneg %i32\n\tret\n}", 1);
+    assert_eq!(unit.to_string(), "func 0:\"f\" () -> (i32) {\n\tpushlit %i32 18446744073709551615\n\tret\n}\n");
+}
+
+/// Normal test - Verify that pointer sized arithmetic wraps to the width of the target's pointers
+#[test]
+fn opt_fold_pointer_width() {
+    let source = "func 0:\"f\" () -> (uptr, iptr) {
+	pushlit %uptr 4294967295
+	inc %uptr 1
+	pushlit %iptr 2147483647
+	inc %iptr 1
+	ret
+}";
+
+    let unit = optimise_for(source, 1, 4);
+    assert_eq!(unit.to_string(), "func 0:\"f\" () -> (uptr, iptr) {
+	pushlit %uptr 0
+	pushlit %iptr 18446744071562067968
+	ret
+}
+");
+
+    let unit = optimise_for(source, 1, 8);
+    assert_eq!(unit.to_string(), "func 0:\"f\" () -> (uptr, iptr) {
+	pushlit %uptr 4294967296
+	pushlit %iptr 2147483648
+	ret
+}
+");
+}
+
+/// Normal test - Verify that a local assigned a literal once, before it is read, is replaced by the literal, even within
+/// blocks, and that locals which may hold something else when they are read are left alone
+#[test]
+fn opt_propagate() {
+    let source = "func 0:\"f\" (i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	local 2: i32
+	local 3: i32
+	pushpath %i32 #lcl(1)
+	pushlit %i32 10
+	pop %i32
+	pushlit %i32 7
+	poplocal %i32 #lcl(2)
+	loop
+		code {
+			pushpath %i32 #lcl(2)
+			push %i32
+			pushpath %i32 #lcl(1)
+			push %i32
+			add %i32
+			pushpath %i32 #lcl(3)
+			push %i32
+			add %i32
+			pushpath %i32 #lcl(0)
+			push %i32
+			add %i32
+			poplocal %i32 #lcl(3)
+			break 0
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+		}
+	pushpath %i32 #lcl(3)
+	push %i32
+	ret
+}";
+
+    let unit = optimise(source, 1);
+    assert_eq!(unit.to_string(), "func 0:\"f\" (i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	local 2: i32
+	local 3: i32
+	loop
+		code {
+			pushlit %i32 17
+			pushpath %i32 #lcl(3)
+			push %i32
+			add %i32
+			pushpath %i32 #lcl(0)
+			push %i32
+			add %i32
+			poplocal %i32 #lcl(3)
+			break 0
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+		}
+	pushpath %i32 #lcl(3)
+	push %i32
+	ret
+}
+");
+
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(Interpreter::new(&unit, &mut host).call(FunctionIndex::new(0), &[5]), Ok(CallResult::Return(vec![ 22 ])));
+
+    // A read before the assignment sees the local's default, and a second assignment may change it
+    let source = "func 0:\"f\" () -> (i32) {
+	local 0: i32
+	local 1: i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushlit %i32 1
+	poplocal %i32 #lcl(0)
+	pushpath %i32 #lcl(0)
+	push %i32
+	add %i32
+	pushlit %i32 2
+	poplocal %i32 #lcl(1)
+	pushlit %i32 3
+	poplocal %i32 #lcl(1)
+	pushpath %i32 #lcl(1)
+	push %i32
+	add %i32
+	ret
+}";
+    assert_eq!(optimise(source, 1).to_string(), parse_unit(source).unwrap().to_string());
+}
+/// Normal test - Verify that a small function is put in place of each call to it, with its params and locals moved into the
+/// caller, and that a function returning from more than one place is wrapped in a loop each return breaks out of
+#[test]
+fn opt_inline() {
+    let source = "func 0:\"sub\" (i32, i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushpath %i32 #lcl(1)
+	push %i32
+	sub %i32
+	ret
+}
+func 1:\"max\" (i32, i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	if
+		then {
+			pushpath %i32 #lcl(0)
+			push %i32
+			ret
+		}
+		cond {
+			pushpath %i32 #lcl(0)
+			push %i32
+			pushpath %i32 #lcl(1)
+			push %i32
+			gt %i32
+		}
+	pushpath %i32 #lcl(1)
+	push %i32
+	ret
+}
+func 2:\"f\" (i32) -> (i32) {
+	local 0: i32
+	pushlit %i32 100
+	pushpath %i32 #lcl(0)
+	push %i32
+	pushlit %i32 3
+	call #fn(0)
+	pushlit %i32 5
+	call #fn(1)
+	add %i32
+	ret
+}";
+
+    let unit = optimise(source, 2);
+    assert!(unit.to_string().ends_with("func 2:\"f\" (i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	local 2: i32
+	local 3: i32
+	local 4: i32
+	local 5: i32
+	local 6: i32
+	pushlit %i32 100
+	pushpath %i32 #lcl(0)
+	push %i32
+	poplocal %i32 #lcl(1)
+	if
+		then {
+			pushpath %i32 #lcl(1)
+			push %i32
+			pushlit %i32 3
+			sub %i32
+			poplocal %i32 #lcl(3)
+		}
+		else {
+		}
+		cond {
+			pushlit %bool 1
+		}
+	pushpath %i32 #lcl(3)
+	push %i32
+	poplocal %i32 #lcl(4)
+	if
+		then {
+			loop
+				code {
+					if
+						then {
+							pushpath %i32 #lcl(4)
+							push %i32
+							poplocal %i32 #lcl(6)
+							break 1
+						}
+						cond {
+							pushpath %i32 #lcl(4)
+							push %i32
+							pushlit %i32 5
+							gt %i32
+						}
+					pushlit %i32 5
+					poplocal %i32 #lcl(6)
+					break 0
+				}
+				cond {
+					pushlit %bool 1
+				}
+				inc {
+				}
+		}
+		else {
+		}
+		cond {
+			pushlit %bool 1
+		}
+	pushpath %i32 #lcl(6)
+	push %i32
+	add %i32
+	ret
+}
+"));
+
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(FunctionIndex::new(2), &[10]), Ok(CallResult::Return(vec![ 107 ])));
+    assert_eq!(interp.call(FunctionIndex::new(2), &[-10i64 as u64]), Ok(CallResult::Return(vec![ 105 ])));
+}
+
+/// Normal test - Verify that a function marked to be inlined is inlined however large it is, and that a function is never
+/// inlined into itself
+#[test]
+fn opt_inline_limits() {
+    let source = "func 0:\"id\" (i32) -> (i32) @inline {
+	local 0: i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	ret
+}
+func 1:\"count\" (i32) -> (i32) {
+	local 0: i32
+	if
+		then {
+			pushlit %i32 0
+			ret
+		}
+		cond {
+			pushpath %i32 #lcl(0)
+			push %i32
+			pushlit %i32 0
+			eq %i32
+		}
+	pushpath %i32 #lcl(0)
+	push %i32
+	dec %i32 1
+	call #fn(1)
+	inc %i32 1
+	ret
+}
+func 2:\"f\" (i32) -> (i32) {
+	local 0: i32
+	pushpath %i32 #lcl(0)
+	push %i32
+	call #fn(0)
+	call #fn(1)
+	ret
+}";
+
+    let mut unit = parse_unit(source).unwrap();
+    let mut inline = Inline::new(0);
+    assert!(inline.run(&mut unit));
+    assert!(unit.validate().is_ok());
+
+    let text = unit.to_string();
+    assert!(!text.contains("call #fn(0)"));
+    assert_eq!(text.matches("call #fn(1)").count(), 2);
+
+    // The recursive call is inlined into f at a size which allows it, but never into count itself
+    let unit = optimise(source, 2);
+    assert_eq!(unit.to_string().matches("call #fn(1)").count(), 2);
+
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(Interpreter::new(&unit, &mut host).call(FunctionIndex::new(2), &[4]), Ok(CallResult::Return(vec![ 4 ])));
+}
+
+/// Normal test - Verify that functions and globals which cannot be reached from the entry point, an exported function or a
+/// named global are removed, with those left renumbered, that identical read only strings are merged, and that code after a
+/// return or break is removed
+#[test]
+fn opt_dead_code() {
+    let source = "global 0: readonly #slicedata(u8) = data \"hi\"
+global 1: readonly #slice(u8) = slice(#glbl(0), 0, 2)
+global 2: readonly ref(#slice(u8)) = ref #glbl(1)
+global 3:\"unused\" i32 = i32 4
+global 4: readonly #slicedata(u8) = data \"hi\"
+global 5: readonly #slice(u8) = slice(#glbl(4), 0, 2)
+global 6: readonly ref(#slice(u8)) = ref #glbl(5)
+global 7: #slicedata(u8) = data \"hi\"
+global 8: #slice(u8) = slice(#glbl(7), 0, 2)
+global 9: ref(#slice(u8)) = ref #glbl(8)
+func 0:\"unused\" () {
+	ret
+}
+func 1:\"same\" (ref(#slice(u8)), ref(#slice(u8))) -> (bool) extern
+func 2:\"main\" () -> (i32) @entry {
+	pushpath %ref(#slice(u8)) #glbl(2)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(6)
+	push %ref(#slice(u8))
+	call #fn(1)
+	drop
+	pushpath %ref(#slice(u8)) #glbl(6)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(9)
+	push %ref(#slice(u8))
+	call #fn(1)
+	drop
+	loop
+		code {
+			break 0
+			pushlit %i32 1
+			drop
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+		}
+	pushlit %i32 0
+	ret
+	pushlit %i32 1
+	ret
+}
+func 3:\"callback\" () @export {
+	pushpath %ref(#slice(u8)) #glbl(9)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(9)
+	push %ref(#slice(u8))
+	call #fn(1)
+	drop
+	ret
+}";
+
+    let unit = optimise(source, 1);
+    assert_eq!(unit.to_string(), "global 0: readonly #slicedata(u8) = data \"hi\"
+global 1: readonly #slice(u8) = slice(#glbl(0), 0, 2)
+global 2: readonly ref(#slice(u8)) = ref #glbl(1)
+global 3:\"unused\" i32 = i32 4
+global 4: #slicedata(u8) = data \"hi\"
+global 5: #slice(u8) = slice(#glbl(4), 0, 2)
+global 6: ref(#slice(u8)) = ref #glbl(5)
+func 0:\"same\" (ref(#slice(u8)), ref(#slice(u8))) -> (bool) extern
+func 1:\"main\" () -> (i32) @entry {
+	pushpath %ref(#slice(u8)) #glbl(2)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(2)
+	push %ref(#slice(u8))
+	call #fn(0)
+	drop
+	pushpath %ref(#slice(u8)) #glbl(2)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(6)
+	push %ref(#slice(u8))
+	call #fn(0)
+	drop
+	loop
+		code {
+			break 0
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+		}
+	pushlit %i32 0
+	ret
+}
+func 2:\"callback\" () @export {
+	pushpath %ref(#slice(u8)) #glbl(6)
+	push %ref(#slice(u8))
+	pushpath %ref(#slice(u8)) #glbl(6)
+	push %ref(#slice(u8))
+	call #fn(0)
+	drop
+	ret
+}
+");
+
+    // Without an entry point every function is kept, though unnamed globals may still be merged
+    let unit = optimise(&source.replace(" @entry", ""), 1);
+    assert_eq!(unit.function_count(), 4);
+    assert_eq!(unit.global_count(), 7);
+    assert_eq!(unit.get_global(GlobalIndex::new(3)).unwrap().name(), Some("unused"));
+}
+
+/// Normal test - Verify that an object only reached into and freed through one local is kept in that local, zeroed where
+/// it was made, and that one passed to a call is left on the heap
+#[test]
+fn opt_stack_allocate() {
+    let unit = optimise("struct \"P\" {
+	\"x\": i32,
+	\"y\": bool
+}
+func 0:\"keep\" (ref(#comp(\"P\"))) extern
+func 1:\"f\" () -> (i32) {
+	local 0: ref(#comp(\"P\"))
+	local 1: ref(#comp(\"P\"))
+	pushpath %ref(#comp(\"P\")) #lcl(0)
+	new #comp(\"P\")
+	pop %ref(#comp(\"P\"))
+	pushpath %ref(#comp(\"P\")) #lcl(0)
+	push %ref(#comp(\"P\"))
+	pushpath %i32 deref(#comp(\"P\"))/prop(0)
+	pushlit %i32 3
+	pop %i32
+	new #comp(\"P\")
+	poplocal %ref(#comp(\"P\")) #lcl(1)
+	pushpath %ref(#comp(\"P\")) #lcl(1)
+	push %ref(#comp(\"P\"))
+	call #fn(0)
+	pushpath %ref(#comp(\"P\")) #lcl(0)
+	push %ref(#comp(\"P\"))
+	pushpath %i32 deref(#comp(\"P\"))/prop(0)
+	push %i32
+	pushpath %ref(#comp(\"P\")) #lcl(0)
+	push %ref(#comp(\"P\"))
+	free #comp(\"P\")
+	ret
+}", 1);
+
+    assert_eq!(unit.to_string(), "struct \"P\" {
+	\"x\": i32,
+	\"y\": bool
+}
+func 0:\"keep\" (ref(#comp(\"P\"))) extern
+func 1:\"f\" () -> (i32) {
+	local 0: #comp(\"P\")
+	local 1: ref(#comp(\"P\"))
+	pushpath %i32 #lcl(0)/prop(0)
+	pushlit %i32 0
+	pop %i32
+	pushpath %bool #lcl(0)/prop(1)
+	pushlit %bool 0
+	pop %bool
+	pushpath %i32 #lcl(0)/prop(0)
+	pushlit %i32 3
+	pop %i32
+	new #comp(\"P\")
+	poplocal %ref(#comp(\"P\")) #lcl(1)
+	pushpath %ref(#comp(\"P\")) #lcl(1)
+	push %ref(#comp(\"P\"))
+	call #fn(0)
+	pushpath %i32 #lcl(0)/prop(0)
+	push %i32
+	ret
+}
+");
+}
@@ -0,0 +1,156 @@
+use crate::*;
+use crate::ssa::*;
+
+use super::ir_interp::{CONTROL_FLOW, HEAP, MATH};
+
+/// Puts each function of the unit in SSA form and gives back its code, checking that the unit is still valid
+fn round_trip(source: &str) -> TranslationUnit {
+    let mut unit = parse_unit(source).unwrap();
+    assert!(unit.validate().is_ok());
+
+    for f in 0..unit.function_count() {
+        let idx = FunctionIndex::new(f);
+        if unit.get_function(idx).unwrap().is_extern() { continue; }
+
+        let ssa = SsaFunction::new(unit.get_function(idx).unwrap(), &unit);
+        ssa.write_to(unit.get_function_mut(idx).unwrap());
+    }
+
+    assert!(unit.validate().is_ok());
+    unit
+}
+
+const COUNT: &str = "func 0:\"count\" (i32) -> (i32) {
+	local 0: i32
+	local 1: i32
+	local 2: i32
+	loop
+		code {
+			if
+				then {
+					continue 1
+				}
+				cond {
+					pushpath %i32 #lcl(1)
+					push %i32
+					pushlit %i32 2
+					eq %i32
+				}
+			pushpath %i32 #lcl(2)
+			pushpath %i32 #lcl(2)
+			push %i32
+			pushpath %i32 #lcl(1)
+			push %i32
+			add %i32
+			pop %i32
+		}
+		cond {
+			pushpath %i32 #lcl(1)
+			push %i32
+			pushpath %i32 #lcl(0)
+			push %i32
+			lt %i32
+		}
+		inc {
+			pushpath %i32 #lcl(1)
+			pushpath %i32 #lcl(1)
+			push %i32
+			inc %i32 1
+			pop %i32
+		}
+	pushpath %i32 #lcl(2)
+	push %i32
+	ret
+}
+";
+
+/// Normal test - Verify that a loop is put in SSA form with a phi at its start for each local it changes, and that the
+/// phis of the block the loop continues to are left out where only one value reaches them
+#[test]
+fn ssa_phis() {
+    let unit = parse_unit(COUNT).unwrap();
+    let ssa = SsaFunction::new(unit.get_function(FunctionIndex::new(0)).unwrap(), &unit);
+
+    assert_eq!(ssa.to_string(), "block 0:
+	%0 = param 0
+	%1 = pushlit %i32 0
+	%2 = pushlit %i32 0
+	jump 1
+block 1:
+	%4 = phi 0:%1 3:%25
+	%5 = phi 0:%2 3:%23
+	%6 = lt %i32 %4 %0
+	branch %6 2 4
+block 2:
+	%13 = pushlit %i32 2
+	%14 = eq %i32 %4 %13
+	branch %14 5 6
+block 3:
+	%23 = phi 5:%5 6:%24
+	%25 = inc %i32 1 %4
+	jump 1
+block 4:
+	ret %5
+block 5:
+	jump 3
+block 6:
+	%24 = add %i32 %5 %4
+	jump 3
+");
+
+    let dominators = Dominators::new(&ssa);
+    assert_eq!(dominators.idom(BlockIndex::new(0)), None);
+    assert_eq!(dominators.idom(BlockIndex::new(3)), Some(BlockIndex::new(2)));
+    assert_eq!(dominators.idom(BlockIndex::new(4)), Some(BlockIndex::new(1)));
+    assert!(dominators.dominates(BlockIndex::new(1), BlockIndex::new(6)));
+    assert!(!dominators.dominates(BlockIndex::new(5), BlockIndex::new(3)));
+    assert_eq!(dominators.children(BlockIndex::new(2)), &vec![ BlockIndex::new(6), BlockIndex::new(5), BlockIndex::new(3) ]);
+
+    let frontiers = dominators.frontiers(&ssa);
+    assert_eq!(frontiers[5], vec![ BlockIndex::new(3) ]);
+    assert_eq!(frontiers[3], vec![ BlockIndex::new(1) ]);
+    assert_eq!(frontiers[1], vec![ BlockIndex::new(1) ]);
+
+    let liveness = Liveness::new(&ssa);
+    let regs = |regs: &[usize]| regs.iter().map(|r| Reg::new(*r)).collect::<std::collections::BTreeSet<_>>();
+    assert_eq!(liveness.live_in(BlockIndex::new(1)), &regs(&[ 0, 4, 5 ]));
+    assert_eq!(liveness.live_out(BlockIndex::new(1)), &regs(&[ 0, 4, 5 ]));
+    assert_eq!(liveness.live_out(BlockIndex::new(5)), &regs(&[ 0, 4, 5 ]));
+    assert_eq!(liveness.live_out(BlockIndex::new(6)), &regs(&[ 0, 4, 24 ]));
+    assert_eq!(liveness.live_in(BlockIndex::new(4)), &regs(&[ 5 ]));
+    assert!(!liveness.is_live_out(BlockIndex::new(4), Reg::new(5)));
+}
+
+/// Normal test - Verify that code given back from SSA form does the same as it did before, through loops, branches,
+/// switches, calls with many returns and paths through references and slices
+#[test]
+fn ssa_round_trip() {
+    let unit = round_trip(COUNT);
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(FunctionIndex::new(0), &[5]), Ok(CallResult::Return(vec![ 1 + 3 + 4 ])));
+
+    let unit = round_trip(MATH);
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(FunctionIndex::new(0), &[-7i64 as u64, 2]), Ok(CallResult::Return(vec![ -3i64 as u64, -1i64 as u64 ])));
+    assert_eq!(interp.call(FunctionIndex::new(1), &[10]), Ok(CallResult::Return(vec![ 4 ])));
+    assert_eq!(interp.call(FunctionIndex::new(2), &[-1i64 as u64]), Ok(CallResult::Return(vec![ 0xffffffff, u64::MAX, 1 ])));
+
+    let unit = round_trip(CONTROL_FLOW);
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(unit.interpret(&mut host), Ok(9));
+    assert_eq!(host.out(), b"023!");
+
+    let unit = round_trip(&CONTROL_FLOW.replace("pushlit %u32 4\n", "pushlit %u32 5\n"));
+    let mut host = StdHost::new(Vec::new());
+    assert_eq!(unit.interpret(&mut host), Ok(3));
+    assert_eq!(host.out(), b"0234");
+
+    let unit = round_trip(HEAP);
+    let mut host = StdHost::new(Vec::new());
+    let mut interp = Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.run_entry(), Ok(1 + 121 + 3));
+    assert_eq!(interp.call(FunctionIndex::new(1), &[]), Ok(CallResult::Return(Vec::new())));
+    assert_eq!(interp.live_object_count(), 0);
+}
@@ -0,0 +1,136 @@
+use crate::*;
+
+/// A unit written exactly as `Display` writes it, using each kind of declaration and the instructions with blocks
+pub(super) const UNIT: &str = "struct \"Point\" @repr(c) @align(8) {
+	\"x\": i32,
+	\"name\": #slice(u8)
+}
+global 0: #slicedata(u8) = data \"a \\\"b\\\"\\n\"
+global 1:\"origin\" #comp(\"Point\") = struct {i32 -4, slice(#glbl(0), 0, 5)}
+global 2:\"limit\" readonly u64 = u64 18446744073709551615
+func 0:\"putchar\" (u32) @extern(\"putchar\") extern
+func 1:\"get_x\" (ref(#comp(\"Point\"))) -> (i32) @method(\"Point\") {
+	local 0: ref(#comp(\"Point\"))
+	pushpath %ref(#comp(\"Point\")) #lcl(0)
+	push %ref(#comp(\"Point\"))
+	pushpath %i32 deref(#comp(\"Point\"))/prop(0)
+	push %i32
+	ret
+}
+func 2:\"main\" () -> (i32) @entry {
+	local 0: i32
+	local 1: uptr
+	pushpath %i32 #lcl(0)
+	pushlit %i32 18446744073709551615
+	pop %i32
+	pushpath %uptr #lcl(1)
+	pushpath %uptr #glbl(1)/prop(1)/length
+	push %uptr
+	pop %uptr
+	loop
+		code {
+			switch %i32
+				case 1, 2 {
+					break 1
+				}
+				default {
+					continue 1
+				}
+				value {
+					pushpath %i32 #lcl(0)
+					push %i32
+				}
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+			pushpath %i32 #lcl(0)
+			pushpath %i32 #lcl(0)
+			push %i32
+			inc %i32 1
+			pop %i32
+		}
+	if
+		then {
+			pushlit %u32 65
+			call #fn(0)
+		}
+		else {
+		}
+		cond {
+			pushpath %i32 #lcl(0)
+			push %i32
+			pushlit %i32 2
+			eq %i32
+		}
+	asm x86_64
+		in rdi #lcl(0) %i32
+		out rax #lcl(0) %i32
+		code {
+			mov rax, [rdi + rcx * 8 - 16];
+			nop;
+		}
+	if %i32
+		then {
+			pushlit %i32 1
+		}
+		else {
+			pushlit %i32 2
+		}
+		cond {
+			pushlit %bool 1
+		}
+	drop
+	pushpath %i32 #lcl(0)
+	push %i32
+	ret
+}
+";
+
+/// Normal test - Verify that a unit read from text is written back out exactly as it was read, and is valid
+#[test]
+fn text_round_trip() {
+    let unit = parse_unit(UNIT).unwrap();
+    assert_eq!(unit.to_string(), UNIT);
+    assert!(unit.validate().is_ok());
+
+    let ct = unit.find_type("Point").unwrap();
+    match ct.content() {
+        CompoundContent::Struct(s) => {
+            assert!(matches!(s.repr(), StructRepr::C));
+            assert_eq!(s.min_align(), Some(8));
+        }
+    }
+
+    assert!(matches!(unit.get_global(GlobalIndex::new(0)).unwrap().default(), Some(StorableValue::SliceData(data)) if data.len() == 6));
+    assert!(unit.get_global(GlobalIndex::new(2)).unwrap().is_read_only());
+}
+
+/// Normal test - Verify that comments and extra whitespace are skipped, that declared indices can be left out, and that
+/// negative literals are read as their bits
+#[test]
+fn text_comments() {
+    let unit = parse_unit("// A comment\nglobal   u8 = u8 3 // After a global\nfunc \"f\" () {\n\tpushlit %i8 -1 drop\n\tret // Return\n}").unwrap();
+    assert_eq!(unit.to_string(), "global 0: u8 = u8 3\nfunc 0:\"f\" () {\n\tpushlit %i8 18446744073709551615\n\tdrop\n\tret\n}\n");
+}
+
+/// Erroneous test - Verify that references to things which do not exist, and badly formed text, are reported as errors
+#[test]
+fn text_errors() {
+    let error = |source: &str| match parse_unit(source) {
+        Ok(_) => panic!("{} should not parse", source),
+        Err(e) => e.message().to_owned()
+    };
+
+    assert_eq!(error("func \"f\" () {\n\tpushpath %u8 #lcl(0)\n}"), "Local does not exist");
+    assert_eq!(error("global 1: u8"), "This is global 0, as they are numbered in the order they are declared");
+    assert_eq!(error("global #comp(\"Point\")"), "Unknown type Point");
+    assert_eq!(error("global u8 = u8 256"), "Value is out of range for u8");
+    assert_eq!(error("func \"f\" () {\n\tjump\n}"), "Unknown instruction 'jump'");
+    assert_eq!(error("func \"f\" () {\n\tret\n"), "Expected instruction or '}'");
+    assert_eq!(error("global readonly u8"), "A readonly global must have a value");
+    assert_eq!(error("func \"f\" () {\n\tif %i32 then {\n\t\tpushlit %i32 1\n\t} cond {\n\t\tpushlit %bool 1\n\t}\n}"), "Only an if with an else can leave a value");
+    assert_eq!(error("struct \"S\" @align(16) { \"a\": u8 }"), "Alignment must be no more than 8");
+    assert_eq!(error("struct \"S\" { \"a\": u8 }\nfunc \"f\" () {\n\tlocal 0: #comp(\"S\")\n\tpushpath %u8 #lcl(0)/slice\n}"), "Path is not to a slice");
+}
\ No newline at end of file
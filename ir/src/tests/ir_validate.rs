@@ -0,0 +1,112 @@
+use crate::*;
+
+/// Erroneous test - Verify that an error nested in blocks is located by the position of each block and instruction in
+/// turn, and that a branch without a return is located by the branch
+#[test]
+fn validate_positions() {
+    let unit = parse_unit("func \"f\" () -> (i32) {
+	loop
+		code {
+			switch %i32
+				case 1 {
+					pushlit %i64 1
+					pushlit %i32 1
+					add %i32
+					drop
+				}
+				default {
+				}
+				value {
+					pushlit %i32 1
+				}
+		}
+		cond {
+			pushlit %bool 1
+		}
+		inc {
+		}
+	pushlit %i32 0
+	ret
+}").unwrap();
+
+    let error = unit.validate().expect_err("Not incorrect");
+    assert_eq!(error.location(), &ValidationLocation::Function(FunctionIndex::new(0), vec![ 0, 0, 0, 0, 2 ]));
+    assert_eq!(error.kind(), &ValidationErrorKind::StackIncorrectType(ValueType::I32, ValueType::I64));
+    assert_eq!(error.to_string(), "Expected i32 on the stack, found i64 (at instruction 0/0/0/0/2 of function 0)");
+
+    let unit = parse_unit("func \"f\" () {\n\tret\n}\nfunc \"g\" () -> (i32) {\n\tif\n\t\tthen {\n\t\t\tpushlit %i32 1\n\t\t\tret\n\t\t}\n\t\telse {\n\t\t}\n\t\tcond {\n\t\t\tpushlit %bool 1\n\t\t}\n}").unwrap();
+    assert_eq!(unit.validate().expect_err("Not incorrect"), ValidationError::new(
+        ValidationLocation::Function(FunctionIndex::new(1), vec![ 0, 1 ]),
+        ValidationErrorKind::NoFinalReturn
+    ));
+}
+
+/// Normal / erroneous test - Verify that global defaults are checked against the type of the global, including the bounds
+/// of slices and the type of globals referenced
+#[test]
+fn validate_globals() {
+    let error = |source: &str| parse_unit(source).unwrap().validate().expect_err("Not incorrect");
+    let data = "global 0: #slicedata(u8) = data \"abc\"\n";
+
+    assert!(parse_unit(&format!("{}global #slice(u8) = slice(#glbl(0), 1, 2)\nglobal ref(#slicedata(u8)) = ref #glbl(0)", data)).unwrap().validate().is_ok());
+
+    assert_eq!(error(&format!("{}global #slice(u8) = slice(#glbl(0), 2, 2)", data)), ValidationError::new(
+        ValidationLocation::Global(GlobalIndex::new(1)),
+        ValidationErrorKind::SliceOutOfBounds(GlobalIndex::new(0), 2, 2)
+    ));
+    assert_eq!(error(&format!("{}global #slice(i8) = slice(#glbl(0), 0, 1)", data)).kind(), &ValidationErrorKind::GlobalIncorrectType(
+        StorableType::SliceData(Box::new(StorableType::Value(ValueType::I8))),
+        StorableType::SliceData(Box::new(StorableType::Value(ValueType::U8)))
+    ));
+    assert_eq!(error(&format!("{}global ref(u8) = ref #glbl(0)", data)).kind(), &ValidationErrorKind::GlobalIncorrectType(
+        StorableType::Value(ValueType::U8),
+        StorableType::SliceData(Box::new(StorableType::Value(ValueType::U8)))
+    ));
+    assert_eq!(error("global i32 = u32 4").kind(), &ValidationErrorKind::ValueIncorrectType(StorableType::Value(ValueType::I32)));
+    assert_eq!(error("struct \"S\" {\n\t\"a\": u8,\n\t\"b\": bool\n}\nglobal #comp(\"S\") = struct {u8 1, u8 0}").kind(), &ValidationErrorKind::ValueIncorrectType(StorableType::Value(ValueType::Bool)));
+}
+
+/// Normal / erroneous test - Verify that a function only needs to return where control can reach its end, which a loop
+/// without a condition stops unless it is broken out of, and that code which cannot be reached is given as a warning
+#[test]
+fn validate_reachability() {
+    let endless = |code: &str, after: &str| parse_unit(&format!("func \"f\" () -> (i32) {{
+	loop
+		code {{
+			if
+				then {{
+					{}
+				}}
+				cond {{
+					pushlit %bool 0
+				}}
+			pushlit %i32 1
+			ret
+		}}
+		cond {{
+			pushlit %bool 1
+		}}
+		inc {{
+		}}
+{}
+}}", code, after)).unwrap().validate();
+
+    assert_eq!(endless("", ""), Ok(Vec::new()));
+    assert_eq!(endless("continue 1", ""), Ok(Vec::new()));
+    assert_eq!(endless("", "\tpushlit %i32 0\n\tret"), Ok(vec![ ValidationWarning::new(
+        ValidationLocation::Function(FunctionIndex::new(0), vec![ 1 ]),
+        ValidationWarningKind::UnreachableCode
+    ) ]));
+
+    // Breaking out of the loop, from within the if, means the end can be reached
+    assert_eq!(endless("break 1", "").expect_err("Not incorrect"), ValidationError::new(
+        ValidationLocation::Function(FunctionIndex::new(0), Vec::new()),
+        ValidationErrorKind::NoFinalReturn
+    ));
+    assert_eq!(endless("break 1", "\tpushlit %i32 0\n\tret"), Ok(Vec::new()));
+
+    // Only the first instruction which cannot be reached is given
+    let unit = parse_unit("func \"f\" () {\n\tret\n\tret\n\tret\n}").unwrap();
+    assert_eq!(unit.validate().unwrap().len(), 1);
+    assert_eq!(unit.validate().unwrap()[0].to_string(), "Code cannot be reached (at instruction 1 of function 0)");
+}
\ No newline at end of file
@@ -1,2 +1,9 @@
 mod ir_control_flow;
-mod ir_do_some_math;
\ No newline at end of file
+mod ir_do_some_math;
+mod ir_layout;
+mod ir_text;
+mod ir_binary;
+mod ir_interp;
+mod ir_validate;
+mod ir_opt;
+mod ir_ssa;
@@ -0,0 +1,852 @@
+use syntax::{MatchResult, Span, SyntaxError, Token};
+
+use crate::*;
+
+#[derive(Debug)]
+enum TokenKind {
+    Ident(String),
+    Number(String),
+    StringLit(Vec<u8>),
+    /// A string literal which could not be lexed, with why
+    InvalidStringLit(String),
+    Char(char),
+    OpenCurly, CloseCurly, OpenParen, CloseParen, OpenBracket, CloseBracket,
+    Colon, Semi, Comma, Slash, Hash, Percent, At, Eq, Add, Sub, Mul, Arrow,
+    Whitespace
+}
+
+impl syntax::TokenKind for TokenKind {
+    fn is_whitespace(&self) -> bool {
+        matches!(self, TokenKind::Whitespace)
+    }
+
+    fn lexer_error(&self) -> Option<String> {
+        match self {
+            TokenKind::InvalidStringLit(error) => Some(error.clone()),
+            _ => None
+        }
+    }
+}
+
+type TokenStream<'a> = syntax::TokenStream<'a, TokenKind>;
+
+struct Matcher;
+impl syntax::TokenMatcher<TokenKind> for Matcher {
+    fn next(&mut self, string: &str, offset: usize) -> Option<(usize, Token<TokenKind>)> {
+        // Comments run to the end of the line, and are skipped along with whitespace
+        if string.starts_with("//") {
+            let len = string.find('\n').unwrap_or(string.len());
+            return Some((len, Token::new(TokenKind::Whitespace, Span::new(offset, offset + len))));
+        }
+
+        syntax::exact_long!(string, offset,
+            "->" => TokenKind::Arrow
+        );
+
+        syntax::exact!(string, offset,
+            '{' => TokenKind::OpenCurly,
+            '}' => TokenKind::CloseCurly,
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '[' => TokenKind::OpenBracket,
+            ']' => TokenKind::CloseBracket,
+            ':' => TokenKind::Colon,
+            ';' => TokenKind::Semi,
+            ',' => TokenKind::Comma,
+            '/' => TokenKind::Slash,
+            '#' => TokenKind::Hash,
+            '%' => TokenKind::Percent,
+            '@' => TokenKind::At,
+            '=' => TokenKind::Eq,
+            '+' => TokenKind::Add,
+            '-' => TokenKind::Sub,
+            '*' => TokenKind::Mul
+        );
+
+        syntax::ident!(string, offset, TokenKind::Ident);
+        syntax::whitespace!(string, offset, TokenKind::Whitespace);
+        syntax::number!(string, offset, TokenKind::Number);
+        syntax::cstring!(string, offset, TokenKind::StringLit, TokenKind::InvalidStringLit);
+
+        syntax::char!(string, offset, TokenKind::Char);
+    }
+}
+
+/// An error covering everything from `start` up to the current token
+fn error_since<T: Into<String>>(stream: &TokenStream, start: usize, msg: T) -> SyntaxError {
+    SyntaxError::new(Span::new(start, stream.tell_start()), msg)
+}
+
+fn is_word(stream: &TokenStream, word: &str) -> bool {
+    matches!(syntax::tk_v!(stream, TokenKind::Ident), Some(w) if w == word)
+}
+
+fn parse_string<'a>(stream: &mut TokenStream<'a>) -> MatchResult<String> {
+    let bytes = syntax::ex!(syntax::tk_v!(stream, TokenKind::StringLit)).clone();
+    match String::from_utf8(bytes) {
+        Ok(string) => {
+            stream.step();
+            MatchResult::Ok(string)
+        },
+        Err(_) => MatchResult::Err(stream.error("String is not valid UTF-8"))
+    }
+}
+
+/// Parses a number with an optional sign, wide enough to hold any value of a signed or unsigned 64 bit integer
+fn parse_integer<'a>(stream: &mut TokenStream<'a>) -> MatchResult<i128> {
+    let negative = syntax::tk_iss!(stream, TokenKind::Sub);
+    let number = match syntax::tk_v!(stream, TokenKind::Number) {
+        Some(number) => number.clone(),
+        None if negative => return MatchResult::Err(stream.error("Expected number")),
+        None => return MatchResult::Fail
+    };
+
+    let value = match number.parse::<u64>() {
+        Ok(value) => value as i128,
+        Err(_) => return MatchResult::Err(stream.error("Number is too large"))
+    };
+    stream.step();
+
+    MatchResult::Ok(if negative { -value } else { value })
+}
+
+/// Parses an integer as the bits of a 64 bit value, so that both `-1` and `18446744073709551615` give all ones
+fn parse_literal<'a>(stream: &mut TokenStream<'a>) -> MatchResult<u64> {
+    let start = stream.tell_start();
+    let value = syntax::ex!(syntax::parse!(stream, parse_integer), stream.error("Expected number"));
+
+    if value < i64::MIN as i128 { return MatchResult::Err(error_since(stream, start, "Number is too small")); }
+    MatchResult::Ok(value as u64)
+}
+
+fn parse_index<'a>(stream: &mut TokenStream<'a>) -> MatchResult<usize> {
+    let number = syntax::ex!(syntax::tk_v!(stream, TokenKind::Number), stream.error("Expected index")).clone();
+    match number.parse::<usize>() {
+        Ok(idx) => {
+            stream.step();
+            MatchResult::Ok(idx)
+        },
+        Err(_) => MatchResult::Err(stream.error("Index is too large"))
+    }
+}
+
+/// Parses `#kind(N)`, which refers to a local, global or function by its index
+fn parse_hash_index<'a>(stream: &mut TokenStream<'a>, kind: &str) -> MatchResult<usize> {
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Hash), stream.error(format!("Expected '#{}(...)'", kind)));
+    syntax::reqs!(stream, is_word(stream, kind), stream.error(format!("Expected '#{}(...)'", kind)));
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+    let idx = syntax::parse!(stream, parse_index).unwrap();
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+    MatchResult::Ok(idx)
+}
+
+/// Parses the optional `N:` before a declaration, which must be the index it is declared at
+fn parse_declared_index<'a>(stream: &mut TokenStream<'a>, expected: usize, what: &str) -> MatchResult<()> {
+    if !syntax::tk_is!(stream, TokenKind::Number(_)) { return MatchResult::Ok(()) }
+
+    let start = stream.tell_start();
+    let idx = syntax::parse!(stream, parse_index).unwrap();
+    if idx != expected {
+        return MatchResult::Err(error_since(stream, start, format!("This is {} {}, as they are numbered in the order they are declared", what, expected)));
+    }
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Colon), stream.error("Expected ':'"));
+    MatchResult::Ok(())
+}
+
+fn parse_value_type<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<ValueType> {
+    let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).clone();
+    let vt = match name.as_str() {
+        "u8" => ValueType::U8,
+        "i8" => ValueType::I8,
+        "u16" => ValueType::U16,
+        "i16" => ValueType::I16,
+        "u32" => ValueType::U32,
+        "i32" => ValueType::I32,
+        "u64" => ValueType::U64,
+        "i64" => ValueType::I64,
+        "uptr" => ValueType::UPtr,
+        "iptr" => ValueType::IPtr,
+        "bool" => ValueType::Bool,
+        "ref" | "idx" => {
+            stream.step();
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+            let st = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+            return MatchResult::Ok(match name.as_str() {
+                "ref" => ValueType::Ref(Box::new(st)),
+                _ => ValueType::Index(Box::new(st))
+            });
+        },
+        _ => return MatchResult::Fail
+    };
+    stream.step();
+
+    MatchResult::Ok(vt)
+}
+
+fn parse_storable_type<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<StorableType> {
+    if !syntax::tk_iss!(stream, TokenKind::Hash) {
+        return match syntax::parse!(stream, parse_value_type, unit) {
+            Some(vt) => MatchResult::Ok(StorableType::Value(vt)),
+            None => MatchResult::Fail
+        };
+    }
+
+    let kind = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected type")).clone();
+    stream.step();
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+    let st = match kind.as_str() {
+        "comp" => {
+            let start = stream.tell_start();
+            let name = syntax::ex!(syntax::parse!(stream, parse_string), stream.error("Expected name"));
+            match unit.find_type(&name) {
+                Some(ct) => StorableType::Compound(ct),
+                None => return MatchResult::Err(error_since(stream, start, format!("Unknown type {}", name)))
+            }
+        },
+        "slice" | "slicedata" => {
+            let st = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+            match kind.as_str() {
+                "slice" => StorableType::Slice(Box::new(st)),
+                _ => StorableType::SliceData(Box::new(st))
+            }
+        },
+        _ => return MatchResult::Err(stream.error("Expected '#comp', '#slice' or '#slicedata'"))
+    };
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+    MatchResult::Ok(st)
+}
+
+/// Parses `%T`, which gives the type of most instructions
+fn parse_typed<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<ValueType> {
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Percent), stream.error("Expected '%'"));
+    MatchResult::Ok(syntax::ex!(syntax::parse!(stream, parse_value_type, unit), stream.error("Expected type")))
+}
+
+fn parse_value<'a>(stream: &mut TokenStream<'a>) -> MatchResult<Value> {
+    let kind = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).clone();
+    match kind.as_str() {
+        "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "uptr" | "iptr" => {},
+        "bool" => {
+            stream.step();
+            let value = match syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str) {
+                Some("true") => true,
+                Some("false") => false,
+                _ => return MatchResult::Err(stream.error("Expected 'true' or 'false'"))
+            };
+            stream.step();
+            return MatchResult::Ok(Value::Bool(value));
+        },
+        "ref" => {
+            stream.step();
+            return MatchResult::Ok(Value::Ref(GlobalIndex::new(syntax::parse!(stream, parse_hash_index, "glbl").unwrap())));
+        },
+        _ => return MatchResult::Fail
+    }
+    stream.step();
+
+    let start = stream.tell_start();
+    let value = syntax::ex!(syntax::parse!(stream, parse_integer), stream.error("Expected number"));
+
+    macro_rules! in_range {
+        ( $t:ty, $variant:path ) => {
+            match <$t as std::convert::TryFrom<i128>>::try_from(value) {
+                Ok(value) => $variant(value),
+                Err(_) => return MatchResult::Err(error_since(stream, start, format!("Value is out of range for {}", kind)))
+            }
+        };
+    }
+
+    MatchResult::Ok(match kind.as_str() {
+        "u8" => in_range!(u8, Value::U8),
+        "i8" => in_range!(i8, Value::I8),
+        "u16" => in_range!(u16, Value::U16),
+        "i16" => in_range!(i16, Value::I16),
+        "u32" => in_range!(u32, Value::U32),
+        "i32" => in_range!(i32, Value::I32),
+        "u64" => in_range!(u64, Value::U64),
+        "i64" => in_range!(i64, Value::I64),
+        "uptr" => in_range!(usize, Value::UPtr),
+        _ => in_range!(isize, Value::IPtr)
+    })
+}
+
+fn parse_storable_value<'a>(stream: &mut TokenStream<'a>) -> MatchResult<StorableValue> {
+    if is_word(stream, "struct") {
+        stream.step();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+        let mut props = Vec::new();
+        while let Some(value) = syntax::parse!(stream, parse_storable_value) {
+            props.push(StructPropertyValue::new(value));
+
+            if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+        MatchResult::Ok(StorableValue::Compound(CompoundValue::Struct(StructValue::new(props))))
+    } else if is_word(stream, "slice") {
+        stream.step();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+        let global = syntax::parse!(stream, parse_hash_index, "glbl").unwrap();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Comma), stream.error("Expected ','"));
+        let start = syntax::parse!(stream, parse_index).unwrap();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Comma), stream.error("Expected ','"));
+        let length = syntax::parse!(stream, parse_index).unwrap();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        MatchResult::Ok(StorableValue::Slice(GlobalIndex::new(global), start, length))
+    } else if is_word(stream, "data") {
+        stream.step();
+
+        // A string stands for its bytes, each as a u8
+        if let Some(bytes) = syntax::tk_v!(stream, TokenKind::StringLit) {
+            let values = bytes.iter().map(|byte| StorableValue::Value(Value::U8(*byte))).collect();
+            stream.step();
+            return MatchResult::Ok(StorableValue::SliceData(values));
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenBracket), stream.error("Expected string or '['"));
+
+        let mut values = Vec::new();
+        while let Some(value) = syntax::parse!(stream, parse_storable_value) {
+            values.push(value);
+
+            if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseBracket), stream.error("Expected ']'"));
+        MatchResult::Ok(StorableValue::SliceData(values))
+    } else {
+        match syntax::parse!(stream, parse_value) {
+            Some(value) => MatchResult::Ok(StorableValue::Value(value)),
+            None => MatchResult::Fail
+        }
+    }
+}
+
+/// Parses a path, working out the type at each step from the locals and globals it starts from
+fn parse_path<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit, func: &Function) -> MatchResult<ValuePath> {
+    let start = stream.tell_start();
+    let (origin, mut curr) = if syntax::tk_iss!(stream, TokenKind::Hash) {
+        let kind = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected '#lcl(...)' or '#glbl(...)'")).clone();
+        stream.step();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+        let idx = syntax::parse!(stream, parse_index).unwrap();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        match kind.as_str() {
+            "lcl" => match func.get_local(LocalIndex::new(idx)) {
+                Some(local) => (ValuePathOrigin::Local(LocalIndex::new(idx), local.local_type().clone()), local.local_type().clone()),
+                None => return MatchResult::Err(error_since(stream, start, "Local does not exist"))
+            },
+            "glbl" => match unit.get_global(GlobalIndex::new(idx)) {
+                Some(global) => (ValuePathOrigin::Global(GlobalIndex::new(idx), global.global_type().clone()), global.global_type().clone()),
+                None => return MatchResult::Err(error_since(stream, start, "Global does not exist"))
+            },
+            _ => return MatchResult::Err(error_since(stream, start, "Expected '#lcl(...)' or '#glbl(...)'"))
+        }
+    } else if is_word(stream, "deref") {
+        stream.step();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+        let st = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        (ValuePathOrigin::Deref(st.clone()), st)
+    } else {
+        return MatchResult::Err(stream.error("Expected path"));
+    };
+
+    let mut components = Vec::new();
+    while syntax::tk_iss!(stream, TokenKind::Slash) {
+        let start = stream.tell_start();
+        let kind = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected 'slice', 'prop(...)' or 'length'")).clone();
+        stream.step();
+
+        let next = match (kind.as_str(), &curr) {
+            ("slice", StorableType::Slice(element)) => {
+                components.push(ValuePathComponent::Slice(element.as_ref().clone()));
+                element.as_ref().clone()
+            },
+            ("length", StorableType::Slice(_)) => {
+                components.push(ValuePathComponent::Length);
+                StorableType::Value(ValueType::UPtr)
+            },
+            ("prop", StorableType::Compound(ct)) => {
+                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+                let idx = PropertyIndex::new(syntax::parse!(stream, parse_index).unwrap());
+                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+                let prop_type = match ct.content() {
+                    CompoundContent::Struct(s) => match s.prop(idx) {
+                        Some(prop) => prop.prop_type().clone(),
+                        None => return MatchResult::Err(error_since(stream, start, format!("{} has no property {}", ct.name(), idx)))
+                    }
+                };
+
+                components.push(ValuePathComponent::Property(idx, ct.clone(), prop_type.clone()));
+                prop_type
+            },
+            ("slice" | "length", _) => return MatchResult::Err(error_since(stream, start, "Path is not to a slice")),
+            ("prop", _) => return MatchResult::Err(error_since(stream, start, "Path is not to a struct")),
+            _ => return MatchResult::Err(error_since(stream, start, "Expected 'slice', 'prop(...)' or 'length'"))
+        };
+        curr = next;
+    }
+
+    MatchResult::Ok(ValuePath::new(origin, components))
+}
+
+fn parse_block<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit, func: &Function) -> MatchResult<Vec<Ins>> {
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+    let mut code = Vec::new();
+    while !syntax::tk_is!(stream, TokenKind::CloseCurly) {
+        code.push(syntax::ex!(syntax::parse!(stream, parse_ins, unit, func), stream.error("Expected instruction or '}'")));
+    }
+    stream.step();
+
+    MatchResult::Ok(code)
+}
+
+/// Parses a named block, such as the `cond { ... }` of a loop
+fn parse_named_block<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit, func: &Function, name: &str) -> MatchResult<Vec<Ins>> {
+    syntax::reqs!(stream, is_word(stream, name), stream.error(format!("Expected '{}'", name)));
+    parse_block(stream, unit, func)
+}
+
+fn parse_asm_operand<'a>(stream: &mut TokenStream<'a>) -> MatchResult<AsmOperand> {
+    if let Some(reg) = syntax::tk_v!(stream, TokenKind::Ident) {
+        let reg = reg.clone();
+        stream.step();
+        return MatchResult::Ok(AsmOperand::Reg(reg));
+    }
+
+    if syntax::tk_iss!(stream, TokenKind::OpenBracket) {
+        let base = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected register")).clone();
+        stream.step();
+
+        // The index must come first, then the offset, as in `[base + index * scale - offset]`
+        let mut index = None;
+        let mut offset = 0;
+        while syntax::tk_is!(stream, TokenKind::Add) || syntax::tk_is!(stream, TokenKind::Sub) {
+            let negative = syntax::tk_is!(stream, TokenKind::Sub);
+            stream.step();
+
+            match syntax::tk_v!(stream, TokenKind::Ident).cloned() {
+                Some(reg) if !negative && index.is_none() && offset == 0 => {
+                    stream.step();
+                    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Mul), stream.error("Expected '*'"));
+                    let scale = match syntax::tk_v!(stream, TokenKind::Number).map(|number| number.parse::<u8>()) {
+                        Some(Ok(scale)) => scale,
+                        _ => return MatchResult::Err(stream.error("Expected scale"))
+                    };
+                    stream.step();
+                    index = Some((reg, scale));
+                },
+                Some(_) => return MatchResult::Err(stream.error("Expected number")),
+                None => {
+                    let value = syntax::ex!(syntax::parse!(stream, parse_literal), stream.error("Expected number")) as i64;
+                    offset = if negative { value.wrapping_neg() } else { value };
+                }
+            }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseBracket), stream.error("Expected ']'"));
+        return MatchResult::Ok(AsmOperand::Mem(base, index, offset));
+    }
+
+    if !syntax::tk_is!(stream, TokenKind::Number(_)) && !syntax::tk_is!(stream, TokenKind::Sub) { return MatchResult::Fail; }
+    MatchResult::Ok(AsmOperand::Imm(syntax::parse!(stream, parse_literal).unwrap() as i64))
+}
+
+fn parse_asm<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<AsmBlock> {
+    let arch = match syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str) {
+        Some("x86_64") => AsmArch::X8664,
+        Some("arm64") => AsmArch::Arm64,
+        _ => return MatchResult::Err(stream.error("Expected 'x86_64' or 'arm64'"))
+    };
+    stream.step();
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    while is_word(stream, "in") || is_word(stream, "out") {
+        let is_input = is_word(stream, "in");
+        stream.step();
+
+        let reg = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected register")).clone();
+        stream.step();
+        let local = LocalIndex::new(syntax::parse!(stream, parse_hash_index, "lcl").unwrap());
+        let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+
+        match is_input {
+            true => inputs.push(AsmBinding::new(reg, local, vt)),
+            false => outputs.push(AsmBinding::new(reg, local, vt))
+        }
+    }
+
+    syntax::reqs!(stream, is_word(stream, "code"), stream.error("Expected 'in', 'out' or 'code'"));
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+    let mut code = Vec::new();
+    while let Some(mnemonic) = syntax::tk_v!(stream, TokenKind::Ident) {
+        let mnemonic = mnemonic.clone();
+        stream.step();
+
+        let mut operands = Vec::new();
+        while let Some(operand) = syntax::parse!(stream, parse_asm_operand) {
+            operands.push(operand);
+
+            if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+        code.push(AsmIns::new(mnemonic, operands));
+    }
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected instruction or '}'"));
+    MatchResult::Ok(AsmBlock::new(arch, inputs, outputs, code))
+}
+
+fn parse_ins<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit, func: &Function) -> MatchResult<Ins> {
+    let start = stream.tell_start();
+    let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).clone();
+    stream.step();
+
+    MatchResult::Ok(match name.as_str() {
+        "pushpath" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+            Ins::PushPath(syntax::parse!(stream, parse_path, unit, func).unwrap(), vt)
+        },
+        "push" => Ins::Push(syntax::parse!(stream, parse_typed, unit).unwrap()),
+        "pop" => Ins::Pop(syntax::parse!(stream, parse_typed, unit).unwrap()),
+        "poplocal" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+            Ins::PopLocal(LocalIndex::new(syntax::parse!(stream, parse_hash_index, "lcl").unwrap()), vt)
+        },
+        "index" => {
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Percent), stream.error("Expected '%'"));
+            Ins::Index(syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type")))
+        },
+        "new" | "newslice" | "free" | "freeslice" => {
+            let st = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+            match name.as_str() {
+                "new" => Ins::New(st),
+                "newslice" => Ins::NewSlice(st),
+                "free" => Ins::Free(st),
+                _ => Ins::FreeSlice(st)
+            }
+        },
+        "conv" => {
+            let from = syntax::ex!(syntax::parse!(stream, parse_value_type, unit), stream.error("Expected type"));
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Comma), stream.error("Expected ','"));
+            let to = syntax::ex!(syntax::parse!(stream, parse_value_type, unit), stream.error("Expected type"));
+            Ins::Convert(from, to)
+        },
+        "call" => Ins::Call(FunctionIndex::new(syntax::parse!(stream, parse_hash_index, "fn").unwrap())),
+        "ret" => Ins::Ret,
+        "inc" | "dec" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+            let value = syntax::ex!(syntax::parse!(stream, parse_literal), stream.error("Expected number"));
+            match name.as_str() {
+                "inc" => Ins::Inc(vt, value),
+                _ => Ins::Dec(vt, value)
+            }
+        },
+        "add" | "mul" | "div" | "sub" | "neg" | "eq" | "ne" | "lt" | "le" | "gt" | "ge" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+            match name.as_str() {
+                "add" => Ins::Add(vt),
+                "mul" => Ins::Mul(vt),
+                "div" => Ins::Div(vt),
+                "sub" => Ins::Sub(vt),
+                "neg" => Ins::Neg(vt),
+                "eq" => Ins::Eq(vt),
+                "ne" => Ins::Ne(vt),
+                "lt" => Ins::Lt(vt),
+                "le" => Ins::Le(vt),
+                "gt" => Ins::Gt(vt),
+                _ => Ins::Ge(vt)
+            }
+        },
+        "and" => Ins::BoolAnd,
+        "or" => Ins::BoolOr,
+        "loop" => {
+            let code = syntax::parse!(stream, parse_named_block, unit, func, "code").unwrap();
+            let cond = syntax::parse!(stream, parse_named_block, unit, func, "cond").unwrap();
+            let inc = syntax::parse!(stream, parse_named_block, unit, func, "inc").unwrap();
+            Ins::Loop(code, cond, inc)
+        },
+        "if" => {
+            // Only an IfElse leaves a value, so only it can be typed
+            let vt = match syntax::tk_is!(stream, TokenKind::Percent) {
+                true => syntax::parse!(stream, parse_typed, unit),
+                false => None
+            };
+            let true_then = syntax::parse!(stream, parse_named_block, unit, func, "then").unwrap();
+            let false_then = match is_word(stream, "else") {
+                true => Some(syntax::parse!(stream, parse_named_block, unit, func, "else").unwrap()),
+                false => None
+            };
+            let cond = syntax::parse!(stream, parse_named_block, unit, func, "cond").unwrap();
+
+            match false_then {
+                Some(false_then) => Ins::IfElse(vt, true_then, false_then, cond),
+                None if vt.is_none() => Ins::If(true_then, cond),
+                None => return MatchResult::Err(error_since(stream, start, "Only an if with an else can leave a value"))
+            }
+        },
+        "switch" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+
+            let mut cases = Vec::new();
+            while is_word(stream, "case") {
+                stream.step();
+
+                let mut values = Vec::new();
+                loop {
+                    values.push(syntax::ex!(syntax::parse!(stream, parse_literal), stream.error("Expected number")));
+                    if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+                }
+
+                cases.push((values, syntax::parse!(stream, parse_block, unit, func).unwrap()));
+            }
+
+            let default = syntax::parse!(stream, parse_named_block, unit, func, "default").unwrap();
+            let value = syntax::parse!(stream, parse_named_block, unit, func, "value").unwrap();
+            Ins::Switch(vt, cases, default, value)
+        },
+        "asm" => Ins::Asm(syntax::parse!(stream, parse_asm, unit).unwrap()),
+        "break" => Ins::Break(syntax::parse!(stream, parse_index).unwrap()),
+        "continue" => Ins::Continue(syntax::parse!(stream, parse_index).unwrap()),
+        "pushlit" => {
+            let vt = syntax::parse!(stream, parse_typed, unit).unwrap();
+            Ins::PushLiteral(vt, syntax::ex!(syntax::parse!(stream, parse_literal), stream.error("Expected number")))
+        },
+        "drop" => Ins::Drop,
+        _ => return MatchResult::Err(error_since(stream, start, format!("Unknown instruction '{}'", name)))
+    })
+}
+
+fn parse_struct<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<CompoundTypeRef> {
+    syntax::reqs!(stream, is_word(stream, "struct"));
+
+    let start = stream.tell_start();
+    let name = syntax::ex!(syntax::parse!(stream, parse_string), stream.error("Expected name"));
+    if unit.find_type(&name).is_some() {
+        return MatchResult::Err(error_since(stream, start, format!("Type {} is already declared", name)));
+    }
+
+    let mut content = StructContent::new();
+    while syntax::tk_iss!(stream, TokenKind::At) {
+        let attr = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected 'repr' or 'align'")).clone();
+        stream.step();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+        match attr.as_str() {
+            "repr" => {
+                content.set_repr(match syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str) {
+                    Some("c") => StructRepr::C,
+                    Some("packed") => StructRepr::Packed,
+                    _ => return MatchResult::Err(stream.error("Expected 'c' or 'packed'"))
+                });
+                stream.step();
+            },
+            "align" => {
+                let start = stream.tell_start();
+                let align = syntax::parse!(stream, parse_index).unwrap();
+                if !align.is_power_of_two() {
+                    return MatchResult::Err(error_since(stream, start, "Alignment must be a power of two"));
+                }
+                if align > MAX_ALIGN {
+                    return MatchResult::Err(error_since(stream, start, format!("Alignment must be no more than {}", MAX_ALIGN)));
+                }
+                content.set_min_align(Some(align));
+            },
+            _ => return MatchResult::Err(stream.error("Expected 'repr' or 'align'"))
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+    }
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+    while let Some(name) = syntax::parse!(stream, parse_string) {
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Colon), stream.error("Expected ':'"));
+        let prop_type = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+        content.push_prop(StructProperty::new(name, prop_type));
+
+        if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+    }
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+
+    MatchResult::Ok(CompoundType::new(name, CompoundContent::Struct(content)))
+}
+
+fn parse_global<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<Global> {
+    syntax::reqs!(stream, is_word(stream, "global"));
+    syntax::parse!(stream, parse_declared_index, unit.global_count(), "global");
+
+    let name = syntax::parse!(stream, parse_string);
+    let read_only = is_word(stream, "readonly");
+    if read_only { stream.step(); }
+
+    let global_type = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+
+    let default = match syntax::tk_iss!(stream, TokenKind::Eq) {
+        true => Some(syntax::ex!(syntax::parse!(stream, parse_storable_value), stream.error("Expected value"))),
+        false => None
+    };
+
+    MatchResult::Ok(match (default, read_only) {
+        (Some(default), true) => Global::new_read_only(name, global_type, default),
+        (Some(default), false) => Global::new_default(name, global_type, default),
+        (None, false) => Global::new(name, global_type),
+        (None, true) => return MatchResult::Err(stream.error("A readonly global must have a value"))
+    })
+}
+
+fn parse_value_types<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<Vec<ValueType>> {
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+    let mut types = Vec::new();
+    while let Some(vt) = syntax::parse!(stream, parse_value_type, unit) {
+        types.push(vt);
+
+        if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+    }
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+    MatchResult::Ok(types)
+}
+
+fn parse_function<'a>(stream: &mut TokenStream<'a>, unit: &TranslationUnit) -> MatchResult<Function> {
+    syntax::reqs!(stream, is_word(stream, "func"));
+    syntax::parse!(stream, parse_declared_index, unit.function_count(), "function");
+
+    let name = syntax::ex!(syntax::parse!(stream, parse_string), stream.error("Expected name"));
+
+    let params = syntax::parse!(stream, parse_value_types, unit).unwrap();
+    let returns = match syntax::tk_iss!(stream, TokenKind::Arrow) {
+        true => syntax::parse!(stream, parse_value_types, unit).unwrap(),
+        false => Vec::new()
+    };
+    let signature = Signature::new(params, returns);
+
+    let mut method_data = None;
+    let mut attrs = Vec::new();
+    while syntax::tk_iss!(stream, TokenKind::At) {
+        let start = stream.tell_start();
+        let attr = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected attribute")).clone();
+        stream.step();
+
+        match attr.as_str() {
+            "entry" => attrs.push(FunctionAttr::Entry),
+            "alloc" => attrs.push(FunctionAttr::Alloc),
+            "alloc_slice" => attrs.push(FunctionAttr::AllocSlice),
+            "free" => attrs.push(FunctionAttr::Free),
+            "free_slice" => attrs.push(FunctionAttr::FreeSlice),
+            "panic" => attrs.push(FunctionAttr::Panic),
+            "fallible" => attrs.push(FunctionAttr::Fallible),
+            "test" => attrs.push(FunctionAttr::Test),
+            "inline" => attrs.push(FunctionAttr::Inline),
+            "export" => attrs.push(FunctionAttr::Export),
+            "extern" | "method" | "static" => {
+                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+                let value = syntax::ex!(syntax::parse!(stream, parse_string), stream.error("Expected string"));
+                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+                if attr == "extern" {
+                    attrs.push(FunctionAttr::ExternLocation(value));
+                    continue;
+                }
+
+                let ct = match unit.find_type(&value) {
+                    Some(ct) => ct,
+                    None => return MatchResult::Err(error_since(stream, start, format!("Unknown type {}", value)))
+                };
+                method_data = Some(match attr.as_str() {
+                    "method" => MethodData::new_virtual(ct),
+                    _ => MethodData::new_static(ct)
+                });
+            },
+            _ => return MatchResult::Err(error_since(stream, start, format!("Unknown attribute '{}'", attr)))
+        }
+    }
+
+    let is_extern = is_word(stream, "extern");
+    let mut func = match (is_extern, method_data) {
+        (false, None) => Function::new(name, signature),
+        (false, Some(method_data)) => Function::new_method(name, signature, method_data),
+        (true, None) => Function::new_extern(name, signature),
+        (true, Some(method_data)) => Function::new_extern_method(name, signature, method_data)
+    };
+    for attr in attrs {
+        func.push_attr(attr);
+    }
+
+    if is_extern {
+        stream.step();
+        return MatchResult::Ok(func);
+    }
+
+    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{' or 'extern'"));
+
+    while is_word(stream, "local") {
+        stream.step();
+        syntax::parse!(stream, parse_declared_index, func.local_count(), "local");
+        let local_type = syntax::ex!(syntax::parse!(stream, parse_storable_type, unit), stream.error("Expected type"));
+        func.push_local(Local::new(local_type));
+    }
+
+    let mut code = Vec::new();
+    while !syntax::tk_is!(stream, TokenKind::CloseCurly) {
+        code.push(syntax::ex!(syntax::parse!(stream, parse_ins, unit, &func), stream.error("Expected instruction or '}'")));
+    }
+    stream.step();
+
+    for ins in code {
+        func.push(ins);
+    }
+
+    MatchResult::Ok(func)
+}
+
+fn parse_items<'a>(stream: &mut TokenStream<'a>, unit: &mut TranslationUnit) -> MatchResult<()> {
+    while !stream.finished() {
+        if let Some(ct) = syntax::parse!(stream, parse_struct, unit) {
+            unit.add_type(ct);
+        } else if let Some(global) = syntax::parse!(stream, parse_global, unit) {
+            unit.add_global(global);
+        } else if let Some(func) = syntax::parse!(stream, parse_function, unit) {
+            unit.add_function(func);
+        } else if let Some(c) = syntax::tk_v!(stream, TokenKind::Char) {
+            return MatchResult::Err(stream.error(format!("Unexpected character '{}'", c)));
+        } else {
+            return MatchResult::Err(stream.error("Expected 'struct', 'global' or 'func'"));
+        }
+    }
+
+    MatchResult::Ok(())
+}
+
+/// Reads a unit from the text format written by its `Display` implementation, which is described in `ir/syntax.md`.
+/// Types, globals and functions are numbered in the order they are declared, and types must be declared before they are
+/// used. The unit is not validated.
+pub fn parse_unit(source: &str) -> Result<TranslationUnit, SyntaxError> {
+    // With a newline at the end, stepping past the last token always leaves no token, rather than leaving the last in place
+    let source = format!("{}\n", source);
+    let mut stream = TokenStream::new(&source, Box::new(Matcher {}));
+    stream.step();
+
+    let mut unit = TranslationUnit::new();
+    match parse_items(&mut stream, &mut unit) {
+        MatchResult::Ok(_) => Ok(unit),
+        MatchResult::Err(e) => Err(e),
+        MatchResult::Fail => unreachable!()
+    }
+}
\ No newline at end of file
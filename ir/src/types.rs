@@ -34,7 +34,7 @@ pub enum StorableType {
     SliceData(Box<StorableType>)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PropertyIndex(usize);
 
 impl PropertyIndex {
@@ -76,16 +76,59 @@ impl StructProperty {
     }
 }
 
+/// How the properties of a struct are placed in memory. Properties are always placed in the order they are declared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructRepr {
+    /// Properties are placed one after another without padding. This is not guaranteed and may change, so structs which
+    /// are shared with other languages should use one of the other representations.
+    Default,
+    /// Each property is aligned as a C compiler would align it, with padding between properties and at the end
+    C,
+    /// Properties are placed one after another without padding, and the struct only needs to be aligned to a byte
+    Packed
+}
+
+/// The largest alignment a struct can be given. It is the most every target can place objects at, on the stack, in the
+/// data sections and on the heap
+pub const MAX_ALIGN: usize = 8;
+
 #[derive(Debug)]
 pub struct StructContent {
-    props: Vec<StructProperty>
+    props: Vec<StructProperty>,
+    repr: StructRepr,
+    min_align: Option<usize>
 }
 
 impl StructContent {
     pub fn new() -> StructContent {
         StructContent {
-            props: Vec::new()
+            props: Vec::new(),
+            repr: StructRepr::Default,
+            min_align: None
+        }
+    }
+
+    pub fn repr(&self) -> StructRepr {
+        self.repr
+    }
+
+    pub fn set_repr(&mut self, repr: StructRepr) {
+        self.repr = repr;
+    }
+
+    /// The alignment the struct must have at least, whatever its properties need. Its size is rounded up to a multiple
+    /// of it.
+    pub fn min_align(&self) -> Option<usize> {
+        self.min_align
+    }
+
+    /// Sets the least alignment of the struct, which must be a power of two no more than [`MAX_ALIGN`]
+    pub fn set_min_align(&mut self, min_align: Option<usize>) {
+        if let Some(min_align) = min_align {
+            assert!(min_align.is_power_of_two(), "Alignment must be a power of two");
+            assert!(min_align <= MAX_ALIGN, "Alignment must be no more than {}", MAX_ALIGN);
         }
+        self.min_align = min_align;
     }
 
     pub fn push_prop(&mut self, prop: StructProperty) {
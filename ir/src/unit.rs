@@ -67,14 +67,30 @@ impl TranslationUnit {
         None
     }
 
+    pub fn find_panic(&self) -> Option<FunctionIndex> {
+        for (f, function) in self.functions.iter().enumerate() {
+            if function.is_panic() { return Some(FunctionIndex::new(f)); }
+        }
+        None
+    }
+
     pub fn globals(&self) -> &Vec<Global> {
         &self.globals
     }
 
+    pub fn global_count(&self) -> usize {
+        self.globals.len()
+    }
+
     pub fn get_global(&self, idx: GlobalIndex) -> Option<&Global> {
         self.globals.get(idx.idx())
     }
 
+    /// Any global removed or moved must not be referred to by its old index afterwards
+    pub(crate) fn globals_mut(&mut self) -> &mut Vec<Global> {
+        &mut self.globals
+    }
+
     pub fn add_function(&mut self, function: Function) -> FunctionIndex {
         self.functions.push(function);
         FunctionIndex::new(self.functions.len() - 1)
@@ -91,6 +107,11 @@ impl TranslationUnit {
         self.functions.get_mut(idx.idx())
     }
 
+    /// Any function removed or moved must not be called by its old index afterwards
+    pub(crate) fn functions_mut(&mut self) -> &mut Vec<Function> {
+        &mut self.functions
+    }
+
     pub fn find_function_index(&self, name: &str) -> Option<FunctionIndex> {
         for (c, ct) in self.functions.iter().enumerate() {
             if ct.method_of().is_none() && ct.name() == name {
@@ -124,7 +145,7 @@ impl TranslationUnit {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LocalIndex(usize);
 
 impl LocalIndex {
@@ -158,7 +179,7 @@ impl Local {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct FunctionIndex(usize);
 
 impl FunctionIndex {
@@ -215,7 +236,25 @@ pub enum FunctionAttr {
     FreeSlice,
 
     /// Specifies the location for an extern function - used for example to specify module in wasm and class in java
-    ExternLocation(String)
+    ExternLocation(String),
+
+    /// Marks function as being the implementation for panic, which takes the message and the location it was raised from,
+    /// and never returns
+    Panic,
+
+    /// Marks function as fallible. The last two returns are the error value and a bool which is 1 if the function failed,
+    /// any returns before them make up the value returned on success.
+    Fallible,
+
+    /// Marks function as a test, which takes no parameters and returns true if it passed
+    Test,
+
+    /// Marks function to be inlined at each call when optimising, however large it is
+    Inline,
+
+    /// Marks function as called from outside the unit, such as from C code it is linked with, so that it is kept even when
+    /// nothing in the unit calls it
+    Export
 }
 
 #[derive(Debug)]
@@ -368,6 +407,51 @@ impl Function {
         false
     }
     
+    pub fn is_panic(&self) -> bool {
+        for attr in &self.attrs {
+            if matches!(attr, FunctionAttr::Panic) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_fallible(&self) -> bool {
+        for attr in &self.attrs {
+            if matches!(attr, FunctionAttr::Fallible) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_test(&self) -> bool {
+        for attr in &self.attrs {
+            if matches!(attr, FunctionAttr::Test) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_inline(&self) -> bool {
+        for attr in &self.attrs {
+            if matches!(attr, FunctionAttr::Inline) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_export(&self) -> bool {
+        for attr in &self.attrs {
+            if matches!(attr, FunctionAttr::Export) {
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn location(&self) -> Option<&str> {
         for attr in &self.attrs {
             if let FunctionAttr::ExternLocation(name) = attr {
@@ -426,6 +510,7 @@ impl Function {
     }
 
     pub fn locals(&self) -> &Vec<Local> { &self.locals }
+    pub(crate) fn locals_mut(&mut self) -> &mut Vec<Local> { &mut self.locals }
     pub fn local_count(&self) -> usize { self.locals.len() }
 
     pub fn last_local_index(&self) -> Option<LocalIndex> {
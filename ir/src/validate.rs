@@ -1,43 +1,28 @@
-use crate::{Function, Ins, StorableType, TranslationUnit, CompoundContent, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
+use crate::{Function, FunctionIndex, Global, GlobalIndex, Ins, LocalIndex, PropertyIndex, StorableType, StorableValue, TranslationUnit, CompoundContent, CompoundValue, Value, ValuePath, ValuePathComponent, ValuePathOrigin, ValueType};
 
 macro_rules! pop {
-    ($stack:expr, $( $pattern:pat_param )|+ $( if $guard: expr )? $(,)?) => {
-        match $stack.pop()? {
-            $( $pattern )|+ $( if $guard )? => {},
-            _ => return Err(ValidationError::StackIncorrectType)
-        };
-    };
     ($stack:expr, = $e:expr) => {
-        if $stack.pop()? != $e {
-            return Err(ValidationError::StackIncorrectType);
+        let found = $stack.pop()?;
+        if found != $e {
+            return Err(ValidationErrorKind::StackIncorrectType(($e).clone(), found));
         }
     };
 }
 
 macro_rules! pop_path {
-    ($stack:expr, $( $pattern:pat_param )|+ $( if $guard: expr )? $(,)?) => {
-        match $stack.pop_path()? {
-            $( $pattern )|+ $( if $guard )? => {},
-            _ => return Err(ValidationError::StackIncorrectType)
-        };
-    };
     ($stack:expr, = $e:expr) => {
-        if $stack.pop_path()? != $e {
-            return Err(ValidationError::StackIncorrectType);
+        let found = $stack.pop_path()?;
+        if found != $e {
+            return Err(ValidationErrorKind::StackIncorrectType(($e).clone(), found));
         }
     };
 }
 
 macro_rules! peek {
-    ($stack:expr, $idx:expr, $( $pattern:pat_param )|+ $( if $guard: expr )? $(,)?) => {
-        match $stack.peek($idx)? {
-            $( $pattern )|+ $( if $guard )? => {},
-            _ => return Err(ValidationError::StackIncorrectType)
-        };
-    };
     ($stack:expr, $idx:expr, = $e:expr) => {
-        if $stack.peek($idx)? != $e {
-            return Err(ValidationError::StackIncorrectType);
+        let found = $stack.peek($idx)?;
+        if found != $e {
+            return Err(ValidationErrorKind::StackIncorrectType(($e).clone(), found.clone()));
         }
     };
 }
@@ -63,22 +48,22 @@ impl TypeStack {
         self.types.push(ValueOrPath::Value(value_type));
     }
 
-    fn peek(&self, index: usize) -> Result<&ValueType, ValidationError> {
+    fn peek(&self, index: usize) -> Result<&ValueType, ValidationErrorKind> {
         // Catches subtraction with overflow
-        if index + 1 > self.types.len() { return Err(ValidationError::StackUnderflow) }
+        if index + 1 > self.types.len() { return Err(ValidationErrorKind::StackUnderflow) }
 
         match self.types.get(self.types.len() - 1 - index) {
             Some(ValueOrPath::Value(v)) => Ok(v),
-            Some(ValueOrPath::Path(_)) => Err(ValidationError::StackIsPath),
-            None => Err(ValidationError::StackUnderflow)
+            Some(ValueOrPath::Path(_)) => Err(ValidationErrorKind::StackIsPath),
+            None => Err(ValidationErrorKind::StackUnderflow)
         }
     }
 
-    fn pop(&mut self) -> Result<ValueType, ValidationError> {
+    fn pop(&mut self) -> Result<ValueType, ValidationErrorKind> {
         match self.types.pop() {
             Some(ValueOrPath::Value(v)) => Ok(v),
-            Some(ValueOrPath::Path(_)) => Err(ValidationError::StackIsPath),
-            None => Err(ValidationError::StackUnderflow)
+            Some(ValueOrPath::Path(_)) => Err(ValidationErrorKind::StackIsPath),
+            None => Err(ValidationErrorKind::StackUnderflow)
         }
     }
 
@@ -86,11 +71,11 @@ impl TypeStack {
         self.types.push(ValueOrPath::Path(path_value));
     }
 
-    fn pop_path(&mut self) -> Result<ValueType, ValidationError> {
+    fn pop_path(&mut self) -> Result<ValueType, ValidationErrorKind> {
         match self.types.pop() {
             Some(ValueOrPath::Path(v)) => Ok(v),
-            Some(ValueOrPath::Value(_)) => Err(ValidationError::StackIsPath),
-            None => Err(ValidationError::StackUnderflow)
+            Some(ValueOrPath::Value(_)) => Err(ValidationErrorKind::StackIsPath),
+            None => Err(ValidationErrorKind::StackUnderflow)
         }
     }
 
@@ -102,90 +87,264 @@ impl TypeStack {
 enum BlockElement {
     Loop,
     If,
-    IfElse
+    IfElse,
+    Switch
 }
 
 struct BlockStack {
-    elements: Vec<BlockElement>
+    elements: Vec<BlockElement>,
+    /// The position of the instruction being validated, which is left as it is when an error is found
+    position: Vec<usize>
 }
 
 impl BlockStack {
     fn new() -> BlockStack {
         BlockStack {
-            elements: Vec::new()
+            elements: Vec::new(),
+            position: Vec::new()
         }
     }
 
-    fn with<T>(&mut self, element: BlockElement, mut cb: T) -> Result<(), ValidationError> where T: FnMut(&mut BlockStack) -> Result<(), ValidationError> {
-        self.elements.push(element);
-        let res = cb(self);
-        self.elements.pop();
+    /// Validates the code of a block held by the instruction at the current position, which is the nth block it holds. The
+    /// element is given for blocks which a break or continue can count, and None for conditions and the like.
+    fn validate_block(&mut self, n: usize, element: Option<BlockElement>, code: &Vec<Ins>, stack: &mut TypeStack, function: &Function, unit: &TranslationUnit) -> Result<(), ValidationErrorKind> {
+        self.position.push(n);
+        let counted = element.is_some();
+        if let Some(element) = element {
+            self.elements.push(element);
+        }
+
+        validate_code(code, stack, self, function, unit)?;
+
+        if counted {
+            self.elements.pop();
+        }
+        self.position.pop();
 
-        res
+        Ok(())
     }
 
     fn is_breakable(&self, index: usize) -> bool {
-        match self.elements.get(self.elements.len() - 1 - index) {
-            Some(BlockElement::Loop) => true,
-            _ => false
+        match self.elements.len().checked_sub(index + 1) {
+            Some(x) => matches!(self.elements.get(x), Some(BlockElement::Loop)),
+            None => false
         }
     }
 
     fn is_continuable(&self, index: usize) -> bool {
-        match self.elements.get(self.elements.len() - 1 - index) {
-            Some(BlockElement::Loop) => true,
-            _ => false
+        match self.elements.len().checked_sub(index + 1) {
+            Some(x) => matches!(self.elements.get(x), Some(BlockElement::Loop)),
+            None => false
+        }
+    }
+}
+
+/// Where in a unit a validation error was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationLocation {
+    /// The function, and the position of the instruction in it. The position is the index of an instruction in the
+    /// function's code, followed by the index of a block it holds and the index of an instruction in that block, and so on
+    /// for each nested block. Blocks are numbered in the order the instruction holds them, so for a Loop 0 is its code, 1
+    /// its condition and 2 its increment, and for a Switch each case comes first, then the default and the value. An error
+    /// with the function as a whole has an empty position, and a block which does not return ends with the block.
+    Function(FunctionIndex, Vec<usize>),
+    /// The global, whose default does not match its type
+    Global(GlobalIndex)
+}
+
+impl std::fmt::Display for ValidationLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationLocation::Function(idx, position) if position.is_empty() => f.write_fmt(format_args!("function {}", idx)),
+            ValidationLocation::Function(idx, position) => {
+                f.write_str("instruction ")?;
+                for (p, index) in position.iter().enumerate() {
+                    if p != 0 { f.write_str("/")?; }
+                    f.write_fmt(format_args!("{}", index))?;
+                }
+                f.write_fmt(format_args!(" of function {}", idx))
+            },
+            ValidationLocation::Global(idx) => f.write_fmt(format_args!("global {}", idx))
         }
     }
 }
 
+/// Where types are given, the first is the type which was expected and the second the type which was found
 #[derive(Debug, PartialEq)]
-pub enum ValidationError {
+pub enum ValidationErrorKind {
     StackUnderflow,
-    StackIncorrectType,
-    StackNotNum,
+    StackIncorrectType(ValueType, ValueType),
+    StackNotNum(ValueType),
     StackDepthNotZero,
     StackDepthNotOne,
     StackIsPath,
-    StackNotValue,
-    PathIncorrectType,
-    PathNotValue,
-    LocalDoesNotExist,
-    LocalIncorrectType,
+    StackNotValue(ValueType),
+    PathIncorrectType(StorableType, StorableType),
+    /// The type the path leads to, which is not a value
+    PathNotValue(StorableType),
+    /// The type a length was taken of
+    PathNotSlice(StorableType),
+    LocalDoesNotExist(LocalIndex),
+    LocalIncorrectType(StorableType, StorableType),
     LocalUnderflow,
-    GlobalIncorrectType,
-    PropertyIncorrectType,
-    PropertyDoesNotExist,
-    GlobalDoesNotExist,
-    FunctionDoesNotExist,
+    GlobalIncorrectType(StorableType, StorableType),
+    PropertyIncorrectType(StorableType, StorableType),
+    PropertyDoesNotExist(PropertyIndex),
+    GlobalDoesNotExist(GlobalIndex),
+    FunctionDoesNotExist(FunctionIndex),
     NotBreakable,
     NotContinuable,
     NoFinalReturn,
-    NotARef,
+    /// The type which was dereferenced
+    NotARef(ValueType),
     LengthWrite,
     PathUnderflow,
-    InvalidEntry
+    InvalidEntry,
+    /// The type of the value an IfElse is declared to leave and the type of the one a branch leaves, if any
+    BranchValueMismatch(Option<ValueType>, Option<ValueType>),
+    DuplicateCase(u64),
+    /// A default value, or part of one, is not of the type it is stored as, which is given
+    ValueIncorrectType(StorableType),
+    /// A slice default reaches past the end of the data it is a slice of, giving the global with the data, the start and the length
+    SliceOutOfBounds(GlobalIndex, usize, usize)
+}
+
+impl std::fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationErrorKind::StackUnderflow => f.write_str("Stack underflow"),
+            ValidationErrorKind::StackIncorrectType(expected, found) => f.write_fmt(format_args!("Expected {} on the stack, found {}", expected, found)),
+            ValidationErrorKind::StackNotNum(vt) => f.write_fmt(format_args!("{} is not a number", vt)),
+            ValidationErrorKind::StackDepthNotZero => f.write_str("Stack must be empty"),
+            ValidationErrorKind::StackDepthNotOne => f.write_str("Stack must hold exactly one value"),
+            ValidationErrorKind::StackIsPath => f.write_str("Expected a value on the stack, found a path"),
+            ValidationErrorKind::StackNotValue(vt) => f.write_fmt(format_args!("{} cannot be converted", vt)),
+            ValidationErrorKind::PathIncorrectType(expected, found) => f.write_fmt(format_args!("Expected path to {}, found {}", expected, found)),
+            ValidationErrorKind::PathNotValue(st) => f.write_fmt(format_args!("Path leads to {}, which is not a value", st)),
+            ValidationErrorKind::PathNotSlice(st) => f.write_fmt(format_args!("Length taken of {}, which is not a slice", st)),
+            ValidationErrorKind::LocalDoesNotExist(idx) => f.write_fmt(format_args!("Local {} does not exist", idx)),
+            ValidationErrorKind::LocalIncorrectType(expected, found) => f.write_fmt(format_args!("Expected local of type {}, found {}", expected, found)),
+            ValidationErrorKind::LocalUnderflow => f.write_str("Fewer locals than params"),
+            ValidationErrorKind::GlobalIncorrectType(expected, found) => f.write_fmt(format_args!("Expected global of type {}, found {}", expected, found)),
+            ValidationErrorKind::PropertyIncorrectType(expected, found) => f.write_fmt(format_args!("Expected property of type {}, found {}", expected, found)),
+            ValidationErrorKind::PropertyDoesNotExist(idx) => f.write_fmt(format_args!("Property {} does not exist", idx)),
+            ValidationErrorKind::GlobalDoesNotExist(idx) => f.write_fmt(format_args!("Global {} does not exist", idx)),
+            ValidationErrorKind::FunctionDoesNotExist(idx) => f.write_fmt(format_args!("Function {} does not exist", idx)),
+            ValidationErrorKind::NotBreakable => f.write_str("Break does not refer to a loop"),
+            ValidationErrorKind::NotContinuable => f.write_str("Continue does not refer to a loop"),
+            ValidationErrorKind::NoFinalReturn => f.write_str("End of block can be reached without a return"),
+            ValidationErrorKind::NotARef(vt) => f.write_fmt(format_args!("{} is not a reference", vt)),
+            ValidationErrorKind::LengthWrite => f.write_str("Length of a slice cannot be written"),
+            ValidationErrorKind::PathUnderflow => f.write_str("Path underflow"),
+            ValidationErrorKind::InvalidEntry => f.write_str("Entry must take no params and return an i32"),
+            ValidationErrorKind::BranchValueMismatch(expected, found) => {
+                let write_value = |f: &mut std::fmt::Formatter<'_>, value: &Option<ValueType>| match value {
+                    Some(vt) => f.write_fmt(format_args!("{}", vt)),
+                    None => f.write_str("nothing")
+                };
+
+                f.write_str("Branch leaves ")?;
+                write_value(f, found)?;
+                f.write_str(" instead of ")?;
+                write_value(f, expected)
+            },
+            ValidationErrorKind::DuplicateCase(value) => f.write_fmt(format_args!("Case {} is listed more than once", value)),
+            ValidationErrorKind::ValueIncorrectType(st) => f.write_fmt(format_args!("Value is not of type {}", st)),
+            ValidationErrorKind::SliceOutOfBounds(global, start, len) => f.write_fmt(format_args!("Slice of {} elements from {} is out of bounds of global {}", len, start, global)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+    location: ValidationLocation,
+    kind: ValidationErrorKind
+}
+
+impl ValidationError {
+    pub fn new(location: ValidationLocation, kind: ValidationErrorKind) -> ValidationError {
+        ValidationError {
+            location, kind
+        }
+    }
+
+    pub fn location(&self) -> &ValidationLocation {
+        &self.location
+    }
+
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} (at {})", self.kind, self.location))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationWarningKind {
+    /// Code which follows a return, break or continue, or a block which control cannot leave
+    UnreachableCode
+}
+
+impl std::fmt::Display for ValidationWarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationWarningKind::UnreachableCode => f.write_str("Code cannot be reached"),
+        }
+    }
+}
+
+/// Something which is valid, but likely a mistake
+#[derive(Debug, PartialEq)]
+pub struct ValidationWarning {
+    location: ValidationLocation,
+    kind: ValidationWarningKind
+}
+
+impl ValidationWarning {
+    pub fn new(location: ValidationLocation, kind: ValidationWarningKind) -> ValidationWarning {
+        ValidationWarning {
+            location, kind
+        }
+    }
+
+    pub fn location(&self) -> &ValidationLocation {
+        &self.location
+    }
+
+    pub fn kind(&self) -> &ValidationWarningKind {
+        &self.kind
+    }
+}
+
+impl std::fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} (at {})", self.kind, self.location))
+    }
 }
 
 impl Ins {
-    fn resolve_path(path: &ValuePath, stack: &mut TypeStack, function: &Function, unit: &TranslationUnit) -> Result<ValueType, ValidationError> {
+    fn resolve_path(path: &ValuePath, stack: &mut TypeStack, function: &Function, unit: &TranslationUnit) -> Result<ValueType, ValidationErrorKind> {
         let mut curr_type = match path.origin() {
             ValuePathOrigin::Local(local_idx, local_type) =>
                 match function.get_local(*local_idx) {
-                    None => return Err(ValidationError::LocalDoesNotExist),
+                    None => return Err(ValidationErrorKind::LocalDoesNotExist(*local_idx)),
                     Some(x) =>
                         if x.local_type() != local_type {
-                            return Err(ValidationError::LocalIncorrectType)
+                            return Err(ValidationErrorKind::LocalIncorrectType(local_type.clone(), x.local_type().clone()))
                         } else {
                             local_type.clone()
                         },
                 },
             ValuePathOrigin::Global(global_idx, global_type) =>
                 match unit.get_global(*global_idx) {
-                    None => return Err(ValidationError::GlobalDoesNotExist),
+                    None => return Err(ValidationErrorKind::GlobalDoesNotExist(*global_idx)),
                     Some(x) =>
                         if x.global_type() != global_type {
-                            return Err(ValidationError::GlobalIncorrectType)
+                            return Err(ValidationErrorKind::GlobalIncorrectType(global_type.clone(), x.global_type().clone()))
                         } else {
                             global_type.clone()
                         },
@@ -194,11 +353,11 @@ impl Ins {
                 match stack.pop()? {
                     ValueType::Ref(deref_type) =>
                         if deref_type.as_ref() != expected_deref_type {
-                            return Err(ValidationError::StackIncorrectType)
+                            return Err(ValidationErrorKind::StackIncorrectType(ValueType::Ref(Box::new(expected_deref_type.clone())), ValueType::Ref(deref_type)))
                         } else {
                             expected_deref_type.clone()
                         },
-                    _ => return Err(ValidationError::NotARef)
+                    vt => return Err(ValidationErrorKind::NotARef(vt))
                 },
         };
 
@@ -208,49 +367,49 @@ impl Ins {
                     match curr_type {
                         StorableType::Slice(slice_type) =>
                             if slice_type.as_ref() != expected_slice_type {
-                                return Err(ValidationError::PathIncorrectType)
+                                return Err(ValidationErrorKind::PathIncorrectType(StorableType::Slice(Box::new(expected_slice_type.clone())), StorableType::Slice(slice_type)))
                             } else {
-                                pop!(stack, ValueType::Index(s) if s == slice_type);
-                                expected_slice_type.clone()   
+                                pop!(stack, = ValueType::Index(slice_type.clone()));
+                                expected_slice_type.clone()
                             }
-                        _ => return Err(ValidationError::PathIncorrectType)
+                        found => return Err(ValidationErrorKind::PathIncorrectType(StorableType::Slice(Box::new(expected_slice_type.clone())), found))
                     },
                 ValuePathComponent::Property(prop_idx, expected_compound_type, prop_type) =>
                     match curr_type {
                         StorableType::Compound(compound_type) =>
                             if &compound_type != expected_compound_type {
-                                return Err(ValidationError::PathIncorrectType)
+                                return Err(ValidationErrorKind::PathIncorrectType(StorableType::Compound(expected_compound_type.clone()), StorableType::Compound(compound_type)))
                             } else {
                                 match compound_type.content() {
                                     CompoundContent::Struct(struc) =>
                                         match struc.prop(*prop_idx) {
                                             Some(x) =>
                                                 if x.prop_type() != prop_type {
-                                                    return Err(ValidationError::PropertyIncorrectType)
+                                                    return Err(ValidationErrorKind::PropertyIncorrectType(prop_type.clone(), x.prop_type().clone()))
                                                 } else {
                                                     prop_type.clone()
                                                 },
-                                            None => return Err(ValidationError::PropertyDoesNotExist)
+                                            None => return Err(ValidationErrorKind::PropertyDoesNotExist(*prop_idx))
                                         },
                                 }
                             },
-                        _ => return Err(ValidationError::PathIncorrectType)
+                        found => return Err(ValidationErrorKind::PathIncorrectType(StorableType::Compound(expected_compound_type.clone()), found))
                     },
                 ValuePathComponent::Length =>
                     match curr_type {
                         StorableType::Slice(_) => StorableType::Value(ValueType::UPtr),
-                        _ => return Err(ValidationError::PathIncorrectType)
+                        found => return Err(ValidationErrorKind::PathNotSlice(found))
                     }
             }
         }
 
         match curr_type {
             StorableType::Value(v) => Ok(v),
-            _ => return Err(ValidationError::PathNotValue)
+            found => return Err(ValidationErrorKind::PathNotValue(found))
         }
     }
-    
-    fn validate(&self, stack: &mut TypeStack, blocks: &mut BlockStack, function: &Function, unit: &TranslationUnit) -> Result<(), ValidationError> {
+
+    fn validate(&self, stack: &mut TypeStack, blocks: &mut BlockStack, function: &Function, unit: &TranslationUnit) -> Result<(), ValidationErrorKind> {
         match &self {
             Ins::Push(expected_vt) => Ok({
                 pop_path!(stack, = *expected_vt);
@@ -260,35 +419,46 @@ impl Ins {
                 pop!(stack, = *expected_vt);
                 pop_path!(stack, = *expected_vt);
             }),
+            Ins::PopLocal(local_idx, expected_vt) => Ok({
+                match function.get_local(*local_idx) {
+                    None => return Err(ValidationErrorKind::LocalDoesNotExist(*local_idx)),
+                    Some(local) => if local.local_type() != &StorableType::Value(expected_vt.clone()) {
+                        return Err(ValidationErrorKind::LocalIncorrectType(StorableType::Value(expected_vt.clone()), local.local_type().clone()))
+                    }
+                }
+                pop!(stack, = *expected_vt);
+            }),
             Ins::PushPath(path, expected_vt) => Ok({
                 let resolved_vt = Ins::resolve_path(path, stack, function, unit)?;
                 if &resolved_vt != expected_vt {
-                    return Err(ValidationError::PathIncorrectType)
+                    return Err(ValidationErrorKind::PathIncorrectType(StorableType::Value(expected_vt.clone()), StorableType::Value(resolved_vt)))
                 }
                 stack.push_path(resolved_vt);
             }),
             Ins::Index(slice_type) => Ok({
-                pop!(stack, ValueType::UPtr);
+                pop!(stack, = ValueType::UPtr);
                 stack.push(ValueType::Index(Box::new(slice_type.clone())));
             }),
             Ins::New(object_type) => Ok({
                 stack.push(ValueType::Ref(Box::new(object_type.clone())));
             }),
             Ins::NewSlice(slice_type) => Ok({
-                pop!(stack, ValueType::UPtr);
+                pop!(stack, = ValueType::UPtr);
                 stack.push(ValueType::Ref(Box::new(StorableType::Slice(Box::new(slice_type.clone())))));
             }),
             Ins::Free(object_type) => Ok({
-                pop!(stack, ValueType::Ref(target) if target.as_ref() == object_type);
+                pop!(stack, = ValueType::Ref(Box::new(object_type.clone())));
             }),
             Ins::FreeSlice(slice_type) => Ok({
-                pop!(stack, ValueType::Ref(target) if matches!(target.as_ref(), StorableType::Slice(target_slice_type) if target_slice_type.as_ref() == slice_type));
+                pop!(stack, = ValueType::Ref(Box::new(StorableType::Slice(Box::new(slice_type.clone())))));
             }),
             Ins::Convert(from, to) => Ok({
-                if !from.is_num() || !to.is_num() {
-                    return Err(ValidationError::StackNotValue)
+                if !from.is_num() {
+                    return Err(ValidationErrorKind::StackNotValue(from.clone()))
+                } else if !to.is_num() {
+                    return Err(ValidationErrorKind::StackNotValue(to.clone()))
                 }
-                
+
                 pop!(stack, = *from);
                 stack.push(to.clone());
             }),
@@ -306,14 +476,14 @@ impl Ins {
                         stack.push(sig.returns()[i].clone());
                     }
                 } else {
-                    return Err(ValidationError::FunctionDoesNotExist);
+                    return Err(ValidationErrorKind::FunctionDoesNotExist(*idx));
                 }
             }),
             Ins::Ret => Ok({
                 if stack.depth() < function.signature().return_count() {
-                    return Err(ValidationError::StackUnderflow)
+                    return Err(ValidationErrorKind::StackUnderflow)
                 } else if stack.depth() > function.signature().return_count() {
-                    return Err(ValidationError::StackDepthNotZero)
+                    return Err(ValidationErrorKind::StackDepthNotZero)
                 }
 
                 for i in 0..function.signature().return_count() {
@@ -321,15 +491,15 @@ impl Ins {
                 }
             }),
             Ins::Inc(vt, _) | Ins::Dec(vt, _) => Ok({
-                if !vt.is_num() { return Err(ValidationError::StackNotNum) }
+                if !vt.is_num() { return Err(ValidationErrorKind::StackNotNum(vt.clone())) }
                 peek!(stack, 0, = vt);
             }),
             Ins::Neg(vt) => Ok({
-                if !vt.is_num() { return Err(ValidationError::StackNotNum) }
+                if !vt.is_num() { return Err(ValidationErrorKind::StackNotNum(vt.clone())) }
                 peek!(stack, 0, = vt);
             }),
             Ins::Add(operand_type) | Ins::Mul(operand_type) | Ins::Div(operand_type) | Ins::Sub(operand_type) => Ok({
-                if !operand_type.is_num() { return Err(ValidationError::StackNotNum) }
+                if !operand_type.is_num() { return Err(ValidationErrorKind::StackNotNum(operand_type.clone())) }
                 pop!(stack, = *operand_type);
                 peek!(stack, 0, = operand_type);
             }),
@@ -343,62 +513,81 @@ impl Ins {
                 peek!(stack, 0, = &ValueType::Bool);
             }),
             Ins::Loop(block, condition, inc) => Ok({
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-                blocks.with(BlockElement::Loop, |blocks| {
-                    for el in block { el.validate(stack, blocks, function, unit)?; }
-                    Ok(())
-                })?;
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                blocks.validate_block(0, Some(BlockElement::Loop), block, stack, function, unit)?;
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
 
-                for el in inc { el.validate(stack, blocks, function, unit)?; }
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
+                blocks.validate_block(2, None, inc, stack, function, unit)?;
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
 
-                for el in condition { el.validate(stack, blocks, function, unit)?; }
-                if stack.depth() != 1 { return Err(ValidationError::StackDepthNotOne); }
+                blocks.validate_block(1, None, condition, stack, function, unit)?;
+                if stack.depth() != 1 { return Err(ValidationErrorKind::StackDepthNotOne); }
 
-                pop!(stack, ValueType::Bool);
+                pop!(stack, = ValueType::Bool);
             }),
             Ins::If(block, cond) => Ok({
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-
-                for el in cond { el.validate(stack, blocks, function, unit)?; }
-                if stack.depth() != 1 { return Err(ValidationError::StackDepthNotOne); }
-                pop!(stack, ValueType::Bool);
-
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-                blocks.with(BlockElement::If, |blocks| {
-                    for el in block { el.validate(stack, blocks, function, unit)?; }
-                    Ok(())
-                })?;
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+
+                blocks.validate_block(1, None, cond, stack, function, unit)?;
+                if stack.depth() != 1 { return Err(ValidationErrorKind::StackDepthNotOne); }
+                pop!(stack, = ValueType::Bool);
+
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                blocks.validate_block(0, Some(BlockElement::If), block, stack, function, unit)?;
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+            }),
+            Ins::IfElse(vt, true_then, else_then, cond) => Ok({
+                // Unlike an If, the IfElse can be used in an expression, so the condition and branches get a stack of their own
+                let mut cond_stack = TypeStack::new();
+                blocks.validate_block(2, None, cond, &mut cond_stack, function, unit)?;
+                if cond_stack.depth() != 1 { return Err(ValidationErrorKind::StackDepthNotOne); }
+                pop!(cond_stack, = ValueType::Bool);
+
+                let true_value = validate_branch(0, true_then, blocks, function, unit)?;
+                if true_value != *vt { return Err(ValidationErrorKind::BranchValueMismatch(vt.clone(), true_value)); }
+                let else_value = validate_branch(1, else_then, blocks, function, unit)?;
+                if else_value != *vt { return Err(ValidationErrorKind::BranchValueMismatch(vt.clone(), else_value)); }
+
+                if let Some(vt) = vt {
+                    stack.push(vt.clone());
+                }
             }),
-            Ins::IfElse(true_then, else_then, cond) => Ok({
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-
-                for el in cond { el.validate(stack, blocks, function, unit)?; }
-                if stack.depth() != 1 { return Err(ValidationError::StackDepthNotOne); }
-                pop!(stack, ValueType::Bool);
-                
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-                blocks.with(BlockElement::IfElse, |blocks| {
-                    for el in true_then { el.validate(stack, blocks, function, unit)?; }
-                    Ok(())
-                })?;
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-
-                blocks.with(BlockElement::IfElse, |blocks| {
-                    for el in else_then { el.validate(stack, blocks, function, unit)?; }
-                    Ok(())
-                })?;
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
+            Ins::Switch(vt, cases, default, value) => Ok({
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                if !vt.is_num() || *vt == ValueType::Bool { return Err(ValidationErrorKind::StackNotNum(vt.clone())) }
+
+                blocks.validate_block(cases.len() + 1, None, value, stack, function, unit)?;
+                if stack.depth() != 1 { return Err(ValidationErrorKind::StackDepthNotOne); }
+                pop!(stack, = *vt);
+
+                let mut values = cases.iter().flat_map(|(values, _)| values.iter()).collect::<Vec<_>>();
+                values.sort();
+                if let Some(pair) = values.windows(2).find(|pair| pair[0] == pair[1]) { return Err(ValidationErrorKind::DuplicateCase(*pair[0])); }
+
+                for (n, code) in cases.iter().map(|(_, code)| code).chain(std::iter::once(default)).enumerate() {
+                    blocks.validate_block(n, Some(BlockElement::Switch), code, stack, function, unit)?;
+                    if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                }
+            }),
+            Ins::Asm(block) => Ok({
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+
+                for binding in block.inputs().iter().chain(block.outputs().iter()) {
+                    match function.get_local(binding.local()) {
+                        None => return Err(ValidationErrorKind::LocalDoesNotExist(binding.local())),
+                        Some(local) => if local.local_type() != &StorableType::Value(binding.value_type().clone()) {
+                            return Err(ValidationErrorKind::LocalIncorrectType(StorableType::Value(binding.value_type().clone()), local.local_type().clone()))
+                        }
+                    }
+                }
             }),
             Ins::Break(idx) => Ok({
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-                if !blocks.is_breakable(*idx) { return Err(ValidationError::NotBreakable) }
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                if !blocks.is_breakable(*idx) { return Err(ValidationErrorKind::NotBreakable) }
             }),
             Ins::Continue(idx) => Ok({
-                if stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
-                if !blocks.is_continuable(*idx) { return Err(ValidationError::NotContinuable) }
+                if stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
+                if !blocks.is_continuable(*idx) { return Err(ValidationErrorKind::NotContinuable) }
             }),
             Ins::PushLiteral(vt, _) => Ok(stack.push(vt.clone())),
             Ins::Drop => Ok({ stack.pop()?; }),
@@ -406,64 +595,271 @@ impl Ins {
     }
 }
 
-// TODO: Loops are not handled here, so if the only way to exit a loop is to return, it will still be considered invalid
-fn ensure_returns(block: &Vec<Ins>) -> Result<(), ValidationError> {
-    match block.last() {
-        Some(Ins::Ret) => Ok(()),
-        Some(Ins::IfElse(a, b, _)) => {
-            ensure_returns(a)?;
-            ensure_returns(b)?;
-            Ok(())
+/// Validates each instruction of the code in turn, keeping the position up to date so that it is left at the instruction
+/// an error is found at
+fn validate_code(code: &Vec<Ins>, stack: &mut TypeStack, blocks: &mut BlockStack, function: &Function, unit: &TranslationUnit) -> Result<(), ValidationErrorKind> {
+    for (i, ins) in code.iter().enumerate() {
+        blocks.position.push(i);
+        ins.validate(stack, blocks, function, unit)?;
+        blocks.position.pop();
+    }
+
+    Ok(())
+}
+
+/// Validates one branch of an IfElse on a stack of its own, returning the type of the value it leaves, if any
+fn validate_branch(n: usize, block: &Vec<Ins>, blocks: &mut BlockStack, function: &Function, unit: &TranslationUnit) -> Result<Option<ValueType>, ValidationErrorKind> {
+    let mut stack = TypeStack::new();
+    blocks.validate_block(n, Some(BlockElement::IfElse), block, &mut stack, function, unit)?;
+
+    match stack.depth() {
+        0 => Ok(None),
+        1 => Ok(Some(stack.pop()?)),
+        _ => Err(ValidationErrorKind::StackDepthNotOne)
+    }
+}
+
+/// How control can leave a block
+struct Reach {
+    /// Whether the end of the block can be reached
+    falls_through: bool,
+    /// The depth of each loop broken out of from within the block, counted from the outermost block a break can count
+    breaks: Vec<usize>
+}
+
+/// Follows control through a block nested in the given number of blocks a break can count, giving the position of the first
+/// instruction of each block which cannot be reached
+fn reach(block: &[Ins], depth: usize, position: &mut Vec<usize>, unreachable: &mut Vec<Vec<usize>>) -> Reach {
+    let mut reach = Reach {
+        falls_through: true,
+        breaks: Vec::new()
+    };
+
+    for (i, ins) in block.iter().enumerate() {
+        position.push(i);
+
+        if !reach.falls_through {
+            unreachable.push(position.clone());
+            position.pop();
+            break;
+        }
+
+        match ins {
+            Ins::Ret | Ins::Continue(_) => reach.falls_through = false,
+            Ins::Break(idx) => {
+                reach.falls_through = false;
+                if let Some(target) = depth.checked_sub(idx + 1) {
+                    reach.breaks.push(target);
+                }
+            },
+            Ins::Loop(code, cond, inc) => {
+                let code = reach_block(0, code, depth + 1, position, unreachable);
+                let inc = reach_block(2, inc, depth, position, unreachable);
+
+                // A loop without a condition is given one which is always true, and can then only be left by breaking out of it
+                let endless = matches!(cond.as_slice(), [Ins::PushLiteral(ValueType::Bool, x)] if *x != 0);
+                reach.falls_through = !endless || code.breaks.contains(&depth);
+                reach.breaks.extend(code.breaks.into_iter().filter(|target| *target < depth));
+                reach.breaks.extend(inc.breaks);
+            },
+            Ins::If(code, _) => {
+                reach.breaks.extend(reach_block(0, code, depth + 1, position, unreachable).breaks);
+            },
+            Ins::IfElse(_, true_then, else_then, _) => {
+                let true_then = reach_block(0, true_then, depth + 1, position, unreachable);
+                let else_then = reach_block(1, else_then, depth + 1, position, unreachable);
+
+                reach.falls_through = true_then.falls_through || else_then.falls_through;
+                reach.breaks.extend(true_then.breaks);
+                reach.breaks.extend(else_then.breaks);
+            },
+            Ins::Switch(_, cases, default, _) => {
+                reach.falls_through = false;
+                for (n, code) in cases.iter().map(|(_, code)| code).chain(std::iter::once(default)).enumerate() {
+                    let code = reach_block(n, code, depth + 1, position, unreachable);
+                    reach.falls_through |= code.falls_through;
+                    reach.breaks.extend(code.breaks);
+                }
+            },
+            _ => {}
+        }
+
+        position.pop();
+    }
+
+    reach
+}
+
+/// Follows control through the nth block held by the instruction at the current position
+fn reach_block(n: usize, block: &[Ins], depth: usize, position: &mut Vec<usize>, unreachable: &mut Vec<Vec<usize>>) -> Reach {
+    position.push(n);
+    let reach = reach(block, depth, position, unreachable);
+    position.pop();
+    reach
+}
+
+/// Moves the position into the branch whose end is reached, for a block which ends with a branch that falls through
+fn find_fall_through(block: &[Ins], depth: usize, position: &mut Vec<usize>) {
+    let branches = match block.last() {
+        Some(Ins::IfElse(_, a, b, _)) => vec![ a, b ],
+        Some(Ins::Switch(_, cases, default, _)) => cases.iter().map(|(_, code)| code).chain(std::iter::once(default)).collect(),
+        _ => return
+    };
+
+    for (n, code) in branches.into_iter().enumerate() {
+        if reach(code, depth + 1, &mut Vec::new(), &mut Vec::new()).falls_through {
+            position.push(block.len() - 1);
+            position.push(n);
+            find_fall_through(code, depth + 1, position);
+            return;
+        }
+    }
+}
+
+/// Whether the end of a block can be reached, so that code after it would run
+pub fn falls_through(block: &[Ins]) -> bool {
+    reach(block, 0, &mut Vec::new(), &mut Vec::new()).falls_through
+}
+
+/// Checks that a default value, or part of one, is of the type it is stored as
+fn validate_value(value: &StorableValue, storable_type: &StorableType, unit: &TranslationUnit) -> Result<(), ValidationErrorKind> {
+    let matches = match (storable_type, value) {
+        (StorableType::Value(vt), StorableValue::Value(value)) => match (vt, value) {
+            (ValueType::U8, Value::U8(_)) | (ValueType::I8, Value::I8(_)) |
+            (ValueType::U16, Value::U16(_)) | (ValueType::I16, Value::I16(_)) |
+            (ValueType::U32, Value::U32(_)) | (ValueType::I32, Value::I32(_)) |
+            (ValueType::U64, Value::U64(_)) | (ValueType::I64, Value::I64(_)) |
+            (ValueType::UPtr, Value::UPtr(_)) | (ValueType::IPtr, Value::IPtr(_)) |
+            (ValueType::Bool, Value::Bool(_)) => true,
+            (ValueType::Ref(target), Value::Ref(global)) => match unit.get_global(*global) {
+                Some(x) if x.global_type() == target.as_ref() => true,
+                Some(x) => return Err(ValidationErrorKind::GlobalIncorrectType(target.as_ref().clone(), x.global_type().clone())),
+                None => return Err(ValidationErrorKind::GlobalDoesNotExist(*global))
+            },
+            _ => false
+        },
+        (StorableType::Compound(ct), StorableValue::Compound(CompoundValue::Struct(value))) => match ct.content() {
+            CompoundContent::Struct(content) => {
+                if content.props().len() == value.props().len() {
+                    for (prop, prop_value) in content.props().iter().zip(value.props()) {
+                        validate_value(prop_value.value(), prop.prop_type(), unit)?;
+                    }
+                    true
+                } else {
+                    false
+                }
+            }
         },
-        _ => Err(ValidationError::NoFinalReturn)
+        (StorableType::Slice(el), StorableValue::Slice(global, start, len)) => {
+            let data = match unit.get_global(*global) {
+                Some(x) => x,
+                None => return Err(ValidationErrorKind::GlobalDoesNotExist(*global))
+            };
+
+            let data_type = StorableType::SliceData(el.clone());
+            if data.global_type() != &data_type {
+                return Err(ValidationErrorKind::GlobalIncorrectType(data_type, data.global_type().clone()));
+            }
+
+            let count = match data.default() {
+                Some(StorableValue::SliceData(values)) => values.len(),
+                _ => 0
+            };
+            match start.checked_add(*len) {
+                Some(end) if end <= count => true,
+                _ => return Err(ValidationErrorKind::SliceOutOfBounds(*global, *start, *len))
+            }
+        },
+        (StorableType::SliceData(el), StorableValue::SliceData(values)) => {
+            for value in values {
+                validate_value(value, el, unit)?;
+            }
+            true
+        },
+        _ => false
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(ValidationErrorKind::ValueIncorrectType(storable_type.clone()))
     }
 }
 
 impl Function {
-    pub fn validate(&self, unit: &TranslationUnit) -> Result<(), ValidationError> {
-        if self.is_extern() { return Ok(()); }
+    /// Validates the function, which is at the given index of the unit, giving the position of any code which cannot be reached
+    fn validate(&self, idx: FunctionIndex, unit: &TranslationUnit) -> Result<Vec<ValidationWarning>, ValidationError> {
+        if self.is_extern() { return Ok(Vec::new()); }
 
+        let mut block_stack = BlockStack::new();
+        match self.validate_code(&mut block_stack, unit) {
+            Ok(unreachable) => Ok(unreachable.into_iter().map(|position| ValidationWarning::new(
+                ValidationLocation::Function(idx, position),
+                ValidationWarningKind::UnreachableCode
+            )).collect()),
+            Err(kind) => Err(ValidationError::new(ValidationLocation::Function(idx, block_stack.position), kind))
+        }
+    }
+
+    fn validate_code(&self, block_stack: &mut BlockStack, unit: &TranslationUnit) -> Result<Vec<Vec<usize>>, ValidationErrorKind> {
         if self.is_entry() && (
-            self.method_of().is_some() || 
+            self.method_of().is_some() ||
             self.signature().param_count() != 0 ||
             self.signature().return_count() != 1 || self.signature().returns()[0] != ValueType::I32
         ) {
-            return Err(ValidationError::InvalidEntry);
+            return Err(ValidationErrorKind::InvalidEntry);
         }
 
         let mut type_stack = TypeStack::new();
-        let mut block_stack = BlockStack::new();
 
         if self.signature().param_count() > self.local_count() {
-            return Err(ValidationError::LocalUnderflow);
+            return Err(ValidationErrorKind::LocalUnderflow);
         }
 
         for (sig_param, local) in self.signature().params().iter().zip(self.locals()) {
             if !matches!(local.local_type(), StorableType::Value(val) if val == sig_param) {
-                return Err(ValidationError::LocalIncorrectType);
+                return Err(ValidationErrorKind::LocalIncorrectType(StorableType::Value(sig_param.clone()), local.local_type().clone()));
             }
         }
 
-        for ins in self.code().iter() {
-            ins.validate(&mut type_stack, &mut block_stack, self, unit)?;
-        }
+        validate_code(self.code(), &mut type_stack, block_stack, self, unit)?;
 
-        if type_stack.depth() != 0 { return Err(ValidationError::StackDepthNotZero); }
+        if type_stack.depth() != 0 { return Err(ValidationErrorKind::StackDepthNotZero); }
 
-        ensure_returns(self.code())?;
+        // Every path through the function must return, so its end cannot be reached
+        let mut unreachable = Vec::new();
+        if reach(self.code(), 0, &mut Vec::new(), &mut unreachable).falls_through {
+            find_fall_through(self.code(), 0, &mut block_stack.position);
+            return Err(ValidationErrorKind::NoFinalReturn);
+        }
 
-        Ok(())
+        Ok(unreachable)
+    }
+}
+
+impl Global {
+    fn validate(&self, unit: &TranslationUnit) -> Result<(), ValidationErrorKind> {
+        match self.default() {
+            Some(value) => validate_value(value, self.global_type(), unit),
+            None => Ok(())
+        }
     }
 }
 
 impl TranslationUnit {
-    pub fn validate(&self) -> Result<(), ValidationError> {
-        for function in self.functions() {
-            function.validate(self)?;
+    /// Validates every function and global of the unit, giving warnings for anything which is valid but likely a mistake
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>, ValidationError> {
+        let mut warnings = Vec::new();
+        for (f, function) in self.functions().iter().enumerate() {
+            warnings.extend(function.validate(FunctionIndex::new(f), self)?);
         }
 
-        // TODO: Validate global defaults
+        for (g, global) in self.globals().iter().enumerate() {
+            if let Err(kind) = global.validate(self) {
+                return Err(ValidationError::new(ValidationLocation::Global(GlobalIndex::new(g)), kind));
+            }
+        }
 
-        Ok(())
+        Ok(warnings)
     }
 }
\ No newline at end of file
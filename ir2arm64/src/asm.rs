@@ -0,0 +1,190 @@
+/// Why an inline assembly block could not be assembled for arm64
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmError {
+    WrongArch(ir::AsmArch),
+    UnknownRegister(String),
+    UnknownInstruction(String),
+    /// The instruction exists, but not with the given operands
+    InvalidOperands(String),
+    /// The register is used for the frame or the stack, so cannot be bound to a local
+    ReservedRegister(String)
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmError::WrongArch(arch) => write!(f, "Assembly for {} cannot be built for arm64", arch.name()),
+            AsmError::UnknownRegister(reg) => write!(f, "Unknown register '{}'", reg),
+            AsmError::UnknownInstruction(mnemonic) => write!(f, "Unknown instruction '{}'", mnemonic),
+            AsmError::InvalidOperands(ins) => write!(f, "Invalid operands in '{}'", ins),
+            AsmError::ReservedRegister(reg) => write!(f, "Register '{}' is reserved, and cannot be bound to a variable", reg)
+        }
+    }
+}
+
+/// Finds the general purpose register with the given name, along with whether it is the 32 or 64 bit view of it. The
+/// stack pointer and zero register are not included, as they share an encoding and are only valid in some places.
+pub fn reg_for_name(name: &str) -> Option<(arm64::Reg, arm64::SizeFlag)> {
+    let (size, number) = match (name.strip_prefix('x'), name.strip_prefix('w')) {
+        (Some(number), _) => (arm64::SizeFlag::Size64, number),
+        (_, Some(number)) => (arm64::SizeFlag::Size32, number),
+        _ => return None
+    };
+
+    // Reject leading zeros and signs so that each register has exactly one name
+    if number.is_empty() || (number.len() > 1 && number.starts_with('0')) || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match number.parse::<u32>() {
+        Ok(n) if n <= 30 => Some((arm64::Reg(n), size)),
+        _ => None
+    }
+}
+
+fn reg(name: &str) -> Result<(arm64::Reg, arm64::SizeFlag), AsmError> {
+    match reg_for_name(name) {
+        Some(reg) => Ok(reg),
+        None => Err(AsmError::UnknownRegister(name.to_string()))
+    }
+}
+
+/// Finds the registers of a data processing instruction, which must all be the same size
+fn regs(names: &[&String], ins: &ir::AsmIns) -> Result<(Vec<arm64::Reg>, arm64::SizeFlag), AsmError> {
+    let mut regs = Vec::new();
+    let mut size = None;
+
+    for name in names {
+        let (reg, reg_size) = reg(name)?;
+        match size {
+            Some(size) if size as u32 != reg_size as u32 => return Err(AsmError::InvalidOperands(ins.to_string())),
+            _ => size = Some(reg_size)
+        }
+        regs.push(reg);
+    }
+
+    Ok((regs, size.unwrap()))
+}
+
+/// The base of an address may also be the stack pointer
+fn base_reg(name: &str) -> Result<arm64::Reg, AsmError> {
+    match name {
+        "sp" => Ok(arm64::Reg::sp()),
+        name => match reg(name)? {
+            (reg, arm64::SizeFlag::Size64) => Ok(reg),
+            _ => Err(AsmError::UnknownRegister(name.to_string()))
+        }
+    }
+}
+
+/// Assembles a single instruction of a block into the equivalent encoder instruction
+pub fn assemble(ins: &ir::AsmIns) -> Result<arm64::Ins, AsmError> {
+    use ir::AsmOperand::*;
+
+    let invalid = || AsmError::InvalidOperands(ins.to_string());
+
+    Ok(match (ins.mnemonic(), ins.operands().as_slice()) {
+        ("mov", [Reg(dest), Reg(src)]) => {
+            let (regs, size) = regs(&[dest, src], ins)?;
+            arm64::Ins::Mov { size, dest: regs[0], src: regs[1] }
+        },
+        ("mov" | "movz", [Reg(dest), Imm(val)]) => {
+            if *val < 0 || *val > u16::MAX as i64 { return Err(invalid()); }
+            let (dest, size) = reg(dest)?;
+            arm64::Ins::MovZ { size, dest, val: *val as u32, shift: 0 }
+        },
+
+        ("add" | "sub", [Reg(dest), Reg(src), Imm(val)]) => {
+            if *val < 0 || *val > 4095 { return Err(invalid()); }
+            let (regs, size) = regs(&[dest, src], ins)?;
+            match ins.mnemonic() {
+                "add" => arm64::Ins::AddImm { size, dest: regs[0], src: regs[1], val: *val as u32, shift: arm64::ImmShift::Shift0 },
+                _ => arm64::Ins::SubImm { size, dest: regs[0], src: regs[1], val: *val as u32, shift: arm64::ImmShift::Shift0 }
+            }
+        },
+        ("add" | "sub" | "and" | "orr", [Reg(dest), Reg(a), Reg(b)]) => {
+            let (regs, size) = regs(&[dest, a, b], ins)?;
+            let (dest, shifted_src, src) = (regs[0], regs[1], regs[2]);
+            let shift_mode = arm64::ShiftMode::LogicalLeft;
+            match ins.mnemonic() {
+                "add" => arm64::Ins::AddShifted { size, shift_mode, dest, src, shifted_src, shift: 0 },
+                "sub" => arm64::Ins::SubShifted { size, shift_mode, dest, src, shifted_src, shift: 0 },
+                "and" => arm64::Ins::AndShifted { size, shift_mode, dest, src, shifted_src, shift: 0 },
+                _ => arm64::Ins::OrrShifted { size, shift_mode, dest, src, shifted_src, shift: 0 }
+            }
+        },
+        ("cmp", [Reg(a), Reg(b)]) => {
+            let (regs, size) = regs(&[a, b], ins)?;
+            arm64::Ins::SubsShifted { size, shift_mode: arm64::ShiftMode::LogicalLeft, dest: arm64::Reg::zero(), src: regs[1], shifted_src: regs[0], shift: 0 }
+        },
+        ("mul", [Reg(dest), Reg(a), Reg(b)]) => {
+            let (regs, size) = regs(&[dest, a, b], ins)?;
+            arm64::Ins::MAdd { size, dest: regs[0], mul1: regs[1], mul2: regs[2], addend: arm64::Reg::zero() }
+        },
+        ("sdiv" | "udiv", [Reg(dest), Reg(a), Reg(b)]) => {
+            let (regs, size) = regs(&[dest, a, b], ins)?;
+            match ins.mnemonic() {
+                "sdiv" => arm64::Ins::SDiv { size, dest: regs[0], divided: regs[1], divisor: regs[2] },
+                _ => arm64::Ins::UDiv { size, dest: regs[0], divided: regs[1], divisor: regs[2] }
+            }
+        },
+
+        // Only the unscaled forms exist, which take a 9 bit signed offset
+        ("ldur" | "stur", [Reg(r), Mem(base, None, offset)]) => {
+            if *offset < -256 || *offset > 255 { return Err(invalid()); }
+            let (r, size) = reg(r)?;
+            let base = base_reg(base)?;
+            match ins.mnemonic() {
+                "ldur" => arm64::Ins::Ldur { size, dest: r, base, offset: *offset as i32 },
+                _ => arm64::Ins::Stur { size, src: r, base, offset: *offset as i32 }
+            }
+        },
+
+        ("svc", [Imm(imm)]) => {
+            if *imm < 0 || *imm > u16::MAX as i64 { return Err(invalid()); }
+            arm64::Ins::Svc(*imm as u16)
+        },
+
+        ("mov" | "movz" | "add" | "sub" | "and" | "orr" | "cmp" | "mul" | "sdiv" | "udiv" | "ldur" | "stur" | "svc", _) => return Err(invalid()),
+        (mnemonic, _) => return Err(AsmError::UnknownInstruction(mnemonic.to_string()))
+    })
+}
+
+/// Every register the block reads or writes, including those it is bound to
+pub fn registers_of_block(block: &ir::AsmBlock) -> Result<Vec<arm64::Reg>, AsmError> {
+    let mut regs = Vec::new();
+
+    for binding in block.inputs().iter().chain(block.outputs().iter()) {
+        regs.push(reg(binding.reg())?.0);
+    }
+
+    for ins in block.code() {
+        for operand in ins.operands() {
+            match operand {
+                ir::AsmOperand::Reg(r) => regs.push(reg(r)?.0),
+                ir::AsmOperand::Imm(_) => {},
+                ir::AsmOperand::Mem(base, _, _) => regs.push(base_reg(base)?)
+            }
+        }
+    }
+
+    Ok(regs)
+}
+
+/// Checks that a block can be translated, which `TranslationContext::translate_function` does before translating any code
+pub fn check_asm_block(block: &ir::AsmBlock) -> Result<(), AsmError> {
+    if block.arch() != ir::AsmArch::Arm64 { return Err(AsmError::WrongArch(block.arch())); }
+
+    for binding in block.inputs().iter().chain(block.outputs().iter()) {
+        let (r, _) = reg(binding.reg())?;
+        if r.u32() == arm64::Reg::fp().u32() || r.u32() == arm64::Reg::lr().u32() {
+            return Err(AsmError::ReservedRegister(binding.reg().to_string()));
+        }
+    }
+
+    for ins in block.code() {
+        assemble(ins)?;
+    }
+
+    Ok(())
+}
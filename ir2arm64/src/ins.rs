@@ -70,6 +70,93 @@ impl TranslationContext {
         });
     }
 
+    /// Store the value in the given register to the address in the other, using the width of the value type
+    fn store(vt: &ir::ValueType, addr: arm64::Reg, val: arm64::Reg) -> arm64::Ins {
+        Self::store_offset(vt, addr, 0, val)
+    }
+
+    /// Store the value in the given register to the address in the other plus an offset, using the width of the value type
+    fn store_offset(vt: &ir::ValueType, addr: arm64::Reg, offset: i32, val: arm64::Reg) -> arm64::Ins {
+        match crate::util::size_for_value_type(vt) {
+            8 =>
+                arm64::Ins::Stur {
+                    size: arm64::SizeFlag::Size64,
+                    base: addr,
+                    offset,
+                    src: val
+                },
+            4 =>
+                arm64::Ins::Stur {
+                    size: arm64::SizeFlag::Size32,
+                    base: addr,
+                    offset,
+                    src: val
+                },
+            2 =>
+                arm64::Ins::Sturh {
+                    base: addr,
+                    offset,
+                    src: val
+                },
+            1 =>
+                arm64::Ins::Sturb {
+                    base: addr,
+                    offset,
+                    src: val
+                },
+            _ => unreachable!()
+        }
+    }
+
+    /// Load the value at the address in the given register plus an offset into the other, using the width of the value type
+    fn load_offset(vt: &ir::ValueType, addr: arm64::Reg, offset: i32, dest: arm64::Reg) -> arm64::Ins {
+        match crate::util::size_for_value_type(vt) {
+            8 =>
+                arm64::Ins::Ldur {
+                    size: arm64::SizeFlag::Size64,
+                    base: addr,
+                    offset,
+                    dest
+                },
+            4 =>
+                arm64::Ins::Ldur {
+                    size: arm64::SizeFlag::Size32,
+                    base: addr,
+                    offset,
+                    dest
+                },
+            2 =>
+                arm64::Ins::Ldurh {
+                    base: addr,
+                    offset,
+                    dest
+                },
+            1 =>
+                arm64::Ins::Ldurb {
+                    base: addr,
+                    offset,
+                    dest
+                },
+            _ => unreachable!()
+        }
+    }
+
+    /// Load a value into the given register 16 bits at a time, skipping the parts above the lowest which are zero
+    fn load_immediate(size: arm64::SizeFlag, dest: arm64::Reg, val: u64, ins: &mut Vec<arm64::Ins>) {
+        let bits = match size {
+            arm64::SizeFlag::Size64 => 64,
+            arm64::SizeFlag::Size32 => 32
+        };
+
+        ins.push(arm64::Ins::MovZ { size, dest, val: (val & 0xffff) as u32, shift: 0 });
+        for shift in (16..bits).step_by(16) {
+            let part = (val >> shift) & 0xffff;
+            if part != 0 {
+                ins.push(arm64::Ins::MovK { size, dest, val: part as u32, shift });
+            }
+        }
+    }
+
     fn addr_in_path(&self, path: &ir::ValuePath, ftc: &mut FunctionTranslationContext, insns: &mut Vec<arm64::Ins>) {
         match path.origin() {
             ir::ValuePathOrigin::Local(local, _local_type) => {
@@ -227,36 +314,21 @@ impl TranslationContext {
                 let val = ftc.stack().pop();
                 let addr = ftc.stack().pop();
 
-                // Deref
-                ins.push(match crate::util::size_for_value_type(vt) {
-                    8 =>
-                        arm64::Ins::Stur {
-                            size: arm64::SizeFlag::Size64,
-                            base: addr,
-                            offset: 0,
-                            src: val
-                        },
-                    4 =>
-                        arm64::Ins::Stur {
-                            size: arm64::SizeFlag::Size32,
-                            base: addr,
-                            offset: 0,
-                            src: val
-                        },
-                    2 =>
-                        arm64::Ins::Sturh {
-                            base: addr,
-                            offset: 0,
-                            src: val
-                        },
-                    1 =>
-                        arm64::Ins::Sturb {
-                            base: addr,
-                            offset: 0,
-                            src: val
-                        },
-                    _ => unreachable!()
+                ins.push(Self::store(vt, addr, val));
+            },
+            ir::Ins::PopLocal(idx, vt) => {
+                let addr = ftc.stack().push();
+                ins.push(arm64::Ins::SubImm {
+                    size: arm64::SizeFlag::Size64,
+                    dest: addr,
+                    shift: arm64::ImmShift::Shift0,
+                    src: arm64::Reg::fp(),
+                    val: ftc.local_addr(*idx)
                 });
+
+                let addr = ftc.stack().pop();
+                let val = ftc.stack().pop();
+                ins.push(Self::store(vt, addr, val));
             },
             ir::Ins::Index(_) => {
                 // Do nothing, will be handled in addr_in_path
@@ -508,7 +580,7 @@ impl TranslationContext {
 
                 ins.push(arm64::Ins::LocalSymbol(end));
             },
-            ir::Ins::IfElse(true_then, false_then, condition) => {
+            ir::Ins::IfElse(_, true_then, false_then, condition) => {
                 for inner_ins in condition {
                     self.translate_instruction_to(inner_ins, ftc, ins);
                 }
@@ -530,6 +602,7 @@ impl TranslationContext {
                 ins.push(arm64::Ins::ConditionalBranchLocalSymbol(false_start, arm64::Condition::Eq));
 
                 // otherwise (if) true, continue...
+                let depth = ftc.stack_ref().size();
                 ftc.local_symbols().push(LocalSymbol::If);
                 for inner_ins in true_then {
                     self.translate_instruction_to(inner_ins, ftc, ins);
@@ -538,6 +611,10 @@ impl TranslationContext {
                 // then jump to the end
                 ins.push(arm64::Ins::BranchLocalSymbol(end));
 
+                // If the branch left a value, the else branch leaves its value in the same register
+                let values = ftc.stack_ref().size().saturating_sub(depth);
+                ftc.stack().pop_many(values);
+
                 ins.push(arm64::Ins::LocalSymbol(false_start));
                 ftc.local_symbols().push(LocalSymbol::If);
                 for inner_ins in false_then {
@@ -548,8 +625,139 @@ impl TranslationContext {
 
                 ins.push(arm64::Ins::LocalSymbol(end));
             },
-            ir::Ins::Break(_) => todo!(),
-            ir::Ins::Continue(_) => todo!(),
+            ir::Ins::Switch(vt, cases, default, value) => {
+                for inner_ins in value {
+                    self.translate_instruction_to(inner_ins, ftc, ins);
+                }
+
+                let size = match size_for_value_type(vt) {
+                    8 => arm64::SizeFlag::Size64,
+                    _ => arm64::SizeFlag::Size32
+                };
+
+                let case_starts = cases.iter().map(|_| ftc.new_local_symbol()).collect::<Vec<_>>();
+                let default_start = ftc.new_local_symbol();
+                let end = ftc.new_local_symbol();
+
+                // The value is left on the stack until it has been dispatched on, so the registers used alongside it never overlap it
+                let value_reg = ftc.stack().peek();
+                match ir::switch_table_range(vt, cases) {
+                    // The subtraction of min must fit into an immediate
+                    Some((min, len)) if min < 4096 => {
+                        let index = ftc.stack().push();
+                        let table = ftc.stack().push();
+
+                        // value = value - min, which also clears the top half of the register for 32 bit values so it can be used as an index
+                        ins.push(arm64::Ins::SubImm {
+                            size,
+                            src: value_reg,
+                            dest: value_reg,
+                            val: min as u32,
+                            shift: arm64::ImmShift::Shift0
+                        });
+
+                        ins.push(arm64::Ins::MovZ {
+                            size: arm64::SizeFlag::Size64,
+                            dest: index,
+                            val: len as u32,
+                            shift: 0
+                        });
+
+                        ins.push(arm64::Ins::SubsShifted {
+                            size,
+                            shift_mode: arm64::ShiftMode::LogicalLeft,
+                            dest: arm64::Reg::zero(),
+                            src: index,
+                            shifted_src: value_reg,
+                            shift: 0
+                        });
+                        ins.push(arm64::Ins::ConditionalBranchLocalSymbol(default_start, arm64::Condition::Cs));
+
+                        // The table holds the offset of each case from the start of the table, and is placed in the code straight after the
+                        // branch into it rather than in read only data, so it is never run and needs no relocations (see ir2x86.md)
+                        let table_start = ftc.new_local_symbol();
+                        ins.push(arm64::Ins::AdrLocalSymbol(table_start, table));
+                        ins.push(arm64::Ins::LdrswScaled { dest: index, base: table, index: value_reg });
+                        ins.push(arm64::Ins::AddShifted {
+                            size: arm64::SizeFlag::Size64,
+                            shift_mode: arm64::ShiftMode::LogicalLeft,
+                            shift: 0,
+                            dest: table,
+                            src: index,
+                            shifted_src: table
+                        });
+                        ins.push(arm64::Ins::Br(table));
+
+                        ins.push(arm64::Ins::LocalSymbol(table_start));
+                        for i in 0..len {
+                            let case_value = min.wrapping_add(i as u64);
+                            let target = match cases.iter().position(|(values, _)| values.contains(&case_value)) {
+                                Some(case) => case_starts[case],
+                                None => default_start
+                            };
+                            ins.push(arm64::Ins::JumpTableEntry(table_start, target));
+                        }
+
+                        ftc.stack().pop_many(3);
+                    },
+                    _ => {
+                        let scratch = ftc.stack().push();
+
+                        // Values narrower than 32 bits are held zero extended, so negative cases are compared at the width of the value
+                        let mask = match size_for_value_type(vt) {
+                            8 => u64::MAX,
+                            n => (1 << (n * 8)) - 1
+                        };
+
+                        for (case_start, (values, _)) in case_starts.iter().zip(cases) {
+                            for case_value in values {
+                                Self::load_immediate(size, scratch, *case_value & mask, ins);
+
+                                ins.push(arm64::Ins::SubsShifted {
+                                    size,
+                                    shift_mode: arm64::ShiftMode::LogicalLeft,
+                                    dest: arm64::Reg::zero(),
+                                    src: scratch,
+                                    shifted_src: value_reg,
+                                    shift: 0
+                                });
+                                ins.push(arm64::Ins::ConditionalBranchLocalSymbol(*case_start, arm64::Condition::Eq));
+                            }
+                        }
+                        ins.push(arm64::Ins::BranchLocalSymbol(default_start));
+
+                        ftc.stack().pop_many(2);
+                    }
+                }
+
+                for (case_start, (_, code)) in case_starts.iter().zip(cases) {
+                    ins.push(arm64::Ins::LocalSymbol(*case_start));
+                    ftc.local_symbols().push(LocalSymbol::If);
+                    for inner_ins in code {
+                        self.translate_instruction_to(inner_ins, ftc, ins);
+                    }
+                    ftc.local_symbols().pop();
+                    ins.push(arm64::Ins::BranchLocalSymbol(end));
+                }
+
+                ins.push(arm64::Ins::LocalSymbol(default_start));
+                ftc.local_symbols().push(LocalSymbol::If);
+                for inner_ins in default {
+                    self.translate_instruction_to(inner_ins, ftc, ins);
+                }
+                ftc.local_symbols().pop();
+
+                ins.push(arm64::Ins::LocalSymbol(end));
+            },
+            // The stack is empty within the loop when breaking or continuing, so it is as it was at the start of the loop
+            ir::Ins::Break(depth) => {
+                let (_, end) = ftc.local_symbols().loop_at(*depth).expect("Break does not refer to a loop");
+                ins.push(arm64::Ins::BranchLocalSymbol(end));
+            },
+            ir::Ins::Continue(depth) => {
+                let (start, _) = ftc.local_symbols().loop_at(*depth).expect("Continue does not refer to a loop");
+                ins.push(arm64::Ins::BranchLocalSymbol(start));
+            },
             ir::Ins::PushLiteral(_vt, val) => {
                 ins.push(arm64::Ins::MovZ {
                     size: arm64::SizeFlag::Size64,
@@ -558,6 +766,26 @@ impl TranslationContext {
                     val: *val as u32
                 });
             },
+            ir::Ins::Asm(block) => {
+                // The stack is empty before a block, so it is free to use any register
+                for reg in crate::asm::registers_of_block(block).unwrap() {
+                    ftc.stack().clobber(reg);
+                }
+
+                for input in block.inputs() {
+                    let (reg, _) = crate::asm::reg_for_name(input.reg()).unwrap();
+                    ins.push(Self::load_offset(input.value_type(), arm64::Reg::fp(), -(ftc.local_addr(input.local()) as i32), reg));
+                }
+
+                for asm_ins in block.code() {
+                    ins.push(crate::asm::assemble(asm_ins).unwrap());
+                }
+
+                for output in block.outputs() {
+                    let (reg, _) = crate::asm::reg_for_name(output.reg()).unwrap();
+                    ins.push(Self::store_offset(output.value_type(), arm64::Reg::fp(), -(ftc.local_addr(output.local()) as i32), reg));
+                }
+            },
             ir::Ins::Drop => {
                 ftc.stack().pop();
             },
@@ -2,5 +2,7 @@ mod registerify;
 mod ins;
 mod unit;
 mod util;
+mod asm;
 
-pub use unit::*;
\ No newline at end of file
+pub use unit::*;
+pub use asm::*;
\ No newline at end of file
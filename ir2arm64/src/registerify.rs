@@ -1,18 +1,40 @@
+/// The first and last of the callee saved registers, x19 to x28
+pub(crate) const CALLEE_SAVED: (u32, u32) = (19, 28);
+
 pub(crate) struct StackToReg {
     idx: usize,
+    clobbered: u16 // bitmap, bits 0-9 are the registers x19 to x28
 }
 
 impl StackToReg {
     pub fn new() -> StackToReg {
         StackToReg {
             idx: 0,
+            clobbered: 0
+        }
+    }
+
+    pub fn clobbered(&self) -> u16 {
+        self.clobbered
+    }
+
+    fn does_use_reg(&mut self, reg: arm64::Reg) {
+        if reg.u32() >= CALLEE_SAVED.0 && reg.u32() <= CALLEE_SAVED.1 {
+            self.clobbered |= 1 << (reg.u32() - CALLEE_SAVED.0);
         }
     }
 
+    /// Marks a register as written by code other than the stack, such as an inline assembly block, so that it is saved
+    /// by the function if it is callee saved
+    pub fn clobber(&mut self, reg: arm64::Reg) {
+        self.does_use_reg(reg);
+    }
+
     pub fn push(&mut self) -> arm64::Reg {
         self.idx += 1;
 
         let reg = arm64::Reg(self.idx as u32 - 1);
+        self.does_use_reg(reg);
         
         reg
     }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use crate::registerify::{StackToReg};
+use crate::{AsmError, check_asm_block};
+use crate::registerify::{StackToReg, CALLEE_SAVED};
 
 pub(crate) enum LocalSymbol {
     If,
@@ -26,6 +27,14 @@ impl LocalSymbolStack {
     pub(crate) fn pop(&mut self) {
         self.symbols.pop();
     }
+
+    /// The start and end of the loop at the given depth above the current block, if there is a loop there
+    pub(crate) fn loop_at(&self, depth: ir::BlockMoveDepth) -> Option<(arm64::LocalSymbolID, arm64::LocalSymbolID)> {
+        match self.symbols.len().checked_sub(depth + 1).and_then(|idx| self.symbols.get(idx)) {
+            Some(LocalSymbol::Loop(start, end)) => Some((*start, *end)),
+            _ => None
+        }
+    }
 }
 
 pub struct FunctionTranslationContext<'a> {
@@ -72,8 +81,11 @@ impl<'a> FunctionTranslationContext<'a> {
 
         assert!(self.function.local_count() > idx.idx());
 
+        // Locals grow down from the frame pointer, so each one ends at a multiple of its alignment below it
         for i in &self.function.locals()[0..idx.idx() + 1] {
+            let align = crate::util::align_for_storable_type(i.local_type()) as u32;
             addr += crate::util::size_for_storable_type(i.local_type()) as u32;
+            addr = (addr + align - 1) & !(align - 1);
         }
 
         addr
@@ -143,9 +155,15 @@ impl TranslationContext {
         TranslationContext { }
     }
 
-    pub fn translate_function(&self, func: &ir::Function, unit: &ir::TranslationUnit) -> Vec<arm64::Ins> {
+    /// Translates the code of a function, failing if any inline assembly in it cannot be assembled for arm64
+    pub fn translate_function(&self, func: &ir::Function, unit: &ir::TranslationUnit) -> Result<Vec<arm64::Ins>, AsmError> {
         if func.is_extern() { panic!("Cannot translate extern function"); }
 
+        // IR which did not come through the NL frontend may hold any assembly, so it is checked before anything is translated
+        for block in ir::asm_blocks(func.code()) {
+            check_asm_block(block)?;
+        }
+
         let mut arm64_ins = Vec::new();
 
         let mut ftc = FunctionTranslationContext::new(func, unit);
@@ -248,12 +266,50 @@ impl TranslationContext {
             shift: arm64::ImmShift::Shift0
         });
 
+        // Callee saved registers written by the function are saved in pairs above the frame, so sp stays 16 byte aligned
+        let saved = (CALLEE_SAVED.0..=CALLEE_SAVED.1)
+            .filter(|reg| ftc.stack_ref().clobbered() & (1 << (reg - CALLEE_SAVED.0)) != 0)
+            .map(arm64::Reg)
+            .collect::<Vec<_>>();
+
+        for (i, pair) in saved.chunks(2).enumerate() {
+            let second = match pair.get(1) {
+                Some(reg) => *reg,
+                None => arm64::Reg::zero()
+            };
+
+            arm64_ins.insert(i, arm64::Ins::Stp {
+                size: arm64::SizeFlag::Size64,
+                src1: pair[0],
+                src2: second,
+                base: arm64::Reg::sp(),
+                offset: -16,
+                mode: arm64::IndexMode::PreIndex
+            });
+        }
+
+        for pair in saved.chunks(2).rev() {
+            let second = match pair.get(1) {
+                Some(reg) => *reg,
+                None => arm64::Reg::zero()
+            };
+
+            arm64_ins.push(arm64::Ins::Ldp {
+                size: arm64::SizeFlag::Size64,
+                dest1: pair[0],
+                dest2: second,
+                base: arm64::Reg::sp(),
+                offset: 16,
+                mode: arm64::IndexMode::PostIndex
+            });
+        }
+
         arm64_ins.push(arm64::Ins::Ret(arm64::Reg::lr()));
 
-        arm64_ins
+        Ok(arm64_ins)
     }
 
-    fn translate_storable(&self, storable: &ir::StorableValue, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<arm64::Relocation>, offset: usize, section_offset: usize, addend: i64) -> Vec<u8> {
+    fn translate_storable(&self, storable: &ir::StorableValue, storable_type: &ir::StorableType, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<arm64::Relocation>, offset: usize, section_offset: usize, addend: i64) -> Vec<u8> {
         match storable {
             ir::StorableValue::Value(v) => match v {
                 ir::Value::U8(i) => i.to_le_bytes().to_vec(),
@@ -276,12 +332,19 @@ impl TranslationContext {
             ir::StorableValue::Compound(c) => {
                 match c {
                     ir::CompoundValue::Struct(s) => {
+                        let ct = match storable_type {
+                            ir::StorableType::Compound(ct) => ct,
+                            _ => panic!("Struct value for non-struct type")
+                        };
+                        let ir::CompoundContent::Struct(content) = ct.content();
                         let mut data = Vec::new();
 
-                        for p in s.props() {
-                            data.extend(self.translate_storable(p.value(), unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
+                        for ((p, prop), prop_offset) in s.props().iter().zip(content.props()).zip(ct.prop_offsets(8)) {
+                            data.resize(prop_offset, 0);
+                            data.extend(self.translate_storable(p.value(), prop.prop_type(), unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
                         }
 
+                        data.resize(crate::util::size_for_storable_type(storable_type), 0);
                         data
                     }
                 }
@@ -305,8 +368,13 @@ impl TranslationContext {
             ir::StorableValue::SliceData(values) => {
                 let mut data = Vec::new();
 
+                let element_type = match storable_type {
+                    ir::StorableType::SliceData(st) => st.as_ref(),
+                    _ => panic!("Slice data value for non-slice data type")
+                };
+
                 for value in values {
-                    data.extend(self.translate_storable(value, unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
+                    data.extend(self.translate_storable(value, element_type, unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
                 }
 
                 data
@@ -316,7 +384,7 @@ impl TranslationContext {
 
     pub fn translate_global(&self, global: &ir::Global, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<arm64::Relocation>, section_offset: usize, addend: i64) -> Vec<u8> {
         if let Some(default) = global.default() {
-            self.translate_storable(default, unit, gid_allocator, relocs, 0, section_offset, addend)
+            self.translate_storable(default, global.global_type(), unit, gid_allocator, relocs, 0, section_offset, addend)
         } else {
             vec![0; crate::util::size_for_storable_type(global.global_type())]
         }
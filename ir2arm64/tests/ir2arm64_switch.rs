@@ -0,0 +1,78 @@
+/// The instructions of a function which switches on its only param, with a case for each list of values which does nothing
+fn translate_switch(vt: ir::ValueType, cases: Vec<Vec<u64>>) -> Vec<arm64::Ins> {
+    let mut unit = ir::TranslationUnit::new();
+    let ctx = ir2arm64::TranslationContext::new();
+
+    let idx = unit.add_function({
+        let mut func = ir::Function::new("f", ir::Signature::new(vec![ vt.clone() ], vec![ ]));
+        let param = func.push_local(ir::Local::new(ir::StorableType::Value(vt.clone())));
+        func.push(ir::Ins::Switch(vt.clone(), cases.into_iter().map(|values| (values, vec![ ])).collect(), vec![ ], vec![
+            ir::Ins::PushPath(ir::ValuePath::new_origin_only(
+                ir::ValuePathOrigin::Local(param, ir::StorableType::Value(vt.clone()))
+            ), vt.clone()),
+            ir::Ins::Push(vt)
+        ]));
+        func.push(ir::Ins::Ret);
+
+        func
+    });
+
+    ctx.translate_function(unit.get_function(idx).unwrap(), &unit).ok().unwrap()
+}
+
+fn table_len(ins: &[arm64::Ins]) -> usize {
+    ins.iter().filter(|i| matches!(i, arm64::Ins::JumpTableEntry(_, _))).count()
+}
+
+/// The values compared against in turn, as built up 16 bits at a time in the register they are compared with
+fn compared_values(ins: &[arm64::Ins]) -> Vec<u64> {
+    let mut values = Vec::new();
+    let mut value = 0;
+    for i in ins {
+        match i {
+            arm64::Ins::MovZ { val, shift, .. } => value = (*val as u64) << shift,
+            arm64::Ins::MovK { val, shift, .. } => value = (value & !(0xffff << shift)) | ((*val as u64) << shift),
+            arm64::Ins::SubsShifted { dest, .. } if dest.u32() == arm64::Reg::zero().u32() => values.push(value),
+            _ => {}
+        }
+    }
+    values
+}
+
+#[test]
+fn switch_u8() {
+    // Close values are dispatched through a table, including up to the largest u8
+    let ins = translate_switch(ir::ValueType::U8, vec![ vec![ 250, 251 ], vec![ 253, 255 ] ]);
+    assert_eq!(table_len(&ins), 6);
+    assert!(ins.iter().any(|i| matches!(i, arm64::Ins::SubImm { val: 250, .. })));
+    assert_eq!(compared_values(&ins), vec![ 6 ]);
+
+    // Sparse values are compared against in turn
+    let ins = translate_switch(ir::ValueType::U8, vec![ vec![ 0 ], vec![ 100 ], vec![ 255 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ 0, 100, 255 ]);
+}
+
+#[test]
+fn switch_i8() {
+    // Negative values are compared at the width of the value, which is held zero extended
+    let ins = translate_switch(ir::ValueType::I8, vec![ vec![ -1i64 as u64 ], vec![ -128i64 as u64, 5 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ 0xff, 0x80, 5 ]);
+}
+
+#[test]
+fn switch_i64() {
+    // A table starting at 0 with sparse values past it
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ 0 ], vec![ 2, 5 ], vec![ 7 ] ]);
+    assert_eq!(table_len(&ins), 8);
+
+    // Negative values, and values wider than an immediate, are compared against in turn
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ -2i64 as u64 ], vec![ 0, 1 ], vec![ 3 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ -2i64 as u64, 0, 1, 3 ]);
+
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ -5i64 as u64 ], vec![ 100 ], vec![ 10000, 5000000000 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ -5i64 as u64, 100, 10000, 5000000000 ]);
+}
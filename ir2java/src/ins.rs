@@ -4,12 +4,14 @@ use crate::TranslationContext;
 enum Path {
     Local(usize, java::Descriptor),
     Global(usize, java::Descriptor),
-    /// Assumes an index followed by a slice
-    Slice(java::Descriptor),
+    /// Assumes an index followed by a slice, the flag marks an unsigned byte that must be zero extended on load
+    Slice(java::Descriptor, bool),
     /// Assumes a reference
     Prop(usize, java::Descriptor),
     /// Assumes a slice
     Length,
+    /// An int local which was already updated in place by iinc, so there is nothing left to store
+    Incremented,
     // Reference already on stack
     Ref
 }
@@ -25,10 +27,15 @@ impl Path {
                 insns.push(java::Ins::GetStatic { index: *idx });
                 stack_map.stack_push(java::VerificationTypeInfo::from_descriptor(desc, class));
             },
-            Path::Slice(desc) => {
+            Path::Slice(desc, unsigned_byte) => {
                 stack_map.stack_pop();
                 stack_map.stack_pop();
                 insns.push(java::opt::ins::aload(desc));
+                if *unsigned_byte {
+                    // baload sign extends, but a u8 must read back as 0..=255
+                    insns.push(java::Ins::SIPush { value: 0xff });
+                    insns.push(java::Ins::IAnd);
+                }
                 stack_map.stack_push(java::VerificationTypeInfo::from_descriptor(desc, class));
             },
             Path::Prop(idx, desc) => {
@@ -41,7 +48,8 @@ impl Path {
                 insns.push(java::Ins::ArrayLength);
                 stack_map.stack_push(java::VerificationTypeInfo::Integer);
             },
-            Path::Ref => {}
+            Path::Ref => {},
+            Path::Incremented => panic!("Attempt to read incremented local")
         }
     }
 
@@ -55,7 +63,7 @@ impl Path {
                 stack_map.stack_pop();
                 insns.push(java::Ins::PutStatic { index: *idx });
             },
-            Path::Slice(desc) => {
+            Path::Slice(desc, _) => {
                 stack_map.stack_pop();
                 stack_map.stack_pop();
                 stack_map.stack_pop();
@@ -69,7 +77,8 @@ impl Path {
             Path::Length => {
                 panic!("Attempt to write slice length")
             },
-            Path::Ref => panic!("Attempt to write to reference")
+            Path::Ref => panic!("Attempt to write to reference"),
+            Path::Incremented => {}
         }
     }
 }
@@ -92,6 +101,57 @@ impl PathStack {
     fn pop(&mut self) -> Path {
         self.paths.pop().expect("Path stack underflow")
     }
+
+    fn peek(&self) -> Option<&Path> {
+        self.paths.last()
+    }
+}
+
+/// The blocks enclosing the code being translated, innermost last. Each loop holds the location of every break and continue
+/// out of it, which are gotos to be pointed at its end or increment once they are known.
+pub(crate) struct BlockStack {
+    blocks: Vec<Option<Vec<(usize, bool)>>>,
+    /// The location of each goto out of a loop, and the location it goes to
+    moves: Vec<(usize, usize)>
+}
+
+impl BlockStack {
+    pub(crate) fn new() -> BlockStack {
+        BlockStack {
+            blocks: Vec::new(),
+            moves: Vec::new()
+        }
+    }
+
+    fn push_block(&mut self) {
+        self.blocks.push(None);
+    }
+
+    fn push_loop(&mut self) {
+        self.blocks.push(Some(Vec::new()));
+    }
+
+    /// Pops the innermost block, giving the breaks and continues out of it if it is a loop
+    fn pop(&mut self) -> Vec<(usize, bool)> {
+        self.blocks.pop().expect("Block stack underflow").unwrap_or_default()
+    }
+
+    /// Adds a goto at the given location out of the loop at the given depth, a break if the flag is set or a continue if not
+    fn move_out(&mut self, depth: ir::BlockMoveDepth, loc: usize, is_break: bool) {
+        let idx = self.blocks.len() - depth - 1;
+        self.blocks[idx].as_mut().expect("Break or continue does not refer to a loop").push((loc, is_break));
+    }
+
+    /// Points each goto out of a loop in the method's code at where it goes
+    pub(crate) fn apply(&self, insns: &mut [java::Ins]) {
+        let mut loc = 0;
+        for ins in insns.iter_mut() {
+            if let Some((_, to)) = self.moves.iter().find(|(from, _)| *from == loc) {
+                *ins = java::Ins::Goto { branch: (*to as i32 - loc as i32) as i16 };
+            }
+            loc += ins.size(loc);
+        }
+    }
 }
 
 pub(crate) struct InstructionTarget {
@@ -113,6 +173,18 @@ impl InstructionTarget {
         self.insns.len() - 1
     }
 
+    /// Removes the last instruction if it loads the given int local
+    fn take_iload(&mut self, local: usize) -> bool {
+        match self.insns.last() {
+            Some(ins @ java::Ins::ILoad { local: loaded }) if *loaded as usize == local => {
+                self.size -= ins.size(0);
+                self.insns.pop();
+                true
+            },
+            _ => false
+        }
+    }
+
     fn at_mut(&mut self, idx: usize) -> &mut java::Ins {
         &mut self.insns[idx]
     }
@@ -152,6 +224,7 @@ impl<'a> StackMapBuilder<'a> {
         }
     }
 
+    /// Takes the index of the ir local, not the java one, as frames list each local once even if it takes two slots
     pub(crate) fn accessed_local(&mut self, local: usize) {
         if local >= self.first_unused_local {
             self.first_unused_local = local + 1;
@@ -166,6 +239,10 @@ impl<'a> StackMapBuilder<'a> {
         self.stack.pop().expect("Could not pop");
     }
 
+    fn stack_depth(&self) -> usize {
+        self.stack.len()
+    }
+
     fn generate_full_frame(&self, delta: u16, class: &mut java::ClassFile) -> java::StackMapFrame {
         let mut locals = Vec::new();
         for i in 0..self.first_unused_local {
@@ -227,6 +304,14 @@ impl<'a> StackMapBuilder<'a> {
         self.map.add_entry(frame);
     }
 
+    /// Ends code whose last instruction is not a return with a throw. The end cannot be reached, but a branch may still be
+    /// placed there, and code cannot end with one, nor can it follow a return without a frame.
+    pub(crate) fn end_unreachable(&mut self, insns: &mut InstructionTarget, class: &mut java::ClassFile) {
+        if let Some(frame) = self.prepare_frame(insns.tell(), class) { self.push_frame(frame); }
+        insns.push(java::Ins::AConstNull);
+        insns.push(java::Ins::AThrow);
+    }
+
     fn delta_to(&self, offset: usize) -> u16 {
         if self.offset == 0 {
             offset as u16
@@ -241,12 +326,12 @@ impl<'a> StackMapBuilder<'a> {
 }
 
 impl<'a> TranslationContext<'a> {
-    pub(crate) fn translate_ins(&self, func: &ir::Function, ins: &ir::Ins, path_stack: &mut PathStack, insns: &mut InstructionTarget, stack_map: &mut StackMapBuilder, class: &mut java::ClassFile) -> bool {
-        macro_rules! icmp {
-            ($op:ident) => {
+    pub(crate) fn translate_ins(&self, func: &ir::Function, ins: &ir::Ins, path_stack: &mut PathStack, blocks: &mut BlockStack, insns: &mut InstructionTarget, stack_map: &mut StackMapBuilder, class: &mut java::ClassFile) -> bool {
+        // Pops the given number of values, then pushes 1 if the branch is taken or 0 if not
+        macro_rules! cmp {
+            ($op:ident, $pops:expr) => {
                 {
-                    stack_map.stack_pop();
-                    stack_map.stack_pop();
+                    for _ in 0..$pops { stack_map.stack_pop(); }
 
                     insns.push(java::Ins::$op { branch: 3 + 1 + 3 });
                     insns.push(java::Ins::IConst0);
@@ -264,19 +349,30 @@ impl<'a> TranslationContext<'a> {
                 }
             };
         }
+
+        macro_rules! icmp {
+            ($op:ident) => { cmp!($op, 2) };
+        }
+
+        // Longs are first compared to an int, which the branch then compares against zero
+        macro_rules! lcmp {
+            ($op:ident) => {
+                {
+                    stack_map.stack_pop();
+                    stack_map.stack_pop();
+                    insns.push(java::Ins::LCmp);
+                    stack_map.stack_push(java::VerificationTypeInfo::Integer);
+                    cmp!($op, 1)
+                }
+            };
+        }
         
         match ins {
             ir::Ins::PushPath(value_path, _) => {
                 let mut path = match value_path.origin() {
                     ir::ValuePathOrigin::Local(idx, st) => {
-                        let mut javaidx = 0;
-                        for i in 0..idx.idx() {
-                            match func.locals()[i].local_type() {
-                                ir::StorableType::Value(ir::ValueType::U64 | ir::ValueType::I64) => javaidx += 2,
-                                _ => javaidx += 1,
-                            }
-                        }
-                        stack_map.accessed_local(javaidx);
+                        let javaidx = crate::util::java_local_index(func, *idx);
+                        stack_map.accessed_local(idx.idx());
                         Path::Local(javaidx, crate::util::storable_type_to_descriptor(st, &class))
                     },
                     ir::ValuePathOrigin::Global(idx, st) => {
@@ -298,7 +394,8 @@ impl<'a> TranslationContext<'a> {
                     match component {
                         ir::ValuePathComponent::Slice(st) => {
                             insns.push(java::Ins::Swap);
-                            path = Path::Slice(crate::util::storable_type_to_descriptor(st, &class));
+                            let unsigned_byte = matches!(st, ir::StorableType::Value(ir::ValueType::U8));
+                            path = Path::Slice(crate::util::storable_type_to_descriptor(st, &class), unsigned_byte);
                         },
                         ir::ValuePathComponent::Property(prop_idx, ctr, _) => {
                             match ctr.content() {
@@ -329,6 +426,13 @@ impl<'a> TranslationContext<'a> {
                 path_stack.pop().pop(stack_map, insns, class);
                 false
             },
+            ir::Ins::PopLocal(idx, vt) => {
+                let javaidx = crate::util::java_local_index(func, *idx);
+                stack_map.accessed_local(idx.idx());
+                stack_map.stack_pop();
+                insns.push(java::opt::ins::store(javaidx, &crate::util::value_type_to_descriptor(vt, class)));
+                false
+            },
             ir::Ins::Index(_) => {
                 // Do nothing
                 false
@@ -369,18 +473,36 @@ impl<'a> TranslationContext<'a> {
                 }
                 false
             },
-            ir::Ins::Free(_) => {
-                // Does nothing
-                false
-            },
-            ir::Ins::FreeSlice(_) => {
-                // Does nothing
+            ir::Ins::Free(_) | ir::Ins::FreeSlice(_) => {
+                // The collector frees it, so the reference is just dropped
+                insns.push(java::Ins::Pop);
+                stack_map.stack_pop();
                 false
             },
             ir::Ins::Convert(from, to) => {
+                // Bytes and shorts are held sign extended in an int, and ints are sign extended to longs, so unsigned values
+                // are zero extended by hand when they are widened
+                let from_desc = match (from, to) {
+                    (ir::ValueType::U8, ir::ValueType::U8 | ir::ValueType::I8 | ir::ValueType::Bool) => crate::util::value_type_to_descriptor(from, class),
+                    (ir::ValueType::U16, ir::ValueType::U8 | ir::ValueType::I8 | ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::Bool) => crate::util::value_type_to_descriptor(from, class),
+                    (ir::ValueType::U8 | ir::ValueType::U16, _) => {
+                        insns.push(java::opt::ins::iconst(if *from == ir::ValueType::U8 { 0xff } else { 0xffff }, class));
+                        insns.push(java::Ins::IAnd);
+                        stack_map.stack_push(java::VerificationTypeInfo::Integer);
+                        stack_map.stack_pop();
+                        java::Descriptor::Int
+                    },
+                    (ir::ValueType::U32 | ir::ValueType::UPtr, ir::ValueType::U64 | ir::ValueType::I64) => {
+                        let method_ref = class.const_method("java/lang/Integer", "toUnsignedLong", "(I)J");
+                        insns.push(java::Ins::InvokeStatic { index: method_ref });
+                        java::Descriptor::Long
+                    },
+                    _ => crate::util::value_type_to_descriptor(from, class)
+                };
+
                 stack_map.stack_pop();
                 stack_map.stack_push(java::VerificationTypeInfo::from_descriptor(&crate::util::value_type_to_descriptor(to, class), class));
-                if let Some(ins) = java::opt::ins::conv(&crate::util::value_type_to_descriptor(from, class), &crate::util::value_type_to_descriptor(to, class)) {
+                if let Some(ins) = java::opt::ins::conv(&from_desc, &crate::util::value_type_to_descriptor(to, class)) {
                     insns.push(ins);
                 }
 
@@ -405,9 +527,26 @@ impl<'a> TranslationContext<'a> {
 
                 insns.push(java::Ins::InvokeStatic { index: method_ref });
 
+                // The rest of the returns were left in static fields by the callee
+                for (i, return_value) in call_func.signature().returns().iter().enumerate().skip(1) {
+                    let desc = crate::util::value_type_to_descriptor(return_value, class);
+                    let field_ref = class.const_field(&name, &crate::util::field_name_for_return(call_func, i), &desc.to_string());
+                    insns.push(java::Ins::GetStatic { index: field_ref });
+                    stack_map.stack_push(java::VerificationTypeInfo::from_descriptor(&desc, class));
+                }
+
                 false
             },
             ir::Ins::Ret => {
+                // Every return but the first is passed back through a static field, the last is on top of the stack
+                let class_name = class.name().to_string();
+                for (i, ret_type) in func.signature().returns().iter().enumerate().skip(1).rev() {
+                    let desc = crate::util::value_type_to_descriptor(ret_type, class);
+                    let field_ref = class.const_field(&class_name, &crate::util::field_name_for_return(func, i), &desc.to_string());
+                    stack_map.stack_pop();
+                    insns.push(java::Ins::PutStatic { index: field_ref });
+                }
+
                 if let Some(ret_type) = func.signature().returns().get(0) {
                     stack_map.stack_pop();
                     insns.push(java::opt::ins::ret(&crate::util::value_type_to_descriptor(ret_type, class)));
@@ -417,8 +556,14 @@ impl<'a> TranslationContext<'a> {
 
                 true
             },
-            ir::Ins::Inc(_, _) => todo!(),
-            ir::Ins::Dec(_, _) => todo!(),
+            ir::Ins::Inc(vt, i) => {
+                self.translate_step(vt, *i as i64, path_stack, insns, stack_map, class);
+                false
+            },
+            ir::Ins::Dec(vt, i) => {
+                self.translate_step(vt, -(*i as i64), path_stack, insns, stack_map, class);
+                false
+            },
             ir::Ins::Add(vt) => {
                 insns.push(java::opt::ins::add(&crate::util::value_type_to_descriptor(vt, class)));
                 stack_map.stack_pop();
@@ -430,7 +575,18 @@ impl<'a> TranslationContext<'a> {
                 false
             },
             ir::Ins::Div(vt) => {
-                insns.push(java::opt::ins::div(&crate::util::value_type_to_descriptor(vt, class)));
+                // The JVM only divides signed numbers, unsigned ones are divided by Integer and Long instead
+                match vt {
+                    ir::ValueType::U32 | ir::ValueType::UPtr => {
+                        let method_ref = class.const_method("java/lang/Integer", "divideUnsigned", "(II)I");
+                        insns.push(java::Ins::InvokeStatic { index: method_ref });
+                    },
+                    ir::ValueType::U64 => {
+                        let method_ref = class.const_method("java/lang/Long", "divideUnsigned", "(JJ)J");
+                        insns.push(java::Ins::InvokeStatic { index: method_ref });
+                    },
+                    _ => { insns.push(java::opt::ins::div(&crate::util::value_type_to_descriptor(vt, class))); }
+                }
                 stack_map.stack_pop();
                 false
             }
@@ -456,44 +612,52 @@ impl<'a> TranslationContext<'a> {
             ir::Ins::Eq(vt) =>
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
-                    ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpEq),
+                    ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 |
+                    ir::ValueType::Bool => icmp!(IfICmpEq),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfEq),
                     _ => todo!()
                 },
             ir::Ins::Ne(vt) =>
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
-                    ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpNe),
+                    ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 |
+                    ir::ValueType::Bool => icmp!(IfICmpNe),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfNe),
                     _ => todo!()
                 },
             ir::Ins::Lt(vt) => 
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
                     ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpLt),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfLt),
                     _ => todo!()
                 },
             ir::Ins::Le(vt) =>
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
                     ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpLe),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfLe),
                     _ => todo!()
                 },
             ir::Ins::Gt(vt) =>
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
                     ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpGt),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfGt),
                     _ => todo!()
                 },
             ir::Ins::Ge(vt) =>
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 |
                     ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 => icmp!(IfICmpGe),
+                    ir::ValueType::U64 | ir::ValueType::I64 => lcmp!(IfGe),
                     _ => todo!()
                 },
             ir::Ins::Loop(code, condition, inc) => {
                 if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
                 
                 let mut condition_branch = InstructionTarget::new(insns.tell());
-                for ins in condition { self.translate_ins(func, ins, path_stack, &mut condition_branch, stack_map, class); }
+                for ins in condition { self.translate_ins(func, ins, path_stack, blocks, &mut condition_branch, stack_map, class); }
                 let condition_branch_size = condition_branch.tell() - insns.tell();
 
                 stack_map.stack_pop();
@@ -501,11 +665,22 @@ impl<'a> TranslationContext<'a> {
                 // Ifeq jump to end - 3 bytes
 
                 let mut code_branch = InstructionTarget::new(insns.tell() + condition_branch_size + 3);
-                for ins in code { self.translate_ins(func, ins, path_stack, &mut code_branch, stack_map, class); }
+                let mut unreachable = false;
+                blocks.push_loop();
+                for ins in code {
+                    unreachable = unreachable || self.translate_ins(func, ins, path_stack, blocks, &mut code_branch, stack_map, class);
+                }
+                let moves = blocks.pop();
                 let code_branch_size = code_branch.tell() - condition_branch_size - 3 - insns.tell();
 
+                // The increment needs a frame if it is continued to, or the code before it does not reach it
+                let inc_loc = insns.tell() + condition_branch_size + 3 + code_branch_size;
+                if unreachable || moves.iter().any(|(_, is_break)| !is_break) {
+                    if let Some(frame) = stack_map.prepare_frame(inc_loc, class) { stack_map.push_frame(frame); }
+                }
+
                 let mut inc_branch = InstructionTarget::new(insns.tell() + condition_branch_size + 3 + code_branch_size);
-                for ins in inc { self.translate_ins(func, ins, path_stack, &mut inc_branch, stack_map, class); }
+                for ins in inc { self.translate_ins(func, ins, path_stack, blocks, &mut inc_branch, stack_map, class); }
                 let inc_branch_size = inc_branch.tell() - condition_branch_size - 3 - code_branch_size - insns.tell();
 
                 let overlap = insns.tell();
@@ -516,6 +691,11 @@ impl<'a> TranslationContext<'a> {
                 insns.extend(inc_branch, overlap + condition_branch_size + 3 + code_branch_size);
                 insns.push(java::Ins::Goto { branch: -((condition_branch_size + code_branch_size + inc_branch_size + 3) as i16) });
 
+                let end_loc = insns.tell();
+                for (loc, is_break) in moves {
+                    blocks.moves.push((loc, if is_break { end_loc } else { inc_loc }));
+                }
+
                 let frame = stack_map.prepare_frame(insns.tell(), class).unwrap();
                 stack_map.push_frame(frame);
 
@@ -523,7 +703,7 @@ impl<'a> TranslationContext<'a> {
             },
             ir::Ins::If(true_then, condition) => {
                 let mut condition_branch = InstructionTarget::new(insns.tell());
-                for ins in condition { self.translate_ins(func, ins, path_stack, &mut condition_branch, stack_map, class); }
+                for ins in condition { self.translate_ins(func, ins, path_stack, blocks, &mut condition_branch, stack_map, class); }
                 let condition_branch_size = condition_branch.tell() - insns.tell();
 
                 stack_map.stack_pop();
@@ -534,7 +714,9 @@ impl<'a> TranslationContext<'a> {
                     // Ifeq jump to end - 3 bytes
 
                     let mut true_branch = InstructionTarget::new(insns.tell() + condition_branch_size + 3);
-                    for ins in true_then { self.translate_ins(func, ins, path_stack, &mut true_branch, stack_map, class); }
+                    blocks.push_block();
+                    for ins in true_then { self.translate_ins(func, ins, path_stack, blocks, &mut true_branch, stack_map, class); }
+                    blocks.pop();
                     let true_branch_size = true_branch.tell() - condition_branch_size - 3 - insns.tell();
                     
                     insns.extend(condition_branch, overlap);
@@ -552,16 +734,24 @@ impl<'a> TranslationContext<'a> {
 
                 false
             },
-            ir::Ins::IfElse(true_then, false_then, condition) => {
-                for ins in condition { self.translate_ins(func, ins, path_stack, insns, stack_map, class); }
+            ir::Ins::IfElse(_, true_then, false_then, condition) => {
+                for ins in condition { self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class); }
 
                 let false_then_branch_loc = insns.tell();
                 let false_then_branch = insns.push(java::Ins::IfEq { branch: 0 as i16 });
                 stack_map.stack_pop();
 
+                let depth = stack_map.stack_depth();
                 let mut unreachable = false;
+                blocks.push_block();
                 for ins in true_then {
-                    unreachable = unreachable || self.translate_ins(func, ins, path_stack, insns, stack_map, class);
+                    unreachable = unreachable || self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class);
+                }
+                blocks.pop();
+
+                // If the branch left a value, the else branch starts without it and pushes its own
+                for _ in depth..stack_map.stack_depth() {
+                    stack_map.stack_pop();
                 }
 
                 let end_branch_loc = insns.tell();
@@ -574,17 +764,151 @@ impl<'a> TranslationContext<'a> {
                 *insns.at_mut(false_then_branch) = java::Ins::IfEq { branch: (insns.tell() - false_then_branch_loc) as i16 };
                 if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
 
-                for ins in false_then { self.translate_ins(func, ins, path_stack, insns, stack_map, class); }
+                let mut else_unreachable = false;
+                blocks.push_block();
+                for ins in false_then {
+                    else_unreachable = else_unreachable || self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class);
+                }
+                blocks.pop();
 
                 if let Some(end_branch) = end_branch {
                     *insns.at_mut(end_branch) = java::Ins::Goto { branch: (insns.tell() - end_branch_loc) as i16 };
                     if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
                 }
 
-                false
+                // Where neither branch reaches the end, what follows has no frame, so must be ended as unreachable
+                unreachable && else_unreachable
+            },
+            ir::Ins::Switch(vt, cases, default, value) => {
+                for ins in value { self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class); }
+
+                // Switch instructions only take ints, so longs are compared against each case in turn instead, leaving the value on the
+                // stack until the case it matched pops it
+                let is_long = matches!(vt, ir::ValueType::U64 | ir::ValueType::I64);
+
+                let switch_loc = insns.tell();
+                let mut switch = None;
+                let mut compare_branches = Vec::new();
+                let mut default_branch = None;
+                if is_long {
+                    for (case, (values, _)) in cases.iter().enumerate() {
+                        for case_value in values {
+                            insns.push(java::Ins::Dup2);
+                            insns.push(java::opt::ins::lconst(*case_value as i64, class));
+                            insns.push(java::Ins::LCmp);
+                            let loc = insns.tell();
+                            compare_branches.push((case, insns.push(java::Ins::IfEq { branch: 0 }), loc));
+                        }
+                    }
+
+                    insns.push(java::Ins::Pop2);
+                    stack_map.stack_pop();
+                    let loc = insns.tell();
+                    default_branch = Some((insns.push(java::Ins::Goto { branch: 0 }), loc));
+                } else {
+                    let table = match ir::switch_table_range(vt, cases) {
+                        Some((min, len)) if (min as i32) <= (min.wrapping_add(len as u64 - 1) as i32) => Some((min as i32, len)),
+                        _ => None
+                    };
+
+                    switch = Some(match table {
+                        Some((low, len)) => insns.push(java::Ins::TableSwitch { default: 0, low, high: low + len as i32 - 1, offsets: vec![0; len] }),
+                        None => {
+                            let mut pairs = cases.iter().flat_map(|(values, _)| values.iter()).map(|case_value| (*case_value as i32, 0)).collect::<Vec<_>>();
+                            pairs.sort();
+                            insns.push(java::Ins::LookupSwitch { default: 0, npairs: pairs.len() as i32, pairs })
+                        }
+                    });
+                    stack_map.stack_pop();
+                }
+
+                let mut case_locs = Vec::new();
+                let mut end_branches = Vec::new();
+                for (_, code) in cases {
+                    if is_long { stack_map.stack_push(java::VerificationTypeInfo::Long); }
+                    case_locs.push(insns.tell());
+                    if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
+
+                    if is_long {
+                        insns.push(java::Ins::Pop2);
+                        stack_map.stack_pop();
+                    }
+
+                    let mut unreachable = false;
+                    blocks.push_block();
+                    for ins in code {
+                        unreachable = unreachable || self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class);
+                    }
+                    blocks.pop();
+
+                    if !unreachable {
+                        let loc = insns.tell();
+                        end_branches.push((insns.push(java::Ins::Goto { branch: 0 }), loc));
+                    }
+                }
+
+                let default_loc = insns.tell();
+                if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
+
+                let mut unreachable = false;
+                blocks.push_block();
+                for ins in default {
+                    unreachable = unreachable || self.translate_ins(func, ins, path_stack, blocks, insns, stack_map, class);
+                }
+                blocks.pop();
+
+                // Now every location is known, point the branches at them
+                let offset_to = |loc: usize, from: usize| loc as i32 - from as i32;
+                if let Some(switch) = switch {
+                    match insns.at_mut(switch) {
+                        java::Ins::TableSwitch { default: switch_default, low, offsets, .. } => {
+                            *switch_default = offset_to(default_loc, switch_loc);
+                            for (i, offset) in offsets.iter_mut().enumerate() {
+                                let case_value = (*low as i64 + i as i64) as u64;
+                                *offset = match cases.iter().position(|(values, _)| values.iter().any(|value| *value as i32 == case_value as i32)) {
+                                    Some(case) => offset_to(case_locs[case], switch_loc),
+                                    None => offset_to(default_loc, switch_loc)
+                                };
+                            }
+                        },
+                        java::Ins::LookupSwitch { default: switch_default, pairs, .. } => {
+                            *switch_default = offset_to(default_loc, switch_loc);
+                            for (case_value, offset) in pairs.iter_mut() {
+                                let case = cases.iter().position(|(values, _)| values.iter().any(|value| *value as i32 == *case_value)).unwrap();
+                                *offset = offset_to(case_locs[case], switch_loc);
+                            }
+                        },
+                        _ => unreachable!()
+                    }
+                }
+
+                for (case, branch, loc) in compare_branches {
+                    *insns.at_mut(branch) = java::Ins::IfEq { branch: offset_to(case_locs[case], loc) as i16 };
+                }
+
+                if let Some((branch, loc)) = default_branch {
+                    *insns.at_mut(branch) = java::Ins::Goto { branch: offset_to(default_loc, loc) as i16 };
+                }
+
+                let end_loc = insns.tell();
+                let reached_end = !end_branches.is_empty() || !unreachable;
+                for (branch, loc) in end_branches {
+                    *insns.at_mut(branch) = java::Ins::Goto { branch: offset_to(end_loc, loc) as i16 };
+                }
+
+                if reached_end {
+                    if let Some(frame) = stack_map.prepare_frame(insns.tell(), class) { stack_map.push_frame(frame); }
+                }
+
+                !reached_end
+            },
+            ir::Ins::Asm(_) => panic!("Cannot translate inline assembly to the JVM"),
+            // Where the loop ends, or its increment starts, is not known until all of it has been translated
+            ir::Ins::Break(depth) | ir::Ins::Continue(depth) => {
+                blocks.move_out(*depth, insns.tell(), matches!(ins, ir::Ins::Break(_)));
+                insns.push(java::Ins::Goto { branch: 0 });
+                true
             },
-            ir::Ins::Break(_) => todo!(),
-            ir::Ins::Continue(_) => todo!(),
             ir::Ins::PushLiteral(vt, i) => 
                 match vt {
                     ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::U8 | ir::ValueType::I8 | ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 | ir::ValueType::Bool => {
@@ -606,4 +930,36 @@ impl<'a> TranslationContext<'a> {
             },
         }
     }
-}
\ No newline at end of file
+
+    /// Adds a constant to the value on top of the stack. If that value was just loaded from the int local it is about to be
+    /// stored back to, as with `x += 1`, the load is dropped and the local is updated in place with an iinc instead
+    fn translate_step(&self, vt: &ir::ValueType, amount: i64, path_stack: &mut PathStack, insns: &mut InstructionTarget, stack_map: &mut StackMapBuilder, class: &mut java::ClassFile) {
+        let desc = crate::util::value_type_to_descriptor(vt, class);
+
+        let local = match path_stack.peek() {
+            Some(Path::Local(idx, java::Descriptor::Int)) if matches!(desc, java::Descriptor::Int) => Some(*idx),
+            _ => None
+        };
+
+        if let Some(local) = local {
+            if local <= u8::MAX as usize && amount >= i8::MIN as i64 && amount <= i8::MAX as i64 && insns.take_iload(local) {
+                stack_map.stack_pop();
+                insns.push(java::Ins::IInc { local: local as u8, value: amount as i8 });
+                path_stack.pop();
+                path_stack.push(Path::Incremented);
+                return;
+            }
+        }
+
+        match desc {
+            java::Descriptor::Long => {
+                insns.push(java::opt::ins::lconst(amount, class));
+                insns.push(java::Ins::LAdd);
+            },
+            _ => {
+                insns.push(java::opt::ins::iconst(amount as i32, class));
+                insns.push(java::Ins::IAdd);
+            }
+        }
+    }
+}
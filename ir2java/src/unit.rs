@@ -1,4 +1,4 @@
-use crate::ins::{InstructionTarget, PathStack, StackMapBuilder};
+use crate::ins::{BlockStack, InstructionTarget, PathStack, StackMapBuilder};
 
 pub struct TranslationContext<'a> {
     unit: &'a ir::TranslationUnit,
@@ -15,10 +15,9 @@ impl<'a> TranslationContext<'a> {
                 &params,
                 &if signature.return_count() == 0 {
                     java::Descriptor::Void
-                } else if signature.return_count() == 1 {
-                    crate::util::value_type_to_descriptor(signature.returns().get(0).unwrap(), class)
                 } else {
-                    todo!("Multiple returns")
+                    // Any further returns are passed through static fields, see field_name_for_return
+                    crate::util::value_type_to_descriptor(signature.returns().get(0).unwrap(), class)
                 }
             )
     }
@@ -84,12 +83,18 @@ impl<'a> TranslationContext<'a> {
             }
 
             let mut locals_size = 0;
-            for local in func.locals()[func.signature().param_count()..].iter() {
+            for (l, local) in func.locals().iter().enumerate().skip(func.signature().param_count()) {
                 let prev_locals_size = params_locals_size + locals_size;
 
                 match local.local_type() {
-                    ir::StorableType::Compound(_) => {
-                        insns.push(java::Ins::AConstNull);
+                    // An object kept in a local needs its own, as there is nowhere else to keep it
+                    ir::StorableType::Compound(ctr) => {
+                        let name = crate::util::class_name_for_compound(&classfile, ctr);
+                        let class_index = classfile.const_class(&name);
+                        insns.push(java::Ins::New { index: class_index });
+                        insns.push(java::Ins::Dup);
+                        let method = classfile.const_method(&name, "<init>", "()V");
+                        insns.push(java::Ins::InvokeSpecial { index: method });
                         insns.push(java::Ins::AStore { local: prev_locals_size as u8 });
                         locals_size += 1;
                     },
@@ -119,24 +124,40 @@ impl<'a> TranslationContext<'a> {
                     ir::StorableType::SliceData(_) => panic!(),
                 }
 
-                stack_map.accessed_local(prev_locals_size);
+                stack_map.accessed_local(l);
             }
 
             let mut path_stack = PathStack::new();
+            let mut blocks = BlockStack::new();
             for ins in func.code() {
-                ctx.translate_ins(func, ins, &mut path_stack, &mut insns, &mut stack_map, &mut classfile);
+                ctx.translate_ins(func, ins, &mut path_stack, &mut blocks, &mut insns, &mut stack_map, &mut classfile);
+            }
+
+            // Every path returns before the end of a valid function, but the last of them may be in a loop or branch
+            if !matches!(func.code().last(), Some(ir::Ins::Ret)) {
+                stack_map.end_unreachable(&mut insns, &mut classfile);
             }
 
             let method = java::Method::new_on(crate::util::name_for_function(func), TranslationContext::signature_as_descriptor(func.signature(), &classfile), &mut classfile);
 
             // TODO: Find correct max size
-            let mut code = java::Code::new(10, (params_locals_size + locals_size) as u16, insns.take());
+            let mut code = java::Code::new(10, (params_locals_size + locals_size) as u16, {
+                let mut insns = insns.take();
+                blocks.apply(&mut insns);
+                insns
+            });
             code.add_map(stack_map.take());
             method.add_code(code);
             method.set_access(java::MethodAccessFlags::from_bits(
                 java::MethodAccessFlags::ACC_PUBLIC | java::MethodAccessFlags::ACC_STATIC
             ));
 
+            for (i, ret_type) in func.signature().returns().iter().enumerate().skip(1) {
+                let desc = crate::util::value_type_to_descriptor(ret_type, &classfile).to_string();
+                let field = java::Field::new_on(crate::util::field_name_for_return(func, i), &desc, &mut classfile);
+                field.set_access(java::FieldAccessFlags::from_bits(java::FieldAccessFlags::ACC_PUBLIC | java::FieldAccessFlags::ACC_STATIC));
+            }
+
             if func.is_entry() {
                 main_name = Some(crate::util::name_for_function(func));
             }
@@ -36,7 +36,7 @@ pub(crate) fn verification_type_for_storable(storable: &ir::StorableType, class:
             ir::ValueType::U16 | ir::ValueType::I16 |
             ir::ValueType::U32 | ir::ValueType::I32 | ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::Index(_) => java::VerificationTypeInfo::Integer,
             ir::ValueType::U64 | ir::ValueType::I64 => java::VerificationTypeInfo::Long,
-            ir::ValueType::Ref(c) => verification_type_for_storable(c, class)
+            ir::ValueType::Ref(c) => verification_type_for_storable(c, class),
         },
         ir::StorableType::Slice(st) => java::VerificationTypeInfo::Object(class.const_class(&format!("[{}", storable_type_to_descriptor(st, class).to_string()))),
         ir::StorableType::SliceData(_) => todo!(),
@@ -50,6 +50,23 @@ pub(crate) fn field_name_for_global(global: &ir::Global, index: ir::GlobalIndex)
     }
 }
 
+/// Java only allows a single return value, so any further returns are passed through static fields on the class
+pub(crate) fn field_name_for_return(func: &ir::Function, index: usize) -> String {
+    format!("{}$ret{}", name_for_function(func), index)
+}
+
+/// Longs take up two local slots, so the java index of a local depends on the types of those before it
+pub(crate) fn java_local_index(func: &ir::Function, local: ir::LocalIndex) -> usize {
+    let mut javaidx = 0;
+    for i in 0..local.idx() {
+        match func.locals()[i].local_type() {
+            ir::StorableType::Value(ir::ValueType::U64 | ir::ValueType::I64) => javaidx += 2,
+            _ => javaidx += 1,
+        }
+    }
+    javaidx
+}
+
 pub(crate) fn class_name_for_compound(class: &java::ClassFile, compound: &ir::CompoundType) -> String {
     format!("{}${}", class.name(), compound.name())
 }
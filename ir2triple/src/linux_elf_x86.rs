@@ -11,7 +11,9 @@ fn mangle_func_name(func: &ir::Function) -> String {
     }
 }
 
-pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Result<(), String> {
+/// Encodes the unit as an ELF file. If `collected` is set, allocations are made through a garbage collected runtime
+/// (see nl/std/gc.c), which is given a layout descriptor for each allocated type rather than its size.
+pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool, collected: bool) -> Result<(), String> {
     let mut elf = if relocatable {
         elfbuilder::StaticELF::new_relocatable()
     } else {
@@ -19,7 +21,11 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
     };
 
     let mut x86_encoding = x86::EncodeContext::new();
-    let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
+    let ctx = if collected {
+        ir2x86::TranslationContext::new_collected(x86::Mode::X8664, ir2x86::LayoutTable::new(unit, x86::Mode::X8664))
+    } else {
+        ir2x86::TranslationContext::new(x86::Mode::X8664)
+    };
 
     let text_base = if relocatable { 0 } else { TEXT_BASE };
     let mut entry = None;
@@ -28,6 +34,14 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 
     let data_base_symbol = elf.push_symbol(elfbuilder::Symbol::Section(2)); // data is section 2, TODO: This should not be hard coded
 
+    // Named globals are always placed in .data, as that is where object symbols point
+    let in_rodata = |global: &ir::Global| global.is_read_only() && global.name().is_none();
+    let rodata_base_symbol = if unit.globals().iter().any(in_rodata) {
+        Some(elf.push_symbol(elfbuilder::Symbol::Section(3))) // rodata follows data
+    } else {
+        None
+    };
+
     for (i, func) in unit.functions().iter().enumerate() {
         let gid = gid_allocator.global_id_of_function(ir::FunctionIndex::new(i));
         
@@ -37,7 +51,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
             }
             gid_allocator.push_global_symbol_mapping(gid, elf.push_symbol(elfbuilder::Symbol::Relocatable(mangle_func_name(func))), 0);
         } else {
-            let mut ins = ctx.translate_function(&func, unit);
+            let mut ins = ctx.translate_function(&func, unit).map_err(|e| format!("{} in function '{}'", e, mangle_func_name(func)))?;
             x86::opt::pass_zero(&mut ins);
             
             let (addr, length) = x86_encoding.append_function(&ins);
@@ -59,6 +73,11 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
     for (i, global) in unit.globals().iter().enumerate() {
         let gid = gid_allocator.global_id_of_global(ir::GlobalIndex::new(i));
 
+        if in_rodata(global) {
+            continue;
+        }
+
+        ofile::align_up_vec(&mut data, global.global_type().align(8));
         if let Some(name) = global.name() {
             let pushed = ctx.translate_global(global, unit, &gid_allocator, &mut relocs, data.len(), 0);
             gid_allocator.push_global_symbol_mapping(gid, elf.push_symbol(elfbuilder::Symbol::Object(
@@ -74,6 +93,32 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
         }
     }
 
+    if let Some(layouts) = ctx.layouts() {
+        ofile::align_up_vec(&mut data, 8);
+
+        for (i, layout) in layouts.layouts().iter().enumerate() {
+            let gid = gid_allocator.global_id_of_layout(i);
+            gid_allocator.push_global_symbol_mapping(gid, data_base_symbol, data.len() as i64);
+            data.extend(layout.encode(x86::Mode::X8664));
+        }
+    }
+
+    let rodata_base = if relocatable { 0 } else { ofile::align_up(data_base + data.len() as u64, 1 << 12) };
+
+    let mut rodata_relocs = Vec::new();
+    let mut rodata = Vec::new();
+    if let Some(rodata_base_symbol) = rodata_base_symbol {
+        for (i, global) in unit.globals().iter().enumerate() {
+            if !in_rodata(global) { continue; }
+
+            let gid = gid_allocator.global_id_of_global(ir::GlobalIndex::new(i));
+            ofile::align_up_vec(&mut rodata, global.global_type().align(8));
+            let pushed = ctx.translate_global(global, unit, &gid_allocator, &mut rodata_relocs, rodata.len(), 0);
+            gid_allocator.push_global_symbol_mapping(gid, rodata_base_symbol, rodata.len() as i64);
+            rodata.extend(pushed);
+        }
+    }
+
     // Data relocations
     if !relocatable {
         for reloc in relocs {
@@ -87,6 +132,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
                         elfbuilder::Symbol::Section(idx) => match idx {
                             1 => text_base,
                             2 => data_base,
+                            3 => rodata_base,
                             _ => panic!("Invalid section")
                         },
                     } as i64 + reloc.addend() + addend) as u64;
@@ -116,6 +162,49 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
         }
     }
 
+    // Read only data relocations
+    if !relocatable {
+        for reloc in rodata_relocs {
+            match reloc.kind() {
+                x86::RelocationType::AbsoluteGlobalSymbol(id) => {
+                    let (symbol, addend) = gid_allocator.symbol_for_global_id(*id).expect("Invalid relocation symbol");
+                    let addr = (match elf.get_symbol(symbol).expect("Invalid relocation") {
+                        elfbuilder::Symbol::Function(_, vaddr, _) => *vaddr,
+                        elfbuilder::Symbol::Object(_, vaddr, _) => *vaddr,
+                        elfbuilder::Symbol::Relocatable(name) => return Err(format!("Could not statically link due to external reference '{}'", name)),
+                        elfbuilder::Symbol::Section(idx) => match idx {
+                            1 => text_base,
+                            2 => data_base,
+                            3 => rodata_base,
+                            _ => panic!("Invalid section")
+                        },
+                    } as i64 + reloc.addend() + addend) as u64;
+
+                    rodata[reloc.offset()..reloc.offset() + 8].copy_from_slice(
+                        &addr.to_le_bytes()
+                    );
+                },
+                _ => panic!("Cannot relocate non-global symbol in .rodata")
+            }
+        }
+    } else {
+        for reloc in rodata_relocs {
+            match reloc.kind() {
+                x86::RelocationType::AbsoluteGlobalSymbol(id) => {
+                    let (symbol, addend) = gid_allocator.symbol_for_global_id(*id).expect("Invalid relocation symbol");
+
+                    elf.push_rodata_relocation(elf::Rela::new(
+                        reloc.offset() as u64,
+                        symbol as u64,
+                        elf::RelocationType::X86646464,
+                        reloc.addend() + addend
+                    ));
+                },
+                _ => panic!("Cannot relocate non-global symbol in .rodata")
+            }
+        }
+    }
+
     let (mut text, relocs) = x86_encoding.take();
 
     // Text relocations
@@ -131,6 +220,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
                         elfbuilder::Symbol::Section(idx) => match idx {
                             1 => text_base,
                             2 => data_base,
+                            3 => rodata_base,
                             _ => panic!("Invalid section")
                         },
                     } as i64 + reloc.addend() + addend) as u32;
@@ -162,6 +252,9 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 
     elf.set_text(text_base, text);
     elf.set_data(data_base, data);
+    if rodata_base_symbol.is_some() {
+        elf.set_rodata(rodata_base, rodata);
+    }
 
     let header = elf::Header::new_with_entry(
         elf::ABI::SysV,
@@ -82,13 +82,15 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 			continue
 		}
 
-		let ins = ctx.translate_function(&func, unit);
+		let ins = ctx.translate_function(&func, unit).map_err(|e| format!("{} in function '{}'", e, mangle_func_name(func)))?;
 
 		let (addr, _) = arm64_encoding.append_function(&ins);
 		func_locations.push(addr);
 	}
 
-	let (text, text_relocs) = arm64_encoding.take();
+	let (mut text, text_relocs) = arm64_encoding.take();
+	// __data directly follows __text, so the code is padded for the globals to keep their alignment
+	ofile::align_up_vec(&mut text, 8);
 
 	let mut global_offsets = Vec::new();
 	let mut globals = Vec::new();
@@ -193,6 +195,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 						match reloc.0.mode() {
 							arm64::InsRelocMode::Branch26 => unreachable!(),
 							arm64::InsRelocMode::Branch19Shift5 => unreachable!(),
+							arm64::InsRelocMode::Adr21 => unreachable!(),
 							arm64::InsRelocMode::Page21 => macho.push_reloc(macho::Reloc {
 								addr: reloc.1 as u32,
 								symbolnum: *global_symbols.get(&global.idx()).unwrap(),
@@ -221,6 +224,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 							typ: match reloc.0.mode() {
 								arm64::InsRelocMode::Branch26 => macho::RelocType::Arm64Branch26,
 								arm64::InsRelocMode::Branch19Shift5 => unreachable!(),
+								arm64::InsRelocMode::Adr21 => unreachable!(),
 								arm64::InsRelocMode::Page21 => macho::RelocType::Arm64Page21,
 								arm64::InsRelocMode::PageOff12 => macho::RelocType::Arm64PageOff12,
 							}
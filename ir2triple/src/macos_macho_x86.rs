@@ -60,7 +60,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 				addr: 0,
 				size: 0,
 				offset: 0,
-				align: 0,
+				align: 3,
 				reloff: 0,
 				nreloc: 0,
 				sectype: macho::SectionType::Regular,
@@ -98,6 +98,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 
 	let gid_allocator = ir2x86::GlobalIDAllocator::new(unit);
 	for global in unit.globals() {
+		ofile::align_up_vec(&mut globals, global.global_type().align(8));
 		global_offsets.push(globals.len());
 		globals.extend(ctx.translate_global(global, unit, &gid_allocator, &mut globals_relocs, globals.len(), 0));
 	}
@@ -111,7 +112,7 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 	for (i, func) in unit.functions().iter().enumerate() {
 		if func.is_extern() { continue }
 
-		let mut ins = ctx.translate_function(&func, unit);
+		let mut ins = ctx.translate_function(&func, unit).map_err(|e| format!("{} in function '{}'", e, mangle_func_name(func)))?;
         x86::opt::pass_zero(&mut ins);
 
 		let (addr, _) = x86_encoding.append_function(&ins);
@@ -147,8 +148,10 @@ pub fn encode(unit: &ir::TranslationUnit, path: &str, relocatable: bool) -> Resu
 		func_symbols.insert(i, idx);
 	}
 
-	let text_length = x86_encoding.len();
 	let (mut text, text_relocs) = x86_encoding.take();
+	// __data directly follows __text, so the code is padded for the globals to keep their alignment
+	ofile::align_up_vec(&mut text, 8);
+	let text_length = text.len();
 
 	macho.section_mut(0).size = text_length as u64;
 	macho.section_mut(0).nreloc = text_relocs.len() as u32;
@@ -25,8 +25,35 @@ impl PathStack {
     }
 }
 
+/// The number of wasm labels the code of each enclosing ir block is within, innermost last, so that a break or continue can
+/// find the label of its loop
+pub(crate) struct BlockStack {
+    labels: Vec<usize>
+}
+
+impl BlockStack {
+    pub(crate) fn new() -> BlockStack {
+        BlockStack {
+            labels: Vec::new()
+        }
+    }
+
+    fn push(&mut self, labels: usize) {
+        self.labels.push(labels);
+    }
+
+    fn pop(&mut self) {
+        self.labels.pop().expect("Block stack underflow");
+    }
+
+    /// The label with the given index among those of the block at the given depth, counted from its innermost
+    fn label_of(&self, depth: ir::BlockMoveDepth, idx: usize) -> wasm::LabelIdx {
+        self.labels[self.labels.len() - depth..].iter().sum::<usize>() + idx
+    }
+}
+
 impl<'a> TranslationContext<'a> {
-    pub(crate) fn translate_ins(&self, func: &ir::Function, path_stack: &mut PathStack, ins: &ir::Ins, insns: &mut Vec<wasm::Ins>) {
+    pub(crate) fn translate_ins(&self, func: &ir::Function, path_stack: &mut PathStack, blocks: &mut BlockStack, ins: &ir::Ins, insns: &mut Vec<wasm::Ins>) {
         match ins {
             ir::Ins::PushPath(value_path, _) => {
                 let mut path = match value_path.origin() {
@@ -91,6 +118,9 @@ impl<'a> TranslationContext<'a> {
                         }
                 }
             },
+            ir::Ins::PopLocal(local_index, _) => {
+                insns.push(wasm::Ins::LocalSet(crate::util::wasm_local_index_from_ir_local_index(*local_index, func)));
+            },
             ir::Ins::Push(vt) => {
                 match path_stack.pop() {
                     Path::Local(local_index) => insns.push(wasm::Ins::LocalGet(local_index)),
@@ -149,6 +179,18 @@ impl<'a> TranslationContext<'a> {
                 });
             },
             ir::Ins::Add(vt) => insns.push(wasm::Ins::Add(crate::util::value_type_to_num_type(vt))),
+            ir::Ins::Inc(vt, i) | ir::Ins::Dec(vt, i) => {
+                insns.push(match vt {
+                    ir::ValueType::U64 | ir::ValueType::I64 => wasm::Ins::ConstI64(*i as i64),
+                    ir::ValueType::U8 | ir::ValueType::I8 | ir::ValueType::U16 | ir::ValueType::I16 | ir::ValueType::U32 | ir::ValueType::I32 |
+                    ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::Bool => wasm::Ins::ConstI32(*i as i32),
+                    ir::ValueType::Ref(_) | ir::ValueType::Index(_) => unreachable!("inc/dec on non-integer type {:?}", vt),
+                });
+                insns.push(match ins {
+                    ir::Ins::Inc(_, _) => wasm::Ins::Add(crate::util::value_type_to_num_type(vt)),
+                    _ => wasm::Ins::Sub(crate::util::value_type_to_num_type(vt)),
+                });
+            },
             ir::Ins::Mul(vt) => insns.push(wasm::Ins::Mul(crate::util::value_type_to_num_type(vt))),
             ir::Ins::Div(vt) => insns.push(wasm::Ins::Div(crate::util::value_type_to_num_type(vt), vt.is_signed())),
             ir::Ins::Sub(vt) => insns.push(wasm::Ins::Sub(crate::util::value_type_to_num_type(vt))),
@@ -177,13 +219,20 @@ impl<'a> TranslationContext<'a> {
             ir::Ins::Loop(code, condition, inc) => {
                 insns.push(wasm::Ins::Block(wasm::BlockType::Empty, vec![wasm::Ins::Loop(wasm::BlockType::Empty, {
                     let mut inner_insns = Vec::new();
-                    for ins in condition { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                    for ins in condition { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
                     inner_insns.push(wasm::Ins::Eqz(wasm::NumType::I32));
                     inner_insns.push(wasm::Ins::BrIf(1));
 
-                    // TODO: Embed block to allow for continuing
-                    for ins in code { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
-                    for ins in inc { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                    // The code is in a block of its own, so continuing branches out of it to the increment
+                    blocks.push(3);
+                    inner_insns.push(wasm::Ins::Block(wasm::BlockType::Empty, {
+                        let mut code_insns = Vec::new();
+                        for ins in code { self.translate_ins(func, path_stack, blocks, ins, &mut code_insns); }
+                        code_insns
+                    }));
+                    blocks.pop();
+
+                    for ins in inc { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
 
                     inner_insns.push(wasm::Ins::Br(0));
 
@@ -194,44 +243,129 @@ impl<'a> TranslationContext<'a> {
                 insns.push(wasm::Ins::Block(wasm::BlockType::Empty, {
                     let mut inner_insns = Vec::new();
 
-                    for ins in cond { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                    for ins in cond { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
                     inner_insns.push(wasm::Ins::Eqz(wasm::NumType::I32));
                     inner_insns.push(wasm::Ins::BrIf(0));
 
-                    for ins in true_then { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                    blocks.push(1);
+                    for ins in true_then { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
+                    blocks.pop();
 
                     inner_insns
                 }));
             },
-            ir::Ins::IfElse(true_then, false_then, cond) => {
-                insns.push(wasm::Ins::Block(wasm::BlockType::Empty, {
+            ir::Ins::IfElse(Some(_), true_then, false_then, cond) if
+                true_then.iter().chain(false_then.iter()).chain(cond.iter()).all(|ins| ins.is_side_effect_free()) => {
+                // Both branches just compute a value, so compute both and select one instead of branching
+                for ins in true_then { self.translate_ins(func, path_stack, blocks, ins, insns); }
+                for ins in false_then { self.translate_ins(func, path_stack, blocks, ins, insns); }
+                for ins in cond { self.translate_ins(func, path_stack, blocks, ins, insns); }
+                insns.push(wasm::Ins::Select);
+            },
+            ir::Ins::IfElse(vt, true_then, false_then, cond) => {
+                let block_type = match vt {
+                    Some(vt) => wasm::BlockType::Value(crate::util::value_type_to_val_type(vt)),
+                    None => wasm::BlockType::Empty
+                };
+
+                insns.push(wasm::Ins::Block(block_type, {
                     let mut first_inner_insns = Vec::new();
 
                     first_inner_insns.push(wasm::Ins::Block(wasm::BlockType::Empty, {
                         let mut inner_insns = Vec::new();
 
-                        for ins in cond { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                        for ins in cond { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
                         inner_insns.push(wasm::Ins::Eqz(wasm::NumType::I32));
                         inner_insns.push(wasm::Ins::BrIf(0));
     
-                        for ins in true_then { self.translate_ins(func, path_stack, ins, &mut inner_insns); }
+                        blocks.push(2);
+                        for ins in true_then { self.translate_ins(func, path_stack, blocks, ins, &mut inner_insns); }
+                        blocks.pop();
     
                         inner_insns.push(wasm::Ins::Br(1));
     
                         inner_insns
                     }));
 
-                    for ins in false_then { self.translate_ins(func, path_stack, ins, &mut first_inner_insns); }
+                    blocks.push(1);
+                    for ins in false_then { self.translate_ins(func, path_stack, blocks, ins, &mut first_inner_insns); }
+                    blocks.pop();
 
                     first_inner_insns
                 }));
             },
+            ir::Ins::Switch(vt, cases, default, value) => {
+                let num_type = crate::util::value_type_to_num_type(vt);
+                let scratch = crate::util::switch_scratch_local(func, &num_type);
+                let const_of = |case_value: u64| match num_type {
+                    wasm::NumType::I64 => wasm::Ins::ConstI64(case_value as i64),
+                    _ => wasm::Ins::ConstI32(case_value as i32)
+                };
+
+                // Each case follows the end of a block, with the dispatch in the innermost, so branching out of the block with the same
+                // index as a case runs it. The default follows the block after the last case, and each case branches past it when done.
+                let mut dispatch = Vec::new();
+                for ins in value { self.translate_ins(func, path_stack, blocks, ins, &mut dispatch); }
+
+                match ir::switch_table_range(vt, cases) {
+                    Some((min, len)) => {
+                        dispatch.push(const_of(min));
+                        dispatch.push(wasm::Ins::Sub(crate::util::value_type_to_num_type(vt)));
+
+                        // br_table takes an i32, so a 64 bit index has to be checked before it is wrapped
+                        if num_type == wasm::NumType::I64 {
+                            dispatch.push(wasm::Ins::LocalTee(scratch));
+                            dispatch.push(const_of(len as u64));
+                            dispatch.push(wasm::Ins::Ge(wasm::NumType::I64, false));
+                            dispatch.push(wasm::Ins::BrIf(cases.len()));
+                            dispatch.push(wasm::Ins::LocalGet(scratch));
+                            dispatch.push(wasm::Ins::WrapI64);
+                        }
+
+                        let labels = (0..len).map(|i| {
+                            let case_value = min.wrapping_add(i as u64);
+                            match cases.iter().position(|(values, _)| values.contains(&case_value)) {
+                                Some(case) => case,
+                                None => cases.len()
+                            }
+                        }).collect();
+                        dispatch.push(wasm::Ins::BrTable(labels, cases.len()));
+                    },
+                    None => {
+                        dispatch.push(wasm::Ins::LocalSet(scratch));
+                        for (case, (values, _)) in cases.iter().enumerate() {
+                            for case_value in values {
+                                dispatch.push(wasm::Ins::LocalGet(scratch));
+                                dispatch.push(const_of(*case_value));
+                                dispatch.push(wasm::Ins::Eq(crate::util::value_type_to_num_type(vt)));
+                                dispatch.push(wasm::Ins::BrIf(case));
+                            }
+                        }
+                        dispatch.push(wasm::Ins::Br(cases.len()));
+                    }
+                }
+
+                let mut inner_insns = dispatch;
+                for (case, (_, code)) in cases.iter().enumerate() {
+                    let mut outer_insns = vec![wasm::Ins::Block(wasm::BlockType::Empty, inner_insns)];
+                    blocks.push(cases.len() - case + 1);
+                    for ins in code { self.translate_ins(func, path_stack, blocks, ins, &mut outer_insns); }
+                    blocks.pop();
+                    outer_insns.push(wasm::Ins::Br(cases.len() - case));
+                    inner_insns = outer_insns;
+                }
+
+                let mut outer_insns = vec![wasm::Ins::Block(wasm::BlockType::Empty, inner_insns)];
+                blocks.push(1);
+                for ins in default { self.translate_ins(func, path_stack, blocks, ins, &mut outer_insns); }
+                blocks.pop();
+                insns.push(wasm::Ins::Block(wasm::BlockType::Empty, outer_insns));
+            },
             ir::Ins::Convert(from, to) => {
                 match (from, to) {
                     (ir::ValueType::U64 | ir::ValueType::I64, ir::ValueType::U64 | ir::ValueType::I64) => {},
                     (ir::ValueType::U64 | ir::ValueType::I64, _) => insns.push(wasm::Ins::WrapI64),
-                    (_, ir::ValueType::U64) => insns.push(wasm::Ins::Extend(false)),
-                    (_, ir::ValueType::I64) => insns.push(wasm::Ins::Extend(true)),
+                    (_, ir::ValueType::U64 | ir::ValueType::I64) => insns.push(wasm::Ins::Extend(from.is_signed())),
                     _ => {}
                 }
                 
@@ -239,7 +373,13 @@ impl<'a> TranslationContext<'a> {
             ir::Ins::Ret => {
                 insns.push(wasm::Ins::Return);
             },
-            _ => todo!("{:?}", ins)
+            ir::Ins::Drop => {
+                insns.push(wasm::Ins::Drop);
+            },
+            ir::Ins::Asm(_) => panic!("Cannot translate inline assembly to WebAssembly"),
+            // Within a loop, its code is in a block to continue out of, then the loop, then a block to break out of
+            ir::Ins::Break(depth) => insns.push(wasm::Ins::Br(blocks.label_of(*depth, 2))),
+            ir::Ins::Continue(depth) => insns.push(wasm::Ins::Br(blocks.label_of(*depth, 0))),
         }
     }
-}
\ No newline at end of file
+}
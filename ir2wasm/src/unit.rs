@@ -1,4 +1,4 @@
-use crate::ins::PathStack;
+use crate::ins::{BlockStack, PathStack};
 
 
 pub struct TranslationContext<'a> {
@@ -8,19 +8,27 @@ pub struct TranslationContext<'a> {
 }
 
 impl<'a> TranslationContext<'a> {
-    fn compound_to_memory(&self, ct: &ir::CompoundValue, raw: &mut Vec<u8>) {
-        match ct {
-            ir::CompoundValue::Struct(s) => {
-                for prop in s.props() {
-                    self.storable_to_memory(prop.value(), raw);
+    fn compound_to_memory(&self, ct: &ir::CompoundValue, compound_type: &ir::CompoundTypeRef, raw: &mut Vec<u8>) {
+        let start = raw.len();
+
+        match (ct, compound_type.content()) {
+            (ir::CompoundValue::Struct(s), ir::CompoundContent::Struct(content)) => {
+                for ((prop, prop_type), offset) in s.props().iter().zip(content.props()).zip(compound_type.prop_offsets(4)) {
+                    raw.resize(start + offset, 0);
+                    self.storable_to_memory(prop.value(), prop_type.prop_type(), raw);
                 }
             },
         }
+
+        raw.resize(start + compound_type.size(4), 0);
     }
 
-    fn storable_to_memory(&self, storable: &ir::StorableValue, raw: &mut Vec<u8>) {
+    fn storable_to_memory(&self, storable: &ir::StorableValue, storable_type: &ir::StorableType, raw: &mut Vec<u8>) {
         match storable {
-            ir::StorableValue::Compound(ct) => self.compound_to_memory(ct, raw),
+            ir::StorableValue::Compound(ct) => match storable_type {
+                ir::StorableType::Compound(compound_type) => self.compound_to_memory(ct, compound_type, raw),
+                _ => panic!("Compound value for non-compound type")
+            },
             ir::StorableValue::Value(v) => match v {
                 ir::Value::U8(v) => raw.extend(v.to_le_bytes()),
                 ir::Value::I8(v) => raw.extend(v.to_le_bytes()),
@@ -51,8 +59,13 @@ impl<'a> TranslationContext<'a> {
                 raw.extend((*len as u32).to_le_bytes());
             },
             ir::StorableValue::SliceData(data) => {
+                let element_type = match storable_type {
+                    ir::StorableType::SliceData(st) => st.as_ref(),
+                    _ => panic!("Slice data value for non-slice data type")
+                };
+
                 for element in data {
-                    self.storable_to_memory(element, raw);
+                    self.storable_to_memory(element, element_type, raw);
                 }
             },
         }
@@ -77,9 +90,12 @@ impl<'a> TranslationContext<'a> {
         // TODO: This is very order dependent, which may not always work
         let mut raw = Vec::new();
         for global in unit.globals() {
+            let align = crate::util::align_for_storable_type(global.global_type());
+            raw.resize((raw.len() + align - 1) & !(align - 1), 0);
+
             ctx.globals.push(raw.len() as i32);
             if let Some(default) = global.default() {
-                ctx.storable_to_memory(default, &mut raw);
+                ctx.storable_to_memory(default, global.global_type(), &mut raw);
             } else {
                 raw.extend(vec![0; crate::util::size_for_storable_type(global.global_type())]);
             }
@@ -142,10 +158,22 @@ impl<'a> TranslationContext<'a> {
                 }
             }
 
+            if crate::util::contains_switch(func.code()) {
+                locals.push(wasm::ValType::Num(wasm::NumType::I32));
+                locals.push(wasm::ValType::Num(wasm::NumType::I64));
+            }
+
             let mut path_stack = PathStack::new();
+            let mut blocks = BlockStack::new();
 
             for ins in func.code() {
-                ctx.translate_ins(func, &mut path_stack, ins, &mut code);
+                ctx.translate_ins(func, &mut path_stack, &mut blocks, ins, &mut code);
+            }
+
+            // Every path returns before the end of a valid function, but when the last of them is in a loop or branch the end
+            // still needs the values to be returned on the stack, which it cannot have
+            if !matches!(func.code().last(), Some(ir::Ins::Ret)) {
+                code.push(wasm::Ins::Unreachable);
             }
     
             module.add_code(wasm::Code::new(locals, wasm::Expr::with(code)));
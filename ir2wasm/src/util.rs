@@ -19,51 +19,16 @@ pub(crate) fn value_type_to_val_type(vt: &ir::ValueType) -> wasm::ValType {
     wasm::ValType::Num(value_type_to_num_type(vt))
 }
 
-pub(crate) fn size_for_compound_type(ct: &ir::CompoundType) -> usize {
-    match ct.content() {
-        ir::CompoundContent::Struct(s) => {
-            let mut size = 0;
-
-            for s in s.props() {
-                size += size_for_storable_type(s.prop_type());
-            }
-
-            size
-        },
-    }
-}
-
 pub(crate) fn size_for_compound_type_up_to_prop(ct: &ir::CompoundType, prop_idx: ir::PropertyIndex) -> usize {
-    match ct.content() {
-        ir::CompoundContent::Struct(s) => {
-            let mut size = 0;
-
-            for s in &s.props()[0..prop_idx.idx()] {
-                size += size_for_storable_type(s.prop_type());
-            }
-
-            size
-        },
-    }
+    ct.prop_offset(prop_idx, 4)
 }
 
-pub(crate) fn size_for_value_type(vt: &ir::ValueType) -> usize {
-    match vt {
-        ir::ValueType::U8 | ir::ValueType::I8 | ir::ValueType::Bool => 1,
-        ir::ValueType::U16 | ir::ValueType::I16 => 2,
-        ir::ValueType::U32 | ir::ValueType::I32 => 4,
-        ir::ValueType::U64 | ir::ValueType::I64 => 8,
-        ir::ValueType::UPtr | ir::ValueType::IPtr | ir::ValueType::Ref(_) | ir::ValueType::Index(_) => 4,
-    }
+pub(crate) fn size_for_storable_type(st: &ir::StorableType) -> usize {
+    st.size(4)
 }
 
-pub(crate) fn size_for_storable_type(st: &ir::StorableType) -> usize {
-    match st {
-        ir::StorableType::Compound(ct) => size_for_compound_type(ct),
-        ir::StorableType::Value(vt) => size_for_value_type(vt),
-        ir::StorableType::Slice(_) => 8,
-        ir::StorableType::SliceData(_) => panic!("Cannot compute raw size of SliceData type"),
-    }
+pub(crate) fn align_for_storable_type(st: &ir::StorableType) -> usize {
+    st.align(4)
 }
 
 pub(crate) fn value_types_for_compound_type(ct: &ir::CompoundTypeRef) -> Vec<ir::ValueType> {
@@ -126,4 +91,28 @@ pub(crate) fn wasm_local_index_from_ir_local_index(local: ir::LocalIndex, func:
         index += crate::util::value_type_count_for_storable_type(local.local_type());
     }
     index
-}
\ No newline at end of file
+}
+/// Whether the code contains a switch anywhere, in which case the function needs the scratch locals switches dispatch with
+pub(crate) fn contains_switch(code: &[ir::Ins]) -> bool {
+    code.iter().any(|ins| match ins {
+        ir::Ins::Switch(_, _, _, _) => true,
+        ir::Ins::Loop(body, condition, increment) => contains_switch(body) || contains_switch(condition) || contains_switch(increment),
+        ir::Ins::If(body, condition) => contains_switch(body) || contains_switch(condition),
+        ir::Ins::IfElse(_, true_body, false_body, condition) => contains_switch(true_body) || contains_switch(false_body) || contains_switch(condition),
+        _ => false
+    })
+}
+
+/// The local a switch on a value of the given type keeps its value in while comparing it against each case. These come after all of the
+/// function's own locals, an i32 and then an i64, and are only added if the function contains a switch.
+pub(crate) fn switch_scratch_local(func: &ir::Function, num_type: &wasm::NumType) -> usize {
+    let mut index = 0;
+    for local in func.locals() {
+        index += crate::util::value_type_count_for_storable_type(local.local_type());
+    }
+
+    match num_type {
+        wasm::NumType::I64 => index + 1,
+        _ => index
+    }
+}
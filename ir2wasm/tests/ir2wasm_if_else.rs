@@ -0,0 +1,47 @@
+#[test]
+fn value() {
+    let mut unit = ir::TranslationUnit::new();
+
+    let putchar = unit.add_function({
+        let mut func = ir::Function::new_extern("putchar", ir::Signature::new(vec![ ir::ValueType::I32 ], vec![]));
+        func.push_attr(ir::FunctionAttr::ExternLocation("core".to_string()));
+        func
+    });
+
+    unit.add_function({
+        let mut func = ir::Function::new("main", ir::Signature::new(vec![ ], vec![ ]));
+
+        // The true branch does not end with the instruction which pushes its value, so its type comes from the IfElse
+        func.push(ir::Ins::IfElse(Some(ir::ValueType::I32), vec![
+            ir::Ins::PushLiteral(ir::ValueType::I32, '!' as u64),
+            ir::Ins::Call(putchar),
+            ir::Ins::PushLiteral(ir::ValueType::I32, '7' as u64),
+            ir::Ins::PushLiteral(ir::ValueType::I32, 0),
+            ir::Ins::Drop,
+        ], vec![
+            ir::Ins::PushLiteral(ir::ValueType::I32, '3' as u64),
+        ], vec![
+            ir::Ins::PushLiteral(ir::ValueType::Bool, 1),
+        ]));
+        func.push(ir::Ins::Call(putchar));
+        func.push(ir::Ins::Ret);
+
+        func
+    });
+
+    unit.validate().expect("Invalid unit");
+
+    let module = ir2wasm::TranslationContext::translate_unit(&unit).expect("Translation");
+
+    let raw = module.encode();
+    std::fs::write("tests/if_else.wasm", &raw).expect("Could not write output");
+
+    let output = std::process::Command::new("node")
+        .arg("tests/wasm.js")
+        .arg("tests/if_else.wasm")
+        .output().expect("Could not run");
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!(output.stdout, "!7".as_bytes());
+}
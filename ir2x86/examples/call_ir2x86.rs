@@ -25,11 +25,11 @@ fn main() {
     let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
     let mut encode_ctx = x86::EncodeContext::new();
 
-    let mut ins_a = ctx.translate_function(unit.get_function(func_a_id).unwrap(), &unit);
+    let mut ins_a = ctx.translate_function(unit.get_function(func_a_id).unwrap(), &unit).unwrap();
     x86::opt::pass_zero(&mut ins_a);
     encode_ctx.append_function(&ins_a);
 
-    let mut ins_b = ctx.translate_function(unit.get_function(func_b_id).unwrap(), &unit);
+    let mut ins_b = ctx.translate_function(unit.get_function(func_b_id).unwrap(), &unit).unwrap();
     x86::opt::pass_zero(&mut ins_b);
     encode_ctx.append_function(&ins_b);
     
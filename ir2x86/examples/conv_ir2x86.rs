@@ -20,7 +20,7 @@ fn main() {
     
     // let mut ins = func.build_x86(x86::Mode::X8664, &unit);
     let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
-    let mut ins = ctx.translate_function(&func, &unit);
+    let mut ins = ctx.translate_function(&func, &unit).unwrap();
     x86::opt::pass_zero(&mut ins);
 
     let mut ctx = x86::EncodeContext::new();
@@ -0,0 +1,219 @@
+/// Why an inline assembly block could not be assembled for x86-64
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsmError {
+    WrongArch(ir::AsmArch),
+    UnknownRegister(String),
+    UnknownInstruction(String),
+    /// The instruction exists, but not with the given operands
+    InvalidOperands(String),
+    /// The register is used to address locals, so cannot be bound to one
+    ReservedRegister(String)
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmError::WrongArch(arch) => write!(f, "Assembly for {} cannot be built for x86_64", arch.name()),
+            AsmError::UnknownRegister(reg) => write!(f, "Unknown register '{}'", reg),
+            AsmError::UnknownInstruction(mnemonic) => write!(f, "Unknown instruction '{}'", mnemonic),
+            AsmError::InvalidOperands(ins) => write!(f, "Invalid operands in '{}'", ins),
+            AsmError::ReservedRegister(reg) => write!(f, "Register '{}' is reserved, and cannot be bound to a variable", reg)
+        }
+    }
+}
+
+/// Finds the register with the given name, as written in Intel syntax. The high byte registers and the instruction
+/// pointer cannot be named.
+pub fn reg_for_name(name: &str) -> Option<x86::Reg> {
+    use x86::Reg::*;
+
+    Some(match name {
+        "rax" => Rax, "eax" => Eax, "ax" => Ax, "al" => Al,
+        "rbx" => Rbx, "ebx" => Ebx, "bx" => Bx, "bl" => Bl,
+        "rcx" => Rcx, "ecx" => Ecx, "cx" => Cx, "cl" => Cl,
+        "rdx" => Rdx, "edx" => Edx, "dx" => Dx, "dl" => Dl,
+        "rbp" => Rbp, "ebp" => Ebp, "bp" => Bp, "bpl" => Bpl,
+        "rsp" => Rsp, "esp" => Esp, "sp" => Sp, "spl" => Spl,
+        "rsi" => Rsi, "esi" => Esi, "si" => Si, "sil" => Sil,
+        "rdi" => Rdi, "edi" => Edi, "di" => Di, "dil" => Dil,
+        "r8" => R8, "r8d" => R8D, "r8w" => R8W, "r8b" => R8B,
+        "r9" => R9, "r9d" => R9D, "r9w" => R9W, "r9b" => R9B,
+        "r10" => R10, "r10d" => R10D, "r10w" => R10W, "r10b" => R10B,
+        "r11" => R11, "r11d" => R11D, "r11w" => R11W, "r11b" => R11B,
+        "r12" => R12, "r12d" => R12D, "r12w" => R12W, "r12b" => R12B,
+        "r13" => R13, "r13d" => R13D, "r13w" => R13W, "r13b" => R13B,
+        "r14" => R14, "r14d" => R14D, "r14w" => R14W, "r14b" => R14B,
+        "r15" => R15, "r15d" => R15D, "r15w" => R15W, "r15b" => R15B,
+        _ => return None
+    })
+}
+
+fn reg(name: &str) -> Result<x86::Reg, AsmError> {
+    match reg_for_name(name) {
+        Some(reg) => Ok(reg),
+        None => Err(AsmError::UnknownRegister(name.to_string()))
+    }
+}
+
+/// Addresses are always 64 bit, so the base and index must be named by their 64 bit registers
+fn address_reg(name: &str, ins: &ir::AsmIns) -> Result<x86::RegClass, AsmError> {
+    let reg = reg(name)?;
+    if reg.size() != x86::Size::Quad { return Err(AsmError::InvalidOperands(ins.to_string())); }
+    Ok(reg.class())
+}
+
+fn mem(base: &str, index: &Option<(String, u8)>, offset: i64, ins: &ir::AsmIns) -> Result<x86::Mem, AsmError> {
+    let mut mem = x86::Mem::new().base(address_reg(base, ins)?);
+
+    if let Some((index, scale)) = index {
+        let index = address_reg(index, ins)?;
+        if index == x86::RegClass::Esp { return Err(AsmError::InvalidOperands(ins.to_string())); }
+
+        mem = mem.index(index).scale(match scale {
+            1 => 0,
+            2 => 1,
+            4 => 2,
+            8 => 3,
+            _ => return Err(AsmError::InvalidOperands(ins.to_string()))
+        });
+    }
+
+    if offset != 0 {
+        if offset != offset as i32 as i64 { return Err(AsmError::InvalidOperands(ins.to_string())); }
+        mem = mem.disp(offset);
+    }
+
+    Ok(mem)
+}
+
+/// Immediates other than those moved into a register are encoded in at most 32 bits, and sign extended to the register
+fn imm(imm: i64, size: x86::Size, ins: &ir::AsmIns) -> Result<u64, AsmError> {
+    let fits = match size {
+        x86::Size::Byte => imm >= i8::MIN as i64 && imm <= u8::MAX as i64,
+        x86::Size::Word => imm >= i16::MIN as i64 && imm <= u16::MAX as i64,
+        x86::Size::Double => imm >= i32::MIN as i64 && imm <= u32::MAX as i64,
+        x86::Size::Quad => imm >= i32::MIN as i64 && imm <= i32::MAX as i64,
+    };
+
+    match fits {
+        true => Ok(imm as u64),
+        false => Err(AsmError::InvalidOperands(ins.to_string()))
+    }
+}
+
+/// Assembles a single instruction of a block into the equivalent encoder instruction
+pub fn assemble(ins: &ir::AsmIns) -> Result<x86::Ins, AsmError> {
+    use ir::AsmOperand::*;
+
+    let invalid = || AsmError::InvalidOperands(ins.to_string());
+
+    let ops = ins.operands().as_slice();
+    // The sizes of two register operands must always match
+    if let [Reg(a), Reg(b)] = ops {
+        if reg(a)?.size() != reg(b)?.size() { return Err(invalid()); }
+    }
+
+    Ok(match (ins.mnemonic(), ops) {
+        ("mov", [Reg(a), Reg(b)]) => x86::Ins::MovRegReg(reg(a)?, reg(b)?),
+        ("mov", [Reg(r), Mem(b, i, o)]) => x86::Ins::MovRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("mov", [Mem(b, i, o), Reg(r)]) => x86::Ins::MovMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("mov", [Reg(r), Imm(i)]) => {
+            let r = reg(r)?;
+            match r.size() {
+                x86::Size::Quad => x86::Ins::MovRegImm(r, *i as u64),
+                size => x86::Ins::MovRegImm(r, imm(*i, size, ins)?)
+            }
+        },
+
+        ("add", [Reg(a), Reg(b)]) => x86::Ins::AddRegReg(reg(a)?, reg(b)?),
+        ("add", [Reg(r), Mem(b, i, o)]) => x86::Ins::AddRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("add", [Mem(b, i, o), Reg(r)]) => x86::Ins::AddMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("add", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::AddRegImm(r, imm(*i, r.size(), ins)?) },
+
+        ("sub", [Reg(a), Reg(b)]) => x86::Ins::SubRegReg(reg(a)?, reg(b)?),
+        ("sub", [Reg(r), Mem(b, i, o)]) => x86::Ins::SubRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("sub", [Mem(b, i, o), Reg(r)]) => x86::Ins::SubMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("sub", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::SubRegImm(r, imm(*i, r.size(), ins)?) },
+
+        ("and", [Reg(a), Reg(b)]) => x86::Ins::AndRegReg(reg(a)?, reg(b)?),
+        ("and", [Reg(r), Mem(b, i, o)]) => x86::Ins::AndRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("and", [Mem(b, i, o), Reg(r)]) => x86::Ins::AndMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("and", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::AndRegImm(r, imm(*i, r.size(), ins)?) },
+
+        ("or", [Reg(a), Reg(b)]) => x86::Ins::OrRegReg(reg(a)?, reg(b)?),
+        ("or", [Reg(r), Mem(b, i, o)]) => x86::Ins::OrRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("or", [Mem(b, i, o), Reg(r)]) => x86::Ins::OrMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("or", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::OrRegImm(r, imm(*i, r.size(), ins)?) },
+
+        ("cmp", [Reg(a), Reg(b)]) => x86::Ins::CmpRegReg(reg(a)?, reg(b)?),
+        ("cmp", [Reg(r), Mem(b, i, o)]) => x86::Ins::CmpRegMem(reg(r)?, mem(b, i, *o, ins)?),
+        ("cmp", [Mem(b, i, o), Reg(r)]) => x86::Ins::CmpMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("cmp", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::CmpRegImm(r, imm(*i, r.size(), ins)?) },
+
+        ("test", [Reg(a), Reg(b)]) => x86::Ins::TestRegReg(reg(a)?, reg(b)?),
+        ("test", [Mem(b, i, o), Reg(r)]) => x86::Ins::TestMemReg(mem(b, i, *o, ins)?, reg(r)?),
+        ("test", [Reg(r), Imm(i)]) => { let r = reg(r)?; x86::Ins::TestRegImm(r, imm(*i, r.size(), ins)?) },
+
+        // There is no 8 bit form of the two operand imul
+        ("imul", [Reg(a), Reg(b)]) if reg(a)?.size() != x86::Size::Byte => x86::Ins::IMulRegReg(reg(a)?, reg(b)?),
+        ("imul", [Reg(r), Mem(b, i, o)]) if reg(r)?.size() != x86::Size::Byte => x86::Ins::IMulRegMem(reg(r)?, mem(b, i, *o, ins)?),
+
+        ("neg", [Reg(r)]) => x86::Ins::NegReg(reg(r)?),
+
+        ("lea", [Reg(r), Mem(b, i, o)]) if reg(r)?.size() == x86::Size::Quad => x86::Ins::LeaRegMem(reg(r)?, mem(b, i, *o, ins)?),
+
+        // The stack can only hold full registers
+        ("push", [Reg(r)]) if reg(r)?.size() == x86::Size::Quad => x86::Ins::PushReg(reg(r)?),
+        ("push", [Imm(i)]) => x86::Ins::PushImm(imm(*i, x86::Size::Quad, ins)?),
+        ("pop", [Reg(r)]) if reg(r)?.size() == x86::Size::Quad => x86::Ins::PopReg(reg(r)?),
+
+        ("syscall", []) => x86::Ins::Syscall,
+
+        ("mov" | "add" | "sub" | "and" | "or" | "cmp" | "test" | "imul" | "neg" | "lea" | "push" | "pop" | "syscall", _) => return Err(invalid()),
+        (mnemonic, _) => return Err(AsmError::UnknownInstruction(mnemonic.to_string()))
+    })
+}
+
+/// Every register the block reads or writes, including those it is bound to
+pub fn registers_of_block(block: &ir::AsmBlock) -> Result<Vec<x86::RegClass>, AsmError> {
+    let mut regs = Vec::new();
+
+    for binding in block.inputs().iter().chain(block.outputs().iter()) {
+        regs.push(reg(binding.reg())?.class());
+    }
+
+    for ins in block.code() {
+        for operand in ins.operands() {
+            match operand {
+                ir::AsmOperand::Reg(r) => regs.push(reg(r)?.class()),
+                ir::AsmOperand::Imm(_) => {},
+                ir::AsmOperand::Mem(base, index, _) => {
+                    regs.push(reg(base)?.class());
+                    if let Some((index, _)) = index {
+                        regs.push(reg(index)?.class());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(regs)
+}
+
+/// Checks that a block can be translated, which `TranslationContext::translate_function` does before translating any code
+pub fn check_asm_block(block: &ir::AsmBlock) -> Result<(), AsmError> {
+    if block.arch() != ir::AsmArch::X8664 { return Err(AsmError::WrongArch(block.arch())); }
+
+    for binding in block.inputs().iter().chain(block.outputs().iter()) {
+        match reg(binding.reg())?.class() {
+            x86::RegClass::Esp | x86::RegClass::Ebp => return Err(AsmError::ReservedRegister(binding.reg().to_string())),
+            _ => {}
+        }
+    }
+
+    for ins in block.code() {
+        assemble(ins)?;
+    }
+
+    Ok(())
+}
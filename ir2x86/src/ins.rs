@@ -31,8 +31,8 @@ impl TranslationContext {
             insns.push(x86::Ins::AddRegImm(x86::Reg::Rsp, 8));
         }
 
-        // Move return values to new places on stack
-        for (i, ret) in ftc.unit().get_function(idx).unwrap().signature().returns().iter().enumerate() {
+        // Move return values to new places on stack, last first since they can only move up
+        for (i, ret) in ftc.unit().get_function(idx).unwrap().signature().returns().iter().enumerate().rev() {
             insns.push(x86::Ins::MovRegReg(
                 ftc.stack_ref().at_vt(ftc.stack_ref().size() + i, ret),
                 crate::util::reg_for_value_type(ret, self.mode, crate::registerify::SYS_V_ABI_RET[i]),
@@ -47,6 +47,25 @@ impl TranslationContext {
         }
     }
 
+    /// Pushes the size of the type being allocated, or a pointer to its layout descriptor if targeting a garbage collected runtime
+    fn push_alloc_argument(&self, st: &ir::StorableType, ftc: &mut FunctionTranslationContext, insns: &mut Vec<x86::Ins>) {
+        match &self.layouts {
+            Some(layouts) => {
+                let idx = layouts.find(st).expect("Allocated type missing from layout table");
+                insns.push(x86::Ins::LeaRegGlobalSymbol(
+                    ftc.stack().push_ptr(),
+                    ftc.symbol_id_for_layout(idx)
+                ));
+            },
+            None => {
+                insns.push(x86::Ins::MovRegImm(
+                    ftc.stack().push_vt(&ir::ValueType::Ref(Box::new(ir::StorableType::Slice(Box::new(st.clone()))))),
+                    crate::util::size_for_storable_type(st, self.mode) as u64
+                ));
+            }
+        }
+    }
+
     fn addr_in_path(&self, path: &ir::ValuePath, ftc: &mut FunctionTranslationContext, insns: &mut Vec<x86::Ins>) {
         match path.origin() {
             ir::ValuePathOrigin::Local(local, _local_type) => {
@@ -134,22 +153,24 @@ impl TranslationContext {
                     val
                 ));
             },
+            ir::Ins::PopLocal(idx, vt) => {
+                let val = ftc.stack().pop_vt(vt);
+
+                ins.push(x86::Ins::MovMemReg(
+                    ftc.local_mem(*idx),
+                    val
+                ));
+            },
             ir::Ins::Index(_) => {
                 // Do nothing, will be handled in addr_in_path
             },
             ir::Ins::New(st) => {
-                ins.push(x86::Ins::MovRegImm(
-                    ftc.stack().push_vt(&ir::ValueType::Ref(Box::new(ir::StorableType::Slice(Box::new(st.clone()))))),
-                    crate::util::size_for_storable_type(st, self.mode) as u64
-                ));
+                self.push_alloc_argument(st, ftc, ins);
 
                 self.insert_call(ftc.unit().find_alloc().expect("No alloc implementation included"), ftc, ins);
             },
             ir::Ins::NewSlice(st) => {
-                ins.push(x86::Ins::MovRegImm(
-                    ftc.stack().push_vt(&ir::ValueType::Ref(Box::new(ir::StorableType::Slice(Box::new(st.clone()))))),
-                    crate::util::size_for_storable_type(st, self.mode) as u64
-                ));
+                self.push_alloc_argument(st, ftc, ins);
 
                 self.insert_call(ftc.unit().find_alloc_slice().expect("No alloc slice implementation included"), ftc, ins);
             },
@@ -171,9 +192,9 @@ impl TranslationContext {
                 let size_a = crate::util::size_for_value_type(from, self.mode);
                 let size_b = crate::util::size_for_value_type(to, self.mode);
 
-                // Only need to do anything if promoting to a higher size
+                // Only need to do anything if promoting to a higher size, which extends by the signedness of the value promoted
                 if size_b > size_a {
-                    if to.is_signed() {
+                    if from.is_signed() {
                         ins.push(x86::Ins::MovsxRegReg(ftc.stack().peek_vt(to), ftc.stack().peek_vt(from)));
                     } else if size_a != 4 { // No need to zero extend from 32 bits, as this has already happened (I think?)
                         ins.push(x86::Ins::MovzxRegReg(ftc.stack().peek_vt(to), ftc.stack().peek_vt(from)));
@@ -220,16 +241,13 @@ impl TranslationContext {
                 ));
             },
             ir::Ins::Mul(vt) => {
-                if vt.is_signed() {
-                    let b = ftc.stack().pop_vt(vt);
-                    let a = ftc.stack().peek_vt(vt);
-                    // a = a * b
-                    ins.push(x86::Ins::IMulRegReg(
-                        a, b,
-                    ));
-                } else {
-                    todo!()
-                }
+                // The truncated product is the same whether or not the operands are signed, so imul works for both
+                let b = ftc.stack().pop_vt(vt);
+                let a = ftc.stack().peek_vt(vt);
+                // a = a * b
+                ins.push(x86::Ins::IMulRegReg(
+                    a, b,
+                ));
             },
             ir::Ins::Div(vt) => {
                 let b = ftc.stack().pop_vt(vt);
@@ -243,25 +261,29 @@ impl TranslationContext {
                 let uses_rcx = ftc.stack().uses(x86::RegClass::Ecx);
                 if uses_rcx { ins.push(x86::Ins::PushReg(x86::Reg::Rcx)); }
 
-                // ecx = b
-                ins.push(x86::Ins::MovRegReg(
-                    crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Ecx), b
-                ));
-
-                // eax = a 
-                ins.push(x86::Ins::MovRegReg(
-                    crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Eax), a
-                ));
-                ins.push(x86::Ins::Cdq(a.size()));
-                // eax = eax / ecx
+                // A byte division divides ax rather than edx:eax, so bytes are extended and divided as doubles instead
+                let (divisor, dividend) = if a.size() == x86::Size::Byte {
+                    let extend = if vt.is_signed() { x86::Ins::MovsxRegReg } else { x86::Ins::MovzxRegReg };
+                    ins.push(extend(x86::RegClass::Ecx.u32(), b));
+                    ins.push(extend(x86::RegClass::Eax.u32(), a));
+                    (x86::RegClass::Ecx.u32(), x86::RegClass::Eax.u32())
+                } else {
+                    let divisor = crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Ecx);
+                    let dividend = crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Eax);
+                    // ecx = b
+                    ins.push(x86::Ins::MovRegReg(divisor, b));
+                    // eax = a
+                    ins.push(x86::Ins::MovRegReg(dividend, a));
+                    (divisor, dividend)
+                };
+
+                // eax = eax / ecx, the dividend is edx:eax so edx must be extended from eax
                 if vt.is_signed() {
-                        ins.push(x86::Ins::IDivReg(
-                        crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Ecx)
-                    ));
+                    ins.push(x86::Ins::Cdq(dividend.size()));
+                    ins.push(x86::Ins::IDivReg(divisor));
                 } else {
-                    ins.push(x86::Ins::DivReg(
-                        crate::util::reg_for_value_type(vt, self.mode, x86::RegClass::Ecx)
-                    ));
+                    ins.push(x86::Ins::MovRegImm(x86::RegClass::Edx.u32(), 0));
+                    ins.push(x86::Ins::DivReg(divisor));
                 }
 
                 // a = eax
@@ -419,41 +441,188 @@ impl TranslationContext {
 
                 ins.push(x86::Ins::LocalSymbol(end));
             },
-            ir::Ins::IfElse(true_then, false_then, condition) => {
+            ir::Ins::IfElse(vt, true_then, false_then, condition) => {
                 for inner_ins in condition {
                     self.translate_instruction_to(inner_ins, ftc, ins);
                 }
 
-                let cond = ftc.stack().pop_vt(&ir::ValueType::Bool);
-                ins.push(x86::Ins::TestRegReg(cond, cond));
+                // If both branches just compute a value, compute both and pick one with a cmov instead of branching
+                if vt.is_some() && true_then.iter().chain(false_then.iter()).all(|inner_ins| inner_ins.is_side_effect_free()) {
+                    for inner_ins in true_then.iter().chain(false_then.iter()) {
+                        self.translate_instruction_to(inner_ins, ftc, ins);
+                    }
+
+                    let false_value = ftc.stack().pop_ptr();
+                    let true_value = ftc.stack().pop_ptr();
+                    let cond = ftc.stack().pop_vt(&ir::ValueType::Bool);
+                    ins.push(x86::Ins::TestRegReg(cond, cond));
+                    ins.push(x86::Ins::CMovRegReg(x86::Condition::Zero, true_value, false_value));
+                    ins.push(x86::Ins::MovRegReg(ftc.stack().push_ptr(), true_value));
+                } else {
+                    let cond = ftc.stack().pop_vt(&ir::ValueType::Bool);
+                    ins.push(x86::Ins::TestRegReg(cond, cond));
                 
-                let end = ftc.new_local_symbol();
-                let false_start = ftc.new_local_symbol();
+                    let end = ftc.new_local_symbol();
+                    let false_start = ftc.new_local_symbol();
 
-                // if false, jump to false_start...
-                ins.push(x86::Ins::JumpConditionalLocalSymbol(x86::Condition::Zero, false_start));
+                    // if false, jump to false_start...
+                    ins.push(x86::Ins::JumpConditionalLocalSymbol(x86::Condition::Zero, false_start));
 
-                // otherwise (if) true, continue...
-                ftc.local_symbols().push(LocalSymbol::If);
-                for inner_ins in true_then {
+                    // otherwise (if) true, continue...
+                    let depth = ftc.stack_ref().size();
+                    ftc.local_symbols().push(LocalSymbol::If);
+                    for inner_ins in true_then {
+                        self.translate_instruction_to(inner_ins, ftc, ins);
+                    }
+                    ftc.local_symbols().pop();
+                    // then jump to the end
+                    ins.push(x86::Ins::JumpLocalSymbol(end));
+
+                    // If the branch left a value, the else branch leaves its value in the same register
+                    let values = ftc.stack_ref().size().saturating_sub(depth);
+                    ftc.stack().pop_many(values);
+
+                    ins.push(x86::Ins::LocalSymbol(false_start));
+                    ftc.local_symbols().push(LocalSymbol::If);
+                    for inner_ins in false_then {
+                        self.translate_instruction_to(inner_ins, ftc, ins);
+                    }
+                    ftc.local_symbols().pop();
+                    // just continue to end
+
+                    ins.push(x86::Ins::LocalSymbol(end));
+                }
+            },
+            ir::Ins::Switch(vt, cases, default, value) => {
+                for inner_ins in value {
                     self.translate_instruction_to(inner_ins, ftc, ins);
                 }
-                ftc.local_symbols().pop();
-                // then jump to the end
-                ins.push(x86::Ins::JumpLocalSymbol(end));
 
-                ins.push(x86::Ins::LocalSymbol(false_start));
+                let case_starts = cases.iter().map(|_| ftc.new_local_symbol()).collect::<Vec<_>>();
+                let default_start = ftc.new_local_symbol();
+                let end = ftc.new_local_symbol();
+
+                // The value is left on the stack until it has been dispatched on, so the registers used alongside it never overlap it
+                let value_reg = ftc.stack().peek_vt(vt);
+                match ir::switch_table_range(vt, cases) {
+                    Some((min, len)) => {
+                        let index = ftc.stack().push_ptr();
+                        let table = ftc.stack().push_ptr();
+
+                        // index = value - min, wrapping at the width of the value so anything below min is out of range too
+                        if min != 0 {
+                            if value_reg.size() == x86::Size::Quad && min as i64 != min as i32 as i64 {
+                                ins.push(x86::Ins::MovRegImm(index, min));
+                                ins.push(x86::Ins::SubRegReg(value_reg, index));
+                            } else {
+                                ins.push(x86::Ins::SubRegImm(value_reg, min));
+                            }
+                        }
+
+                        ins.push(match value_reg.size() {
+                            x86::Size::Byte | x86::Size::Word => x86::Ins::MovzxRegReg(index.class().u32(), value_reg),
+                            x86::Size::Double => x86::Ins::MovRegReg(index.class().u32(), value_reg),
+                            x86::Size::Quad => x86::Ins::MovRegReg(index, value_reg),
+                        });
+
+                        ins.push(x86::Ins::CmpRegImm(index, len as u64));
+                        ins.push(x86::Ins::JumpConditionalLocalSymbol(x86::Condition::AboveOrEqual, default_start));
+
+                        // The table holds the offset of each case from the start of the table, and is placed in the code straight after the
+                        // jump into it rather than in read only data, so it is never run and needs no relocations (see ir2x86.md)
+                        let table_start = ftc.new_local_symbol();
+                        ins.push(x86::Ins::LeaRegLocalSymbol(table, table_start));
+                        ins.push(x86::Ins::MovsxRegMem(x86::Size::Double, index, x86::Mem::new().base(table.class()).index(index.class()).scale(2)));
+                        ins.push(x86::Ins::AddRegReg(table, index));
+                        ins.push(x86::Ins::JumpReg(table));
+
+                        ins.push(x86::Ins::LocalSymbol(table_start));
+                        for i in 0..len {
+                            let case_value = min.wrapping_add(i as u64);
+                            let target = match cases.iter().position(|(values, _)| values.contains(&case_value)) {
+                                Some(case) => case_starts[case],
+                                None => default_start
+                            };
+                            ins.push(x86::Ins::JumpTableEntry(table_start, target));
+                        }
+
+                        ftc.stack().pop_many(3);
+                    },
+                    None => {
+                        // Only used for 64 bit values which don't fit into an immediate
+                        let scratch = ftc.stack().push_vt(vt);
+
+                        for (case_start, (values, _)) in case_starts.iter().zip(cases) {
+                            for case_value in values {
+                                if value_reg.size() == x86::Size::Quad && *case_value as i64 != *case_value as i32 as i64 {
+                                    ins.push(x86::Ins::MovRegImm(scratch, *case_value));
+                                    ins.push(x86::Ins::CmpRegReg(value_reg, scratch));
+                                } else {
+                                    ins.push(x86::Ins::CmpRegImm(value_reg, *case_value));
+                                }
+                                ins.push(x86::Ins::JumpConditionalLocalSymbol(x86::Condition::Zero, *case_start));
+                            }
+                        }
+                        ins.push(x86::Ins::JumpLocalSymbol(default_start));
+
+                        ftc.stack().pop_many(2);
+                    }
+                }
+
+                for (case_start, (_, code)) in case_starts.iter().zip(cases) {
+                    ins.push(x86::Ins::LocalSymbol(*case_start));
+                    ftc.local_symbols().push(LocalSymbol::If);
+                    for inner_ins in code {
+                        self.translate_instruction_to(inner_ins, ftc, ins);
+                    }
+                    ftc.local_symbols().pop();
+                    ins.push(x86::Ins::JumpLocalSymbol(end));
+                }
+
+                ins.push(x86::Ins::LocalSymbol(default_start));
                 ftc.local_symbols().push(LocalSymbol::If);
-                for inner_ins in false_then {
+                for inner_ins in default {
                     self.translate_instruction_to(inner_ins, ftc, ins);
                 }
                 ftc.local_symbols().pop();
-                // just continue to end
 
                 ins.push(x86::Ins::LocalSymbol(end));
             },
-            ir::Ins::Break(_) => todo!(),
-            ir::Ins::Continue(_) => todo!(),
+            ir::Ins::Asm(block) => {
+                // The stack is empty before a block, so it is free to use any register
+                for reg in crate::asm::registers_of_block(block).unwrap() {
+                    ftc.stack().clobber(reg);
+                }
+
+                for input in block.inputs() {
+                    let class = crate::asm::reg_for_name(input.reg()).unwrap().class();
+                    ins.push(x86::Ins::MovRegMem(
+                        crate::util::reg_for_value_type(input.value_type(), self.mode, class),
+                        ftc.local_mem(input.local())
+                    ));
+                }
+
+                for asm_ins in block.code() {
+                    ins.push(crate::asm::assemble(asm_ins).unwrap());
+                }
+
+                for output in block.outputs() {
+                    let class = crate::asm::reg_for_name(output.reg()).unwrap().class();
+                    ins.push(x86::Ins::MovMemReg(
+                        ftc.local_mem(output.local()),
+                        crate::util::reg_for_value_type(output.value_type(), self.mode, class)
+                    ));
+                }
+            },
+            // The stack is empty within the loop when breaking or continuing, so it is as it was at the start of the loop
+            ir::Ins::Break(depth) => {
+                let (_, end) = ftc.local_symbols().loop_at(*depth).expect("Break does not refer to a loop");
+                ins.push(x86::Ins::JumpLocalSymbol(end));
+            },
+            ir::Ins::Continue(depth) => {
+                let (start, _) = ftc.local_symbols().loop_at(*depth).expect("Continue does not refer to a loop");
+                ins.push(x86::Ins::JumpLocalSymbol(start));
+            },
             ir::Ins::PushLiteral(vt, val) => {
                 ins.push(x86::Ins::MovRegImm(
                     ftc.stack().push_vt(vt),
@@ -0,0 +1,141 @@
+/// Describes the memory layout of a heap allocated type, as needed by a tracing garbage collector.
+/// Every offset in `ref_offsets` holds a pointer which may point into the heap, everything else is plain data.
+#[derive(Debug)]
+pub struct TypeLayout {
+    size: usize,
+    ref_offsets: Vec<usize>
+}
+
+impl TypeLayout {
+    pub fn new(st: &ir::StorableType, mode: x86::Mode) -> TypeLayout {
+        let mut ref_offsets = Vec::new();
+        append_ref_offsets(st, 0, mode, &mut ref_offsets);
+
+        TypeLayout {
+            size: crate::util::size_for_storable_type(st, mode),
+            ref_offsets
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn ref_offsets(&self) -> &Vec<usize> {
+        &self.ref_offsets
+    }
+
+    pub fn ref_offset_count(&self) -> usize {
+        self.ref_offsets.len()
+    }
+
+    /// Encodes the layout as a descriptor, which is an array of pointer sized words, laid out as
+    /// `size, ref_offset_count, ref_offsets...`
+    pub fn encode(&self, mode: x86::Mode) -> Vec<u8> {
+        let mut words = vec![self.size, self.ref_offset_count()];
+        words.extend(&self.ref_offsets);
+
+        let mut data = Vec::new();
+        for word in words {
+            match mode {
+                x86::Mode::X86 => data.extend((word as u32).to_le_bytes().to_vec()),
+                x86::Mode::X8664 => data.extend((word as u64).to_le_bytes().to_vec()),
+            }
+        }
+
+        data
+    }
+}
+
+fn append_ref_offsets(st: &ir::StorableType, base: usize, mode: x86::Mode, offsets: &mut Vec<usize>) {
+    match st {
+        ir::StorableType::Compound(ct) => match ct.content() {
+            ir::CompoundContent::Struct(s) => {
+                for (p, prop) in s.props().iter().enumerate() {
+                    append_ref_offsets(
+                        prop.prop_type(),
+                        base + crate::util::offset_of_compound_property(ct, ir::PropertyIndex::new(p), mode),
+                        mode, offsets
+                    );
+                }
+            }
+        },
+        ir::StorableType::Value(vt) => if let ir::ValueType::Ref(_) = vt {
+            offsets.push(base);
+        },
+        // Only the data pointer needs to be traced, the length is plain data
+        ir::StorableType::Slice(_) => offsets.push(base),
+        ir::StorableType::SliceData(_) => panic!("Cannot compute layout of SliceData type"),
+    }
+}
+
+/// Every type allocated by a `New` or `NewSlice` instruction in the unit, along with its layout. When this is passed to a
+/// `TranslationContext`, the alloc and alloc_slice functions are given a pointer to the descriptor of the type being
+/// allocated, rather than its size.
+pub struct LayoutTable {
+    types: Vec<ir::StorableType>,
+    layouts: Vec<TypeLayout>
+}
+
+impl LayoutTable {
+    pub fn new(unit: &ir::TranslationUnit, mode: x86::Mode) -> LayoutTable {
+        let mut table = LayoutTable {
+            types: Vec::new(),
+            layouts: Vec::new()
+        };
+
+        for func in unit.functions() {
+            if !func.is_extern() {
+                table.append_from_code(func.code(), mode);
+            }
+        }
+
+        table
+    }
+
+    fn append_from_code(&mut self, code: &[ir::Ins], mode: x86::Mode) {
+        for ins in code {
+            match ins {
+                ir::Ins::New(st) | ir::Ins::NewSlice(st) if self.find(st).is_none() => {
+                    self.layouts.push(TypeLayout::new(st, mode));
+                    self.types.push(st.clone());
+                },
+                ir::Ins::Loop(body, condition, increment) => {
+                    self.append_from_code(body, mode);
+                    self.append_from_code(condition, mode);
+                    self.append_from_code(increment, mode);
+                },
+                ir::Ins::If(body, condition) => {
+                    self.append_from_code(body, mode);
+                    self.append_from_code(condition, mode);
+                },
+                ir::Ins::IfElse(_, true_body, false_body, condition) => {
+                    self.append_from_code(true_body, mode);
+                    self.append_from_code(false_body, mode);
+                    self.append_from_code(condition, mode);
+                },
+                ir::Ins::Switch(_, cases, default, value) => {
+                    for (_, code) in cases {
+                        self.append_from_code(code, mode);
+                    }
+                    self.append_from_code(default, mode);
+                    self.append_from_code(value, mode);
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Find the index of the layout for the given type
+    pub fn find(&self, st: &ir::StorableType) -> Option<usize> {
+        self.types.iter().position(|x| x == st)
+    }
+
+    pub fn layouts(&self) -> &Vec<TypeLayout> {
+        &self.layouts
+    }
+
+    pub fn layout_count(&self) -> usize {
+        self.layouts.len()
+    }
+}
@@ -2,5 +2,9 @@ mod registerify;
 mod ins;
 mod unit;
 mod util;
+mod layout;
+mod asm;
 
-pub use unit::*;
\ No newline at end of file
+pub use unit::*;
+pub use layout::*;
+pub use asm::*;
\ No newline at end of file
@@ -85,6 +85,12 @@ impl StackToReg {
         }
     }
 
+    /// Marks a register as written by code other than the stack, such as an inline assembly block, so that it is saved
+    /// by the function if it is callee saved
+    pub fn clobber(&mut self, reg: x86::RegClass) {
+        self.does_use_reg(reg);
+    }
+
     pub fn push(&mut self) -> x86::RegClass {
         self.idx += 1;
 
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use crate::registerify::{StackToReg, SYS_V_CALLEE_SAVED};
+use crate::{AsmError, LayoutTable, check_asm_block};
+use crate::registerify::{StackToReg, SYS_V_ABI_RET, SYS_V_CALLEE_SAVED};
 
 pub(crate) enum LocalSymbol {
     If,
@@ -26,6 +27,14 @@ impl LocalSymbolStack {
     pub(crate) fn pop(&mut self) {
         self.symbols.pop();
     }
+
+    /// The start and end of the loop at the given depth above the current block, if there is a loop there
+    pub(crate) fn loop_at(&self, depth: ir::BlockMoveDepth) -> Option<(x86::LocalSymbolID, x86::LocalSymbolID)> {
+        match self.symbols.len().checked_sub(depth + 1).and_then(|idx| self.symbols.get(idx)) {
+            Some(LocalSymbol::Loop(start, end)) => Some((*start, *end)),
+            _ => None
+        }
+    }
 }
 
 pub struct FunctionTranslationContext<'a> {
@@ -81,8 +90,11 @@ impl<'a> FunctionTranslationContext<'a> {
 
         assert!(self.function.local_count() > idx.idx());
 
+        // Locals grow down from the base pointer, so each one ends at a multiple of its alignment below it
         for i in &self.function.locals()[0..idx.idx() + 1] {
+            let align = crate::util::align_for_storable_type(i.local_type(), self.mode) as u64;
             addr += crate::util::size_for_storable_type(i.local_type(), self.mode) as u64;
+            addr = (addr + align - 1) & !(align - 1);
         }
 
         addr
@@ -99,6 +111,10 @@ impl<'a> FunctionTranslationContext<'a> {
     pub(crate) fn symbol_id_for_global(&self, idx: ir::GlobalIndex) -> x86::GlobalSymbolID {
         x86::GlobalSymbolID::new(self.unit.function_count() + idx.idx())
     }
+
+    pub(crate) fn symbol_id_for_layout(&self, idx: usize) -> x86::GlobalSymbolID {
+        x86::GlobalSymbolID::new(self.unit.function_count() + self.unit.global_count() + idx)
+    }
 }
 
 pub enum GlobalObjectId {
@@ -127,6 +143,11 @@ impl<'a> GlobalIDAllocator<'a> {
         x86::GlobalSymbolID::new(self.unit.function_count() + global.idx())
     }
 
+    /// Layout descriptors are numbered after all functions and globals, see `LayoutTable`
+    pub fn global_id_of_layout(&self, layout: usize) -> x86::GlobalSymbolID {
+        x86::GlobalSymbolID::new(self.unit.function_count() + self.unit.global_count() + layout)
+    }
+
     pub fn push_global_symbol_mapping(&mut self, global: x86::GlobalSymbolID, symbol: usize, addend: i64) {
         self.symbol_ids.insert(global, (symbol, addend));
     }
@@ -148,31 +169,80 @@ impl<'a> GlobalIDAllocator<'a> {
 }
 
 pub struct TranslationContext {
-    pub(crate) mode: x86::Mode
+    pub(crate) mode: x86::Mode,
+    pub(crate) layouts: Option<LayoutTable>
 }
 
 impl TranslationContext {
     pub fn new(mode: x86::Mode) -> TranslationContext {
         TranslationContext {
-            mode
+            mode,
+            layouts: None
         }
     }
 
-    pub fn translate_function(&self, func: &ir::Function, unit: &ir::TranslationUnit) -> Vec<x86::Ins> {
+    /// Creates a context targeting a garbage collected runtime, where allocations are passed a pointer to the layout
+    /// descriptor of the allocated type instead of its size. The descriptors must be emitted by the caller, using
+    /// `GlobalIDAllocator::global_id_of_layout` for their symbols.
+    pub fn new_collected(mode: x86::Mode, layouts: LayoutTable) -> TranslationContext {
+        TranslationContext {
+            mode,
+            layouts: Some(layouts)
+        }
+    }
+
+    pub fn layouts(&self) -> Option<&LayoutTable> {
+        self.layouts.as_ref()
+    }
+
+    /// Translates the code of a function, failing if any inline assembly in it cannot be assembled for x86-64
+    pub fn translate_function(&self, func: &ir::Function, unit: &ir::TranslationUnit) -> Result<Vec<x86::Ins>, AsmError> {
         if func.is_extern() { panic!("Cannot translate extern function"); }
 
-        let mut x86_ins = Vec::new();
+        // IR which did not come through the NL frontend may hold any assembly, so it is checked before anything is translated
+        for block in ir::asm_blocks(func.code()) {
+            check_asm_block(block)?;
+        }
 
         let mut ftc = FunctionTranslationContext::new(self.mode, func, unit);
 
+        let mut code = Vec::new();
+        for ins in func.code() {
+            self.translate_instruction_to(ins, &mut ftc, &mut code);
+        }
+
+        // Registers holding return values must not be restored. Ebx is callee saved but used for the third return value,
+        // this is fine since functions with that many returns are only ever called from generated code.
+        let return_regs = &SYS_V_ABI_RET[..func.signature().return_count()];
+        let saved: Vec<x86::Reg> = SYS_V_CALLEE_SAVED.iter().enumerate()
+            .filter(|(i, regclass)| ftc.stack().clobbered() & (1 << i) != 0 && !return_regs.contains(regclass))
+            .map(|(_, regclass)| regclass.uptr(&self.mode))
+            .collect();
+
+        // Saved registers are kept in the frame below the locals, rather than pushed before the frame pointer, so the
+        // return address is always directly above the saved frame pointer for anything walking the chain
+        let ptr_size = self.mode.ptr_size() as u64;
+        let locals_size = match func.last_local_index() {
+            Some(last) => (ftc.local_addr(last) + ptr_size - 1) & !(ptr_size - 1),
+            None => 0
+        };
+        let saved_mem = |i: usize| x86::Mem::new().base(x86::RegClass::Ebp).disp(-((locals_size + (i as u64 + 1) * ptr_size) as i64));
+        let frame_size = locals_size + saved.len() as u64 * ptr_size;
+
+        let mut x86_ins = Vec::new();
+
         x86_ins.push(x86::Ins::PushReg(self.mode.base_ptr()));
         x86_ins.push(x86::Ins::MovRegReg(self.mode.base_ptr(), self.mode.stack_ptr()));
         
-        if func.local_count() > 0 {
-            x86_ins.push(x86::Ins::SubRegImm(self.mode.stack_ptr(), ftc.local_addr(func.last_local_index().unwrap())));
+        if frame_size > 0 {
+            x86_ins.push(x86::Ins::SubRegImm(self.mode.stack_ptr(), frame_size));
         }
 
         x86_ins.push(x86::Ins::AndRegImm(self.mode.stack_ptr(), (-16 as i64) as u64));
+
+        for (i, reg) in saved.iter().enumerate() {
+            x86_ins.push(x86::Ins::MovMemReg(saved_mem(i), *reg));
+        }
         
         // Put params into locals
         for (p, param) in func.signature().params().iter().enumerate() {
@@ -182,28 +252,24 @@ impl TranslationContext {
             ));
         }
 
-        for ins in func.code() {
-            self.translate_instruction_to(ins, &mut ftc, &mut x86_ins);
-        }
+        x86_ins.extend(code);
 
         // Root is always 0
         x86_ins.push(x86::Ins::LocalSymbol(x86::LocalSymbolID::new(0)));
-        x86_ins.push(x86::Ins::MovRegReg(self.mode.stack_ptr(), self.mode.base_ptr()));
-        x86_ins.push(x86::Ins::PopReg(self.mode.base_ptr()));
 
-        for (i, regclass) in SYS_V_CALLEE_SAVED.iter().enumerate() {
-            if ftc.stack().clobbered() & (1 << i) != 0 {
-                x86_ins.insert(0, x86::Ins::PushReg(regclass.uptr(&self.mode)));
-                x86_ins.push(x86::Ins::PopReg(regclass.uptr(&self.mode)));
-            }
+        for (i, reg) in saved.iter().enumerate() {
+            x86_ins.push(x86::Ins::MovRegMem(*reg, saved_mem(i)));
         }
 
+        x86_ins.push(x86::Ins::MovRegReg(self.mode.stack_ptr(), self.mode.base_ptr()));
+        x86_ins.push(x86::Ins::PopReg(self.mode.base_ptr()));
+
         x86_ins.push(x86::Ins::Ret);
 
-        x86_ins
+        Ok(x86_ins)
     }
 
-    fn translate_storable(&self, storable: &ir::StorableValue, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<x86::Relocation>, offset: usize, section_offset: usize, addend: i64) -> Vec<u8> {
+    fn translate_storable(&self, storable: &ir::StorableValue, storable_type: &ir::StorableType, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<x86::Relocation>, offset: usize, section_offset: usize, addend: i64) -> Vec<u8> {
         match storable {
             ir::StorableValue::Value(v) => match v {
                 ir::Value::U8(i) => i.to_le_bytes().to_vec(),
@@ -235,12 +301,19 @@ impl TranslationContext {
             ir::StorableValue::Compound(c) => {
                 match c {
                     ir::CompoundValue::Struct(s) => {
+                        let ct = match storable_type {
+                            ir::StorableType::Compound(ct) => ct,
+                            _ => panic!("Struct value for non-struct type")
+                        };
+                        let ir::CompoundContent::Struct(content) = ct.content();
                         let mut data = Vec::new();
 
-                        for p in s.props() {
-                            data.extend(self.translate_storable(p.value(), unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
+                        for ((p, prop), prop_offset) in s.props().iter().zip(content.props()).zip(ct.prop_offsets(self.mode.ptr_size())) {
+                            data.resize(prop_offset, 0);
+                            data.extend(self.translate_storable(p.value(), prop.prop_type(), unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
                         }
 
+                        data.resize(crate::util::size_for_storable_type(storable_type, self.mode), 0);
                         data
                     }
                 }
@@ -267,8 +340,13 @@ impl TranslationContext {
             ir::StorableValue::SliceData(values) => {
                 let mut data = Vec::new();
 
+                let element_type = match storable_type {
+                    ir::StorableType::SliceData(st) => st.as_ref(),
+                    _ => panic!("Slice data value for non-slice data type")
+                };
+
                 for value in values {
-                    data.extend(self.translate_storable(value, unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
+                    data.extend(self.translate_storable(value, element_type, unit, gid_allocator, relocs, offset + data.len(), section_offset, addend));
                 }
 
                 data
@@ -278,7 +356,7 @@ impl TranslationContext {
 
     pub fn translate_global(&self, global: &ir::Global, unit: &ir::TranslationUnit, gid_allocator: &GlobalIDAllocator, relocs: &mut Vec<x86::Relocation>, section_offset: usize, addend: i64) -> Vec<u8> {
         if let Some(default) = global.default() {
-            self.translate_storable(default, unit, gid_allocator, relocs, 0, section_offset, addend)
+            self.translate_storable(default, global.global_type(), unit, gid_allocator, relocs, 0, section_offset, addend)
         } else {
             vec![0; crate::util::size_for_storable_type(global.global_type(), self.mode)]
         }
@@ -0,0 +1,35 @@
+/// A function running the block inside an If, as assembly which did not come through the NL frontend can be anywhere
+fn function_with(block: ir::AsmBlock) -> ir::Function {
+    let mut func = ir::Function::new("main", ir::Signature::new(vec![ ], vec![ ]));
+
+    func.push(ir::Ins::If(vec![
+        ir::Ins::Asm(block)
+    ], vec![
+        ir::Ins::PushLiteral(ir::ValueType::Bool, 1)
+    ]));
+    func.push(ir::Ins::Ret);
+
+    func
+}
+
+#[test]
+fn invalid_asm() {
+    let mut unit = ir::TranslationUnit::new();
+    let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
+
+    let valid = unit.add_function(function_with(ir::AsmBlock::new(ir::AsmArch::X8664, vec![ ], vec![ ], vec![
+        ir::AsmIns::new("mov", vec![ ir::AsmOperand::Reg("rax".to_string()), ir::AsmOperand::Reg("rbx".to_string()) ])
+    ])));
+    let wrong_arch = unit.add_function(function_with(ir::AsmBlock::new(ir::AsmArch::Arm64, vec![ ], vec![ ], vec![
+        ir::AsmIns::new("nop", vec![ ])
+    ])));
+    let unknown = unit.add_function(function_with(ir::AsmBlock::new(ir::AsmArch::X8664, vec![ ], vec![ ], vec![
+        ir::AsmIns::new("mov", vec![ ir::AsmOperand::Reg("rax".to_string()), ir::AsmOperand::Reg("xmm0".to_string()) ])
+    ])));
+
+    // Errors are returned rather than panicking, so the caller can report them
+    assert!(ctx.translate_function(unit.get_function(valid).unwrap(), &unit).is_ok());
+    assert_eq!(ctx.translate_function(unit.get_function(wrong_arch).unwrap(), &unit).err(), Some(ir2x86::AsmError::WrongArch(ir::AsmArch::Arm64)));
+    assert_eq!(ctx.translate_function(unit.get_function(unknown).unwrap(), &unit).err(), Some(ir2x86::AsmError::UnknownRegister("xmm0".to_string())));
+    assert_eq!(ir2x86::AsmError::WrongArch(ir::AsmArch::Arm64).to_string(), "Assembly for arm64 cannot be built for x86_64");
+}
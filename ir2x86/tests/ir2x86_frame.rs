@@ -0,0 +1,38 @@
+#[test]
+fn callee_saved_in_frame() {
+    let mut unit = ir::TranslationUnit::new();
+    let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
+
+    // Keeping four values on the stack at once uses rbx, which is callee saved
+    let idx = unit.add_function({
+        let mut func = ir::Function::new("sum", ir::Signature::new(vec![ ir::ValueType::I32 ], vec![ ir::ValueType::I32 ]));
+        let l = func.push_local(ir::Local::new(ir::StorableType::Value(ir::ValueType::I32)));
+        for _ in 0..4 {
+            func.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(
+                ir::ValuePathOrigin::Local(l, ir::StorableType::Value(ir::ValueType::I32))
+            ), ir::ValueType::I32));
+            func.push(ir::Ins::Push(ir::ValueType::I32));
+        }
+        for _ in 0..3 {
+            func.push(ir::Ins::Add(ir::ValueType::I32));
+        }
+        func.push(ir::Ins::Ret);
+
+        func
+    });
+
+    let ins = ctx.translate_function(unit.get_function(idx).unwrap(), &unit).ok().unwrap();
+
+    // The frame pointer is pushed first, so the return address is directly above it for anything walking the chain
+    assert!(matches!(ins[0], x86::Ins::PushReg(x86::Reg::Rbp)));
+    assert!(matches!(ins[1], x86::Ins::MovRegReg(x86::Reg::Rbp, x86::Reg::Rsp)));
+    assert!(!ins.iter().any(|i| matches!(i, x86::Ins::PushReg(x86::Reg::Rbx) | x86::Ins::PopReg(x86::Reg::Rbx))));
+
+    // rbx is stored in the frame and loaded back before it is torn down
+    assert!(ins.iter().any(|i| matches!(i, x86::Ins::MovMemReg(_, x86::Reg::Rbx))));
+    let n = ins.len();
+    assert!(matches!(ins[n - 4], x86::Ins::MovRegMem(x86::Reg::Rbx, _)));
+    assert!(matches!(ins[n - 3], x86::Ins::MovRegReg(x86::Reg::Rsp, x86::Reg::Rbp)));
+    assert!(matches!(ins[n - 2], x86::Ins::PopReg(x86::Reg::Rbp)));
+    assert!(matches!(ins[n - 1], x86::Ins::Ret));
+}
@@ -0,0 +1,52 @@
+use ir;
+use ir2x86;
+
+#[test]
+fn compound_layout() {
+    let mut unit = ir::TranslationUnit::new();
+
+    let inner = ir::CompoundType::new("Inner", ir::CompoundContent::Struct({
+        let mut content = ir::StructContent::new();
+        content.push_prop(ir::StructProperty::new("a", ir::StorableType::Value(ir::ValueType::I32)));
+        content.push_prop(ir::StructProperty::new("data", ir::StorableType::Slice(Box::new(ir::StorableType::Value(ir::ValueType::U8)))));
+        content
+    }));
+    unit.add_type(inner.clone());
+
+    let outer = ir::CompoundType::new("Outer", ir::CompoundContent::Struct({
+        let mut content = ir::StructContent::new();
+        content.push_prop(ir::StructProperty::new("b", ir::StorableType::Value(ir::ValueType::U64)));
+        content.push_prop(ir::StructProperty::new("inner", ir::StorableType::Compound(inner.clone())));
+        content.push_prop(ir::StructProperty::new("next", ir::StorableType::Value(ir::ValueType::Ref(Box::new(ir::StorableType::Compound(inner.clone()))))));
+        content
+    }));
+    unit.add_type(outer.clone());
+
+    unit.add_function({
+        let mut func = ir::Function::new("main", ir::Signature::new(vec![ ], vec![ ]));
+
+        func.push(ir::Ins::New(ir::StorableType::Compound(outer.clone())));
+        func.push(ir::Ins::Drop);
+        func.push(ir::Ins::PushLiteral(ir::ValueType::UPtr, 4));
+        func.push(ir::Ins::NewSlice(ir::StorableType::Value(ir::ValueType::I32)));
+        func.push(ir::Ins::Drop);
+        func.push(ir::Ins::New(ir::StorableType::Compound(outer.clone())));
+        func.push(ir::Ins::Drop);
+        func.push(ir::Ins::Ret);
+
+        func
+    });
+
+    let table = ir2x86::LayoutTable::new(&unit, x86::Mode::X8664);
+    assert_eq!(table.layout_count(), 2);
+
+    // b: 0, inner.a: 8, inner.data: 12, next: 28
+    let layout = &table.layouts()[table.find(&ir::StorableType::Compound(outer.clone())).unwrap()];
+    assert_eq!(layout.size(), 36);
+    assert_eq!(layout.ref_offsets(), &vec![12, 28]);
+    assert_eq!(layout.encode(x86::Mode::X8664).len(), 4 * 8);
+
+    let layout = &table.layouts()[table.find(&ir::StorableType::Value(ir::ValueType::I32)).unwrap()];
+    assert_eq!(layout.size(), 4);
+    assert_eq!(layout.ref_offset_count(), 0);
+}
@@ -0,0 +1,66 @@
+/// The instructions of a function which switches on its only param, with a case for each list of values which does nothing
+fn translate_switch(vt: ir::ValueType, cases: Vec<Vec<u64>>) -> Vec<x86::Ins> {
+    let mut unit = ir::TranslationUnit::new();
+    let ctx = ir2x86::TranslationContext::new(x86::Mode::X8664);
+
+    let idx = unit.add_function({
+        let mut func = ir::Function::new("f", ir::Signature::new(vec![ vt.clone() ], vec![ ]));
+        let param = func.push_local(ir::Local::new(ir::StorableType::Value(vt.clone())));
+        func.push(ir::Ins::Switch(vt.clone(), cases.into_iter().map(|values| (values, vec![ ])).collect(), vec![ ], vec![
+            ir::Ins::PushPath(ir::ValuePath::new_origin_only(
+                ir::ValuePathOrigin::Local(param, ir::StorableType::Value(vt.clone()))
+            ), vt.clone()),
+            ir::Ins::Push(vt)
+        ]));
+        func.push(ir::Ins::Ret);
+
+        func
+    });
+
+    ctx.translate_function(unit.get_function(idx).unwrap(), &unit).ok().unwrap()
+}
+
+fn table_len(ins: &[x86::Ins]) -> usize {
+    ins.iter().filter(|i| matches!(i, x86::Ins::JumpTableEntry(_, _))).count()
+}
+
+/// The values compared against in turn, whether they fit into an immediate or are moved into a register first
+fn compared_values(ins: &[x86::Ins]) -> Vec<u64> {
+    ins.windows(2).filter_map(|w| match w {
+        [ x86::Ins::CmpRegImm(_, value), _ ] => Some(*value),
+        [ x86::Ins::MovRegImm(_, value), x86::Ins::CmpRegReg(_, _) ] => Some(*value),
+        _ => None
+    }).collect()
+}
+
+#[test]
+fn switch_u8() {
+    // Close values are dispatched through a table, including up to the largest u8
+    let ins = translate_switch(ir::ValueType::U8, vec![ vec![ 250, 251 ], vec![ 253, 255 ] ]);
+    assert_eq!(table_len(&ins), 6);
+    assert!(ins.iter().any(|i| matches!(i, x86::Ins::SubRegImm(_, 250))));
+    assert_eq!(compared_values(&ins), vec![ 6 ]);
+
+    // Sparse values are compared against in turn
+    let ins = translate_switch(ir::ValueType::U8, vec![ vec![ 0 ], vec![ 100 ], vec![ 255 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ 0, 100, 255 ]);
+}
+
+#[test]
+fn switch_i64() {
+    // Negative values in a table are offset by the smallest of them
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ -2i64 as u64 ], vec![ 0, 1 ], vec![ 3 ] ]);
+    assert_eq!(table_len(&ins), 6);
+    assert!(ins.iter().any(|i| matches!(i, x86::Ins::SubRegImm(_, value) if *value == -2i64 as u64)));
+
+    // A table whose smallest value does not fit into an immediate
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ 5000000000 ], vec![ 5000000001, 5000000003 ] ]);
+    assert_eq!(table_len(&ins), 4);
+    assert!(ins.iter().any(|i| matches!(i, x86::Ins::MovRegImm(_, 5000000000))));
+
+    // Sparse values, negative or too wide for an immediate, are compared against in turn
+    let ins = translate_switch(ir::ValueType::I64, vec![ vec![ -5i64 as u64 ], vec![ 100 ], vec![ 10000, 5000000000 ] ]);
+    assert_eq!(table_len(&ins), 0);
+    assert_eq!(compared_values(&ins), vec![ -5i64 as u64, 100, 10000, 5000000000 ]);
+}
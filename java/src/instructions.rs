@@ -658,7 +658,7 @@ impl Ins {
                     pad = 4 - pad;
                 }
 
-                1 + 4*2 + 4*pairs.len() + pad
+                1 + 4*2 + 8*pairs.len() + pad
             },
             Ins::IReturn => 1,
             Ins::LReturn => 1,
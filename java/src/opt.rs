@@ -31,8 +31,8 @@ pub mod ins {
 			0 => Ins::LConst0,
 			1 => Ins::LConst1,
 			_ => {
-				let idx = classfile.add_constant(Constant::Long(Long::new(i as u64)));
-				ldc(idx, classfile)
+				// Longs take two constant pool entries, so can only be loaded by ldc2_w
+				Ins::Ldc2W { index: classfile.add_constant(Constant::Long(Long::new(i as u64))) }
 			}
 		}
 	}
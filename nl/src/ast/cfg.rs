@@ -0,0 +1,94 @@
+use crate::irgen::BuildConfig;
+use crate::lexer::{TokenKind, TokenStream};
+
+/// The condition of a `[cfg(...)]` attribute, the item it is placed on is only compiled if it holds
+#[derive(Debug)]
+pub enum CfgExpr {
+    Any(Vec<CfgExpr>),
+    All(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+    /// Holds if the option is set, and has the value if one is given
+    Option(String, Option<String>)
+}
+
+impl CfgExpr {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<CfgExpr> {
+        let start = stream.tell_start();
+        let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
+        stream.step();
+
+        // any, all and not are only combinators if followed by a bracket, otherwise they are options like any other
+        if (name == "any" || name == "all" || name == "not") && syntax::tk_iss!(stream, TokenKind::OpenParen) {
+            let mut conditions = Vec::new();
+            loop {
+                conditions.push(match syntax::parse!(stream, CfgExpr::parse) {
+                    Some(x) => x,
+                    None => break
+                });
+
+                if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+            }
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+            return syntax::MatchResult::Ok(match name.as_str() {
+                "any" => CfgExpr::Any(conditions),
+                "all" => CfgExpr::All(conditions),
+                _ => {
+                    if conditions.len() != 1 {
+                        return syntax::MatchResult::Err(syntax::SyntaxError::new(syntax::Span::new(start, stream.tell_start()), "Expected exactly one condition in not"));
+                    }
+
+                    CfgExpr::Not(Box::new(conditions.pop().unwrap()))
+                }
+            });
+        }
+
+        let value = if syntax::tk_iss!(stream, TokenKind::Eq) {
+            let bytes = syntax::ex!(syntax::tk_v!(stream, TokenKind::StringLit), stream.error("Expected string")).clone();
+            stream.step();
+
+            match String::from_utf8(bytes) {
+                Ok(value) => Some(value),
+                Err(_) => return syntax::MatchResult::Err(stream.error("String is not valid UTF-8"))
+            }
+        } else {
+            None
+        };
+
+        syntax::MatchResult::Ok(CfgExpr::Option(name, value))
+    }
+
+    /// Parse a lone `[cfg(...)]` attribute, as placed before struct fields and blocks of code
+    pub fn parse_attribute<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<CfgExpr> {
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenBracket));
+
+        let condition = syntax::ex!(syntax::parse!(stream, CfgExpr::parse_call), stream.error("Expected 'cfg(...)'"));
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseBracket), stream.error("Expected ']'"));
+
+        syntax::MatchResult::Ok(condition)
+    }
+
+    /// Parse `cfg(...)`, without the square brackets around it
+    pub fn parse_call<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<CfgExpr> {
+        syntax::req!(syntax::tk_v!(stream, TokenKind::Ident).map_or(false, |name| name == "cfg"));
+        stream.step();
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+        let condition = syntax::ex!(syntax::parse!(stream, CfgExpr::parse), stream.error("Expected condition"));
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        syntax::MatchResult::Ok(condition)
+    }
+
+    pub fn evaluate(&self, config: &BuildConfig) -> bool {
+        match self {
+            CfgExpr::Any(conditions) => conditions.iter().any(|condition| condition.evaluate(config)),
+            CfgExpr::All(conditions) => conditions.iter().all(|condition| condition.evaluate(config)),
+            CfgExpr::Not(condition) => !condition.evaluate(config),
+            CfgExpr::Option(name, None) => config.option(name).is_some(),
+            CfgExpr::Option(name, Some(value)) => config.option(name) == Some(Some(value.as_str())),
+        }
+    }
+}
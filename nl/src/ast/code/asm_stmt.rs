@@ -0,0 +1,240 @@
+use syntax::Span;
+
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind, storable_type_to_string};
+use crate::types::StorableType;
+
+/// Binds a register to a variable, with the register given first, as in `rax = x`
+#[derive(Debug)]
+pub struct AsmStmtBinding {
+    pub span: Span,
+    pub reg: String,
+    pub name: String
+}
+
+#[derive(Debug)]
+pub struct AsmStmt {
+    pub span: Span,
+    pub arch: ir::AsmArch,
+    pub inputs: Vec<AsmStmtBinding>,
+    pub outputs: Vec<AsmStmtBinding>,
+    pub code: Vec<ir::AsmIns>
+}
+
+impl AsmStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<AsmStmt> {
+        let start = stream.tell_start();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::AsmKeyword));
+
+        let arch = match syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str) {
+            Some("x86_64") => ir::AsmArch::X8664,
+            Some("arm64") => ir::AsmArch::Arm64,
+            _ => return syntax::MatchResult::Err(stream.error("Expected architecture, either x86_64 or arm64"))
+        };
+        stream.step();
+
+        // Both sets of bindings are optional, but inputs must come first
+        let mut inputs = Vec::new();
+        if matches!(syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str), Some("in")) {
+            stream.step();
+            inputs = syntax::ex!(syntax::parse!(stream, AsmStmt::parse_bindings));
+        }
+
+        let mut outputs = Vec::new();
+        if matches!(syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str), Some("out")) {
+            stream.step();
+            outputs = syntax::ex!(syntax::parse!(stream, AsmStmt::parse_bindings));
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+        // Each instruction is a mnemonic followed by comma separated operands, and ends with a ';'
+        let mut code = Vec::new();
+        while !syntax::tk_iss!(stream, TokenKind::CloseCurly) {
+            let mnemonic = match syntax::tk_v!(stream, TokenKind::Ident) {
+                Some(mnemonic) => mnemonic.clone(),
+                None => return syntax::MatchResult::Err(stream.error("Expected instruction"))
+            };
+            stream.step();
+
+            let mut operands = Vec::new();
+            if !syntax::tk_is!(stream, TokenKind::Semi) {
+                loop {
+                    operands.push(syntax::ex!(syntax::parse!(stream, AsmStmt::parse_operand)));
+                    if !syntax::tk_iss!(stream, TokenKind::Comma) { break; }
+                }
+            }
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+
+            code.push(ir::AsmIns::new(mnemonic, operands));
+        }
+
+        syntax::MatchResult::Ok(AsmStmt {
+            span: Span::new(start, stream.tell_start()),
+            arch, inputs, outputs, code
+        })
+    }
+
+    /// Parse a bracketed, comma separated list of `reg = name` bindings
+    fn parse_bindings<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<Vec<AsmStmtBinding>> {
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+        let mut bindings = Vec::new();
+        loop {
+            let binding_start = stream.tell_start();
+            let reg = match syntax::tk_v!(stream, TokenKind::Ident) {
+                Some(reg) => reg.clone(),
+                None => return syntax::MatchResult::Err(stream.error("Expected register"))
+            };
+            stream.step();
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Eq), stream.error("Expected '='"));
+
+            let name = match syntax::tk_v!(stream, TokenKind::Ident) {
+                Some(name) => name.clone(),
+                None => return syntax::MatchResult::Err(stream.error("Expected variable name"))
+            };
+            stream.step();
+
+            bindings.push(AsmStmtBinding {
+                span: Span::new(binding_start, stream.tell_start()),
+                reg, name
+            });
+
+            if !syntax::tk_iss!(stream, TokenKind::Comma) { break; }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        syntax::MatchResult::Ok(bindings)
+    }
+
+    /// Parse a register, an immediate (optionally prefixed with '#') or an address. Addresses are either
+    /// `[base + index * scale + offset]`, or `[base, #offset]` as written on arm64.
+    fn parse_operand<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<ir::AsmOperand> {
+        if let Some(reg) = syntax::tk_v!(stream, TokenKind::Ident) {
+            let reg = reg.clone();
+            stream.step();
+            return syntax::MatchResult::Ok(ir::AsmOperand::Reg(reg));
+        }
+
+        if !syntax::tk_iss!(stream, TokenKind::OpenBracket) {
+            syntax::tk_iss!(stream, TokenKind::Hash);
+            let imm = syntax::ex!(syntax::parse!(stream, AsmStmt::parse_integer));
+            return syntax::MatchResult::Ok(ir::AsmOperand::Imm(imm));
+        }
+
+        let base = match syntax::tk_v!(stream, TokenKind::Ident) {
+            Some(base) => base.clone(),
+            None => return syntax::MatchResult::Err(stream.error("Expected register"))
+        };
+        stream.step();
+
+        let mut index = None;
+        let mut offset = 0i64;
+        loop {
+            if syntax::tk_iss!(stream, TokenKind::Comma) {
+                syntax::tk_iss!(stream, TokenKind::Hash);
+                offset = offset.wrapping_add(syntax::ex!(syntax::parse!(stream, AsmStmt::parse_integer)));
+            } else if syntax::tk_iss!(stream, TokenKind::Sub) {
+                offset = offset.wrapping_sub(syntax::ex!(syntax::parse!(stream, AsmStmt::parse_integer)));
+            } else if syntax::tk_iss!(stream, TokenKind::Add) {
+                match syntax::tk_v!(stream, TokenKind::Ident) {
+                    Some(reg) if index.is_none() => {
+                        let reg = reg.clone();
+                        stream.step();
+
+                        let mut scale = 1;
+                        if syntax::tk_iss!(stream, TokenKind::Mul) {
+                            scale = match syntax::tk_v!(stream, TokenKind::Number).map(|scale| scale.parse::<u8>()) {
+                                Some(Ok(scale)) => scale,
+                                _ => return syntax::MatchResult::Err(stream.error("Expected scale"))
+                            };
+                            stream.step();
+                        }
+
+                        index = Some((reg, scale));
+                    },
+                    Some(_) => return syntax::MatchResult::Err(stream.error("Address already has an index")),
+                    None => offset = offset.wrapping_add(syntax::ex!(syntax::parse!(stream, AsmStmt::parse_integer)))
+                }
+            } else {
+                break;
+            }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseBracket), stream.error("Expected ']'"));
+
+        syntax::MatchResult::Ok(ir::AsmOperand::Mem(base, index, offset))
+    }
+
+    /// Parse a possibly negative integer
+    fn parse_integer<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<i64> {
+        let negative = syntax::tk_iss!(stream, TokenKind::Sub);
+        let number = match syntax::tk_v!(stream, TokenKind::Number) {
+            Some(number) => number.clone(),
+            None => return syntax::MatchResult::Err(stream.error("Expected integer"))
+        };
+
+        let value = match negative {
+            true => format!("-{}", number).parse::<i64>(),
+            false => number.parse::<i64>()
+        };
+
+        match value {
+            Ok(value) => {
+                stream.step();
+                syntax::MatchResult::Ok(value)
+            },
+            Err(_) => syntax::MatchResult::Err(stream.error("Invalid integer"))
+        }
+    }
+
+    /// The architecture blocks must be written for to be built for the target, or None if the target is not a native one
+    fn target_arch(target: &str) -> Option<ir::AsmArch> {
+        match target {
+            "linux-x86" | "macos-x86" => Some(ir::AsmArch::X8664),
+            "macos-arm64" => Some(ir::AsmArch::Arm64),
+            _ => None
+        }
+    }
+
+    fn resolve_bindings<'a>(bindings: &[AsmStmtBinding], ctx: &IrGenFunctionContext<'a>) -> Result<Vec<ir::AsmBinding>, IrGenError> {
+        let mut resolved = Vec::new();
+
+        for binding in bindings {
+            let local = match ctx.local_map.get(binding.name.as_str()) {
+                Some(local) => *local,
+                None => return Err(IrGenError::new(binding.span.clone(), IrGenErrorKind::VariableDoesNotExist(binding.name.clone())))
+            };
+
+            match ctx.local_type(local) {
+                StorableType::Value(vt) => resolved.push(ir::AsmBinding::new(binding.reg.clone(), local, vt.to_ir())),
+                st => return Err(IrGenError::new(binding.span.clone(), IrGenErrorKind::InvalidAsmBinding(binding.name.clone(), storable_type_to_string(st))))
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // 1. The block must be for the target, blocks for other targets can be excluded with cfg
+        match AsmStmt::target_arch(ctx.config.target()) {
+            Some(arch) if arch == self.arch => {},
+            // Nothing is built when there is no target, so blocks for any architecture are allowed
+            None if ctx.config.target() == "none" => {},
+            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::AsmArchMismatch(self.arch.name().to_string(), ctx.config.target().to_string())))
+        }
+
+        // 2. Find the locals each register is bound to
+        let inputs = AsmStmt::resolve_bindings(&self.inputs, ctx)?;
+        let outputs = AsmStmt::resolve_bindings(&self.outputs, ctx)?;
+
+        // 3. Whether each instruction can be assembled is left to the backend, which checks every block before translating
+        let block = ir::AsmBlock::new(self.arch, inputs, outputs, self.code.clone());
+        target.push(ir::Ins::Asm(block));
+
+        Ok(())
+    }
+}
@@ -1,34 +1,43 @@
 use syntax::Span;
 
-use crate::ast::Expr;
+use crate::ast::{BinaryOp, Expr};
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
+use crate::types::{StorableType, ValueType};
+
+/// The temporary locals which the operands of a path were moved into, and their types
+type PathOperands = Vec<(ir::LocalIndex, ir::ValueType)>;
 
 #[derive(Debug)]
 pub struct Assignment {
     pub span: Span,
     pub left: Expr,
+    /// The operator of a compound assignment such as `+=`, which applies it to the current value and the RHS
+    pub op: Option<BinaryOp>,
     pub right: Expr
 }
 
 // NOTE: Parsing is handled in Code, since Assignment is tightly bound with expression parsing.
 impl Assignment {
     pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        if let Some(op) = &self.op {
+            return self.append_compound_ir(op, ctx, target);
+        }
+
         match &self.left {
             Expr::Name(name) => {
                 if let Some(local_idx) = ctx.local_map.get(name.name.as_str()) {
-                    // Only valid local indices go in the local_map, so safe to unwrap
-                    let local = ctx.func().get_local(*local_idx).unwrap();
+                    let local_idx = *local_idx;
 
                     // Check that the local type is a ValueType
-                    let expected = match local.local_type() {
-                        ir::StorableType::Value(t) => t.clone(),
+                    let expected = match ctx.local_type(local_idx) {
+                        StorableType::Value(t) => t.clone(),
                         _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidRHS)),
                     };
 
                     // 1. Push a path to the target, in this case a local
                     target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(
-                        ir::ValuePathOrigin::Local(*local_idx, ir::StorableType::Value(expected.clone())),
-                    ), expected.clone()));
+                        ir::ValuePathOrigin::Local(local_idx, ir::StorableType::Value(expected.to_ir())),
+                    ), expected.to_ir()));
 
                     // 2. Push the value...
                     let vt = self.right.append_ir_value(ctx, target, Some(&expected))?;
@@ -39,24 +48,31 @@ impl Assignment {
                     }
 
                     // 3. Pop
-                    target.push(ir::Ins::Pop(vt));
+                    target.push(ir::Ins::Pop(vt.to_ir()));
                 } else {
                     return Err(IrGenError::new(name.span.clone(), IrGenErrorKind::VariableDoesNotExist(name.name.clone())));
                 }
             },
             _ => {
-                // 1. Construct a path to the target
-                let (st, path) = self.left.construct_path_to(ctx, target, None)?;
+                // 1. Construct a path to the target, if the RHS hoists code the operands of the path are built ahead of it
+                let (st, path) = match self.right.hoists() {
+                    true => {
+                        let (st, path, locals) = self.construct_spilled_path(ctx, target)?;
+                        Self::push_path_operands(&locals, target);
+                        (st, path)
+                    },
+                    false => self.left.construct_path_to(ctx, target, None)?
+                };
 
                 // Check that the type that the path references is storable
                 let st_v = match st {
-                    ir::StorableType::Value(x) => x.clone(),
+                    StorableType::Value(x) => x.clone(),
                     _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidLHS))
                 };
 
                 // 2. Push the path
                 target.push(ir::Ins::PushPath(
-                    path, st_v.clone()
+                    path, st_v.to_ir()
                 ));
 
                 // 3. Append the value...
@@ -68,10 +84,114 @@ impl Assignment {
                 }
 
                 // 4. Pop
-                target.push(ir::Ins::Pop(vt));
+                target.push(ir::Ins::Pop(vt.to_ir()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The LHS of a compound assignment is only evaluated once, the operands of its path are kept in temporary locals so that
+    /// the path can be pushed twice, once to write the new value and once to read the current one
+    fn append_compound_ir<'a>(&'a self, op: &BinaryOp, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // 1. Construct a path to the target, moving its operands into locals
+        let (st, path, locals) = self.construct_spilled_path(ctx, target)?;
+
+        // Check that the path references a number
+        let vt = match st {
+            StorableType::Value(x) if x.is_num() && x.underlying() != &ValueType::Bool => x,
+            StorableType::Value(x) => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::NotANumber(value_type_to_string(&x)))),
+            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidLHS))
+        };
+
+        // 2. Push the path twice, first as the target and then to load the current value
+        let ir_vt = vt.to_ir();
+        for _ in 0..2 {
+            Self::push_path_operands(&locals, target);
+            target.push(ir::Ins::PushPath(path.clone(), ir_vt.clone()));
+        }
+
+        target.push(ir::Ins::Push(ir_vt.clone()));
+
+        // 3. Apply the operator, adding or subtracting a literal which fits the type is an increment or decrement...
+        let literal = match (&self.right, op) {
+            (Expr::NumberLit(number), BinaryOp::Add | BinaryOp::Sub) => match number.typed_value(Some(&vt))? {
+                (literal_vt, amount) if literal_vt == vt => Some(amount),
+                _ => None
+            },
+            _ => None
+        };
+
+        match (literal, op) {
+            (Some(amount), BinaryOp::Add) => target.push(ir::Ins::Inc(ir_vt.clone(), amount)),
+            (Some(amount), _) => target.push(ir::Ins::Dec(ir_vt.clone(), amount)),
+            (None, _) => {
+                // ... otherwise append the value and check it is the right type
+                let right = self.right.append_ir_value(ctx, target, Some(&vt))?;
+                if right != vt {
+                    return Err(IrGenError::new(self.span.clone(),
+                        IrGenErrorKind::AssignmentTypeMismatch(value_type_to_string(&right), value_type_to_string(&vt))
+                    ));
+                }
+
+                target.push(match op {
+                    BinaryOp::Add => ir::Ins::Add(ir_vt.clone()),
+                    BinaryOp::Sub => ir::Ins::Sub(ir_vt.clone()),
+                    BinaryOp::Mul => ir::Ins::Mul(ir_vt.clone()),
+                    BinaryOp::Div => ir::Ins::Div(ir_vt.clone()),
+                    _ => unreachable!("Only arithmetic operators have compound assignments")
+                });
             }
         }
 
+        // 4. Pop
+        target.push(ir::Ins::Pop(ir_vt));
+
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Construct a path to the LHS and move the operands it leaves on the stack into temporary locals, the reference being
+    /// dereferenced is on top, with the index of each slice below it. If the RHS hoists code, this is hoisted ahead of it too,
+    /// so that the LHS is still evaluated first.
+    fn construct_spilled_path<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(StorableType, ir::ValuePath, PathOperands), IrGenError> {
+        let mut hoisted = IrGenCodeTarget::new();
+        let left = match self.right.hoists() {
+            true => &mut hoisted,
+            false => &mut *target
+        };
+
+        let (st, path) = self.left.construct_path_to(ctx, left, None)?;
+
+        let mut operands = Vec::new();
+        if let ir::ValuePathOrigin::Deref(origin) = path.origin() {
+            operands.push(ir::ValueType::Ref(Box::new(origin.clone())));
+        }
+
+        for component in path.components() {
+            if let ir::ValuePathComponent::Slice(el) = component {
+                operands.push(ir::ValueType::Index(Box::new(el.clone())));
+            }
+        }
+
+        let mut locals = Vec::new();
+        for operand in operands {
+            let local = ctx.push_temp_local(StorableType::from(&ir::StorableType::Value(operand.clone())));
+            left.push(ir::Ins::PopLocal(local, operand.clone()));
+            locals.push((local, operand));
+        }
+
+        target.hoist(hoisted);
+
+        Ok((st, path, locals))
+    }
+
+    /// Push the operands of a path back from the locals they were moved into
+    fn push_path_operands(locals: &PathOperands, target: &mut IrGenCodeTarget) {
+        for (local, operand) in locals.iter().rev() {
+            target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(
+                ir::ValuePathOrigin::Local(*local, ir::StorableType::Value(operand.clone()))
+            ), operand.clone()));
+            target.push(ir::Ins::Push(operand.clone()));
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use syntax::Span;
+
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext};
+
+/// Either a break, which leaves the innermost loop, or a continue, which moves on to its next iteration
+#[derive(Debug)]
+pub struct BreakStmt {
+    pub span: Span,
+    pub is_continue: bool
+}
+
+impl BreakStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>, terminated: bool) -> syntax::MatchResult<BreakStmt> {
+        let start = stream.tell_start();
+
+        let is_continue = match stream.token_kind() {
+            Some(TokenKind::BreakKeyword) => false,
+            Some(TokenKind::ContinueKeyword) => true,
+            _ => return syntax::MatchResult::Fail
+        };
+        stream.step();
+
+        if terminated {
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+        }
+
+        syntax::MatchResult::Ok(BreakStmt {
+            span: syntax::Span::new(start, stream.tell_start()),
+            is_continue
+        })
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // Anything deferred within the loop is run first, as the blocks it was deferred in are left
+        ctx.append_loop_exit(&self.span, self.is_continue, target)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::ast::CfgExpr;
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext};
+
+use super::Code;
+
+/// A block of code which is only compiled if its condition holds, otherwise it is never even type checked
+#[derive(Debug)]
+pub struct CfgBlock {
+    pub condition: CfgExpr,
+    pub code: Vec<Code>
+}
+
+impl CfgBlock {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<CfgBlock> {
+        let condition = syntax::ex!(syntax::parse!(stream, CfgExpr::parse_attribute));
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+        let mut code = Vec::new();
+        loop {
+            code.push(match syntax::parse!(stream, Code::parse, true) {
+                Some(x) => x,
+                None => break
+            });
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+
+        syntax::MatchResult::Ok(CfgBlock {
+            condition, code
+        })
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        if self.condition.evaluate(ctx.config) {
+            Code::append_block_ir(&self.code, ctx, target)?;
+        }
+
+        Ok(())
+    }
+}
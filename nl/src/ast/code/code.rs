@@ -1,8 +1,8 @@
-use crate::ast::Expr;
+use crate::ast::{BinaryOp, Expr, NumberLitExpr};
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext};
 use crate::lexer::{TokenKind, TokenStream};
 
-use super::{Assignment, ForStmt, IfStmt, ReturnStmt, VarDeclaration, DropStmt};
+use super::{Assignment, ForStmt, IfStmt, SwitchStmt, ReturnStmt, VarDeclaration, DropStmt, DeferStmt, FailStmt, PanicStmt, CfgBlock, AsmStmt, BreakStmt};
 
 #[derive(Debug)]
 pub enum Code {
@@ -11,8 +11,15 @@ pub enum Code {
     ExprStmt(Expr),
     Assignment(Assignment),
     IfStmt(IfStmt),
+    SwitchStmt(SwitchStmt),
     ForStmt(ForStmt),
-    DropStmt(DropStmt)
+    DropStmt(DropStmt),
+    DeferStmt(DeferStmt),
+    FailStmt(FailStmt),
+    PanicStmt(PanicStmt),
+    CfgBlock(CfgBlock),
+    AsmStmt(AsmStmt),
+    BreakStmt(BreakStmt)
 }
 
 impl Code {
@@ -28,24 +35,52 @@ impl Code {
             Some(TokenKind::ReturnKeyword) => Code::ReturnStmt(syntax::parse!(stream, ReturnStmt::parse, terminated).unwrap()),
             Some(TokenKind::VarKeyword) => Code::VarDeclaration(syntax::parse!(stream, VarDeclaration::parse, terminated).unwrap()),
             Some(TokenKind::IfKeyword) => Code::IfStmt(syntax::parse!(stream, IfStmt::parse).unwrap()),
+            Some(TokenKind::SwitchKeyword) => Code::SwitchStmt(syntax::parse!(stream, SwitchStmt::parse).unwrap()),
             Some(TokenKind::ForKeyword) => Code::ForStmt(syntax::parse!(stream, ForStmt::parse).unwrap()),
             Some(TokenKind::DropKeyword) => Code::DropStmt(syntax::parse!(stream, DropStmt::parse, terminated).unwrap()),
+            Some(TokenKind::DeferKeyword) => Code::DeferStmt(syntax::parse!(stream, DeferStmt::parse, terminated).unwrap()),
+            Some(TokenKind::FailKeyword) => Code::FailStmt(syntax::parse!(stream, FailStmt::parse, terminated).unwrap()),
+            Some(TokenKind::PanicKeyword) => Code::PanicStmt(syntax::parse!(stream, PanicStmt::parse, terminated).unwrap()),
+            Some(TokenKind::OpenBracket) => Code::CfgBlock(syntax::parse!(stream, CfgBlock::parse).unwrap()),
+            Some(TokenKind::AsmKeyword) => Code::AsmStmt(syntax::parse!(stream, AsmStmt::parse).unwrap()),
+            Some(TokenKind::BreakKeyword | TokenKind::ContinueKeyword) => Code::BreakStmt(syntax::parse!(stream, BreakStmt::parse, terminated).unwrap()),
             
             // Special case for ExprStmt / Assignment
             _ => {
                 // 1. Parse an expression
                 let expr = syntax::ex!(syntax::parse!(stream, Expr::parse));
 
-                // 2. If the next token is an equal, it is an assignment...
-                if syntax::tk_iss!(stream, TokenKind::Eq) {
-                    // Parse the RHS
-                    let right = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected RHS"));
+                // 2. If the next token is an equal, or an operator followed by one, it is an assignment...
+                let op = match stream.token_kind() {
+                    Some(TokenKind::Eq) => Some(None),
+                    Some(TokenKind::AddEq | TokenKind::DblAdd) => Some(Some(BinaryOp::Add)),
+                    Some(TokenKind::SubEq | TokenKind::DblSub) => Some(Some(BinaryOp::Sub)),
+                    Some(TokenKind::MulEq) => Some(Some(BinaryOp::Mul)),
+                    Some(TokenKind::DivEq) => Some(Some(BinaryOp::Div)),
+                    _ => None
+                };
+
+                if let Some(op) = op {
+                    let right = match stream.token_kind() {
+                        // x++ and x-- are x += 1 and x -= 1
+                        Some(TokenKind::DblAdd | TokenKind::DblSub) => {
+                            let span = syntax::Span::new(stream.tell_start(), stream.tell());
+                            stream.step();
+                            Expr::NumberLit(NumberLitExpr { span, number: "1".to_string() })
+                        },
+                        _ => {
+                            // Parse the RHS
+                            stream.step();
+                            syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected RHS"))
+                        }
+                    };
                     
                     if terminated { syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'")); }
 
                     Code::Assignment(Assignment {
                         span: syntax::Span::new(start, stream.tell_start()),
                         left: expr,
+                        op,
                         right
                     })
                 } else {
@@ -65,24 +100,61 @@ impl Code {
     }
 
     pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // Each statement gets its own target, so anything hoisted out of an expression runs just before the statement
+        let mut statement = IrGenCodeTarget::new();
+
         match self {
-            Code::ReturnStmt(stmt) => stmt.append_ir(ctx, target),
-            Code::VarDeclaration(vardecl) => vardecl.append_ir(ctx, target),
-            Code::ExprStmt(expr) => {
-                match expr {
-                    Expr::Call(call_expr) => call_expr.append_ir_out_expr(ctx, target)?,
-                    _ => {
-                        expr.append_ir_value(ctx, target, None)?;
-                        target.push(ir::Ins::Drop); // Drop result as it's not used
-                    }
-                }
+            Code::ReturnStmt(stmt) => stmt.append_ir(ctx, &mut statement)?,
+            Code::VarDeclaration(vardecl) => vardecl.append_ir(ctx, &mut statement)?,
+            Code::ExprStmt(expr) => expr.append_ir_out_expr(ctx, &mut statement)?,
+            Code::Assignment(assignment) => assignment.append_ir(ctx, &mut statement)?,
+            Code::IfStmt(if_stmt) => if_stmt.append_ir(ctx, &mut statement)?,
+            Code::SwitchStmt(switch_stmt) => switch_stmt.append_ir(ctx, &mut statement)?,
+            Code::ForStmt(for_stmt) => for_stmt.append_ir(ctx, &mut statement)?,
+            Code::DropStmt(drop_stmt) => drop_stmt.append_ir(ctx, &mut statement)?,
+            Code::DeferStmt(defer_stmt) => defer_stmt.append_ir(ctx, &mut statement)?,
+            Code::FailStmt(fail_stmt) => fail_stmt.append_ir(ctx, &mut statement)?,
+            Code::PanicStmt(panic_stmt) => panic_stmt.append_ir(ctx, &mut statement)?,
+            Code::CfgBlock(cfg_block) => cfg_block.append_ir(ctx, &mut statement)?,
+            Code::AsmStmt(asm_stmt) => asm_stmt.append_ir(ctx, &mut statement)?,
+            Code::BreakStmt(break_stmt) => break_stmt.append_ir(ctx, &mut statement)?
+        }
+
+        target.append(statement);
+
+        Ok(())
+    }
+
+    /// Appends a block of code which is put in a block of its own in the IR, such as the code of an if, which a break or
+    /// continue within it has to count on the way out
+    pub fn append_nested_block_ir<'a>(block: &'a [Code], ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        ctx.block_depth += 1;
+        let result = Code::append_block_ir(block, ctx, target);
+        ctx.block_depth -= 1;
 
-                Ok(())
-            },
-            Code::Assignment(assignment) => assignment.append_ir(ctx, target),
-            Code::IfStmt(if_stmt) => if_stmt.append_ir(ctx, target),
-            Code::ForStmt(for_stmt) => for_stmt.append_ir(ctx, target),
-            Code::DropStmt(drop_stmt) => drop_stmt.append_ir(ctx, target)
+        result
+    }
+
+    /// Appends a block of code in its own defer scope, so that anything deferred within the block is run when the end of it is reached
+    pub fn append_block_ir<'a>(block: &'a [Code], ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        ctx.push_defer_scope();
+
+        for code in block {
+            code.append_ir(ctx, target)?;
+        }
+
+        let deferred = ctx.pop_defer_scope();
+
+        // If the end of the block cannot be reached, as after a return or fail, the deferred code has already been run before it
+        if target.falls_through() {
+            for (i, code) in deferred.iter().enumerate().rev() {
+                // Any code deferred before this one must still run if it returns early
+                ctx.defer_scopes.push(deferred[..i].to_vec());
+                code.append_ir(ctx, target)?;
+                ctx.pop_defer_scope();
+            }
         }
+
+        Ok(())
     }
 }
\ No newline at end of file
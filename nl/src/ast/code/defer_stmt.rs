@@ -0,0 +1,39 @@
+use syntax::Span;
+
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext};
+
+use super::Code;
+
+#[derive(Debug)]
+pub struct DeferStmt {
+    pub code: Box<Code>
+}
+
+impl DeferStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>, terminated: bool) -> syntax::MatchResult<DeferStmt> {
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::DeferKeyword));
+
+        // The deferred code is generated again at every exit of the block, so it must not declare anything or affect control flow
+        let code_start = stream.tell_start();
+        let code = syntax::ex!(syntax::parse!(stream, Code::parse, terminated), stream.error("Expected statement"));
+        match code {
+            Code::ExprStmt(_) | Code::Assignment(_) | Code::DropStmt(_) => {},
+            _ => return syntax::MatchResult::Err(syntax::SyntaxError::new(
+                Span::new(code_start, stream.tell_start()),
+                "Only expressions, assignments and drops can be deferred"
+            ))
+        }
+
+        syntax::MatchResult::Ok(DeferStmt {
+            code: Box::new(code)
+        })
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, _target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // Nothing is emitted here, the code is appended when the enclosing block is exited
+        ctx.defer(&self.code);
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use syntax::Span;
 use crate::ast::Expr;
 use crate::lexer::{TokenKind, TokenStream};
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind, value_type_to_string};
+use crate::types::{StorableType, ValueType};
 
 #[derive(Debug)]
 pub struct DropStmt {
@@ -32,15 +33,14 @@ impl DropStmt {
         // 1. Load expr
         let vt = self.expr.append_ir_value(ctx, target, None)?;
         let st = match vt { // ...check that it is a heap value
-            ir::ValueType::Ref(st) => st, // FIXME: String globals are also Refs, but are not heap values - what should they be?
+            ValueType::Ref(st) => st, // FIXME: String globals are also Refs, but are not heap values - what should they be?
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidDropType(value_type_to_string(&vt))))
         };
 
         // 2. Free it
         match st.as_ref() {
-            ir::StorableType::Compound(_) | ir::StorableType::Value(_) => target.push(ir::Ins::Free(st.as_ref().clone())),
-            ir::StorableType::Slice(slice_type) => target.push(ir::Ins::FreeSlice(slice_type.as_ref().clone())),
-            ir::StorableType::SliceData(_) => unreachable!(),
+            StorableType::Compound(_) | StorableType::Value(_) => target.push(ir::Ins::Free(st.to_ir())),
+            StorableType::Slice(slice_type) => target.push(ir::Ins::FreeSlice(slice_type.to_ir())),
         }
 
         Ok(())
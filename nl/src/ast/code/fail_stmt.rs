@@ -0,0 +1,52 @@
+use syntax::Span;
+
+use crate::ast::Expr;
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind, value_type_to_string};
+
+#[derive(Debug)]
+pub struct FailStmt {
+    pub span: Span,
+    pub expr: Expr
+}
+
+impl FailStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>, terminated: bool) -> syntax::MatchResult<FailStmt> {
+        let start = stream.tell_start();
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::FailKeyword));
+
+        let expr = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected an error"));
+
+        if terminated {
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+        }
+
+        syntax::MatchResult::Ok(FailStmt {
+            span: syntax::Span::new(start, stream.tell_start()),
+            expr
+        })
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let (payload, error_type) = match ctx.fallible_returns(ctx.function_idx) {
+            Some((payload, error_type)) => (payload, error_type),
+            None => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::FailOutsideFallibleFunction))
+        };
+
+        // 1. Placeholders for the success values, which the caller will not read
+        for vt in &payload {
+            ctx.push_zero_value(vt, target);
+        }
+
+        // 2. The error itself
+        let found = self.expr.append_ir_value(ctx, target, Some(&error_type))?;
+        if found != error_type {
+            return Err(IrGenError::new(self.expr.span().clone(), IrGenErrorKind::ErrorTypeMismatch(value_type_to_string(&found), value_type_to_string(&error_type))));
+        }
+
+        // 3. Set the failure flag, and return
+        target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 1));
+        ctx.append_return(target)
+    }
+}
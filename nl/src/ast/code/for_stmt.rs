@@ -1,6 +1,6 @@
 use syntax::Span;
 
-use crate::{ast::Expr, irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext}, lexer::{TokenKind, TokenStream}};
+use crate::{ast::Expr, irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, LoopScope}, lexer::{TokenKind, TokenStream}, types::ValueType};
 
 use super::Code;
 
@@ -82,11 +82,15 @@ impl ForStmt {
             init.append_ir(ctx, target)?;
         }
 
-        // 2. Load the body
+        // 2. Load the body, which a break or continue within it leaves
         let mut body = IrGenCodeTarget::new();
-        for code in &self.code {
-            code.append_ir(ctx, &mut body)?;
-        }
+        ctx.loops.push(LoopScope {
+            depth: ctx.block_depth + 1,
+            defer_scopes: ctx.defer_scopes.len()
+        });
+        let result = Code::append_nested_block_ir(&self.code, ctx, &mut body);
+        ctx.loops.pop();
+        result?;
 
         // 3. Load the increment if there is one
         let mut inc_body = IrGenCodeTarget::new();
@@ -97,7 +101,7 @@ impl ForStmt {
         // 4. Load the condition, if there isn't one - it's just 1 (true)
         let mut condition_body = IrGenCodeTarget::new();
         if let Some(condition) = &self.condition {
-            condition.append_ir_value(ctx, &mut condition_body, Some(&ir::ValueType::Bool))?;
+            condition.append_ir_value(ctx, &mut condition_body, Some(&ValueType::Bool))?;
         } else {
             condition_body.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 1));
         }
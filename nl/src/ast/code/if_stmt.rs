@@ -3,6 +3,7 @@ use syntax::Span;
 use crate::ast::Expr;
 use crate::lexer::{TokenKind, TokenStream};
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind};
+use crate::types::ValueType;
 
 use super::Code;
 
@@ -74,24 +75,21 @@ impl IfStmt {
     pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
         // 1. Load the condition
         let mut cond = IrGenCodeTarget::new();
-        if self.condition.append_ir_value(ctx, &mut cond, Some(&ir::ValueType::Bool))? != ir::ValueType::Bool {
+        if self.condition.append_ir_value(ctx, &mut cond, Some(&ValueType::Bool))? != ValueType::Bool {
             return Err(IrGenError::new(self.condition.span().clone(), IrGenErrorKind::NotABool));
         }
 
         // 2. Load the true then code
         let mut true_then = IrGenCodeTarget::new();
-        for code in &self.code {
-            code.append_ir(ctx, &mut true_then)?;
-        }
+        Code::append_nested_block_ir(&self.code, ctx, &mut true_then)?;
 
         // 3. If there is else code, load it and emit an IfElse...
         if let Some(else_code) = &self.else_code {
             let mut false_then = IrGenCodeTarget::new();
-            for code in else_code {
-                code.append_ir(ctx, &mut false_then)?;
-            }
+            Code::append_nested_block_ir(else_code, ctx, &mut false_then)?;
 
             target.push(ir::Ins::IfElse(
+                None,
                 true_then.take(),
                 false_then.take(),
                 cond.take()
@@ -2,14 +2,28 @@ mod vardecl;
 mod return_stmt;
 mod code;
 mod if_stmt;
+mod switch_stmt;
 mod for_stmt;
 mod assignment;
 mod drop_stmt;
+mod defer_stmt;
+mod fail_stmt;
+mod panic_stmt;
+mod cfg_block;
+mod asm_stmt;
+mod break_stmt;
 
 pub use vardecl::*;
 pub use return_stmt::*;
 pub use code::*;
 pub use if_stmt::*;
+pub use switch_stmt::*;
 pub use for_stmt::*;
 pub use assignment::*;
-pub use drop_stmt::*;
\ No newline at end of file
+pub use drop_stmt::*;
+pub use defer_stmt::*;
+pub use fail_stmt::*;
+pub use panic_stmt::*;
+pub use cfg_block::*;
+pub use asm_stmt::*;
+pub use break_stmt::*;
\ No newline at end of file
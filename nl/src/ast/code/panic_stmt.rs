@@ -0,0 +1,77 @@
+use syntax::Span;
+
+use crate::ast::{Expr, StringLitExpr};
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind, value_type_to_string};
+use crate::types::{StorableType, ValueType};
+
+#[derive(Debug)]
+pub struct PanicStmt {
+    pub span: Span,
+    pub message: Expr
+}
+
+impl PanicStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>, terminated: bool) -> syntax::MatchResult<PanicStmt> {
+        let start = stream.tell_start();
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::PanicKeyword));
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+        let message = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected a message"));
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        if terminated {
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+        }
+
+        syntax::MatchResult::Ok(PanicStmt {
+            span: syntax::Span::new(start, stream.tell_start()),
+            message
+        })
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let (hook, string_type, location_type) = match (ctx.ir_unit.find_panic(), ctx.ir_unit.find_type("String"), ctx.ir_unit.find_type("PanicLocation")) {
+            (Some(hook), Some(string_type), Some(location_type)) => (hook, string_type, ir::StorableType::Compound(location_type)),
+            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::StdLinkError))
+        };
+
+        // 1. The message
+        let expected = ValueType::Ref(Box::new(StorableType::Compound(string_type.clone())));
+        let found = self.message.append_ir_value(ctx, target, Some(&expected))?;
+        if found != expected {
+            return Err(IrGenError::new(self.message.span().clone(), IrGenErrorKind::CallArgTypeMismatch(value_type_to_string(&found), value_type_to_string(&expected))));
+        }
+
+        // 2. Where the panic was raised, this is only ever read so can be kept in read only memory
+        let file = StringLitExpr::add_global(ctx.ir_unit, ir::StorableType::Compound(string_type), ctx.source.path(), true);
+        let location = ctx.ir_unit.add_global(ir::Global::new_read_only::<String>(
+            None,
+            location_type.clone(),
+            ir::StorableValue::Compound(ir::CompoundValue::Struct(ir::StructValue::new(vec![
+                ir::StructPropertyValue::new(ir::StorableValue::Value(ir::Value::Ref(file))),
+                ir::StructPropertyValue::new(ir::StorableValue::Value(ir::Value::U32(ctx.source.line_of(&self.span) as u32)))
+            ])))
+        ));
+
+        let location_ref = ir::ValueType::Ref(Box::new(location_type));
+        let id = ctx.ir_unit.add_global(ir::Global::new_read_only::<String>(
+            None,
+            ir::StorableType::Value(location_ref.clone()),
+            ir::StorableValue::Value(ir::Value::Ref(location))
+        ));
+
+        target.push(ir::Ins::PushPath(
+            ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Global(id, ir::StorableType::Value(location_ref.clone()))),
+            location_ref.clone()
+        ));
+        target.push(ir::Ins::Push(location_ref));
+
+        // 3. Hand over to the runtime, which does not return
+        target.push(ir::Ins::Call(hook));
+
+        Ok(())
+    }
+}
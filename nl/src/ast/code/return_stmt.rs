@@ -29,27 +29,37 @@ impl ReturnStmt {
     }
 
     pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // A fallible function returns its error and failure flag after the values given here
+        let (returns, error_type) = match ctx.fallible_returns(ctx.function_idx) {
+            Some((payload, error_type)) => (payload, Some(error_type)),
+            None => (ctx.signature(ctx.function_idx).returns().clone(), None)
+        };
+
         // FIXME: More than one return type
-        assert!(ctx.func().signature().return_count() <= 1);
+        assert!(returns.len() <= 1);
 
         // 1. Load the expression, if there is one
         if let Some(expr) = &self.expr {
             let result = expr.append_ir_value(ctx, target, None)?;
             
-            if let Some(return_type) = ctx.func().signature().returns().get(0) {
+            if let Some(return_type) = returns.get(0) {
                 if return_type != &result {
                     return Err(IrGenError::new(expr.span().clone(), IrGenErrorKind::IncorrectReturnType(value_type_to_string(&result), value_type_to_string(return_type))));
                 }
             } else{
                 return Err(IrGenError::new(expr.span().clone(), IrGenErrorKind::ReturnValueWhenVoid));
             }
-        } else if ctx.func().signature().return_count() != 0 {
+        } else if returns.len() != 0 {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::NoReturnValue));
         }
 
-        // 2. Ret
-        target.push(ir::Ins::Ret);
+        // 2. Returning from a fallible function is a success, so there is no error
+        if let Some(error_type) = error_type {
+            ctx.push_zero_value(&error_type, target);
+            target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 0));
+        }
 
-        Ok(())
+        // 3. Run any deferred code, the return values stay on the stack underneath it, then ret
+        ctx.append_return(target)
     }
 }
\ No newline at end of file
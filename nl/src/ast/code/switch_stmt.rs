@@ -0,0 +1,138 @@
+use syntax::Span;
+
+use crate::ast::{Expr, NumberLitExpr};
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext, IrGenErrorKind, value_type_to_string};
+use crate::types::ValueType;
+
+use super::Code;
+
+#[derive(Debug)]
+pub struct SwitchCase {
+    pub values: Vec<NumberLitExpr>,
+    pub code: Vec<Code>
+}
+
+#[derive(Debug)]
+pub struct SwitchStmt {
+    pub value: Expr,
+    pub cases: Vec<SwitchCase>,
+    pub else_code: Option<Vec<Code>>
+}
+
+impl SwitchStmt {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<SwitchStmt> {
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::SwitchKeyword));
+
+        // Parse the value being switched on
+        let value = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected value"));
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+
+        let mut cases = Vec::new();
+        let mut else_code = None;
+        while !syntax::tk_iss!(stream, TokenKind::CloseCurly) {
+            // The else case catches every value which no other case handles
+            if syntax::tk_iss!(stream, TokenKind::ElseKeyword) {
+                if else_code.is_some() { return syntax::MatchResult::Err(stream.error("Duplicate else case")); }
+
+                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::FatArrow), stream.error("Expected '=>'"));
+                else_code = Some(syntax::ex!(syntax::parse!(stream, SwitchStmt::parse_body)));
+                continue;
+            }
+
+            // Otherwise it is a comma separated list of (possibly negative) integer literals
+            let mut values = Vec::new();
+            loop {
+                let value_start = stream.tell_start();
+                let negative = syntax::tk_iss!(stream, TokenKind::Sub);
+                let number = match syntax::tk_v!(stream, TokenKind::Number) {
+                    Some(number) => number.clone(),
+                    None => return syntax::MatchResult::Err(stream.error("Expected integer"))
+                };
+                stream.step();
+
+                values.push(NumberLitExpr {
+                    span: Span::new(value_start, stream.tell_start()),
+                    number: match negative {
+                        true => format!("-{}", number),
+                        false => number
+                    }
+                });
+
+                if !syntax::tk_iss!(stream, TokenKind::Comma) { break; }
+            }
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::FatArrow), stream.error("Expected '=>'"));
+            let code = syntax::ex!(syntax::parse!(stream, SwitchStmt::parse_body));
+
+            cases.push(SwitchCase {
+                values, code
+            });
+        }
+
+        syntax::MatchResult::Ok(SwitchStmt {
+            value, cases, else_code
+        })
+    }
+
+    /// Parse the code of a case - either it is 0+ lines surrounded by curly brackets or it is 1 line without
+    fn parse_body<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<Vec<Code>> {
+        if syntax::tk_iss!(stream, TokenKind::OpenCurly) {
+            let mut code = Vec::new();
+            loop {
+                code.push(match syntax::parse!(stream, Code::parse, true) {
+                    Some(x) => x,
+                    None => break
+                });
+            }
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+
+            syntax::MatchResult::Ok(code)
+        } else {
+            syntax::MatchResult::Ok(vec![
+                syntax::ex!(syntax::parse!(stream, Code::parse, true), stream.error("Expected statement"))
+            ])
+        }
+    }
+
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        // 1. Load the value, which must be an integer
+        let mut value = IrGenCodeTarget::new();
+        let vt = self.value.append_ir_value(ctx, &mut value, None)?;
+        if !vt.is_num() || *vt.underlying() == ValueType::Bool {
+            return Err(IrGenError::new(self.value.span().clone(), IrGenErrorKind::NotANumber(value_type_to_string(&vt))));
+        }
+
+        // 2. Load each case, with its values given in the type of the switched value
+        let mut seen = Vec::new();
+        let mut cases = Vec::new();
+        for case in &self.cases {
+            let mut values = Vec::new();
+            for literal in &case.values {
+                let (_, bits) = literal.typed_value(Some(&vt))?;
+                if seen.contains(&bits) {
+                    return Err(IrGenError::new(literal.span.clone(), IrGenErrorKind::DuplicateCase));
+                }
+
+                seen.push(bits);
+                values.push(bits);
+            }
+
+            let mut code = IrGenCodeTarget::new();
+            Code::append_nested_block_ir(&case.code, ctx, &mut code)?;
+            cases.push((values, code.take()));
+        }
+
+        // 3. Load the else code, which does nothing if it is missing
+        let mut default = IrGenCodeTarget::new();
+        if let Some(else_code) = &self.else_code {
+            Code::append_nested_block_ir(else_code, ctx, &mut default)?;
+        }
+
+        target.push(ir::Ins::Switch(vt.to_ir(), cases, default.take(), value.take()));
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use syntax::Span;
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
 use crate::lexer::{TokenKind, TokenStream};
 use crate::ast::{Expr, TypeExpr};
+use crate::types::StorableType;
 
 #[derive(Debug)]
 pub struct VarDeclaration {
@@ -46,7 +47,7 @@ impl VarDeclaration {
     pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
         // 1. Load the explicit type if it's given
         let expected_type = if let Some(var_type) = &self.var_type {
-            Some(var_type.to_ir_value_type(ctx.ir_unit)?)
+            Some(var_type.to_value_type(ctx.ir_unit, ctx.declarations)?)
         } else {
             None
         };
@@ -77,21 +78,21 @@ impl VarDeclaration {
         };
 
         // Create a new local
-        let idx = ctx.push_local(&self.name, ir::StorableType::Value(expr_type.clone()));
+        let idx = ctx.push_local(&self.name, StorableType::Value(expr_type.clone()));
 
         // 4. If there is an expression...
         if let Some(expr) = &self.expr {
             // ...push a path to the local
             target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(
-                ir::ValuePathOrigin::Local(idx, ir::StorableType::Value(expr_type.clone())),
-            ), expr_type.clone()));
+                ir::ValuePathOrigin::Local(idx, ir::StorableType::Value(expr_type.to_ir())),
+            ), expr_type.to_ir()));
 
             // ...load the value
             let v = expr.append_ir_value(ctx, target, Some(&expr_type))?;
             assert_eq!(&v, &expr_type);
             
             // ...push it
-            target.push(ir::Ins::Pop(expr_type));
+            target.push(ir::Ins::Pop(expr_type.to_ir()));
         }
 
         Ok(())
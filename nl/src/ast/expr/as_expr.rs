@@ -2,6 +2,7 @@ use syntax::Span;
 
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
 use crate::ast::TypeExpr;
+use crate::types::{StorableType, ValueType};
 
 use super::Expr;
 
@@ -13,29 +14,32 @@ pub struct AsExpr {
 }
 
 impl AsExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        match self.new_type.to_ir_storable_type(ctx.ir_unit)? {
-            ir::StorableType::Value(v) => Ok(v),
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        match self.new_type.to_storable_type(ctx.ir_unit, ctx.declarations)? {
+            StorableType::Value(v) => Ok(v),
             _ => Err(IrGenError::new(self.span.clone(), IrGenErrorKind::NonValueCast)),
         }
     }
 
-    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let desired_type = match self.new_type.to_ir_storable_type(ctx.ir_unit)? {
-            ir::StorableType::Value(v) => v,
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let desired_type = match self.new_type.to_storable_type(ctx.ir_unit, ctx.declarations)? {
+            StorableType::Value(v) => v,
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::NonValueCast)),
         };
 
-        let curr_type = self.expr.append_ir_value(ctx, target, Some(&desired_type))?;
+        // Casting to a distinct type is how its values are made from the underlying type, so prefer that
+        let curr_type = self.expr.append_ir_value(ctx, target, Some(desired_type.underlying()))?;
 
         // Only numbers can be cast, over values cannot be
         if !curr_type.is_num() || !desired_type.is_num() {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::NonValueCast));
         }
 
-        if curr_type == desired_type { return Ok(desired_type); }
+        // A distinct type is only converted to or from as its underlying type, which may already be the same
+        let (curr_type, desired_ir_type) = (curr_type.to_ir(), desired_type.to_ir());
+        if curr_type == desired_ir_type { return Ok(desired_type); }
 
-        target.push(ir::Ins::Convert(curr_type, desired_type.clone()));
+        target.push(ir::Ins::Convert(curr_type, desired_ir_type));
 
         Ok(desired_type)
     }
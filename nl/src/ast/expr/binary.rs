@@ -2,6 +2,8 @@ use syntax::Span;
 
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
 
+use crate::types::{StorableType, ValueType};
+
 use super::Expr;
 
 #[derive(Debug)]
@@ -26,16 +28,84 @@ impl BinaryOp {
             _ => false
         }
     }
+
+    /// The name of the method which implements this operator on compound types, if it can be overloaded
+    pub fn method_name(&self) -> Option<&'static str> {
+        match self {
+            BinaryOp::Add => Some("op_add"),
+            BinaryOp::Mul => Some("op_mul"),
+            BinaryOp::Div => Some("op_div"),
+            BinaryOp::Sub => Some("op_sub"),
+            BinaryOp::Eq => Some("op_eq"),
+            BinaryOp::Ne => Some("op_ne"),
+            BinaryOp::Lt => Some("op_lt"),
+            BinaryOp::Le => Some("op_le"),
+            BinaryOp::Gt => Some("op_gt"),
+            BinaryOp::Ge => Some("op_ge"),
+            BinaryOp::BoolAnd | BinaryOp::BoolOr => None
+        }
+    }
 }
 
 impl BinaryExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        self.left.resultant_type(ctx, if self.op.is_num() { preferred } else { None })
+    /// If the LHS is a compound type, find the method which implements this operator on it, such as `T.op_add(self, other: T): T`
+    fn find_operator_method<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, left: &ValueType) -> Result<Option<ir::FunctionIndex>, IrGenError> {
+        let compound = match left {
+            ValueType::Ref(r) => match r.as_ref() {
+                StorableType::Compound(c) => c.clone(),
+                _ => return Ok(None)
+            },
+            _ => return Ok(None)
+        };
+
+        let method = match self.op.method_name() {
+            Some(method) => method,
+            None => return Ok(None)
+        };
+
+        let func_idx = match ctx.ir_unit.find_method_index(compound.clone(), method) {
+            Some(idx) => idx,
+            None => {
+                let right = self.right.resultant_type(ctx, Some(left))?;
+                return Err(IrGenError::new(self.span.clone(),
+                    IrGenErrorKind::OperatorMethodMissing(method.to_string(), value_type_to_string(left), value_type_to_string(&right))
+                ));
+            }
+        };
+
+        let func = ctx.ir_unit.get_function(func_idx).unwrap();
+        if !func.is_virtual() || func.is_fallible() || func.signature().param_count() != 2 || func.signature().return_count() != 1 {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidOperatorMethod(method.to_string(), compound.name().to_string())));
+        }
+
+        Ok(Some(func_idx))
+    }
+
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let left = self.left.resultant_type(ctx, if self.op.is_num() { preferred } else { None })?;
+
+        match self.find_operator_method(ctx, &left)? {
+            Some(func_idx) => Ok(ctx.signature(func_idx).returns()[0].clone()),
+            None if self.op.is_num() => Ok(left),
+            None => Ok(ValueType::Bool)
+        }
     }
 
-    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         // 1. Load LHS
-        let left = self.left.append_ir_value(ctx, target, if self.op.is_num() { preferred } else { None })?;
+        let left = self.left.append_ir_operand(self.right.hoists(), ctx, target, if self.op.is_num() { preferred } else { None })?;
+
+        // Operators on compound types are calls to the method implementing them, with the LHS as self
+        if let Some(func_idx) = self.find_operator_method(ctx, &left)? {
+            let expected = ctx.signature(func_idx).params()[1].clone();
+            let right = self.right.append_ir_value(ctx, target, Some(&expected))?;
+            if right != expected {
+                return Err(IrGenError::new(self.right.span().clone(), IrGenErrorKind::CallArgTypeMismatch(value_type_to_string(&right), value_type_to_string(&expected))));
+            }
+
+            target.push(ir::Ins::Call(func_idx));
+            return Ok(ctx.signature(func_idx).returns()[0].clone());
+        }
         
         // 2. Load RHS
         let right = self.right.append_ir_value(ctx, target, if self.op.is_num() { preferred } else { Some(&left) })?;
@@ -48,19 +118,20 @@ impl BinaryExpr {
         }
 
         // 3. Do the operation
+        let vt = left.to_ir();
         target.push(match self.op {
-            BinaryOp::Add => ir::Ins::Add(left.clone()),
-            BinaryOp::Mul => ir::Ins::Mul(left.clone()),
-            BinaryOp::Div => ir::Ins::Div(left.clone()),
-            BinaryOp::Sub => ir::Ins::Sub(left.clone()),
+            BinaryOp::Add => ir::Ins::Add(vt.clone()),
+            BinaryOp::Mul => ir::Ins::Mul(vt.clone()),
+            BinaryOp::Div => ir::Ins::Div(vt.clone()),
+            BinaryOp::Sub => ir::Ins::Sub(vt.clone()),
             
-            BinaryOp::Eq => ir::Ins::Eq(left.clone()),
-            BinaryOp::Ne => ir::Ins::Ne(left.clone()),
+            BinaryOp::Eq => ir::Ins::Eq(vt.clone()),
+            BinaryOp::Ne => ir::Ins::Ne(vt.clone()),
             
-            BinaryOp::Lt => ir::Ins::Lt(left.clone()),
-            BinaryOp::Le => ir::Ins::Le(left.clone()),
-            BinaryOp::Gt => ir::Ins::Gt(left.clone()),
-            BinaryOp::Ge => ir::Ins::Ge(left.clone()),
+            BinaryOp::Lt => ir::Ins::Lt(vt.clone()),
+            BinaryOp::Le => ir::Ins::Le(vt.clone()),
+            BinaryOp::Gt => ir::Ins::Gt(vt.clone()),
+            BinaryOp::Ge => ir::Ins::Ge(vt.clone()),
 
             BinaryOp::BoolAnd => ir::Ins::BoolAnd,
             BinaryOp::BoolOr => ir::Ins::BoolOr,
@@ -68,7 +139,7 @@ impl BinaryExpr {
 
         match self.op {
             BinaryOp::Add | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Sub => Ok(left),
-            _ => Ok(ir::ValueType::Bool)
+            _ => Ok(ValueType::Bool)
         }
     }
 }
\ No newline at end of file
@@ -2,6 +2,8 @@ use syntax::Span;
 
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
 
+use crate::types::{StorableType, ValueType};
+
 use super::Expr;
 
 #[derive(Debug)]
@@ -47,8 +49,8 @@ impl CallExpr {
                 } else {
                     let v = member_access.object.resultant_type(ctx, None)?;
                     match v {
-                        ir::ValueType::Ref(r) => match r.as_ref() {
-                            ir::StorableType::Compound(c) => {
+                        ValueType::Ref(r) => match r.as_ref() {
+                            StorableType::Compound(c) => {
                                 match ctx.ir_unit.find_method_index(c.clone(), &member_access.prop) {
                                     Some(idx) => idx,
                                     _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::FunctionDoesNotExist(member_access.prop.clone()))),
@@ -66,15 +68,20 @@ impl CallExpr {
         Ok(func_idx)
     }
 
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let func = ctx.ir_unit.get_function(self.find_function_index(ctx)?).unwrap();
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let func_idx = self.find_function_index(ctx)?;
+        let func = ctx.ir_unit.get_function(func_idx).unwrap();
+
+        if func.is_fallible() {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::UnhandledFallibleCall));
+        }
 
         // Check return count, but does not check arguments since we are only trying to determine the type - nothing more
         if func.signature().return_count() != 1 {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
         }
 
-        Ok(func.signature().returns()[0].clone())
+        Ok(ctx.signature(func_idx).returns()[0].clone())
     }
 
     fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, in_expr: bool) -> Result<ir::FunctionIndex, IrGenError> {
@@ -85,11 +92,24 @@ impl CallExpr {
         let func_id = self.find_function_index(ctx)?;
         let func = ctx.ir_unit.get_function(func_id).unwrap();
 
+        // Errors from fallible functions cannot be ignored, they must go through a try or catch
+        if func.is_fallible() {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::UnhandledFallibleCall));
+        }
+
         // If we are in an expression, check we have exactly one return argument
         if in_expr && func.signature().return_count() != 1 {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
         }
-        
+
+        self.append_ir_call(ctx, target)
+    }
+
+    /// Push the arguments and call the function, leaving all of its return values on the stack - their count is not checked
+    pub fn append_ir_call<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<ir::FunctionIndex, IrGenError> {
+        let func_id = self.find_function_index(ctx)?;
+        let func = ctx.ir_unit.get_function(func_id).unwrap();
+
         if func.is_virtual() {
             // Check we have the correct number of arguments (+ 1 due to implicit self argument)
             if self.args.len() + 1 != func.signature().param_count() {
@@ -99,7 +119,7 @@ impl CallExpr {
             // Push the argument, we know it's there because we're in a virtual function
             match self.object.as_ref() {
                 Expr::MemberAccess(member_access) => {
-                    member_access.object.append_ir_value(ctx, target, None)?;
+                    member_access.object.append_ir_operand(self.args.iter().any(Expr::hoists), ctx, target, None)?;
                 },
                 _ => unreachable!()
             }
@@ -107,8 +127,8 @@ impl CallExpr {
             // Push the arguments to the stack...
             for (a, arg) in self.args.iter().enumerate() {
                 // Unfortunate repeated lookup, necessary since append_ir_value might mutate so the borrow checker gets mad
-                let expected = ctx.ir_unit.get_function(func_id).unwrap().signature().params()[a + 1].clone();
-                let found = arg.append_ir_value(ctx, target, Some(&expected))?;
+                let expected = ctx.signature(func_id).params()[a + 1].clone();
+                let found = arg.append_ir_operand(self.args[a + 1..].iter().any(Expr::hoists), ctx, target, Some(&expected))?;
                 if found != expected { // ...checking their types as we go
                     return Err(IrGenError::new(arg.span().clone(), IrGenErrorKind::CallArgTypeMismatch(value_type_to_string(&found), value_type_to_string(&expected))));
                 }
@@ -119,8 +139,8 @@ impl CallExpr {
             }
             
             for (a, arg) in self.args.iter().enumerate() {
-                let expected = ctx.ir_unit.get_function(func_id).unwrap().signature().params()[a].clone();
-                let found = arg.append_ir_value(ctx, target, Some(&expected))?;
+                let expected = ctx.signature(func_id).params()[a].clone();
+                let found = arg.append_ir_operand(self.args[a + 1..].iter().any(Expr::hoists), ctx, target, Some(&expected))?;
                 if found != expected {
                     return Err(IrGenError::new(arg.span().clone(), IrGenErrorKind::CallArgTypeMismatch(value_type_to_string(&found), value_type_to_string(&expected))));
                 }
@@ -132,9 +152,9 @@ impl CallExpr {
         Ok(func_id)
     }
 
-    pub fn append_ir_in_expr<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir_in_expr<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         let index = self.append_ir(ctx, target, true)?;
-        Ok(ctx.ir_unit.get_function(index).unwrap().signature().returns()[0].clone())
+        Ok(ctx.signature(index).returns()[0].clone())
     }
 
     // Returned usize is used in Code to drop the return values
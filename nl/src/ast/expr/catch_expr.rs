@@ -0,0 +1,95 @@
+use syntax::Span;
+
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
+
+use crate::types::ValueType;
+
+use super::{Expr, FallibleCallResult};
+
+#[derive(Debug)]
+pub struct CatchExpr {
+    pub span: Span,
+    pub expr: Box<Expr>,
+    pub binding: Option<String>,
+    pub fallback: Box<Expr>
+}
+
+impl CatchExpr {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let (payload, _) = FallibleCallResult::types_of(&self.expr, ctx)?;
+
+        if payload.len() != 1 {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
+        }
+
+        Ok(payload[0].clone())
+    }
+
+    /// Make the error available under the bound name, returning whatever the name referred to before
+    fn bind<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, result: &FallibleCallResult) -> Option<ir::LocalIndex> {
+        match &self.binding {
+            Some(name) => ctx.local_map.insert(name, result.error.0),
+            None => None
+        }
+    }
+
+    fn unbind<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, shadowed: Option<ir::LocalIndex>) {
+        if let Some(name) = &self.binding {
+            match shadowed {
+                Some(idx) => { ctx.local_map.insert(name, idx); },
+                None => { ctx.local_map.remove(name.as_str()); }
+            }
+        }
+    }
+
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        // 1. Make the call
+        let result = FallibleCallResult::append_ir(&self.expr, ctx, target)?;
+
+        if result.payload.len() != 1 {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
+        }
+        let (local, vt) = result.payload[0].clone();
+
+        // 2. If it failed, replace the value it returned with the fallback
+        let mut handler = IrGenCodeTarget::new();
+        let shadowed = self.bind(ctx, &result);
+
+        let ir_vt = vt.to_ir();
+        handler.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(local, ir::StorableType::Value(ir_vt.clone()))), ir_vt.clone()));
+        let found = self.fallback.append_ir_value(ctx, &mut handler, Some(&vt))?;
+        if found != vt {
+            return Err(IrGenError::new(self.fallback.span().clone(), IrGenErrorKind::CatchTypeMismatch(value_type_to_string(&found), value_type_to_string(&vt))));
+        }
+        handler.push(ir::Ins::Pop(ir_vt));
+
+        self.unbind(ctx, shadowed);
+
+        let mut cond = IrGenCodeTarget::new();
+        FallibleCallResult::push_local(result.failed, &ValueType::Bool, &mut cond);
+
+        target.push_hoisted(ir::Ins::If(handler.take(), cond.take()));
+
+        // 3. Either way, the value is now in the local
+        FallibleCallResult::push_local(local, &vt, target);
+
+        Ok(vt)
+    }
+
+    pub fn append_ir_out_expr<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let result = FallibleCallResult::append_ir(&self.expr, ctx, target)?;
+
+        // The result is unused, so the fallback is only run for its side effects
+        let mut handler = IrGenCodeTarget::new();
+        let shadowed = self.bind(ctx, &result);
+        self.fallback.append_ir_out_expr(ctx, &mut handler)?;
+        self.unbind(ctx, shadowed);
+
+        let mut cond = IrGenCodeTarget::new();
+        FallibleCallResult::push_local(result.failed, &ValueType::Bool, &mut cond);
+
+        target.push_hoisted(ir::Ins::If(handler.take(), cond.take()));
+
+        Ok(())
+    }
+}
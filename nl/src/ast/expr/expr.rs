@@ -3,6 +3,7 @@ use syntax::Span;
 use crate::lexer::{TokenKind, TokenStream};
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
 use crate::ast::TypeExpr;
+use crate::types::{Declarations, StorableType, ValueType};
 
 use super::*;
 
@@ -20,7 +21,10 @@ pub enum Expr {
     SliceLit(SliceLitExpr),
     NewExpr(NewExpr),
     BoolLit(BoolLitExpr),
-    Unary(UnaryExpr)
+    Unary(UnaryExpr),
+    Try(TryExpr),
+    Catch(CatchExpr),
+    If(IfExpr)
 }
 
 impl Expr {
@@ -42,12 +46,15 @@ impl Expr {
             Expr::NewExpr(expr) => &expr.span,
             Expr::SliceLit(expr) => &expr.span,
             Expr::BoolLit(expr) => &expr.span,
-            Expr::Unary(expr) => &expr.span
+            Expr::Unary(expr) => &expr.span,
+            Expr::Try(expr) => &expr.span,
+            Expr::Catch(expr) => &expr.span,
+            Expr::If(expr) => &expr.span
         }
     }
 
     /// Build the IR that references this value
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         match self {
             Expr::BinaryExpr(binary_expr) => binary_expr.append_ir(ctx, target, preferred),
             Expr::Name(name_expr) => name_expr.append_ir_value(ctx, target, preferred),
@@ -61,13 +68,68 @@ impl Expr {
             Expr::NewExpr(new_expr) => new_expr.append_ir_value(ctx, target, preferred),
             Expr::SliceLit(slice_lit_expr) => slice_lit_expr.append_ir_value(ctx, target, preferred),
             Expr::BoolLit(bool_lit_expr) => bool_lit_expr.append_ir_value(ctx, target, preferred),
-            Expr::Unary(unary) => unary.append_ir(ctx, target, preferred)
+            Expr::Unary(unary) => unary.append_ir(ctx, target, preferred),
+            Expr::Try(try_expr) => try_expr.append_ir_value(ctx, target, preferred),
+            Expr::Catch(catch_expr) => catch_expr.append_ir_value(ctx, target, preferred),
+            Expr::If(if_expr) => if_expr.append_ir_value(ctx, target, preferred)
         }
     }
 
+    /// Build the IR for an expression used as a statement, so any value it results in is unused
+    pub fn append_ir_out_expr<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        match self {
+            Expr::Call(call_expr) => call_expr.append_ir_out_expr(ctx, target),
+            Expr::Try(try_expr) => try_expr.append_ir_out_expr(ctx, target),
+            Expr::Catch(catch_expr) => catch_expr.append_ir_out_expr(ctx, target),
+            _ => {
+                self.append_ir_value(ctx, target, None)?;
+                target.push(ir::Ins::Drop); // Drop result as it's not used
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether building this expression hoists code to the start of the statement it is in, which a try or catch does to make its call
+    pub fn hoists(&self) -> bool {
+        match self {
+            Expr::BinaryExpr(binary_expr) => binary_expr.left.hoists() || binary_expr.right.hoists(),
+            Expr::Closed(closed_expr) => closed_expr.expr.hoists(),
+            Expr::Call(call_expr) => call_expr.object.hoists() || call_expr.args.iter().any(Expr::hoists),
+            Expr::MemberAccess(member_access) => member_access.object.hoists(),
+            Expr::Index(index_expr) => index_expr.object.hoists() || index_expr.arg.hoists(),
+            Expr::As(as_expr) => as_expr.expr.hoists(),
+            Expr::NewExpr(new_expr) => matches!(new_expr.new_type.slice_lengths.last(), Some(Some(expr)) if expr.hoists()),
+            Expr::Unary(unary) => unary.right.hoists(),
+            Expr::Try(_) | Expr::Catch(_) => true,
+            // The condition and branches of an if are built in their own targets, which keep whatever they hoist
+            Expr::If(_) => false,
+            Expr::Name(_) | Expr::NumberLit(_) | Expr::StringLit(_) | Expr::SliceLit(_) | Expr::BoolLit(_) => false
+        }
+    }
+
+    /// Build the IR for an operand which is evaluated before others. If any of those after it hoist code, its value is kept in a
+    /// temporary local which is hoisted ahead of them, so that the operands are still evaluated from left to right.
+    pub fn append_ir_operand<'a>(&'a self, later_hoists: bool, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        if !later_hoists {
+            return self.append_ir_value(ctx, target, preferred);
+        }
+
+        let mut operand = IrGenCodeTarget::new();
+        let vt = self.append_ir_value(ctx, &mut operand, preferred)?;
+        let local = ctx.push_temp_local(StorableType::Value(vt.clone()));
+        let ir_vt = vt.to_ir();
+        operand.push(ir::Ins::PopLocal(local, ir_vt.clone()));
+        target.hoist(operand);
+
+        target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(local, ir::StorableType::Value(ir_vt.clone()))), ir_vt.clone()));
+        target.push(ir::Ins::Push(ir_vt));
+
+        Ok(vt)
+    }
+
     /// Predicts the type of the object without building an IR - should always match the result of append_ir_value.
     /// This *DOES NOT* necessarily verify that the code is semantically correct, append_ir_value and construct_path do that
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         match self {
             Expr::BinaryExpr(binary_expr) => binary_expr.resultant_type(ctx, preferred),
             Expr::Name(name_expr) => name_expr.resultant_type(ctx, preferred),
@@ -81,12 +143,15 @@ impl Expr {
             Expr::NewExpr(new_expr) => new_expr.resultant_type(ctx, preferred),
             Expr::SliceLit(slice_lit_expr) => slice_lit_expr.resultant_type(ctx, preferred),
             Expr::BoolLit(bool_lit_expr) => bool_lit_expr.resultant_type(ctx, preferred),
-            Expr::Unary(unary) => unary.resultant_type(ctx, preferred)
+            Expr::Unary(unary) => unary.resultant_type(ctx, preferred),
+            Expr::Try(try_expr) => try_expr.resultant_type(ctx, preferred),
+            Expr::Catch(catch_expr) => catch_expr.resultant_type(ctx, preferred),
+            Expr::If(if_expr) => if_expr.resultant_type(ctx, preferred)
         }
     }
 
     /// Builds a path to the location that the expression returns to - allows it to be written to.
-    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<(ir::StorableType, ir::ValuePath), IrGenError> {
+    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<(StorableType, ir::ValuePath), IrGenError> {
         match self {
             Expr::BinaryExpr(binary_expr) => return Err(IrGenError::new(binary_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
             Expr::Name(name_expr) => name_expr.construct_path_to(ctx, target, preferred),
@@ -101,30 +166,33 @@ impl Expr {
             Expr::SliceLit(slice_lit_expr) => return Err(IrGenError::new(slice_lit_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
             Expr::BoolLit(bool_lit_expr) => return Err(IrGenError::new(bool_lit_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
             Expr::Unary(unary) => return Err(IrGenError::new(unary.span.clone(), IrGenErrorKind::InvalidLHS)),
+            Expr::Try(try_expr) => return Err(IrGenError::new(try_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
+            Expr::Catch(catch_expr) => return Err(IrGenError::new(catch_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
+            Expr::If(if_expr) => return Err(IrGenError::new(if_expr.span.clone(), IrGenErrorKind::InvalidLHS)),
         }
     }
 
     /// Will convert an expression to a Value of a given ValueType (if possible)
-    pub fn as_value(&self, ir_unit: &ir::TranslationUnit, value_type: &ir::ValueType) -> Result<ir::Value, IrGenError> {
+    pub fn as_value(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations, value_type: &ValueType) -> Result<ir::Value, IrGenError> {
         match self {
             Expr::NumberLit(num) =>
-                Ok(match value_type {
-                    ir::ValueType::U8 => ir::Value::U8(num.number.parse().unwrap()),
-                    ir::ValueType::I8 => ir::Value::I8(num.number.parse().unwrap()),
-                    ir::ValueType::U16 => ir::Value::U16(num.number.parse().unwrap()),
-                    ir::ValueType::I16 => ir::Value::I16(num.number.parse().unwrap()),
-                    ir::ValueType::U32 => ir::Value::U32(num.number.parse().unwrap()),
-                    ir::ValueType::I32 => ir::Value::I32(num.number.parse().unwrap()),
-                    ir::ValueType::U64 => ir::Value::U64(num.number.parse().unwrap()),
-                    ir::ValueType::I64 => ir::Value::I64(num.number.parse().unwrap()),
+                Ok(match value_type.underlying() {
+                    ValueType::U8 => ir::Value::U8(num.number.parse().unwrap()),
+                    ValueType::I8 => ir::Value::I8(num.number.parse().unwrap()),
+                    ValueType::U16 => ir::Value::U16(num.number.parse().unwrap()),
+                    ValueType::I16 => ir::Value::I16(num.number.parse().unwrap()),
+                    ValueType::U32 => ir::Value::U32(num.number.parse().unwrap()),
+                    ValueType::I32 => ir::Value::I32(num.number.parse().unwrap()),
+                    ValueType::U64 => ir::Value::U64(num.number.parse().unwrap()),
+                    ValueType::I64 => ir::Value::I64(num.number.parse().unwrap()),
                     _ => ir::Value::I32(num.number.parse().unwrap()),
                 }),
             Expr::As(as_expr) => {
-                if as_expr.new_type.to_ir_value_type(ir_unit)? != *value_type {
+                if as_expr.new_type.to_value_type(ir_unit, declarations)? != *value_type {
                     return Err(IrGenError::new(as_expr.span.clone(), IrGenErrorKind::CastTypeMismatch));
                 }
 
-                return as_expr.expr.as_value(ir_unit, value_type);
+                return as_expr.expr.as_value(ir_unit, declarations, value_type);
             },
             // FIXME we don't *technically* know this is a slice
             // TODO handle compile time known expressions, e.g. 1 + 2
@@ -137,7 +205,8 @@ impl Expr {
     //      2. Then * and / are parsed
     //      3. Followed by + and -
     //      4. Then >, >=, <, <=
-    //      5. Finally && and ||
+    //      5. Then && and ||
+    //      6. Finally catch
     // Doing this means that
     //      5*4 - 3/2 > 3 || a <= b + c
     // is equivalent to
@@ -190,22 +259,26 @@ impl Expr {
                     op: UnaryOp::Neg
                 })
             },
-            Some(TokenKind::Number(s)) => {
-                let number = s.to_string();
+            Some(TokenKind::TryKeyword) => {
                 stream.step();
-                Expr::NumberLit(NumberLitExpr {
+
+                let expr = Box::new(syntax::ex!(syntax::parse!(stream, Expr::parse_primary), stream.error("Expected expression after try")));
+
+                Expr::Try(TryExpr {
                     span: syntax::Span::new(start, stream.tell_start()),
-                    number
+                    expr
                 })
             },
-            Some(TokenKind::StringLit(s)) => {
-                let s = s.to_string();
+            Some(TokenKind::Number(s)) => {
+                let number = s.to_string();
                 stream.step();
-                Expr::StringLit(StringLitExpr {
+                Expr::NumberLit(NumberLitExpr {
                     span: syntax::Span::new(start, stream.tell_start()),
-                    value: s
+                    number
                 })
             },
+            Some(TokenKind::StringLit(_)) => Expr::StringLit(syntax::ex!(syntax::parse!(stream, StringLitExpr::parse))),
+            Some(TokenKind::IfKeyword) => Expr::If(syntax::ex!(syntax::parse!(stream, IfExpr::parse))),
             Some(TokenKind::Ident(s)) => {
                 let name = s.to_string();
                 stream.step();
@@ -441,6 +514,30 @@ impl Expr {
     }
 
     pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<Expr> {
-        Expr::parse_op_bool(stream)
+        let start = stream.tell_start();
+        let expr = syntax::ex!(syntax::parse!(stream, Expr::parse_op_bool));
+
+        // A catch has the lowest precedence of all, and can optionally bind the error to a name in parenthesis
+        if !syntax::tk_iss!(stream, TokenKind::CatchKeyword) {
+            return syntax::MatchResult::Ok(expr);
+        }
+
+        let binding = if syntax::tk_iss!(stream, TokenKind::OpenParen) {
+            let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected a name")).to_owned();
+            stream.step();
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+            Some(name)
+        } else {
+            None
+        };
+
+        let fallback = syntax::ex!(syntax::parse!(stream, Expr::parse_op_bool), stream.error("Expected expression after catch"));
+
+        syntax::MatchResult::Ok(Expr::Catch(CatchExpr {
+            span: syntax::Span::new(start, stream.tell_start()),
+            expr: Box::new(expr),
+            binding,
+            fallback: Box::new(fallback)
+        }))
     }
 }
\ No newline at end of file
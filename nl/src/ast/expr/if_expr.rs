@@ -0,0 +1,83 @@
+use syntax::Span;
+
+use crate::lexer::{TokenKind, TokenStream};
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
+
+use crate::types::ValueType;
+
+use super::Expr;
+
+#[derive(Debug)]
+pub struct IfExpr {
+    pub span: Span,
+    pub condition: Box<Expr>,
+    pub value: Box<Expr>,
+    pub else_value: Box<Expr>
+}
+
+impl IfExpr {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<IfExpr> {
+        let start = stream.tell_start();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::IfKeyword));
+
+        // Parse the condition
+        let condition = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected condition"));
+
+        // Parse the value, which is always surrounded by curly brackets
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+        let value = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected expression"));
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+
+        // There must be an else, either as a value in curly brackets or another if expression
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::ElseKeyword), stream.error("Expected else, an if expression must have a value either way"));
+        let else_value = if syntax::tk_is!(stream, TokenKind::IfKeyword) {
+            Expr::If(syntax::ex!(syntax::parse!(stream, IfExpr::parse), stream.error("Expected if expression")))
+        } else {
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenCurly), stream.error("Expected '{'"));
+            let else_value = syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected expression"));
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseCurly), stream.error("Expected '}'"));
+            else_value
+        };
+
+        syntax::MatchResult::Ok(IfExpr {
+            span: syntax::Span::new(start, stream.tell_start()),
+            condition: Box::new(condition),
+            value: Box::new(value),
+            else_value: Box::new(else_value)
+        })
+    }
+
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        self.value.resultant_type(ctx, preferred)
+    }
+
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        // 1. Load the condition
+        let mut cond = IrGenCodeTarget::new();
+        if self.condition.append_ir_value(ctx, &mut cond, Some(&ValueType::Bool))? != ValueType::Bool {
+            return Err(IrGenError::new(self.condition.span().clone(), IrGenErrorKind::NotABool));
+        }
+
+        // 2. Load the value of each branch, the else value must be of the same type as the first
+        let mut true_then = IrGenCodeTarget::new();
+        let vt = self.value.append_ir_value(ctx, &mut true_then, preferred)?;
+
+        let mut false_then = IrGenCodeTarget::new();
+        let else_vt = self.else_value.append_ir_value(ctx, &mut false_then, Some(&vt))?;
+        if else_vt != vt {
+            return Err(IrGenError::new(self.else_value.span().clone(),
+                IrGenErrorKind::IfExprTypeMismatch(value_type_to_string(&else_vt), value_type_to_string(&vt))
+            ));
+        }
+
+        // 3. Emit an IfElse, which leaves the value of whichever branch was taken
+        target.push(ir::Ins::IfElse(
+            Some(vt.to_ir()),
+            true_then.take(),
+            false_then.take(),
+            cond.take()
+        ));
+
+        Ok(vt)
+    }
+}
@@ -2,6 +2,8 @@ use syntax::Span;
 
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
 
+use crate::types::{StorableType, ValueType};
+
 use super::Expr;
 
 #[derive(Debug)]
@@ -12,11 +14,11 @@ pub struct IndexExpr {
 }
 
 impl IndexExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         match self.object.resultant_type(ctx, None)? {
-            ir::ValueType::Ref(st) => match st.as_ref() {
-                ir::StorableType::Slice(t) => match t.as_ref() {
-                    ir::StorableType::Value(v) => Ok(v.clone()),
+            ValueType::Ref(st) => match st.as_ref() {
+                StorableType::Slice(t) => match t.as_ref() {
+                    StorableType::Value(v) => Ok(v.clone()),
                     _ => {
                         Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
                     }
@@ -29,16 +31,16 @@ impl IndexExpr {
         }
     }
 
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {        
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {        
         // 1. Load the index as a uptr
-        if self.arg.append_ir_value(ctx, target, Some(&ir::ValueType::UPtr))? != ir::ValueType::UPtr {
+        if self.arg.append_ir_operand(self.object.hoists(), ctx, target, Some(&ValueType::UPtr))? != ValueType::UPtr {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexValue));
         }
 
         // 2. Convert it into an index
         target.push(ir::Ins::Index(match self.object.resultant_type(ctx, None)? {
-            ir::ValueType::Ref(st) => match st.as_ref() {
-                ir::StorableType::Slice(t) => t.as_ref().clone(),
+            ValueType::Ref(st) => match st.as_ref() {
+                StorableType::Slice(t) => t.to_ir(),
                 _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
             },
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
@@ -46,8 +48,8 @@ impl IndexExpr {
 
         // 3. Load the slice reference
         let el = match self.object.append_ir_value(ctx, target, None)? {
-            ir::ValueType::Ref(st) => match st.as_ref() {
-                ir::StorableType::Slice(t) => t.clone(),
+            ValueType::Ref(st) => match st.as_ref() {
+                StorableType::Slice(t) => t.clone(),
                 _ => {
                     return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
                 }
@@ -56,34 +58,35 @@ impl IndexExpr {
         };
 
         let vt = match el.as_ref() {
-            ir::StorableType::Value(val) => val,
+            StorableType::Value(val) => val,
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidRHS))
         };
 
         // 4. Create a path to access it
+        let (el, ir_vt) = (el.to_ir(), vt.to_ir());
         target.push(ir::Ins::PushPath(ir::ValuePath::new(
-            ir::ValuePathOrigin::Deref(ir::StorableType::Slice(el.clone())),
+            ir::ValuePathOrigin::Deref(ir::StorableType::Slice(Box::new(el))),
             vec![
-                ir::ValuePathComponent::Slice(ir::StorableType::Value(vt.clone()))
+                ir::ValuePathComponent::Slice(ir::StorableType::Value(ir_vt.clone()))
             ]
-        ), vt.clone()));
+        ), ir_vt.clone()));
 
         // 5. Derefence it
-        target.push(ir::Ins::Push(vt.clone()));
+        target.push(ir::Ins::Push(ir_vt));
 
         Ok(vt.clone())
     }
 
-    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<(ir::StorableType, ir::ValuePath), IrGenError> {
+    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<(StorableType, ir::ValuePath), IrGenError> {
         // 1. Load the index as a uptr
-        if self.arg.append_ir_value(ctx, target, Some(&ir::ValueType::UPtr))? != ir::ValueType::UPtr {
+        if self.arg.append_ir_operand(self.object.hoists(), ctx, target, Some(&ValueType::UPtr))? != ValueType::UPtr {
             return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexValue));
         }
         
         // 2. Convert it into an index
         target.push(ir::Ins::Index(match self.object.resultant_type(ctx, None)? {
-            ir::ValueType::Ref(st) => match st.as_ref() {
-                ir::StorableType::Slice(t) => t.as_ref().clone(),
+            ValueType::Ref(st) => match st.as_ref() {
+                StorableType::Slice(t) => t.to_ir(),
                 _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
             },
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
@@ -91,18 +94,19 @@ impl IndexExpr {
         
         // 3. Load the object (a reference to a slice)
         let el = match self.object.append_ir_value(ctx, target, None)? {
-            ir::ValueType::Ref(st) => match st.as_ref() {
-                ir::StorableType::Slice(t) => t.clone(),
+            ValueType::Ref(st) => match st.as_ref() {
+                StorableType::Slice(t) => t.clone(),
                 _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
             },
             _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexObject))
         };
 
         // Return the path, which derefs the slice ref and indexes into it
+        let ir_el = el.to_ir();
         Ok((el.as_ref().clone(), ir::ValuePath::new(
-            ir::ValuePathOrigin::Deref(ir::StorableType::Slice(el.clone())),
+            ir::ValuePathOrigin::Deref(ir::StorableType::Slice(Box::new(ir_el.clone()))),
             vec![
-                ir::ValuePathComponent::Slice(el.as_ref().clone())
+                ir::ValuePathComponent::Slice(ir_el)
             ]
         )))
     }
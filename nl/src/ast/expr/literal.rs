@@ -1,6 +1,8 @@
 use syntax::Span;
 
 use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
+use crate::lexer::{TokenKind, TokenStream};
+use crate::types::{StorableType, ValueType};
 
 use super::Expr;
 
@@ -29,130 +31,170 @@ pub struct BoolLitExpr {
 }
 
 impl StringLitExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let st = ir::StorableType::Compound(match ctx.ir_unit.find_type("String") {
-            Some(x) => x,
-            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::StdLinkError))
-        });
-
-        Ok(ir::ValueType::Ref(Box::new(st)))
+    /// Strings are UTF-8, which the lexer only guarantees for the source text itself, so any escapes must be checked
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<StringLitExpr> {
+        let start = stream.tell_start();
+        let bytes = syntax::ex!(syntax::tk_v!(stream, TokenKind::StringLit)).clone();
+        stream.step();
+
+        let span = syntax::Span::new(start, stream.tell_start());
+        match String::from_utf8(bytes) {
+            Ok(value) => syntax::MatchResult::Ok(StringLitExpr { span, value }),
+            Err(_) => syntax::MatchResult::Err(syntax::SyntaxError::new(span, "String is not valid UTF-8, escapes must not be surrogates or above U+10FFFF"))
+        }
     }
 
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let st = ir::StorableType::Compound(match ctx.ir_unit.find_type("String") {
-            Some(x) => x,
-            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::StdLinkError))
-        });
+    /// Adds a global holding a String struct with the given value, st must be the String type. If read_only is set, the
+    /// string and its contents must never be written to.
+    pub fn add_global(ir_unit: &mut ir::TranslationUnit, st: ir::StorableType, value: &str, read_only: bool) -> ir::GlobalIndex {
+        let new_global = if read_only { ir::Global::new_read_only::<String> } else { ir::Global::new_default::<String> };
 
         // 1. Store the raw bytes of the string
-        let raw_data = ctx.ir_unit.add_global(ir::Global::new_default::<String>(
+        let raw_data = ir_unit.add_global(new_global(
             None,
             ir::StorableType::SliceData(Box::new(ir::StorableType::Value(ir::ValueType::U8))),
-            ir::StorableValue::SliceData(self.value.as_bytes().iter().map(|x| ir::StorableValue::Value(ir::Value::U8(*x))).collect())
+            ir::StorableValue::SliceData(value.as_bytes().iter().map(|x| ir::StorableValue::Value(ir::Value::U8(*x))).collect())
         ));
 
         // 2. Store a slice which refers to the raw bytes
-        let raw_slice = ctx.ir_unit.add_global(ir::Global::new_default::<String>(
+        let raw_slice = ir_unit.add_global(new_global(
             None,
             ir::StorableType::Slice(Box::new(ir::StorableType::Value(ir::ValueType::U8))),
-            ir::StorableValue::Slice(raw_data, 0, self.value.as_bytes().len())
+            ir::StorableValue::Slice(raw_data, 0, value.as_bytes().len())
         ));
 
         // 3. Create the struct value, which refers to the slice
-        let string_id = ctx.ir_unit.add_global(ir::Global::new_default::<String>(
+        ir_unit.add_global(new_global(
             None, 
-            st.clone(),
+            st,
             ir::StorableValue::Compound(ir::CompoundValue::Struct(ir::StructValue::new(vec![
                 ir::StructPropertyValue::new(ir::StorableValue::Value(ir::Value::Ref(raw_slice)))
             ])))
-        ));
+        ))
+    }
+
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let st = StorableType::Compound(match ctx.ir_unit.find_type("String") {
+            Some(x) => x,
+            _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::StdLinkError))
+        });
+
+        Ok(ValueType::Ref(Box::new(st)))
+    }
+
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        StringLitExpr::append_ir_value_of(ctx.ir_unit, &self.span, &self.value, target)
+    }
 
-        // 4. Create a reference to the string struct
+    /// Pushes a reference to a new String with the given value, for code which has no StringLitExpr of its own
+    pub fn append_ir_value_of(ir_unit: &mut ir::TranslationUnit, span: &Span, value: &str, target: &mut IrGenCodeTarget) -> Result<ValueType, IrGenError> {
+        let ct = match ir_unit.find_type("String") {
+            Some(x) => x,
+            _ => return Err(IrGenError::new(span.clone(), IrGenErrorKind::StdLinkError))
+        };
+        let st = ir::StorableType::Compound(ct.clone());
+
+        // 1. Store the string struct, which refers to the raw bytes
+        let string_id = StringLitExpr::add_global(ir_unit, st.clone(), value, false);
+
+        // 2. Create a reference to the string struct
         // FIXME: Is this correct?
-        let id = ctx.ir_unit.add_global(ir::Global::new_default::<String>(
+        let id = ir_unit.add_global(ir::Global::new_default::<String>(
             None, 
             ir::StorableType::Value(ir::ValueType::Ref(Box::new(st.clone()))),
             ir::StorableValue::Value(ir::Value::Ref(string_id))
         ));
 
-        // 5. Push a path to the global
+        // 3. Push a path to the global
         target.push(ir::Ins::PushPath(
             ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Global(id, ir::StorableType::Value(ir::ValueType::Ref(Box::new(st.clone()))))),
             ir::ValueType::Ref(Box::new(st.clone()))
         ));
 
-        // 6. Derefence to it
-        target.push(ir::Ins::Push(ir::ValueType::Ref(Box::new(st.clone()))));
+        // 4. Derefence to it
+        target.push(ir::Ins::Push(ir::ValueType::Ref(Box::new(st))));
 
-        Ok(ir::ValueType::Ref(Box::new(st)))
+        Ok(ValueType::Ref(Box::new(StorableType::Compound(ct))))
     }
 }
 
 impl NumberLitExpr {
-    pub fn resultant_type<'a>(&'a self, _ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn resultant_type<'a>(&'a self, _ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         Ok(match preferred {
             Some(vt) if vt.is_num() => vt.clone(),
-            _ => ir::ValueType::I32
+            _ => ValueType::I32
         })
     }
 
-    pub fn append_ir<'a>(&'a self, _ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir<'a>(&'a self, _ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let (vt, val) = self.typed_value(preferred)?;
+        target.push(ir::Ins::PushLiteral(vt.to_ir(), val));
+        
+        Ok(vt)
+    }
+
+    /// The type of the literal, preferring the given type if the number fits it, and the bits of its value in that type as pushed by PushLiteral
+    pub fn typed_value(&self, preferred: Option<&ValueType>) -> Result<(ValueType, u64), IrGenError> {
         use std::str::FromStr;
 
-        let (vt, val) = match preferred {
+        let (vt, val) = match preferred.map(ValueType::underlying) {
             // Only 0 and 1 are valid in casting to a boolean
-            Some(ir::ValueType::Bool) if self.number == "0" => {
-                (ir::ValueType::Bool, Ok(0))
+            Some(ValueType::Bool) if self.number == "0" => {
+                (ValueType::Bool, Ok(0))
             },
-            Some(ir::ValueType::Bool) if self.number == "1" => {
-                (ir::ValueType::Bool, Ok(1))
+            Some(ValueType::Bool) if self.number == "1" => {
+                (ValueType::Bool, Ok(1))
             },
 
-            Some(ir::ValueType::U8) => (ir::ValueType::U8, u8::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::I8) => (ir::ValueType::I8, i8::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::U16) => (ir::ValueType::U16, u16::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::I16) => (ir::ValueType::I16, i16::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::U32) => (ir::ValueType::U32, u32::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::U8) => (ValueType::U8, u8::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::I8) => (ValueType::I8, i8::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::U16) => (ValueType::U16, u16::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::I16) => (ValueType::I16, i16::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::U32) => (ValueType::U32, u32::from_str(&self.number).map(|x| x as u64)),
             // I32 falls through to end
-            Some(ir::ValueType::U64) => (ir::ValueType::U64, u64::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::I64) => (ir::ValueType::I64, i64::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::U64) => (ValueType::U64, u64::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::I64) => (ValueType::I64, i64::from_str(&self.number).map(|x| x as u64)),
 
             // FIXME: Should these bu u/i64
-            Some(ir::ValueType::UPtr) => (ir::ValueType::UPtr, u64::from_str(&self.number).map(|x| x as u64)),
-            Some(ir::ValueType::IPtr) => (ir::ValueType::IPtr, i64::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::UPtr) => (ValueType::UPtr, u64::from_str(&self.number).map(|x| x as u64)),
+            Some(ValueType::IPtr) => (ValueType::IPtr, i64::from_str(&self.number).map(|x| x as u64)),
             
             // Fall back to signed 32 bit
-            _ => (ir::ValueType::I32, i32::from_str(&self.number).map(|x| x as u64))
+            _ => (ValueType::I32, i32::from_str(&self.number).map(|x| x as u64))
         };
 
-        target.push(ir::Ins::PushLiteral(vt.clone(), match val {
-            Ok(val) => val,
-            Err(_) => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidInteger))
-        }));
-        
-        Ok(vt)
+        // A literal can be of a distinct type directly, without going through its underlying type
+        let vt = match preferred {
+            Some(distinct @ ValueType::Distinct(_)) if distinct.underlying() == &vt => distinct.clone(),
+            _ => vt
+        };
+
+        match val {
+            Ok(val) => Ok((vt, val)),
+            Err(_) => Err(IrGenError::new(self.span.clone(), IrGenErrorKind::InvalidInteger))
+        }
     }
 }
 
 impl SliceLitExpr {
-    fn slice_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    fn slice_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         // If we have no elements, either use the preferred type, or fall back to i32
         if self.values.len() == 0 {
-            if let Some(ir::ValueType::Ref(ref_target)) = preferred {
-                if let ir::StorableType::Slice(st) = ref_target.as_ref() {
-                    if let ir::StorableType::Value(vt) = st.as_ref() {
+            if let Some(ValueType::Ref(ref_target)) = preferred {
+                if let StorableType::Slice(st) = ref_target.as_ref() {
+                    if let StorableType::Value(vt) = st.as_ref() {
                         return Ok(vt.clone());
                     }
                 }
             }
 
-            return Ok(ir::ValueType::I32);
+            return Ok(ValueType::I32);
         }
 
         // Get the resultant type of the first element, using the existing preferred type
-        if let Some(ir::ValueType::Ref(ref_target)) = preferred {
-            if let ir::StorableType::Slice(st) = ref_target.as_ref() {
-                if let ir::StorableType::Value(vt) = st.as_ref() {
+        if let Some(ValueType::Ref(ref_target)) = preferred {
+            if let StorableType::Slice(st) = ref_target.as_ref() {
+                if let StorableType::Value(vt) = st.as_ref() {
                     return self.values[0].resultant_type(ctx, Some(vt));
                 }
             }
@@ -162,19 +204,20 @@ impl SliceLitExpr {
         self.values[0].resultant_type(ctx, None)
     }
 
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        Ok(ir::ValueType::Ref(Box::new(ir::StorableType::Slice(Box::new(ir::StorableType::Value(
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        Ok(ValueType::Ref(Box::new(StorableType::Slice(Box::new(StorableType::Value(
             self.slice_type(ctx, preferred)?
         ))))))
     }
 
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let resultant_type = self.slice_type(ctx, preferred)?;
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let element_type = self.slice_type(ctx, preferred)?;
+        let resultant_type = element_type.to_ir();
 
         // 1. Load all the values as compile time constants
         let mut values = Vec::with_capacity(self.values.len());
         for value in &self.values {
-            values.push(ir::StorableValue::Value(value.as_value(ctx.ir_unit, &resultant_type)?));
+            values.push(ir::StorableValue::Value(value.as_value(ctx.ir_unit, ctx.declarations, &element_type)?));
         }
 
         // 2. Store the raw values of the array
@@ -206,19 +249,19 @@ impl SliceLitExpr {
         ));
 
         // 6. Dereference it
-        target.push(ir::Ins::Push(ir::ValueType::Ref(Box::new(slice.clone()))));
+        target.push(ir::Ins::Push(ir::ValueType::Ref(Box::new(slice))));
 
-        Ok(ir::ValueType::Ref(Box::new(slice)))
+        Ok(ValueType::Ref(Box::new(StorableType::Slice(Box::new(StorableType::Value(element_type))))))
     }
 }
 
 impl BoolLitExpr {
-    pub fn resultant_type<'a>(&'a self, _ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        Ok(ir::ValueType::Bool)
+    pub fn resultant_type<'a>(&'a self, _ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        Ok(ValueType::Bool)
     }
 
-    pub fn append_ir_value<'a>(&'a self, _ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir_value<'a>(&'a self, _ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, if self.value { 1 } else { 0 }));
-        Ok(ir::ValueType::Bool)
+        Ok(ValueType::Bool)
     }
 }
\ No newline at end of file
@@ -1,23 +1,29 @@
 mod as_expr;
 mod binary;
 mod call;
+mod catch_expr;
 mod closed_expr;
 mod expr;
+mod if_expr;
 mod index;
 mod literal;
 mod member_access;
 mod name;
 mod new;
+mod try_expr;
 mod unary;
 
 pub use as_expr::*;
 pub use binary::*;
 pub use call::*;
+pub use catch_expr::*;
 pub use closed_expr::*;
 pub use expr::*;
+pub use if_expr::*;
 pub use index::*;
 pub use literal::*;
 pub use member_access::*;
 pub use name::*;
 pub use new::*;
+pub use try_expr::*;
 pub use unary::*;
\ No newline at end of file
@@ -1,6 +1,6 @@
 use syntax::Span;
 
-use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
+use crate::{irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext}, types::{StorableType, ValueType}};
 
 #[derive(Debug)]
 pub struct NameExpr {
@@ -14,12 +14,10 @@ impl NameExpr {
     // Note that when a type of function is referenced, it is also a NameExpr in the AST,
     // but it will be treated differently by the parent Node, e.g. MemberAccess or Call
 
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         if let Some(idx) = ctx.local_map.get(self.name.as_str()) {
-            let st = ctx.func().get_local(*idx).unwrap().local_type();
-
-            match st {
-                ir::StorableType::Value(vt) => {
+            match ctx.local_type(*idx) {
+                StorableType::Value(vt) => {
                     Ok(vt.clone())
                 },
                 _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CompositeTypeOnStack)),
@@ -29,21 +27,20 @@ impl NameExpr {
         }
     }
 
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         if let Some(idx) = ctx.local_map.get(self.name.as_str()) {
             let idx = *idx;
             
-            let st = ctx.func().get_local(idx).unwrap().local_type();
-
-            match st {
-                ir::StorableType::Value(vt) => {
+            match ctx.local_type(idx) {
+                StorableType::Value(vt) => {
+                    let ir_vt = vt.to_ir();
                     target.push(ir::Ins::PushPath(
                         ir::ValuePath::new_origin_only(
-                            ir::ValuePathOrigin::Local(idx, st.clone()),
+                            ir::ValuePathOrigin::Local(idx, ir::StorableType::Value(ir_vt.clone())),
                         ),
-                        vt.clone()
+                        ir_vt.clone()
                     ));
-                    target.push(ir::Ins::Push(vt.clone()));
+                    target.push(ir::Ins::Push(ir_vt));
                     Ok(vt.clone())
                 },
                 _ => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CompositeTypeOnStack)),
@@ -53,14 +50,14 @@ impl NameExpr {
         }
     }
 
-    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, _target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<(ir::StorableType, ir::ValuePath), IrGenError> {
+    pub fn construct_path_to<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, _target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<(StorableType, ir::ValuePath), IrGenError> {
         if let Some(idx) = ctx.local_map.get(self.name.as_str()) {
             let idx = *idx;
             
-            let st = ctx.func().get_local(idx).unwrap().local_type();
+            let st = ctx.local_type(idx);
 
             Ok((st.clone(), ir::ValuePath::new_origin_only(
-                ir::ValuePathOrigin::Local(idx, st.clone()),
+                ir::ValuePathOrigin::Local(idx, st.to_ir()),
             )))
         } else {
             Err(IrGenError::new(self.span.clone(), IrGenErrorKind::VariableDoesNotExist(self.name.clone())))
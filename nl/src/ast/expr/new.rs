@@ -1,6 +1,6 @@
 use syntax::Span;
 
-use crate::{ast::TypeExpr, irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext}};
+use crate::{ast::TypeExpr, irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext}, types::{StorableType, ValueType}};
 
 #[derive(Debug)]
 pub struct NewExpr {
@@ -9,19 +9,19 @@ pub struct NewExpr {
 }
 
 impl NewExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let st = self.new_type.to_ir_storable_type(ctx.ir_unit)?;
-        Ok(ir::ValueType::Ref(Box::new(st)))
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let st = self.new_type.to_storable_type(ctx.ir_unit, ctx.declarations)?;
+        Ok(ValueType::Ref(Box::new(st)))
     }
 
-    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
-        let st = self.new_type.to_ir_storable_type(ctx.ir_unit)?;
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let st = self.new_type.to_storable_type(ctx.ir_unit, ctx.declarations)?;
         match &st {
-            ir::StorableType::Slice(slice_st) => {
+            StorableType::Slice(slice_st) => {
                 // 1. Push the length (could be calculated at runtime)
                 // .last because we are not created an N dimensional array, we are only creating an array of references
                 if let Some(Some(expr)) = self.new_type.slice_lengths.last() {
-                    if expr.append_ir_value(ctx, target, Some(&ir::ValueType::UPtr))? != ir::ValueType::UPtr {
+                    if expr.append_ir_value(ctx, target, Some(&ValueType::UPtr))? != ValueType::UPtr {
                         return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::IllegalIndexValue));
                     }
                 } else {
@@ -29,15 +29,14 @@ impl NewExpr {
                 }
 
                 // 2. Allocate the slice
-                target.push(ir::Ins::NewSlice(slice_st.as_ref().clone()));
+                target.push(ir::Ins::NewSlice(slice_st.to_ir()));
             },
-            ir::StorableType::SliceData(_) => unreachable!(),
             _ => {
                 // 1. Simply allocate the object - size is not controlled by the programmer
-                target.push(ir::Ins::New(st.clone()));
+                target.push(ir::Ins::New(st.to_ir()));
             },
         }
 
-        Ok(ir::ValueType::Ref(Box::new(st)))
+        Ok(ValueType::Ref(Box::new(st)))
     }
 }
\ No newline at end of file
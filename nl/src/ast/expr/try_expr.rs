@@ -0,0 +1,135 @@
+use syntax::Span;
+
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, value_type_to_string};
+use crate::types::{StorableType, ValueType};
+
+use super::Expr;
+
+#[derive(Debug)]
+pub struct TryExpr {
+    pub span: Span,
+    pub expr: Box<Expr>
+}
+
+/// The locals holding the values returned from a call to a fallible function, once it has been made
+pub struct FallibleCallResult {
+    pub payload: Vec<(ir::LocalIndex, ValueType)>,
+    pub error: (ir::LocalIndex, ValueType),
+    pub failed: ir::LocalIndex
+}
+
+impl FallibleCallResult {
+    /// Find the success and error types of the fallible function called in the given expression
+    pub fn types_of<'a>(expr: &'a Expr, ctx: &IrGenFunctionContext<'a>) -> Result<(Vec<ValueType>, ValueType), IrGenError> {
+        let call = match expr {
+            Expr::Call(call) => call,
+            _ => return Err(IrGenError::new(expr.span().clone(), IrGenErrorKind::NotFallible))
+        };
+
+        match ctx.fallible_returns(call.find_function_index(ctx)?) {
+            Some(returns) => Ok(returns),
+            None => Err(IrGenError::new(expr.span().clone(), IrGenErrorKind::NotFallible))
+        }
+    }
+
+    /// Make the call, storing each of its returns in a new local. The stack may not be empty within an expression, but
+    /// the returns need to be checked with an If, so the call is hoisted to just before the statement it is in.
+    pub fn append_ir<'a>(expr: &'a Expr, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<FallibleCallResult, IrGenError> {
+        let (payload_types, error_type) = FallibleCallResult::types_of(expr, ctx)?;
+        let call = match expr {
+            Expr::Call(call) => call,
+            _ => unreachable!()
+        };
+
+        let mut call_target = IrGenCodeTarget::new();
+        call.append_ir_call(ctx, &mut call_target)?;
+
+        let mut payload = Vec::with_capacity(payload_types.len());
+        for vt in payload_types {
+            payload.push((ctx.push_temp_local(StorableType::Value(vt.clone())), vt));
+        }
+        let error = (ctx.push_temp_local(StorableType::Value(error_type.clone())), error_type);
+        let failed = ctx.push_temp_local(StorableType::Value(ValueType::Bool));
+
+        // Returns come off the stack in reverse order
+        call_target.push(ir::Ins::PopLocal(failed, ir::ValueType::Bool));
+        call_target.push(ir::Ins::PopLocal(error.0, error.1.to_ir()));
+        for (local, vt) in payload.iter().rev() {
+            call_target.push(ir::Ins::PopLocal(*local, vt.to_ir()));
+        }
+
+        target.hoist(call_target);
+
+        Ok(FallibleCallResult {
+            payload, error, failed
+        })
+    }
+
+    /// Push the value of one of the locals holding the result
+    pub fn push_local(local: ir::LocalIndex, vt: &ValueType, target: &mut IrGenCodeTarget) {
+        let vt = vt.to_ir();
+        target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(local, ir::StorableType::Value(vt.clone()))), vt.clone()));
+        target.push(ir::Ins::Push(vt));
+    }
+}
+
+impl TryExpr {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let (payload, _) = FallibleCallResult::types_of(&self.expr, ctx)?;
+
+        if payload.len() != 1 {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
+        }
+
+        Ok(payload[0].clone())
+    }
+
+    fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<FallibleCallResult, IrGenError> {
+        // 1. Check we are able to pass the error on
+        let (own_payload, own_error_type) = match ctx.fallible_returns(ctx.function_idx) {
+            Some(returns) => returns,
+            None => return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::TryOutsideFallibleFunction))
+        };
+
+        // 2. Make the call
+        let result = FallibleCallResult::append_ir(&self.expr, ctx, target)?;
+        if result.error.1 != own_error_type {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::ErrorTypeMismatch(value_type_to_string(&result.error.1), value_type_to_string(&own_error_type))));
+        }
+
+        // 3. If it failed, fail with the same error
+        let mut failure = IrGenCodeTarget::new();
+        for vt in &own_payload {
+            ctx.push_zero_value(vt, &mut failure);
+        }
+        FallibleCallResult::push_local(result.error.0, &result.error.1, &mut failure);
+        failure.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 1));
+        ctx.append_return(&mut failure)?;
+
+        let mut cond = IrGenCodeTarget::new();
+        FallibleCallResult::push_local(result.failed, &ValueType::Bool, &mut cond);
+
+        target.push_hoisted(ir::Ins::If(failure.take(), cond.take()));
+
+        Ok(result)
+    }
+
+    pub fn append_ir_value<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, _preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
+        let result = self.append_ir(ctx, target)?;
+
+        if result.payload.len() != 1 {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::CallNotOneReturnInExpr));
+        }
+
+        // Past this point the call must have succeeded
+        let (local, vt) = &result.payload[0];
+        FallibleCallResult::push_local(*local, vt, target);
+
+        Ok(vt.clone())
+    }
+
+    pub fn append_ir_out_expr<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        self.append_ir(ctx, target)?;
+        Ok(())
+    }
+}
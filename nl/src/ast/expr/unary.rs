@@ -1,6 +1,6 @@
 use syntax::Span;
 
-use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext};
+use crate::{irgen::{IrGenCodeTarget, IrGenError, IrGenFunctionContext}, types::ValueType};
 
 use super::Expr;
 
@@ -25,17 +25,17 @@ impl UnaryOp {
 }
 
 impl UnaryExpr {
-    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn resultant_type<'a>(&'a self, ctx: &IrGenFunctionContext<'a>, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         self.right.resultant_type(ctx, if self.op.is_num() { preferred } else { None })
     }
 
-    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ir::ValueType>) -> Result<ir::ValueType, IrGenError> {
+    pub fn append_ir<'a>(&'a self, ctx: &mut IrGenFunctionContext<'a>, target: &mut IrGenCodeTarget, preferred: Option<&ValueType>) -> Result<ValueType, IrGenError> {
         // 1. Load RHS
         let right = self.right.append_ir_value(ctx, target, if self.op.is_num() { preferred } else { None })?;
 
         // 2. Do the operation
         target.push(match self.op {
-            UnaryOp::Neg => ir::Ins::Neg(right.clone()),
+            UnaryOp::Neg => ir::Ins::Neg(right.to_ir()),
         });
 
         match self.op {
@@ -1,10 +1,12 @@
 mod type_expr;
+mod cfg;
 mod expr;
 mod code;
 mod top_level;
 mod unit;
 
 pub use type_expr::*;
+pub use cfg::*;
 pub use expr::*;
 pub use code::*;
 pub use top_level::*;
@@ -0,0 +1,337 @@
+use syntax::Span;
+
+use crate::ast::StringLitExpr;
+use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, value_type_to_string};
+use crate::lexer::{TokenKind, TokenStream};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeriveKind {
+    Eq,
+    Hash,
+    Print
+}
+
+impl DeriveKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeriveKind::Eq => "eq",
+            DeriveKind::Hash => "hash",
+            DeriveKind::Print => "print"
+        }
+    }
+
+    /// The methods which deriving this adds to a struct
+    pub fn method_names(&self) -> &'static [&'static str] {
+        match self {
+            DeriveKind::Eq => &["op_eq", "op_ne"],
+            DeriveKind::Hash => &["hash"],
+            DeriveKind::Print => &["print"]
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Derive {
+    pub span: Span,
+    pub kind: DeriveKind
+}
+
+impl Derive {
+    /// Parse `derive(...)`, without the square brackets around it
+    pub fn parse_call<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<Vec<Derive>> {
+        syntax::req!(syntax::tk_v!(stream, TokenKind::Ident).map_or(false, |name| name == "derive"));
+        stream.step();
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::OpenParen), stream.error("Expected '('"));
+
+        let mut derives = Vec::new();
+        loop {
+            let start = stream.tell_start();
+            let kind = match syntax::tk_v!(stream, TokenKind::Ident).map(String::as_str) {
+                Some("eq") => DeriveKind::Eq,
+                Some("hash") => DeriveKind::Hash,
+                Some("print") => DeriveKind::Print,
+                Some(_) => return syntax::MatchResult::Err(stream.error("Unknown derive, expected 'eq', 'hash' or 'print'")),
+                None => break
+            };
+            stream.step();
+
+            derives.push(Derive {
+                span: Span::new(start, stream.tell_start()),
+                kind
+            });
+
+            if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+        }
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+
+        syntax::MatchResult::Ok(derives)
+    }
+
+    /// Create the methods this adds to the given struct, without their code
+    pub fn to_ir_bases(&self, ctr: &ir::CompoundTypeRef) -> Vec<ir::Function> {
+        let self_vt = ir::ValueType::Ref(Box::new(ir::StorableType::Compound(ctr.clone())));
+
+        self.kind.method_names().iter().map(|name| {
+            let signature = match self.kind {
+                DeriveKind::Eq => ir::Signature::new(vec![ self_vt.clone(), self_vt.clone() ], vec![ ir::ValueType::Bool ]),
+                DeriveKind::Hash => ir::Signature::new(vec![ self_vt.clone() ], vec![ ir::ValueType::U64 ]),
+                DeriveKind::Print => ir::Signature::new(vec![ self_vt.clone() ], vec![])
+            };
+
+            ir::Function::new_method(*name, signature, ir::MethodData::new_virtual(ctr.clone()))
+        }).collect()
+    }
+
+    /// Push the code of the derived method at idx, which must have been created by to_ir_bases
+    pub fn append_ir(&self, ir_unit: &mut ir::TranslationUnit, idx: ir::FunctionIndex) -> Result<(), IrGenError> {
+        let func = ir_unit.get_function(idx).unwrap();
+        let name = func.name().to_string();
+        let ctr = func.method_of().unwrap();
+
+        // The params are the first locals, followed by any the code needs for itself
+        let mut locals = func.signature().params().iter().map(|vt| ir::StorableType::Value(vt.clone())).collect::<Vec<_>>();
+
+        let mut target = IrGenCodeTarget::new();
+        match name.as_str() {
+            "op_eq" => self.append_eq_ir(ir_unit, &ctr, &mut target)?,
+            "op_ne" => {
+                // a != b is !(a == b), which is (a == b) == false
+                let op_eq = ir_unit.find_method_index(ctr.clone(), "op_eq").unwrap();
+                push_local(&mut target, 0, &locals[0]);
+                push_local(&mut target, 1, &locals[1]);
+                target.push(ir::Ins::Call(op_eq));
+                target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 0));
+                target.push(ir::Ins::Eq(ir::ValueType::Bool));
+                target.push(ir::Ins::Ret);
+            },
+            "hash" => {
+                locals.push(ir::StorableType::Value(ir::ValueType::U64));
+                self.append_hash_ir(ir_unit, &ctr, &locals, &mut target)?;
+            },
+            "print" => self.append_print_ir(ir_unit, &ctr, &mut target)?,
+            _ => unreachable!()
+        }
+
+        let func = ir_unit.get_function_mut(idx).unwrap();
+        for local in locals {
+            func.push_local(ir::Local::new(local));
+        }
+        func.code_mut().extend(target.take());
+
+        Ok(())
+    }
+
+    /// Returns false as soon as a property differs, comparing numbers directly and anything else with its own op_eq
+    fn append_eq_ir(&self, ir_unit: &ir::TranslationUnit, ctr: &ir::CompoundTypeRef, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        for (p, prop) in struct_props(ctr).iter().enumerate() {
+            let vt = self.prop_value_type(ctr, prop)?;
+
+            let mut cond = IrGenCodeTarget::new();
+            push_prop(&mut cond, 0, ctr, p, &vt);
+            push_prop(&mut cond, 1, ctr, p, &vt);
+            if vt.is_num() {
+                cond.push(ir::Ins::Ne(vt));
+            } else {
+                let op_eq = self.find_prop_method(ir_unit, ctr, prop, &vt, "op_eq", &[ vt.clone(), vt.clone() ], &[ ir::ValueType::Bool ])?;
+                cond.push(ir::Ins::Call(op_eq));
+                cond.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 0));
+                cond.push(ir::Ins::Eq(ir::ValueType::Bool));
+            }
+
+            target.push(ir::Ins::If(vec![
+                ir::Ins::PushLiteral(ir::ValueType::Bool, 0),
+                ir::Ins::Ret
+            ], cond.take()));
+        }
+
+        target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 1));
+        target.push(ir::Ins::Ret);
+
+        Ok(())
+    }
+
+    /// Combines the hash of each property as hash = hash * 31 + property, where numbers are their own hash and
+    /// anything else is hashed with its own hash method
+    fn append_hash_ir(&self, ir_unit: &ir::TranslationUnit, ctr: &ir::CompoundTypeRef, locals: &[ir::StorableType], target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let hash = ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(ir::LocalIndex::new(1), locals[1].clone()));
+
+        target.push(ir::Ins::PushPath(hash.clone(), ir::ValueType::U64));
+        target.push(ir::Ins::PushLiteral(ir::ValueType::U64, 17));
+        target.push(ir::Ins::Pop(ir::ValueType::U64));
+
+        for (p, prop) in struct_props(ctr).iter().enumerate() {
+            let vt = self.prop_value_type(ctr, prop)?;
+
+            target.push(ir::Ins::PushPath(hash.clone(), ir::ValueType::U64));
+            target.push(ir::Ins::PushPath(hash.clone(), ir::ValueType::U64));
+            target.push(ir::Ins::Push(ir::ValueType::U64));
+            target.push(ir::Ins::PushLiteral(ir::ValueType::U64, 31));
+            target.push(ir::Ins::Mul(ir::ValueType::U64));
+
+            push_prop(target, 0, ctr, p, &vt);
+            if vt.is_num() {
+                if vt != ir::ValueType::U64 {
+                    target.push(ir::Ins::Convert(vt, ir::ValueType::U64));
+                }
+            } else {
+                let prop_hash = self.find_prop_method(ir_unit, ctr, prop, &vt, "hash", &[ vt.clone() ], &[ ir::ValueType::U64 ])?;
+                target.push(ir::Ins::Call(prop_hash));
+            }
+
+            target.push(ir::Ins::Add(ir::ValueType::U64));
+            target.push(ir::Ins::Pop(ir::ValueType::U64));
+        }
+
+        target.push(ir::Ins::PushPath(hash, ir::ValueType::U64));
+        target.push(ir::Ins::Push(ir::ValueType::U64));
+        target.push(ir::Ins::Ret);
+
+        Ok(())
+    }
+
+    /// Prints the struct as `Name { a: 1, b: true }` with the std print functions, printing any property which is not a
+    /// number or bool with its own print method
+    fn append_print_ir(&self, ir_unit: &mut ir::TranslationUnit, ctr: &ir::CompoundTypeRef, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let print = self.find_std_function(ir_unit, "print")?;
+        let printi64 = self.find_std_function(ir_unit, "printi64")?;
+
+        let props = struct_props(ctr);
+        for (p, prop) in props.iter().enumerate() {
+            let vt = self.prop_value_type(ctr, prop)?;
+
+            let label = match p {
+                0 => format!("{} {{ {}: ", ctr.name(), prop.name()),
+                _ => format!(", {}: ", prop.name())
+            };
+            self.append_print_string_ir(ir_unit, print, &label, target)?;
+
+            if vt == ir::ValueType::Bool {
+                let mut cond = IrGenCodeTarget::new();
+                push_prop(&mut cond, 0, ctr, p, &vt);
+
+                let mut true_then = IrGenCodeTarget::new();
+                self.append_print_string_ir(ir_unit, print, "true", &mut true_then)?;
+                let mut false_then = IrGenCodeTarget::new();
+                self.append_print_string_ir(ir_unit, print, "false", &mut false_then)?;
+
+                target.push(ir::Ins::IfElse(None, true_then.take(), false_then.take(), cond.take()));
+            } else if vt.is_num() {
+                push_prop(target, 0, ctr, p, &vt);
+
+                // Unsigned values go through u64 first, so that they are zero extended rather than sign extended
+                let vt = match vt.is_signed() || vt == ir::ValueType::U64 {
+                    true => vt,
+                    false => {
+                        target.push(ir::Ins::Convert(vt, ir::ValueType::U64));
+                        ir::ValueType::U64
+                    }
+                };
+                if vt != ir::ValueType::I64 {
+                    target.push(ir::Ins::Convert(vt, ir::ValueType::I64));
+                }
+                target.push(ir::Ins::Call(printi64));
+            } else {
+                let prop_print = self.find_prop_method(ir_unit, ctr, prop, &vt, "print", &[ vt.clone() ], &[])?;
+                push_prop(target, 0, ctr, p, &vt);
+                target.push(ir::Ins::Call(prop_print));
+            }
+        }
+
+        let end = match props.len() {
+            0 => format!("{} {{}}", ctr.name()),
+            _ => " }".to_string()
+        };
+        self.append_print_string_ir(ir_unit, print, &end, target)?;
+        target.push(ir::Ins::Ret);
+
+        Ok(())
+    }
+
+    fn append_print_string_ir(&self, ir_unit: &mut ir::TranslationUnit, print: ir::FunctionIndex, value: &str, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        StringLitExpr::append_ir_value_of(ir_unit, &self.span, value, target)?;
+        target.push(ir::Ins::Call(print));
+
+        Ok(())
+    }
+
+    fn find_std_function(&self, ir_unit: &ir::TranslationUnit, name: &str) -> Result<ir::FunctionIndex, IrGenError> {
+        match ir_unit.find_function_index(name) {
+            Some(idx) => Ok(idx),
+            None => Err(IrGenError::new(self.span.clone(), IrGenErrorKind::FunctionDoesNotExist(name.to_string())))
+        }
+    }
+
+    fn prop_value_type(&self, ctr: &ir::CompoundTypeRef, prop: &ir::StructProperty) -> Result<ir::ValueType, IrGenError> {
+        match prop.prop_type() {
+            ir::StorableType::Value(vt) => Ok(vt.clone()),
+            _ => Err(self.underivable(ctr, prop))
+        }
+    }
+
+    /// Find the method implementing this derive on a property which is not a number, so it must be a struct with a
+    /// method of the given name and signature, either hand written or derived itself
+    fn find_prop_method(&self, ir_unit: &ir::TranslationUnit, ctr: &ir::CompoundTypeRef, prop: &ir::StructProperty, vt: &ir::ValueType, name: &str, params: &[ir::ValueType], returns: &[ir::ValueType]) -> Result<ir::FunctionIndex, IrGenError> {
+        let compound = match vt {
+            ir::ValueType::Ref(r) => match r.as_ref() {
+                ir::StorableType::Compound(c) => c.clone(),
+                _ => return Err(self.underivable(ctr, prop))
+            },
+            _ => return Err(self.underivable(ctr, prop))
+        };
+
+        let idx = match ir_unit.find_method_index(compound, name) {
+            Some(idx) => idx,
+            None => return Err(self.underivable(ctr, prop))
+        };
+
+        let func = ir_unit.get_function(idx).unwrap();
+        if !func.is_virtual() || func.is_fallible() || func.signature().params() != params || func.signature().returns() != returns {
+            return Err(self.underivable(ctr, prop));
+        }
+
+        Ok(idx)
+    }
+
+    fn underivable(&self, ctr: &ir::CompoundTypeRef, prop: &ir::StructProperty) -> IrGenError {
+        let type_name = match prop.prop_type() {
+            ir::StorableType::Value(vt) => value_type_to_string(&vt.into()),
+            st => crate::irgen::storable_type_to_string(&st.into())
+        };
+
+        IrGenError::new(self.span.clone(), IrGenErrorKind::UnderivableProperty(
+            self.kind.name().to_string(), format!("{}.{}", ctr.name(), prop.name()), type_name
+        ))
+    }
+}
+
+fn struct_props(ctr: &ir::CompoundTypeRef) -> &Vec<ir::StructProperty> {
+    match ctr.content() {
+        ir::CompoundContent::Struct(s) => s.props()
+    }
+}
+
+fn push_local(target: &mut IrGenCodeTarget, local: usize, st: &ir::StorableType) {
+    let vt = match st {
+        ir::StorableType::Value(vt) => vt.clone(),
+        _ => unreachable!()
+    };
+
+    target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(ir::LocalIndex::new(local), st.clone())), vt.clone()));
+    target.push(ir::Ins::Push(vt));
+}
+
+/// Push the value of a property of the struct referred to by the given param
+fn push_prop(target: &mut IrGenCodeTarget, local: usize, ctr: &ir::CompoundTypeRef, prop_idx: usize, vt: &ir::ValueType) {
+    let struct_type = ir::StorableType::Compound(ctr.clone());
+    push_local(target, local, &ir::StorableType::Value(ir::ValueType::Ref(Box::new(struct_type.clone()))));
+
+    target.push(ir::Ins::PushPath(ir::ValuePath::new(
+        ir::ValuePathOrigin::Deref(struct_type),
+        vec![
+            ir::ValuePathComponent::Property(ir::PropertyIndex::new(prop_idx), ctr.clone(), ir::StorableType::Value(vt.clone()))
+        ]
+    ), vt.clone()));
+    target.push(ir::Ins::Push(vt.clone()));
+}
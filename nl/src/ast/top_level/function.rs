@@ -2,15 +2,18 @@ use std::collections::HashMap;
 
 use syntax::Span;
 
-use crate::ast::{Code, Expr, TranslationUnit, TypeExpr};
+use crate::ast::{CfgExpr, Code, Expr, TranslationUnit, TypeExpr};
 use crate::lexer::{TokenKind, TokenStream};
-use crate::irgen::{IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext};
+use crate::irgen::{BuildConfig, IrGenCodeTarget, IrGenError, IrGenErrorKind, IrGenFunctionContext, SourceFile};
+use crate::types::{Declarations, Signature, StorableType, ValueType};
 
 #[derive(Debug)]
 pub struct FunctionAnnotation {
     pub span: Span,
     pub name: String,
-    pub value: Option<Expr>
+    pub value: Option<Expr>,
+    /// Only set for cfg annotations
+    pub condition: Option<CfgExpr>
 }
 
 #[derive(Debug)]
@@ -29,6 +32,7 @@ pub struct Function {
     pub code: Option<Vec<Code>>,
     pub annotations: Vec<FunctionAnnotation>,
     pub return_types: Vec<TypeExpr>,
+    pub error_type: Option<TypeExpr>,
     pub is_static: bool
 }
 
@@ -53,15 +57,36 @@ impl Function {
         return Ok(true);
     }
 
+    /// Tests if this function should be compiled at all, which requires it to match the arch and all of its cfg annotations
+    pub fn is_enabled(&self, config: &BuildConfig) -> Result<bool, IrGenError> {
+        for annotation in &self.annotations {
+            if let Some(condition) = &annotation.condition {
+                if !condition.evaluate(config) { return Ok(false) }
+            }
+
+            // The test runner replaces the entry point when testing
+            if annotation.name == "entry" && config.testing() { return Ok(false) }
+        }
+
+        self.arch_matches(config.target())
+    }
+
     /// Create the signature / method_of etc fields for a function - everything but the code, in effect.
     /// This means that an imported function will have an ir_base but not full ir.
-    /// This function expects that arch_matches
-    pub fn to_ir_base(&self, ir_unit: &ir::TranslationUnit, _unit: &TranslationUnit) -> Result<ir::Function, IrGenError> {
+    /// This function expects that is_enabled, and gives the signature as NL sees it alongside the function.
+    pub fn to_ir_base(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations, _unit: &TranslationUnit) -> Result<(ir::Function, Signature), IrGenError> {
         let mut returns = Vec::with_capacity(self.return_types.len());
         for return_type in &self.return_types {
-            returns.push(return_type.to_ir_value_type(ir_unit)?);
+            returns.push(return_type.to_value_type(ir_unit, declarations)?);
+        }
+
+        // Fallible functions also return their error, and whether or not they failed
+        if let Some(error_type) = &self.error_type {
+            returns.push(error_type.to_value_type(ir_unit, declarations)?);
+            returns.push(ValueType::Bool);
         }
 
+        let signature;
         let mut func = if self.path.len() > 0 {
             assert_eq!(self.path.len(), 1); // Currently we only support associating a function with a type, but nothing more
             
@@ -75,12 +100,13 @@ impl Function {
             
             // For virtual functions (methods), push self as an argument, before the others
             if !self.is_static {
-                params.push(ir::ValueType::Ref(Box::new(ir::StorableType::Compound(ctr.clone()))));
+                params.push(ValueType::Ref(Box::new(StorableType::Compound(ctr.clone()))));
             }
 
             for param in &self.params {
-                params.push(param.param_type.to_ir_value_type(ir_unit)?);
+                params.push(param.param_type.to_value_type(ir_unit, declarations)?);
             }
+            signature = Signature::new(params, returns);
 
             let method_data = if self.is_static {
                 ir::MethodData::new_static(ctr)
@@ -89,39 +115,54 @@ impl Function {
             };
 
             if self.code.is_some() {
-                ir::Function::new_method(&self.name, ir::Signature::new(params, returns), method_data)
+                ir::Function::new_method(&self.name, signature.to_ir(), method_data)
             } else {
-                ir::Function::new_extern_method(&self.name, ir::Signature::new(params, returns), method_data)
+                ir::Function::new_extern_method(&self.name, signature.to_ir(), method_data)
             }
         } else {
             let mut params = Vec::with_capacity(self.params.len());
             for param in &self.params {
-                params.push(param.param_type.to_ir_value_type(ir_unit)?);
+                params.push(param.param_type.to_value_type(ir_unit, declarations)?);
             }
+            signature = Signature::new(params, returns);
 
             if self.code.is_some() {
-                ir::Function::new(&self.name, ir::Signature::new(params, returns))
+                ir::Function::new(&self.name, signature.to_ir())
             } else {
-                ir::Function::new_extern(&self.name, ir::Signature::new(params, returns))
+                ir::Function::new_extern(&self.name, signature.to_ir())
             }
         };
 
+        if self.error_type.is_some() {
+            func.push_attr(ir::FunctionAttr::Fallible);
+        }
+
         // Push the function annotations to the ir
         for annotation in &self.annotations {
             match annotation.name.as_str() {
                 "entry" => {
-                    if func.signature().return_count() != 1 || func.signature().returns()[0] != ir::ValueType::I32 {
+                    if signature.returns().len() != 1 || signature.returns()[0] != ValueType::I32 {
                         return Err(IrGenError::new(annotation.span.clone(), IrGenErrorKind::InvalidEntryReturns))
                     }
-                    if func.signature().param_count() != 0 {
+                    if !signature.params().is_empty() {
                         return Err(IrGenError::new(annotation.span.clone(), IrGenErrorKind::InvalidEntryParams))
                     }
                     func.push_attr(ir::FunctionAttr::Entry);
                 },
+                "test" => {
+                    if func.method_of().is_some() || !signature.params().is_empty()
+                        || signature.returns().len() != 1 || signature.returns()[0] != ValueType::Bool {
+                        return Err(IrGenError::new(annotation.span.clone(), IrGenErrorKind::InvalidTestSignature))
+                    }
+                    func.push_attr(ir::FunctionAttr::Test);
+                },
                 "alloc" => func.push_attr(ir::FunctionAttr::Alloc),
                 "alloc_slice" => func.push_attr(ir::FunctionAttr::AllocSlice),
                 "free" => func.push_attr(ir::FunctionAttr::Free),
                 "free_slice" => func.push_attr(ir::FunctionAttr::FreeSlice),
+                "panic" => func.push_attr(ir::FunctionAttr::Panic),
+                "inline" => func.push_attr(ir::FunctionAttr::Inline),
+                "export" => func.push_attr(ir::FunctionAttr::Export),
                 "location" =>
                     match &annotation.value {
                         Some(Expr::StringLit(string)) => {
@@ -129,44 +170,60 @@ impl Function {
                         },
                         _ => return Err(IrGenError::new(annotation.span.clone(), IrGenErrorKind::InvalidAnnotationExpression("string".to_string())))
                     },
-                "arch" => {},
+                "arch" | "cfg" => {},
                 _ => return Err(IrGenError::new(annotation.span.clone(), IrGenErrorKind::UnknownAnnotation(annotation.name.clone())))
             }
         }
 
-        Ok(func)
+        Ok((func, signature))
     }
 
     /// Push the actual code to this function.
     /// This assumes idx points to the result of append_ir_base for this function.
-    pub fn append_ir(&self, ir_unit: &mut ir::TranslationUnit, idx: ir::FunctionIndex) -> Result<(), IrGenError> {
+    pub fn append_ir(&self, ir_unit: &mut ir::TranslationUnit, declarations: &Declarations, idx: ir::FunctionIndex, source: &SourceFile, config: &BuildConfig) -> Result<(), IrGenError> {
         let mut ctx = IrGenFunctionContext {
             ir_unit,
             function_idx: idx,
-            local_map: HashMap::new()
+            source,
+            config,
+            declarations,
+            local_map: HashMap::new(),
+            local_types: Vec::new(),
+            defer_scopes: Vec::new(),
+            block_depth: 0,
+            loops: Vec::new()
         };
 
         if !self.is_static {
             // For virtual functions, define the name self as the first local
-            ctx.push_local("self", ir::StorableType::Value(
-                ir::ValueType::Ref(Box::new(ir::StorableType::Compound(ctx.func().method_of().unwrap())))
+            ctx.push_local("self", StorableType::Value(
+                ValueType::Ref(Box::new(StorableType::Compound(ctx.func().method_of().unwrap())))
             ));
         }
 
         // Push the params and their names
         for param in &self.params {
-            let vt = param.param_type.to_ir_value_type(ctx.ir_unit)?;
-            ctx.push_local(&param.name, ir::StorableType::Value(vt.clone()));
+            let vt = param.param_type.to_value_type(ctx.ir_unit, ctx.declarations)?;
+            ctx.push_local(&param.name, StorableType::Value(vt));
         }
 
         // Push the code
         let mut target = IrGenCodeTarget::new();
-        for code in self.code.as_ref().unwrap() {
-            code.append_ir(&mut ctx, &mut target)?;
+        Code::append_block_ir(self.code.as_ref().unwrap(), &mut ctx, &mut target)?;
+
+        // Add a trailing ret if we return void, and the end of the function can be reached
+        if ctx.func().signature().return_count() == 0 && target.falls_through() {
+            target.push(ir::Ins::Ret);
         }
 
-        // Add a trailing ret if we return void
-        if ctx.func().signature().return_count() == 0 && !matches!(ctx.func().code().last(), Some(ir::Ins::Ret)) {
+        // Reaching the end of a fallible function with nothing to return is a success
+        let void_error_type = match ctx.fallible_returns(idx) {
+            Some((payload, error_type)) if payload.len() == 0 && target.falls_through() => Some(error_type),
+            _ => None
+        };
+        if let Some(error_type) = void_error_type {
+            ctx.push_zero_value(&error_type, &mut target);
+            target.push(ir::Ins::PushLiteral(ir::ValueType::Bool, 0));
             target.push(ir::Ins::Ret);
         }
 
@@ -229,22 +286,30 @@ impl Function {
 
         syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
 
-        // 4. Parse return values, either nothing, a single value or a collection of values
+        // 4. Parse return values, either nothing, a single value or a collection of values, optionally followed by '!' and an error type
         let mut returns = Vec::new();
+        let mut error_type = None;
         if syntax::tk_iss!(stream, TokenKind::Colon) {
-            if syntax::tk_iss!(stream, TokenKind::OpenParen) {
-                loop {
-                    returns.push(match syntax::parse!(stream, TypeExpr::parse) {
-                        Some(x) => x,
-                        None => break
-                    });
+            // A fallible function which returns nothing on success goes straight to the '!'
+            if !syntax::tk_is!(stream, TokenKind::Bang) {
+                if syntax::tk_iss!(stream, TokenKind::OpenParen) {
+                    loop {
+                        returns.push(match syntax::parse!(stream, TypeExpr::parse) {
+                            Some(x) => x,
+                            None => break
+                        });
         
-                    if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+                        if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+                    }
+
+                    syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
+                } else {
+                    returns.push(syntax::ex!(syntax::parse!(stream, TypeExpr::parse), stream.error("Expected return type")));
                 }
+            }
 
-                syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseParen), stream.error("Expected ')'"));
-            } else {
-                returns.push(syntax::ex!(syntax::parse!(stream, TypeExpr::parse), stream.error("Expected return type")));
+            if syntax::tk_iss!(stream, TokenKind::Bang) {
+                error_type = Some(syntax::ex!(syntax::parse!(stream, TypeExpr::parse), stream.error("Expected error type")));
             }
         }
 
@@ -273,6 +338,7 @@ impl Function {
             span: syntax::Span::new(start, end),
             path, name, params, code,
             return_types: returns,
+            error_type,
             annotations,
             is_static
         })
@@ -282,8 +348,14 @@ impl Function {
 impl FunctionParam {
     pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<FunctionParam> {
         let start = stream.tell_start();
-        let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
-        stream.step();
+        // panic is a keyword, but is also the name of the annotation marking the panic implementation
+        let name = if syntax::tk_iss!(stream, TokenKind::PanicKeyword) {
+            "panic".to_string()
+        } else {
+            let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
+            stream.step();
+            name
+        };
 
         syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Colon), stream.error("Expected ':'"));
 
@@ -300,8 +372,24 @@ impl FunctionParam {
 impl FunctionAnnotation {
     pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<FunctionAnnotation> {
         let start = stream.tell_start();
-        let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
-        stream.step();
+
+        if let Some(condition) = syntax::parse!(stream, CfgExpr::parse_call) {
+            return syntax::MatchResult::Ok(FunctionAnnotation {
+                span: syntax::Span::new(start, stream.tell_start()),
+                name: "cfg".to_string(),
+                value: None,
+                condition: Some(condition)
+            });
+        }
+
+        // panic is a keyword, but is also the name of the annotation marking the panic implementation
+        let name = if syntax::tk_iss!(stream, TokenKind::PanicKeyword) {
+            "panic".to_string()
+        } else {
+            let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
+            stream.step();
+            name
+        };
 
         let value = if syntax::tk_iss!(stream, TokenKind::Eq) {
             Some(syntax::ex!(syntax::parse!(stream, Expr::parse), stream.error("Expected expression")))
@@ -312,7 +400,8 @@ impl FunctionAnnotation {
         syntax::MatchResult::Ok(FunctionAnnotation {
             span: syntax::Span::new(start, stream.tell_start()),
             name,
-            value
+            value,
+            condition: None
         })
     }
 }
\ No newline at end of file
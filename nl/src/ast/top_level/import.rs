@@ -1,11 +1,12 @@
 use syntax::Span;
 
-use crate::lexer::{TokenKind, TokenStream};
+use crate::{ast::CfgExpr, irgen::BuildConfig, lexer::{TokenKind, TokenStream}};
 
 #[derive(Debug)]
 pub struct ImportStmt {
     pub span: Span,
-    pub path: Vec<String>
+    pub path: Vec<String>,
+    pub condition: Option<CfgExpr>
 }
 
 impl ImportStmt {
@@ -15,6 +16,8 @@ impl ImportStmt {
         let start = stream.tell_start();
         syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::ImportKeyword));
 
+        let condition = syntax::parse!(stream, CfgExpr::parse_attribute);
+
         let mut path = Vec::new();
         loop {
             path.push(syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected identifier")).to_owned());
@@ -25,7 +28,16 @@ impl ImportStmt {
 
         syntax::MatchResult::Ok(ImportStmt {
             span: syntax::Span::new(start, stream.tell_start()),
-            path
+            path,
+            condition
         })
     }
+
+    /// Tests if this import should be resolved at all under the given config
+    pub fn is_enabled(&self, config: &BuildConfig) -> bool {
+        match &self.condition {
+            Some(condition) => condition.evaluate(config),
+            None => true
+        }
+    }
 }
\ No newline at end of file
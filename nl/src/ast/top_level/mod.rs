@@ -2,8 +2,12 @@ mod top_level;
 mod function;
 mod import;
 mod struct_decl;
+mod type_decl;
+mod derive;
 
 pub use function::*;
 pub use top_level::*;
 pub use import::*;
-pub use struct_decl::*;
\ No newline at end of file
+pub use struct_decl::*;
+pub use type_decl::*;
+pub use derive::*;
\ No newline at end of file
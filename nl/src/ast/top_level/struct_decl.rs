@@ -1,12 +1,16 @@
 use syntax::Span;
 
-use crate::{ast::{TranslationUnit, TypeExpr}, irgen::IrGenError, lexer::{TokenKind, TokenStream}};
+use crate::{ast::{CfgExpr, Derive, TranslationUnit, TypeExpr}, irgen::{BuildConfig, IrGenError, IrGenErrorKind}, lexer::{TokenKind, TokenStream}, types::{Declarations, StorableType}};
 
 #[derive(Debug)]
 pub struct StructDeclaration {
     pub span: Span,
     pub name: String,
     pub fields: Vec<StructFieldDeclaration>,
+    pub condition: Option<CfgExpr>,
+    pub derives: Vec<Derive>,
+    pub repr: ir::StructRepr,
+    pub align: Option<usize>
 }
 
 #[derive(Debug)]
@@ -14,12 +18,19 @@ pub struct StructFieldDeclaration {
     pub span: Span,
     pub name: String,
     pub field_type: TypeExpr,
+    pub condition: Option<CfgExpr>
 }
 
 impl StructFieldDeclaration {
     pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<StructFieldDeclaration> {
         let start = stream.tell_start();
-        let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident)).to_owned();
+        let condition = syntax::parse!(stream, CfgExpr::parse_attribute);
+
+        let name = match syntax::tk_v!(stream, TokenKind::Ident) {
+            Some(name) => name.to_owned(),
+            None if condition.is_some() => return syntax::MatchResult::Err(stream.error("Expected a name")),
+            None => return syntax::MatchResult::Fail
+        };
         stream.step();
 
         syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Colon), stream.error("Expected ':'"));
@@ -29,7 +40,8 @@ impl StructFieldDeclaration {
         syntax::MatchResult::Ok(StructFieldDeclaration {
             span: syntax::Span::new(start, stream.tell_start()),
             name,
-            field_type
+            field_type,
+            condition
         })
     }
 }
@@ -39,6 +51,66 @@ impl StructDeclaration {
         let start = stream.tell_start();
         syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::StructKeyword));
 
+        // Parse the attributes, which can be a cfg, any number of derives and the layout
+        let mut condition = None;
+        let mut derives = Vec::new();
+        let mut repr = ir::StructRepr::Default;
+        let mut align = None;
+        if syntax::tk_iss!(stream, TokenKind::OpenBracket) {
+            loop {
+                if let Some(cfg) = syntax::parse!(stream, CfgExpr::parse_call) {
+                    condition = Some(cfg);
+                } else if let Some(derive) = syntax::parse!(stream, Derive::parse_call) {
+                    derives.extend(derive);
+                } else {
+                    let attribute = syntax::tk_v!(stream, TokenKind::Ident).cloned();
+                    match attribute.as_deref() {
+                        Some("repr") => {
+                            stream.step();
+                            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Eq), stream.error("Expected '='"));
+                            match syntax::tk_v!(stream, TokenKind::StringLit) {
+                                Some(value) if value == b"c" => {},
+                                Some(_) => return syntax::MatchResult::Err(stream.error("Unknown repr, expected \"c\"")),
+                                None => return syntax::MatchResult::Err(stream.error("Expected string"))
+                            }
+                            stream.step();
+
+                            if repr == ir::StructRepr::Packed {
+                                return syntax::MatchResult::Err(stream.error("A struct cannot be both packed and repr=\"c\""));
+                            }
+                            repr = ir::StructRepr::C;
+                        },
+                        Some("packed") => {
+                            stream.step();
+
+                            if repr == ir::StructRepr::C {
+                                return syntax::MatchResult::Err(stream.error("A struct cannot be both packed and repr=\"c\""));
+                            }
+                            repr = ir::StructRepr::Packed;
+                        },
+                        Some("align") => {
+                            stream.step();
+                            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Eq), stream.error("Expected '='"));
+                            let value = match syntax::tk_v!(stream, TokenKind::Number).map(|number| number.parse::<usize>()) {
+                                Some(Ok(value)) if value.is_power_of_two() && value <= ir::MAX_ALIGN => value,
+                                Some(Ok(value)) if value.is_power_of_two() => return syntax::MatchResult::Err(stream.error(format!("Alignment must be no more than {}", ir::MAX_ALIGN))),
+                                Some(_) => return syntax::MatchResult::Err(stream.error("Alignment must be a power of two")),
+                                None => return syntax::MatchResult::Err(stream.error("Expected alignment"))
+                            };
+                            stream.step();
+
+                            align = Some(value);
+                        },
+                        _ => return syntax::MatchResult::Err(stream.error("Expected 'cfg(...)', 'derive(...)', 'repr', 'packed' or 'align'"))
+                    }
+                }
+
+                if !syntax::tk_iss!(stream, TokenKind::Comma) { break }
+            }
+
+            syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::CloseBracket), stream.error("Expected ']'"));
+        }
+
         let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected a name")).to_owned();
         stream.step();
 
@@ -58,19 +130,53 @@ impl StructDeclaration {
 
         syntax::MatchResult::Ok(StructDeclaration {
             span: syntax::Span::new(start, stream.tell_start()),
-            name, fields
+            name, fields, condition, derives, repr, align
         })
     }
 
-    pub fn to_ir(&self, ir_unit: &ir::TranslationUnit, _unit: &TranslationUnit) -> Result<ir::CompoundTypeRef, IrGenError> {
+    /// Tests if this struct should be declared at all under the given config
+    pub fn is_enabled(&self, config: &BuildConfig) -> bool {
+        match &self.condition {
+            Some(condition) => condition.evaluate(config),
+            None => true
+        }
+    }
+
+    /// Create the type, leaving out any fields whose cfg does not hold, alongside the type of each field as NL sees it
+    pub fn to_ir(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations, _unit: &TranslationUnit, config: &BuildConfig) -> Result<(ir::CompoundTypeRef, Vec<StorableType>), IrGenError> {
         let mut ir_struct = ir::StructContent::new();
+        ir_struct.set_repr(self.repr);
+        ir_struct.set_min_align(self.align);
+        let mut props = Vec::new();
         for field in &self.fields {
-            ir_struct.push_prop(ir::StructProperty::new(
-                &field.name,
-                ir::StorableType::Value(field.field_type.to_ir_value_type(ir_unit)?)
-            ));
+            if let Some(condition) = &field.condition {
+                if !condition.evaluate(config) { continue }
+            }
+
+            let st = StorableType::Value(field.field_type.to_value_type(ir_unit, declarations)?);
+            ir_struct.push_prop(ir::StructProperty::new(&field.name, st.to_ir()));
+            props.push(st);
+        }
+
+        Ok((ir::CompoundType::new(&self.name, ir::CompoundContent::Struct(ir_struct)), props))
+    }
+
+    /// Add the methods of every derive to the unit, without their code, returning the index of each alongside the
+    /// derive which creates it. The type must already have been added.
+    pub fn derived_to_ir_base(&self, ir_unit: &mut ir::TranslationUnit) -> Result<Vec<(&Derive, ir::FunctionIndex)>, IrGenError> {
+        let ctr = ir_unit.find_type(&self.name).unwrap();
+
+        let mut methods = Vec::new();
+        for derive in &self.derives {
+            for func in derive.to_ir_bases(&ctr) {
+                if ir_unit.find_method_index(ctr.clone(), func.name()).is_some() {
+                    return Err(IrGenError::new(derive.span.clone(), IrGenErrorKind::DerivedMethodExists(func.name().to_string(), self.name.clone())));
+                }
+
+                methods.push((derive, ir_unit.add_function(func)));
+            }
         }
 
-        Ok(ir::CompoundType::new(&self.name, ir::CompoundContent::Struct(ir_struct)))
+        Ok(methods)
     }
 }
\ No newline at end of file
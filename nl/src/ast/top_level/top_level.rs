@@ -1,12 +1,13 @@
 use crate::lexer::{TokenKind, TokenStream};
 use crate::ast::Function;
 
-use super::{ImportStmt, StructDeclaration};
+use super::{ImportStmt, StructDeclaration, TypeDeclaration};
 
 #[derive(Debug)]
 pub enum TopLevelNode {
     Function(Function),
     StructDeclaration(StructDeclaration),
+    TypeDeclaration(TypeDeclaration),
     Import(ImportStmt)
 }
 
@@ -15,6 +16,7 @@ impl TopLevelNode {
         match stream.token_kind() {
             Some(TokenKind::FuncKeyword) => syntax::MatchResult::Ok(TopLevelNode::Function(syntax::parse!(stream, Function::parse).unwrap())),
             Some(TokenKind::StructKeyword) => syntax::MatchResult::Ok(TopLevelNode::StructDeclaration(syntax::parse!(stream, StructDeclaration::parse).unwrap())),
+            Some(TokenKind::TypeKeyword) => syntax::MatchResult::Ok(TopLevelNode::TypeDeclaration(syntax::parse!(stream, TypeDeclaration::parse).unwrap())),
             Some(TokenKind::ImportKeyword) => syntax::MatchResult::Ok(TopLevelNode::Import(syntax::parse!(stream, ImportStmt::parse).unwrap())),
             
             _ => syntax::MatchResult::Fail
@@ -0,0 +1,70 @@
+use syntax::Span;
+
+use crate::{ast::{CfgExpr, TypeExpr}, irgen::{BuildConfig, IrGenError, IrGenErrorKind}, lexer::{TokenKind, TokenStream}, types::{Declarations, DistinctType, StorableType}};
+
+/// Either an alias, which is interchangeable with the type it names, or a distinct type, which is stored like its
+/// underlying type but can only be mixed with it through `as`.
+#[derive(Debug)]
+pub struct TypeDeclaration {
+    pub span: Span,
+    pub name: String,
+    pub distinct: bool,
+    pub target: TypeExpr,
+    pub condition: Option<CfgExpr>
+}
+
+impl TypeDeclaration {
+    pub fn parse<'a>(stream: &mut TokenStream<'a>) -> syntax::MatchResult<TypeDeclaration> {
+        let start = stream.tell_start();
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::TypeKeyword));
+
+        let condition = syntax::parse!(stream, CfgExpr::parse_attribute);
+
+        let name = syntax::ex!(syntax::tk_v!(stream, TokenKind::Ident), stream.error("Expected a name")).to_owned();
+        stream.step();
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Eq), stream.error("Expected '='"));
+
+        let distinct = syntax::tk_iss!(stream, TokenKind::DistinctKeyword);
+        let target = syntax::ex!(syntax::parse!(stream, TypeExpr::parse), stream.error("Expected type"));
+
+        syntax::reqs!(stream, syntax::tk_is!(stream, TokenKind::Semi), stream.error("Expected ';'"));
+
+        syntax::MatchResult::Ok(TypeDeclaration {
+            span: syntax::Span::new(start, stream.tell_start()),
+            name, distinct, target, condition
+        })
+    }
+
+    /// Tests if this type should be declared at all under the given config
+    pub fn is_enabled(&self, config: &BuildConfig) -> bool {
+        match &self.condition {
+            Some(condition) => condition.evaluate(config),
+            None => true
+        }
+    }
+
+    pub fn to_ir_on(&self, ir_unit: &ir::TranslationUnit, declarations: &mut Declarations) -> Result<(), IrGenError> {
+        // Otherwise whichever type of the name was declared first would be used, without saying so
+        if ir_unit.find_type(&self.name).is_some() || declarations.find_distinct_type(&self.name).is_some() || declarations.find_alias(&self.name).is_some() {
+            return Err(IrGenError::new(self.span.clone(), IrGenErrorKind::TypeAlreadyDeclared(self.name.clone())));
+        }
+
+        let target = self.target.to_storable_type(ir_unit, declarations)?;
+
+        if !self.distinct {
+            declarations.add_alias(&self.name, target);
+            return Ok(());
+        }
+
+        // Distinct types only make sense for plain values, as structs and slices are already only equal to themselves
+        match target {
+            StorableType::Value(vt) if vt.is_num() => {
+                // A distinct type of a distinct type is just another of the same underlying type
+                declarations.add_distinct_type(DistinctType::new(&self.name, vt.underlying().clone()));
+                Ok(())
+            },
+            _ => Err(IrGenError::new(self.target.span.clone(), IrGenErrorKind::InvalidDistinctType(self.name.clone())))
+        }
+    }
+}
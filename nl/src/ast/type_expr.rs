@@ -2,6 +2,7 @@ use syntax::Span;
 
 use crate::lexer::{TokenKind, TokenStream};
 use crate::irgen::{IrGenError, IrGenErrorKind};
+use crate::types::{Declarations, StorableType, ValueType};
 
 use super::Expr;
 
@@ -42,48 +43,58 @@ impl TypeExpr {
         })
     }
 
-    pub fn to_ir_base_storable_type(&self, ir_unit: &ir::TranslationUnit) -> Result<ir::StorableType, IrGenError> {
+    pub fn to_base_storable_type(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations) -> Result<StorableType, IrGenError> {
         // 1. Attempt to match the name with an iternal type
         // There must be a first item, or else this shouldn't have parsed
         match self.path.get(0).unwrap().as_str() {
-            "u8" => return Ok(ir::StorableType::Value(ir::ValueType::U8)),
-            "i8" => return Ok(ir::StorableType::Value(ir::ValueType::I8)),
-            "u16" => return Ok(ir::StorableType::Value(ir::ValueType::U16)),
-            "i16" => return Ok(ir::StorableType::Value(ir::ValueType::I16)),
-            "u32" => return Ok(ir::StorableType::Value(ir::ValueType::U32)),
-            "i32" => return Ok(ir::StorableType::Value(ir::ValueType::I32)),
-            "u64" => return Ok(ir::StorableType::Value(ir::ValueType::U64)),
-            "i64" => return Ok(ir::StorableType::Value(ir::ValueType::I64)),
-            "uptr" => return Ok(ir::StorableType::Value(ir::ValueType::UPtr)),
-            "iptr" => return Ok(ir::StorableType::Value(ir::ValueType::IPtr)),
+            "u8" => return Ok(StorableType::Value(ValueType::U8)),
+            "i8" => return Ok(StorableType::Value(ValueType::I8)),
+            "u16" => return Ok(StorableType::Value(ValueType::U16)),
+            "i16" => return Ok(StorableType::Value(ValueType::I16)),
+            "u32" => return Ok(StorableType::Value(ValueType::U32)),
+            "i32" => return Ok(StorableType::Value(ValueType::I32)),
+            "u64" => return Ok(StorableType::Value(ValueType::U64)),
+            "i64" => return Ok(StorableType::Value(ValueType::I64)),
+            "uptr" => return Ok(StorableType::Value(ValueType::UPtr)),
+            "iptr" => return Ok(StorableType::Value(ValueType::IPtr)),
+            "bool" => return Ok(StorableType::Value(ValueType::Bool)),
+            "char" => return Ok(StorableType::Value(ValueType::U32)), // A unicode code point
             _ => {}
         }
 
         // 2. If that fails, look for the type in the unit
         if let Some(ct) = ir_unit.find_type(&self.path.get(0).unwrap()) {
-            return Ok(ir::StorableType::Compound(ct));
+            return Ok(StorableType::Compound(ct));
+        }
+
+        if let Some(dt) = declarations.find_distinct_type(&self.path.get(0).unwrap()) {
+            return Ok(StorableType::Value(ValueType::Distinct(dt)));
+        }
+
+        // 3. Aliases are replaced by the type they name
+        if let Some(target) = declarations.find_alias(&self.path.get(0).unwrap()) {
+            return Ok(target.clone());
         }
 
         Err(IrGenError::new(self.span.clone(), IrGenErrorKind::UnknownType))
     }
 
-    pub fn to_ir_storable_type(&self, ir_unit: &ir::TranslationUnit) -> Result<ir::StorableType, IrGenError> {
-        let mut st = self.to_ir_base_storable_type(ir_unit)?;
+    pub fn to_storable_type(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations) -> Result<StorableType, IrGenError> {
+        let mut st = self.to_base_storable_type(ir_unit, declarations)?;
 
         for _ in 0..self.slice_lengths.len() {
-            st = ir::StorableType::Slice(Box::new(st));
+            st = StorableType::Slice(Box::new(st));
         }
 
         Ok(st)
     }
 
     /// This is where NL feels more like java or python that C, in that objects are always treated as pointers.
-    pub fn to_ir_value_type(&self, ir_unit: &ir::TranslationUnit) -> Result<ir::ValueType, IrGenError> {
-        match self.to_ir_storable_type(ir_unit)? {
-            ir::StorableType::Compound(ct) => Ok(ir::ValueType::Ref(Box::new(ir::StorableType::Compound(ct)))),
-            ir::StorableType::Slice(st) => Ok(ir::ValueType::Ref(Box::new(ir::StorableType::Slice(st)))),
-            ir::StorableType::Value(v) => Ok(v),
-            ir::StorableType::SliceData(_) => unreachable!()
+    pub fn to_value_type(&self, ir_unit: &ir::TranslationUnit, declarations: &Declarations) -> Result<ValueType, IrGenError> {
+        match self.to_storable_type(ir_unit, declarations)? {
+            StorableType::Compound(ct) => Ok(ValueType::Ref(Box::new(StorableType::Compound(ct)))),
+            StorableType::Slice(st) => Ok(ValueType::Ref(Box::new(StorableType::Slice(st)))),
+            StorableType::Value(v) => Ok(v)
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::{irgen::IrGenError, lexer::TokenStream};
+use crate::{irgen::{BuildConfig, IrGenError, SourceFile}, lexer::TokenStream, types::Declarations};
 
 use super::TopLevelNode;
 
@@ -23,14 +23,16 @@ impl TranslationUnit {
 
 impl TranslationUnit {
     /// Defines this unit in the ir, but does not append function code
-    pub fn to_extern_ir_on(&self, unit: &mut ir::TranslationUnit, target_arch_name: &str) -> Result<(), IrGenError> {
+    pub fn to_extern_ir_on(&self, unit: &mut ir::TranslationUnit, declarations: &mut Declarations, config: &BuildConfig) -> Result<(), IrGenError> {
         // 1. Declare all the types - must be done first so function signatures can use these types
         for node in &self.nodes {
             match node {
-                TopLevelNode::StructDeclaration(decl) => {
-                    let ct = decl.to_ir(unit, self)?;
+                TopLevelNode::StructDeclaration(decl) if decl.is_enabled(config) => {
+                    let (ct, props) = decl.to_ir(unit, declarations, self, config)?;
+                    declarations.set_props(&ct, props);
                     unit.add_type(ct);
                 },
+                TopLevelNode::TypeDeclaration(decl) if decl.is_enabled(config) => decl.to_ir_on(unit, declarations)?,
                 _ => {}
             }
         }
@@ -39,12 +41,25 @@ impl TranslationUnit {
         for node in &self.nodes {
             match node {
                 TopLevelNode::Function(func) => {
-                    // Filter out functions not of the correct arch
-                    if !func.arch_matches(target_arch_name)? { continue; }
+                    // Filter out functions not of the correct arch, or whose cfg does not hold
+                    if !func.is_enabled(config)? { continue; }
                     
-                    let mut func = func.to_ir_base(unit, self)?;
+                    let (mut func, signature) = func.to_ir_base(unit, declarations, self)?;
                     func.set_extern();
-                    unit.add_function(func);
+                    let idx = unit.add_function(func);
+                    declarations.set_signature(idx, signature);
+                },
+                _ => {}
+            }
+        }
+
+        // 3. And the bases of derived methods
+        for node in &self.nodes {
+            match node {
+                TopLevelNode::StructDeclaration(decl) if decl.is_enabled(config) => {
+                    for (_, idx) in decl.derived_to_ir_base(unit)? {
+                        unit.get_function_mut(idx).unwrap().set_extern();
+                    }
                 },
                 _ => {}
             }
@@ -54,14 +69,16 @@ impl TranslationUnit {
     }
 
     /// Both defines the unit and appends function code - to_extern_ir_on should *not* have been called first.
-    pub fn to_ir_on(&self, unit: &mut ir::TranslationUnit, target_arch_name: &str) -> Result<(), IrGenError> {
+    pub fn to_ir_on(&self, unit: &mut ir::TranslationUnit, declarations: &mut Declarations, config: &BuildConfig, source: &SourceFile) -> Result<(), IrGenError> {
         // 1. Declare all the types - must be done first so function signatures can use these types
         for node in &self.nodes {
             match node {
-                TopLevelNode::StructDeclaration(decl) => {
-                    let ct = decl.to_ir(unit, self)?;
+                TopLevelNode::StructDeclaration(decl) if decl.is_enabled(config) => {
+                    let (ct, props) = decl.to_ir(unit, declarations, self, config)?;
+                    declarations.set_props(&ct, props);
                     unit.add_type(ct);
                 },
+                TopLevelNode::TypeDeclaration(decl) if decl.is_enabled(config) => decl.to_ir_on(unit, declarations)?,
                 _ => {}
             }
         }
@@ -71,11 +88,12 @@ impl TranslationUnit {
         for node in &self.nodes {
             match node {
                 TopLevelNode::Function(func) => {
-                    // Filter out functions not of the correct arch
-                    if !func.arch_matches(target_arch_name)? { continue; }
+                    // Filter out functions not of the correct arch, or whose cfg does not hold
+                    if !func.is_enabled(config)? { continue; }
 
-                    let func = func.to_ir_base(unit, self)?;
+                    let (func, signature) = func.to_ir_base(unit, declarations, self)?;
                     let idx = unit.add_function(func);
+                    declarations.set_signature(idx, signature);
                     if first_index.is_none() {
                         first_index = Some(idx.idx());
                     }
@@ -84,16 +102,25 @@ impl TranslationUnit {
             }
         }
 
-        // 3. Then add code
+        // 3. Add the bases of derived methods, which are given code once everything else has been
+        let mut derived = Vec::new();
+        for node in &self.nodes {
+            match node {
+                TopLevelNode::StructDeclaration(decl) if decl.is_enabled(config) => derived.extend(decl.derived_to_ir_base(unit)?),
+                _ => {}
+            }
+        }
+
+        // 4. Then add code
         let mut id = 0;
         for node in &self.nodes {
             match node {
                 TopLevelNode::Function(func) => {
-                    if !func.arch_matches(target_arch_name)? { continue; }
+                    if !func.is_enabled(config)? { continue; }
 
                     if func.code.is_some() {
                         // Safe to unwrap as this wouldn't be running otherwise
-                        func.append_ir(unit, ir::FunctionIndex::new(id + first_index.unwrap()))?;
+                        func.append_ir(unit, declarations, ir::FunctionIndex::new(id + first_index.unwrap()), source, config)?;
                     }
                     id += 1;
                 },
@@ -101,6 +128,10 @@ impl TranslationUnit {
             }
         }
 
+        for (derive, idx) in derived {
+            derive.append_ir(unit, idx)?;
+        }
+
         Ok(())
     }
 }
\ No newline at end of file
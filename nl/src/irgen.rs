@@ -2,6 +2,9 @@ use std::collections::HashMap;
 use ir;
 use syntax::Span;
 
+use crate::ast::Code;
+use crate::types;
+
 /// Represents a specific kind of error, and any necessary metadata it needs to show a nice error message
 #[derive(Debug)]
 pub enum IrGenErrorKind {
@@ -34,9 +37,29 @@ pub enum IrGenErrorKind {
     ReturnValueWhenVoid,
     IncorrectReturnType(String, String),
     InvalidEntryReturns,
-    InvalidEntryParams
+    InvalidEntryParams,
+    InvalidTestSignature,
+    UnhandledFallibleCall,
+    NotFallible,
+    TryOutsideFallibleFunction,
+    FailOutsideFallibleFunction,
+    ErrorTypeMismatch(String, String), // Found, expected
+    CatchTypeMismatch(String, String), // Found, expected
+    OperatorMethodMissing(String, String, String), // Method name, lhs type, rhs type
+    InvalidOperatorMethod(String, String), // Method name, type name
+    InvalidDistinctType(String), // Type name
+    TypeAlreadyDeclared(String), // Type name
+    NotANumber(String), // Type name
+    IfExprTypeMismatch(String, String), // Found, expected
+    DuplicateCase,
+    UnderivableProperty(String, String, String), // Derive name, prop name, type name
+    DerivedMethodExists(String, String), // Method name, type name
+    AsmArchMismatch(String, String), // Block architecture, target
+    InvalidAsmBinding(String, String), // Variable name, type name
+    BreakOutsideLoop
 }
 
+#[derive(Debug)]
 pub struct IrGenError {
     span: Span,
     kind: IrGenErrorKind
@@ -88,50 +111,181 @@ impl IrGenError {
             IrGenErrorKind::ReturnValueWhenVoid => format!("No value expected in return for void function"),
             IrGenErrorKind::IncorrectReturnType(found, expected) => format!("Return type mismatch, found {}, expected {}", found, expected),
             IrGenErrorKind::InvalidEntryReturns => format!("Entry point must return an i32, and an i32 only"),
-            IrGenErrorKind::InvalidEntryParams => format!("Entry point must have no params")
+            IrGenErrorKind::InvalidEntryParams => format!("Entry point must have no params"),
+            IrGenErrorKind::InvalidTestSignature => format!("Test must be a function with no params, returning a bool and a bool only"),
+            IrGenErrorKind::UnhandledFallibleCall => format!("Call to fallible function must be handled with try or catch"),
+            IrGenErrorKind::NotFallible => format!("Can only try or catch a call to a fallible function"),
+            IrGenErrorKind::TryOutsideFallibleFunction => format!("Can only use try inside of a fallible function"),
+            IrGenErrorKind::FailOutsideFallibleFunction => format!("Can only fail inside of a fallible function"),
+            IrGenErrorKind::ErrorTypeMismatch(found, expected) => format!("Error type mismatch, found {}, expected {}", found, expected),
+            IrGenErrorKind::CatchTypeMismatch(found, expected) => format!("Type mismatch in catch, found {}, expected {}", found, expected),
+            IrGenErrorKind::OperatorMethodMissing(method, lhs, rhs) => format!("No method '{}' to implement binary operation, lhs is {}, rhs is {}", method, lhs, rhs),
+            IrGenErrorKind::InvalidOperatorMethod(method, type_name) => format!("Method '{}.{}' must take self and one other value, and return exactly one value", type_name, method),
+            IrGenErrorKind::InvalidDistinctType(name) => format!("Distinct type '{}' must be of a number or bool", name),
+            IrGenErrorKind::TypeAlreadyDeclared(name) => format!("Type '{}' is already declared", name),
+            IrGenErrorKind::NotANumber(name) => format!("Expected a number, found {}", name),
+            IrGenErrorKind::IfExprTypeMismatch(found, expected) => format!("Type mismatch in if expression, else is {}, expected {}", found, expected),
+            IrGenErrorKind::DuplicateCase => format!("Value is already handled by an earlier case"),
+            IrGenErrorKind::UnderivableProperty(derive, prop, type_name) => format!("Cannot derive {} as {} is of type {}, which is not a number, bool or struct deriving {}", derive, prop, type_name, derive),
+            IrGenErrorKind::DerivedMethodExists(method, type_name) => format!("Cannot derive method '{}', as {} already has a method of that name", method, type_name),
+            IrGenErrorKind::AsmArchMismatch(arch, target) => format!("Assembly for {} cannot be built for {}, try putting it in a cfg block", arch, target),
+            IrGenErrorKind::InvalidAsmBinding(name, type_name) => format!("Cannot bind '{}' to a register, as it is of type {}", name, type_name),
+            IrGenErrorKind::BreakOutsideLoop => format!("Can only break or continue inside of a loop")
         }
     }
 }
 
 /// Convert a storable type to a user displayable name
-pub fn storable_type_to_string(st: &ir::StorableType) -> String {
+pub fn storable_type_to_string(st: &types::StorableType) -> String {
     match st {
-        ir::StorableType::Compound(ct) => ct.name().to_string(),
-        ir::StorableType::Value(v) => value_type_to_string(v),
-        ir::StorableType::Slice(slice_type) => {
+        types::StorableType::Compound(ct) => ct.name().to_string(),
+        types::StorableType::Value(v) => value_type_to_string(v),
+        types::StorableType::Slice(slice_type) => {
             let mut s = storable_type_to_string(slice_type);
             s.push_str("[]");
             s
-        },
-        ir::StorableType::SliceData(_) => unreachable!(),
+        }
     }
 }
 
 /// Convert a value type to a user displayable name
-pub fn value_type_to_string(vt: &ir::ValueType) -> String {
+pub fn value_type_to_string(vt: &types::ValueType) -> String {
     match vt {
-        ir::ValueType::U8 => "u8".to_string(),
-        ir::ValueType::I8 => "i8".to_string(),
-        ir::ValueType::U16 => "u16".to_string(),
-        ir::ValueType::I16 => "i16".to_string(),
-        ir::ValueType::U32 => "u32".to_string(),
-        ir::ValueType::I32 => "i32".to_string(),
-        ir::ValueType::U64 => "u64".to_string(),
-        ir::ValueType::I64 => "i64".to_string(),
-        ir::ValueType::UPtr => "uptr".to_string(),
-        ir::ValueType::IPtr => "iptr".to_string(),
-        ir::ValueType::Bool => "bool".to_string(),
-        ir::ValueType::Ref(st) => storable_type_to_string(st),
-        ir::ValueType::Index(_) => "uptr".to_string(),
+        types::ValueType::U8 => "u8".to_string(),
+        types::ValueType::I8 => "i8".to_string(),
+        types::ValueType::U16 => "u16".to_string(),
+        types::ValueType::I16 => "i16".to_string(),
+        types::ValueType::U32 => "u32".to_string(),
+        types::ValueType::I32 => "i32".to_string(),
+        types::ValueType::U64 => "u64".to_string(),
+        types::ValueType::I64 => "i64".to_string(),
+        types::ValueType::UPtr => "uptr".to_string(),
+        types::ValueType::IPtr => "iptr".to_string(),
+        types::ValueType::Bool => "bool".to_string(),
+        types::ValueType::Ref(st) => storable_type_to_string(st),
+        types::ValueType::Index(_) => "uptr".to_string(),
+        types::ValueType::Distinct(distinct) => distinct.name().to_string(),
+    }
+}
+
+/// The file IR is being generated from, used to describe locations in the source to the running program
+pub struct SourceFile {
+    path: String,
+    line_starts: Vec<usize>
+}
+
+impl SourceFile {
+    pub fn new<T: Into<String>>(path: T, content: &str) -> SourceFile {
+        let mut line_starts = vec![0];
+        for (i, chr) in content.bytes().enumerate() {
+            if chr == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+
+        SourceFile {
+            path: path.into(),
+            line_starts
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The line (starting from 1) the span begins on
+    pub fn line_of(&self, span: &Span) -> usize {
+        match self.line_starts.binary_search(&span.start) {
+            Ok(line) => line + 1,
+            Err(line) => line
+        }
+    }
+}
+
+/// What is being built for, which `[cfg(...)]` conditions are evaluated against
+pub struct BuildConfig {
+    target: String,
+    pointer_width: String,
+    /// Set when building the test runner, where the test option is set and entry points are replaced by the runner
+    testing: bool,
+    /// Flags given on the command line with -D, later definitions replace earlier ones
+    flags: Vec<(String, Option<String>)>
+}
+
+impl BuildConfig {
+    pub fn new<T: Into<String>>(target: T, pointer_width: usize) -> BuildConfig {
+        BuildConfig {
+            target: target.into(),
+            pointer_width: pointer_width.to_string(),
+            testing: false,
+            flags: Vec::new()
+        }
+    }
+
+    pub fn define<T: Into<String>>(&mut self, name: T, value: Option<String>) {
+        self.flags.push((name.into(), value));
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn set_testing(&mut self) {
+        self.testing = true;
+    }
+
+    pub fn testing(&self) -> bool {
+        self.testing
+    }
+
+    /// Looks up an option, which is either the target, the pointer width, test or a flag. Gives None if the option is not set,
+    /// or the value it was set to otherwise (which a flag may not have)
+    pub fn option(&self, name: &str) -> Option<Option<&str>> {
+        match name {
+            "target" => return Some(Some(&self.target)),
+            "pointer_width" => return Some(Some(&self.pointer_width)),
+            "test" if self.testing => return Some(None),
+            _ => {}
+        }
+
+        for (flag, value) in self.flags.iter().rev() {
+            if flag == name {
+                return Some(value.as_deref());
+            }
+        }
+
+        None
     }
 }
 
+/// A loop whose body is being generated, which a break or continue leaves
+pub struct LoopScope {
+    /// The block depth of the body
+    pub depth: usize,
+    /// How many defer scopes are open outside of the body
+    pub defer_scopes: usize
+}
+
 /// Represents the function level context while generating IR, is aware of locals (and their names), and which function this is
 pub struct IrGenFunctionContext<'a> {
     pub ir_unit: &'a mut ir::TranslationUnit,
     pub function_idx: ir::FunctionIndex,
+    pub source: &'a SourceFile,
+    pub config: &'a BuildConfig,
+    pub declarations: &'a types::Declarations,
+
+    pub local_map: HashMap<&'a str, ir::LocalIndex>,
+    /// The type of each local as NL sees it, by index
+    pub local_types: Vec<types::StorableType>,
 
-    pub local_map: HashMap<&'a str, ir::LocalIndex>
+    /// Code deferred by each enclosing block, innermost last
+    pub defer_scopes: Vec<Vec<&'a Code>>,
+
+    /// How many blocks are open around the code being generated which a break counts on the way out, such as the code of
+    /// an if or a loop
+    pub block_depth: usize,
+    /// Each enclosing loop, innermost last
+    pub loops: Vec<LoopScope>
 }
 
 impl<'a> IrGenFunctionContext<'a> {
@@ -143,23 +297,137 @@ impl<'a> IrGenFunctionContext<'a> {
         self.ir_unit.get_function_mut(self.function_idx).unwrap()
     }
 
-    pub fn push_local(&mut self, name: &'a str, st: ir::StorableType) -> ir::LocalIndex {
-        let idx = self.func_mut().push_local(ir::Local::new(st));
+    pub fn push_local(&mut self, name: &'a str, st: types::StorableType) -> ir::LocalIndex {
+        let idx = self.push_temp_local(st);
         self.local_map.insert(name, idx);
 
         idx
     }
+
+    /// Push an unnamed local, for values which only exist within the IR
+    pub fn push_temp_local(&mut self, st: types::StorableType) -> ir::LocalIndex {
+        let idx = self.func_mut().push_local(ir::Local::new(st.to_ir()));
+        self.local_types.push(st);
+
+        idx
+    }
+
+    pub fn local_type(&self, idx: ir::LocalIndex) -> &types::StorableType {
+        &self.local_types[idx.idx()]
+    }
+
+    /// The signature of a function in the unit as NL sees it
+    pub fn signature(&self, idx: ir::FunctionIndex) -> types::Signature {
+        self.declarations.signature(self.ir_unit, idx)
+    }
+
+    /// Splits the returns of a fallible function into the values returned on success and the error type, or None if the
+    /// function cannot fail. Fallible functions return their success values, followed by the error and a bool which is set
+    /// on failure.
+    pub fn fallible_returns(&self, idx: ir::FunctionIndex) -> Option<(Vec<types::ValueType>, types::ValueType)> {
+        if !self.ir_unit.get_function(idx).unwrap().is_fallible() {
+            return None;
+        }
+
+        let returns = self.signature(idx).returns().clone();
+        Some((returns[..returns.len() - 2].to_vec(), returns[returns.len() - 2].clone()))
+    }
+
+    /// Push the zero value of the given type. References cannot be written as literals, so they are read from a new
+    /// local instead, which is never written to. The value is only a placeholder, and should never be read.
+    pub fn push_zero_value(&mut self, vt: &types::ValueType, target: &mut IrGenCodeTarget) {
+        let ir_vt = vt.to_ir();
+        match ir_vt {
+            ir::ValueType::Ref(_) | ir::ValueType::Index(_) => {
+                let local = self.push_temp_local(types::StorableType::Value(vt.clone()));
+                target.push(ir::Ins::PushPath(ir::ValuePath::new_origin_only(ir::ValuePathOrigin::Local(local, ir::StorableType::Value(ir_vt.clone()))), ir_vt.clone()));
+                target.push(ir::Ins::Push(ir_vt));
+            },
+            _ => target.push(ir::Ins::PushLiteral(ir_vt, 0))
+        }
+    }
+
+    pub fn push_defer_scope(&mut self) {
+        self.defer_scopes.push(Vec::new());
+    }
+
+    pub fn pop_defer_scope(&mut self) -> Vec<&'a Code> {
+        self.defer_scopes.pop().expect("No defer scope to pop")
+    }
+
+    pub fn defer(&mut self, code: &'a Code) {
+        self.defer_scopes.last_mut().expect("Defer outside of a block").push(code);
+    }
+
+    /// All deferred code in the order it should be run when returning, i.e. from the most recently deferred to the first
+    pub fn deferred_for_return(&self) -> Vec<&'a Code> {
+        let mut deferred = Vec::new();
+        for scope in self.defer_scopes.iter().rev() {
+            deferred.extend(scope.iter().rev());
+        }
+
+        deferred
+    }
+
+    /// Run all deferred code and then return, the return values should already be on the stack
+    pub fn append_return(&mut self, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let deferred = self.deferred_for_return();
+        let scopes = std::mem::take(&mut self.defer_scopes);
+
+        for (i, code) in deferred.iter().enumerate() {
+            // If the deferred code returns early itself (with a try), only the code after it still needs to run
+            self.defer_scopes = vec![deferred[i + 1..].iter().rev().cloned().collect()];
+            code.append_ir(self, target)?;
+        }
+
+        self.defer_scopes = scopes;
+        target.push(ir::Ins::Ret);
+
+        Ok(())
+    }
+
+    /// Run the code deferred within the innermost loop, then either break out of it or continue it
+    pub fn append_loop_exit(&mut self, span: &Span, is_continue: bool, target: &mut IrGenCodeTarget) -> Result<(), IrGenError> {
+        let (depth, outer_scopes) = match self.loops.last() {
+            Some(scope) => (scope.depth, scope.defer_scopes),
+            None => return Err(IrGenError::new(span.clone(), IrGenErrorKind::BreakOutsideLoop))
+        };
+
+        let mut deferred = Vec::new();
+        for scope in self.defer_scopes[outer_scopes..].iter().rev() {
+            deferred.extend(scope.iter().rev());
+        }
+        let scopes = std::mem::take(&mut self.defer_scopes);
+
+        for (i, code) in deferred.iter().enumerate() {
+            // If the deferred code returns early itself (with a try), the code deferred outside of the loop must run too
+            self.defer_scopes = scopes[..outer_scopes].to_vec();
+            self.defer_scopes.push(deferred[i + 1..].iter().rev().cloned().collect());
+            code.append_ir(self, target)?;
+        }
+
+        self.defer_scopes = scopes;
+        target.push(match is_continue {
+            true => ir::Ins::Continue(self.block_depth - depth),
+            false => ir::Ins::Break(self.block_depth - depth)
+        });
+
+        Ok(())
+    }
 }
 
-/// A generic target for IR code - acts as a bridge to a Vec<Ins> for now, but may carry more information on the current block in future
+/// A generic target for IR code - acts as a bridge to a Vec<Ins> for now, but may carry more information on the current block in future.
+/// Every target starts with an empty stack, so code which needs an empty stack (such as an If) can be hoisted to the start of it.
 pub struct IrGenCodeTarget {
-    ins: Vec<ir::Ins>
+    ins: Vec<ir::Ins>,
+    hoisted: Vec<ir::Ins>
 }
 
 impl IrGenCodeTarget {
     pub fn new() -> IrGenCodeTarget {
         IrGenCodeTarget {
-            ins: Vec::new()
+            ins: Vec::new(),
+            hoisted: Vec::new()
         }
     }
 
@@ -167,7 +435,30 @@ impl IrGenCodeTarget {
         self.ins.push(ins);
     }
 
+    /// Push an instruction to be run before any code already in this target, after anything hoisted previously
+    pub fn push_hoisted(&mut self, ins: ir::Ins) {
+        self.hoisted.push(ins);
+    }
+
+    /// Hoist all code from another target, including what it hoisted
+    pub fn hoist(&mut self, other: IrGenCodeTarget) {
+        self.hoisted.extend(other.take());
+    }
+
+    /// Append all code from another target, including what it hoisted
+    pub fn append(&mut self, other: IrGenCodeTarget) {
+        self.ins.extend(other.take());
+    }
+
+    /// Whether code appended to this target could be reached, which it cannot after a return or break
+    pub fn falls_through(&self) -> bool {
+        ir::falls_through(&self.hoisted) && ir::falls_through(&self.ins)
+    }
+
+    /// Take the code, with any hoisted code first
     pub fn take(self) -> Vec<ir::Ins> {
-        self.ins
+        let mut ins = self.hoisted;
+        ins.extend(self.ins);
+        ins
     }
 }
\ No newline at end of file
@@ -1,15 +1,21 @@
 #[derive(Debug)]
 pub enum TokenKind {
     FuncKeyword, ReturnKeyword, VarKeyword, IfKeyword, ElseKeyword, ForKeyword, ExternKeyword, StructKeyword,
-    AsKeyword, NewKeyword, ImportKeyword, SelfKeyword, TrueKeyword, FalseKeyword, DropKeyword,
+    AsKeyword, NewKeyword, ImportKeyword, SelfKeyword, TrueKeyword, FalseKeyword, DropKeyword, DeferKeyword,
+    TryKeyword, CatchKeyword, FailKeyword, PanicKeyword, TypeKeyword, DistinctKeyword, SwitchKeyword,
+    AsmKeyword, BreakKeyword, ContinueKeyword,
     Ident(String),
+    /// Any character which does not start another token, which is never valid
     Char(char),
     Number(String),
-    StringLit(String),
+    StringLit(Vec<u8>),
+    /// A string literal which could not be lexed, with why
+    InvalidStringLit(String),
     OpenCurly, CloseCurly, OpenParen, CloseParen, OpenBracket, CloseBracket,
-    Colon, Semi, Dot, Comma, Add, Mul, Div, Sub, Eq,
+    Colon, Semi, Dot, Comma, Add, Mul, Div, Sub, Eq, Bang, Hash,
     DblEq, NotEq, Lt, Gt, Le, Ge,
-    BoolAnd, BoolOr,
+    AddEq, SubEq, MulEq, DivEq, DblAdd, DblSub,
+    BoolAnd, BoolOr, FatArrow,
     Whitespace
 }
 
@@ -17,6 +23,14 @@ impl syntax::TokenKind for TokenKind {
     fn is_whitespace(&self) -> bool {
         matches!(self, TokenKind::Whitespace)
     }
+
+    fn lexer_error(&self) -> Option<String> {
+        match self {
+            TokenKind::InvalidStringLit(error) => Some(error.clone()),
+            TokenKind::Char(c) => Some(format!("Unexpected character '{}'", c)),
+            _ => None
+        }
+    }
 }
 
 pub type Token = syntax::Token<TokenKind>;
@@ -31,7 +45,14 @@ impl syntax::TokenMatcher<TokenKind> for Matcher {
             "<=" => TokenKind::Le,
             ">=" => TokenKind::Ge,
             "&&" => TokenKind::BoolAnd,
-            "||" => TokenKind::BoolOr
+            "||" => TokenKind::BoolOr,
+            "+=" => TokenKind::AddEq,
+            "-=" => TokenKind::SubEq,
+            "*=" => TokenKind::MulEq,
+            "/=" => TokenKind::DivEq,
+            "++" => TokenKind::DblAdd,
+            "--" => TokenKind::DblSub,
+            "=>" => TokenKind::FatArrow
         );
 
         syntax::exact!(string, offset, 
@@ -51,7 +72,9 @@ impl syntax::TokenMatcher<TokenKind> for Matcher {
             '-' => TokenKind::Sub,
             '=' => TokenKind::Eq,
             '<' => TokenKind::Lt,
-            '>' => TokenKind::Gt
+            '>' => TokenKind::Gt,
+            '!' => TokenKind::Bang,
+            '#' => TokenKind::Hash
         );
 
         syntax::keywords!(string, offset,
@@ -69,13 +92,24 @@ impl syntax::TokenMatcher<TokenKind> for Matcher {
             "self" => TokenKind::SelfKeyword,
             "true" => TokenKind::TrueKeyword,
             "false" => TokenKind::FalseKeyword,
-            "drop" => TokenKind::DropKeyword
+            "drop" => TokenKind::DropKeyword,
+            "defer" => TokenKind::DeferKeyword,
+            "try" => TokenKind::TryKeyword,
+            "catch" => TokenKind::CatchKeyword,
+            "fail" => TokenKind::FailKeyword,
+            "panic" => TokenKind::PanicKeyword,
+            "type" => TokenKind::TypeKeyword,
+            "distinct" => TokenKind::DistinctKeyword,
+            "switch" => TokenKind::SwitchKeyword,
+            "asm" => TokenKind::AsmKeyword,
+            "break" => TokenKind::BreakKeyword,
+            "continue" => TokenKind::ContinueKeyword
         );
 
         syntax::ident!(string, offset, TokenKind::Ident);
         syntax::whitespace!(string, offset, TokenKind::Whitespace);
         syntax::number!(string, offset, TokenKind::Number);
-        syntax::cstring!(string, offset, TokenKind::StringLit);
+        syntax::cstring!(string, offset, TokenKind::StringLit, TokenKind::InvalidStringLit);
         
         syntax::char!(string, offset, TokenKind::Char);
     }
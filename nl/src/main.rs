@@ -1,6 +1,7 @@
 mod lexer;
 mod ast;
 mod irgen;
+mod types;
 
 #[cfg(test)]
 mod tests;
@@ -22,13 +23,14 @@ struct Opts {
 #[derive(Clap)]
 enum SubCommand {
     Build(BuildOpts),
-    Run(BuildOpts)
+    Run(BuildOpts),
+    Test(TestOpts)
 }
 
 #[derive(Clap, Debug)]
 #[clap(setting = AppSettings::ColoredHelp)]
 struct BuildOpts {
-    /// Paths to root source file
+    /// Paths to root source file. Whole units, either in the ir text format (.nlir) or IR objects (.nlo), are merged into the unit built from the source files
     path: Vec<String>,
 
     /// Add directories to import search path
@@ -39,7 +41,7 @@ struct BuildOpts {
     #[clap(short, long, default_value = "a.out")]
     output: String,
 
-    /// Target triple. Valid values are linux-elf-x86_64, macos-macho-x86_64, macos-macho-arm64, wasm, java, none and native - which infers the type from the calling system.
+    /// Target triple. Valid values are linux-elf-x86_64, macos-macho-x86_64, macos-macho-arm64, wasm, java, interp - which runs the IR directly, none and native - which infers the type from the calling system.
     #[clap(long, short, default_value = "native")]
     triple: String,
 
@@ -55,6 +57,14 @@ struct BuildOpts {
     #[clap(long)]
     std: bool,
 
+    /// Use the tracing garbage collector runtime instead of std.c, so objects do not need to be dropped (only supported for linux-elf-x86_64 with --link and --std)
+    #[clap(long)]
+    gc: bool,
+
+    /// Build without debugging aids, currently this means panics do not print a backtrace (which is only supported for linux-elf-x86_64 with --link and --std)
+    #[clap(long)]
+    release: bool,
+
     /// Add files to pass to CC, only applies if --link is enabled
     #[clap(short='T', long)]
     ldinc: Vec<String>,
@@ -66,6 +76,30 @@ struct BuildOpts {
     /// Emit the generated IR to stdout
     #[clap(long)]
     emit_ir: bool,
+
+    /// Write the IR to the output as an IR object (.nlo), which can be given to later builds, rather than encoding it for the target
+    #[clap(long)]
+    ir_object: bool,
+
+    /// Define a flag for cfg attributes, either as name or name=value
+    #[clap(short='D', multiple_occurrences = true, number_of_values = 1)]
+    define: Vec<String>,
+
+    /// Optimisation level. 0 does not optimise, 1 folds constants, keeps objects which do not escape on the stack and
+    /// removes dead code, and 2 inlines functions too
+    #[clap(short='O', default_value = "0")]
+    opt_level: u8,
+}
+
+#[derive(Clap, Debug)]
+#[clap(setting = AppSettings::ColoredHelp)]
+struct TestOpts {
+    #[clap(flatten)]
+    build: BuildOpts,
+
+    /// Only run tests whose name contains this string
+    #[clap(long)]
+    filter: Option<String>,
 }
 
 /// Pretty prints an error to stderr caused at the given location, with a message.
@@ -125,20 +159,23 @@ fn env_search_dir_with(name: &str) -> Option<PathBuf> {
 /// Characterises the build system for NL, primarily is concerned with resolving imports and constructing one large IR translation unit
 pub struct BuildContext {
     linked_paths: Vec<PathBuf>,
-    target_arch_name: &'static str,
+    config: irgen::BuildConfig,
     search_dirs: Vec<PathBuf>,
     emit_ast: bool,
-    env_search_dir: Option<PathBuf>
+    env_search_dir: Option<PathBuf>,
+    /// The types declared by the source files, kept for the test runner built after them
+    declarations: types::Declarations
 }
 
 impl BuildContext {
-    pub fn new(linked_paths: &Vec<String>, target_arch_name: &'static str, search_dirs: &Vec<String>, emit_ast: bool) -> BuildContext {
+    pub fn new(linked_paths: &Vec<String>, config: irgen::BuildConfig, search_dirs: &Vec<String>, emit_ast: bool) -> BuildContext {
         BuildContext {
             linked_paths: linked_paths.iter().map(|x| Path::new(x).canonicalize().expect("Invalid path")).collect(),
-            target_arch_name,
+            config,
             search_dirs: search_dirs.iter().map(|x| Path::new(x).canonicalize().expect("Invalid path")).collect(),
             emit_ast,
-            env_search_dir: env_search_dir()
+            env_search_dir: env_search_dir(),
+            declarations: types::Declarations::new()
         }
     }
 
@@ -185,7 +222,7 @@ impl BuildContext {
     }
 
     /// Parses and does IRGen for the given path, pushing the result to ir_unit.
-    fn append_at_path(&self, ir_unit: &mut ir::TranslationUnit, path: &PathBuf, visited_paths: &mut Vec<PathBuf>) {
+    fn append_at_path(&self, ir_unit: &mut ir::TranslationUnit, declarations: &mut types::Declarations, path: &PathBuf, visited_paths: &mut Vec<PathBuf>) {
         let path = path.canonicalize().expect("Invalid path");
 
         // Check we have not already processed this path - this prevents infinite import loops
@@ -223,8 +260,10 @@ impl BuildContext {
         for node in &unit.nodes {
             match node {
                 ast::TopLevelNode::Import(import_stmt) => {
+                    if !import_stmt.is_enabled(&self.config) { continue }
+
                     if let Some(child_path) = self.find_import(path.parent().unwrap().to_path_buf(), &import_stmt.path) {
-                        self.append_at_path(ir_unit, &child_path, visited_paths);
+                        self.append_at_path(ir_unit, declarations, &child_path, visited_paths);
                     } else {
                         let error = format!("Could not resolve import {}", import_stmt.path.join("."));
                         eprintln!("ImportError in {}: {}", path.display(), error);
@@ -239,7 +278,16 @@ impl BuildContext {
         // If this file is `linked` (i.e. it was in the list of source files given on the command line) then the actual code of functions needs to be added to the IR unit.
         // Otherwise, all functions can be made extern.
         if self.linked_paths.iter().position(|x| x == &path).is_some() {
-            match unit.to_ir_on(ir_unit, self.target_arch_name) {
+            // Locations given to the program (e.g. by panic) are relative to the working directory where possible
+            let display_path = match std::env::current_dir() {
+                Ok(dir) => match path.strip_prefix(dir) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => path.clone()
+                },
+                Err(_) => path.clone()
+            };
+
+            match unit.to_ir_on(ir_unit, declarations, &self.config, &irgen::SourceFile::new(display_path.display().to_string(), &content)) {
                 Ok(_) => {},
                 Err(e) => {
                     eprintln!("SemanticError: {}: {}", path.display(), e.message());
@@ -248,7 +296,7 @@ impl BuildContext {
                 }
             }
         } else {
-            match unit.to_extern_ir_on(ir_unit, self.target_arch_name) {
+            match unit.to_extern_ir_on(ir_unit, declarations, &self.config) {
                 Ok(_) => {},
                 Err(e) => {
                     eprintln!("SemanticError: {}: {}", path.display(), e.message());
@@ -259,14 +307,70 @@ impl BuildContext {
         }
     }
 
+    /// Generates an entry point which runs every test built into ir_unit whose name contains the filter, reporting each
+    /// with test_pass or test_fail from std and exiting with a non-zero code if any failed
+    pub fn append_test_runner(&mut self, ir_unit: &mut ir::TranslationUnit, filter: Option<&str>) {
+        let mut content = String::from("func nl_test_main(): i32 {\n\tvar passed = 0;\n\tvar failed = 0;\n");
+        for func in ir_unit.functions() {
+            // Tests in files which are only imported are extern, and are run by the build they are linked into
+            if !func.is_test() || func.is_extern() { continue }
+
+            if let Some(filter) = filter {
+                if !func.name().contains(filter) { continue }
+            }
+
+            content.push_str(&format!(
+                "\tif {0}() {{\n\t\ttest_pass(\"{0}\");\n\t\tpassed = passed + 1;\n\t}} else {{\n\t\ttest_fail(\"{0}\");\n\t\tfailed = failed + 1;\n\t}}\n",
+                func.name()
+            ));
+        }
+        content.push_str("\tprinti(passed);\n\tprint(\" passed, \");\n\tprinti(failed);\n\tprint(\" failed\\n\");\n");
+        content.push_str("\tif failed != 0 {\n\t\texit(1 as i32);\n\t}\n\treturn 0 as i32;\n}\n");
+
+        let path = PathBuf::from("<test runner>");
+        let mut matcher = crate::lexer::TokenStream::new(&content, Box::new(lexer::Matcher {}));
+        matcher.step(); // Focus on the first token
+
+        let unit = match ast::TranslationUnit::parse(&mut matcher) {
+            ::syntax::MatchResult::Ok(code) => code,
+            _ => unreachable!() // The runner is always valid syntax
+        };
+
+        if self.emit_ast {
+            println!("{:#?}", unit);
+        }
+
+        // This can only fail if std, which has the functions the runner calls, is not in the build
+        match unit.to_ir_on(ir_unit, &mut self.declarations, &self.config, &irgen::SourceFile::new(path.display().to_string(), &content)) {
+            Ok(_) => {},
+            Err(e) => {
+                eprintln!("SemanticError: {}: {} (is std included?)", path.display(), e.message());
+                print_error_range(e.start(), e.end(), &content, &path, &e.message());
+                std::process::exit(1);
+            }
+        }
+
+        // Marked as the entry here, as functions with an entry annotation are left out while testing
+        let runner = match ir_unit.find_function_index("nl_test_main") {
+            Some(runner) => runner,
+            None => {
+                eprintln!("SemanticError: {}: Could not find the generated test runner nl_test_main", path.display());
+                std::process::exit(1);
+            }
+        };
+        ir_unit.get_function_mut(runner).unwrap().push_attr(ir::FunctionAttr::Entry);
+    }
+
     /// Root build method of BuildContext, will create, populate and return the TranslationUnit
     pub fn build(&mut self) -> ir::TranslationUnit {
         let mut ir_unit = ir::TranslationUnit::new();
 
+        let mut declarations = types::Declarations::new();
         let mut visited_paths = Vec::new();
         for path in &self.linked_paths {
-            self.append_at_path(&mut ir_unit, path, &mut visited_paths);
+            self.append_at_path(&mut ir_unit, &mut declarations, path, &mut visited_paths);
         }
+        self.declarations = declarations;
 
         ir_unit
     }
@@ -279,6 +383,7 @@ enum Arch {
     MacosArm64,
     Wasm,
     Java,
+    Interp,
     None
 }
 
@@ -291,6 +396,7 @@ impl Arch {
             "macos-macho-arm64" => Some(Arch::MacosArm64),
             "wasm" => Some(Arch::Wasm),
             "java" => Some(Arch::Java),
+            "interp" => Some(Arch::Interp),
             "none" => Some(Arch::None),
             "native" =>
                 if cfg!(target_os = "macos") && cfg!(target_arch="x86_64") {
@@ -311,30 +417,40 @@ impl Arch {
             Arch::MacosArm64 => "macos-arm64",
             Arch::Wasm => "wasm",
             Arch::Java => "java",
+            Arch::Interp => "interp",
             Arch::None => "none",
         }
     }
 
+    /// The width of a pointer in bits, as seen by cfg(pointer_width="...")
+    pub fn pointer_width(&self) -> usize {
+        match self {
+            Arch::LinuxX86 | Arch::MacosX86 | Arch::MacosArm64 | Arch::Interp | Arch::None => 64,
+            Arch::Wasm | Arch::Java => 32
+        }
+    }
+
     fn link(tmp: &PathBuf, build_opts: &BuildOpts) -> Result<(), String> {
+        let mut cc = std::process::Command::new("cc");
+        cc.args(&build_opts.ldinc).arg(tmp);
+
         if build_opts.std {
-            match std::process::Command::new("cc")
-                .args(&build_opts.ldinc)
-                .arg(tmp)
-                .arg(env_search_dir_with("std.c").expect("No NL_ROOT"))
-                .arg("-o").arg(&build_opts.output)
-                .status() {
-                Ok(_) => Ok(()),
-                Err(err) => return Err(format!("{}", err))
+            // The collector and the backtrace printed on panic both walk the frame pointer chain, so it must not be omitted
+            if build_opts.gc || !build_opts.release {
+                cc.arg("-fno-omit-frame-pointer");
             }
-        } else {
-            match std::process::Command::new("cc")
-                .args(&build_opts.ldinc)
-                .arg(tmp)
-                .arg("-o").arg(&build_opts.output)
-                .status() {
-                Ok(_) => Ok(()),
-                Err(err) => return Err(format!("{}", err))
+            if !build_opts.release {
+                cc.arg("-DNL_BACKTRACE");
             }
+
+            cc.arg(env_search_dir_with(if build_opts.gc { "gc.c" } else { "std.c" }).expect("No NL_ROOT"));
+            cc.arg(env_search_dir_with("panic.c").expect("No NL_ROOT"));
+        }
+
+        match cc.arg("-o").arg(&build_opts.output).status() {
+            Ok(code) if code.success() => Ok(()),
+            Ok(code) => Err(format!("Linking failed, cc exitted with {}", code)),
+            Err(err) => Err(format!("{}", err))
         }
     }
 
@@ -344,7 +460,7 @@ impl Arch {
             Arch::LinuxX86 if build_opts.link && build_opts.relocatable => {
                 let mut tmp = std::env::temp_dir();
                 tmp.push("nl-build.o");
-                ir2triple::linux_elf_x86::encode(&ir_unit, tmp.to_str().unwrap(), true)?;
+                ir2triple::linux_elf_x86::encode(&ir_unit, tmp.to_str().unwrap(), true, build_opts.gc)?;
 
                 Arch::link(&tmp, build_opts)?;
 
@@ -355,7 +471,7 @@ impl Arch {
                 
                 Ok(())
             },
-            Arch::LinuxX86 => ir2triple::linux_elf_x86::encode(&ir_unit, &build_opts.output, build_opts.relocatable),
+            Arch::LinuxX86 => ir2triple::linux_elf_x86::encode(&ir_unit, &build_opts.output, build_opts.relocatable, build_opts.gc),
             Arch::MacosX86 if build_opts.link && build_opts.relocatable => {
                 let mut tmp = std::env::temp_dir();
                 tmp.push("nl-build.o");
@@ -388,11 +504,12 @@ impl Arch {
             Arch::MacosArm64 => ir2triple::macos_macho_arm64::encode(&ir_unit, &build_opts.output, build_opts.relocatable),
             Arch::Wasm => ir2triple::wasm::encode(&ir_unit, &build_opts.output, build_opts.relocatable),
             Arch::Java => ir2triple::java::encode(&ir_unit, &build_opts.output, build_opts.relocatable),
-            Arch::None => Ok(()) // Do nothing
+            Arch::Interp | Arch::None => Ok(()) // Do nothing
         }
     }
 
-    pub fn run(&self, build_opts: &BuildOpts) -> Result<(), String> {
+    /// Runs the built program, giving whether or not it exitted successfully
+    pub fn run(&self, ir_unit: &ir::TranslationUnit, build_opts: &BuildOpts) -> Result<bool, String> {
         match self {
             Arch::LinuxX86 | Arch::MacosX86 | Arch::MacosArm64 => {
                 match std::process::Command::new(&PathBuf::from(&build_opts.output).canonicalize().unwrap())
@@ -401,7 +518,7 @@ impl Arch {
                             if !code.success() {
                                 println!("Process exitted with code {}", code);
                             }
-                            Ok(())
+                            Ok(code.success())
                         }
                         Err(err) =>  Err(format!("{}", err))
                 }
@@ -415,7 +532,7 @@ impl Arch {
                             if !code.success() {
                                 println!("Process exitted with code {}", code);
                             }
-                            Ok(())
+                            Ok(code.success())
                         }
                         Err(err) =>  Err(format!("{}", err))
                 }
@@ -430,21 +547,73 @@ impl Arch {
                             if !code.success() {
                                 println!("Process exitted with {}", code);
                             }
-                            Ok(())   
+                            Ok(code.success())   
                         }
                         Err(err) =>  Err(format!("{}", err))
                 }
             },
+            Arch::Interp => {
+                match ir_unit.interpret(&mut ir::StdHost::new(std::io::stdout())) {
+                    Ok(code) => {
+                        if code != 0 {
+                            println!("Process exitted with code {}", code);
+                        }
+                        Ok(code == 0)
+                    }
+                    Err(err) => Err(format!("{}", err))
+                }
+            },
             Arch::None => {
                 eprintln!("Nothing to run");
-                Ok(()) // Do nothing
+                Ok(true) // Do nothing
+            }
+        }
+    }
+}
+
+/// Whether the path is to a whole unit, either as text (.nlir) or as an IR object (.nlo), rather than to NL source
+fn is_unit_path(path: &str) -> bool {
+    Path::new(path).extension().map_or(false, |ext| ext == "nlir" || ext == "nlo")
+}
+
+/// Merges the unit at the given path into ir_unit, so that its functions resolve any extern functions of the same name
+fn merge_unit(ir_unit: &mut ir::TranslationUnit, path: &Path) {
+    let result = if path.extension().map_or(false, |ext| ext == "nlo") {
+        match std::fs::read(path) {
+            Ok(bytes) => ir_unit.merge_binary(&bytes),
+            Err(e) => {
+                eprintln!("Could not open {} - {}", path.display(), e);
+                std::process::exit(1);
             }
         }
+    } else {
+        let content = match std::fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Could not open {} - {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+
+        // The text format is described in `ir/syntax.md`
+        match ir::parse_unit(&content) {
+            Ok(unit) => ir_unit.merge(&unit),
+            Err(e) => {
+                eprintln!("SyntaxError in {}: {}", path.display(), e.message());
+                print_error_range(e.start(), e.end(), &content, path, e.message());
+                std::process::exit(1);
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("MergeError in {}: {}", path.display(), e);
+        std::process::exit(1);
     }
 }
 
-/// Entry point of the build subcommand
-fn build_and_run(build_opts: &BuildOpts, run: bool) {
+/// Entry point of the build, run and test subcommands, only the test subcommand gives test_opts
+fn build_and_run(build_opts: &BuildOpts, run: bool, test_opts: Option<&TestOpts>) {
     if build_opts.path.len() == 0 {
         eprintln!("No source files given");
         std::process::exit(1);
@@ -459,36 +628,79 @@ fn build_and_run(build_opts: &BuildOpts, run: bool) {
         }
     };
 
-    // Parse and build the IR Unit
-    let mut ctx = BuildContext::new(&build_opts.path, arch.short_name(), &build_opts.include, build_opts.emit_ast);
+    // The collector is a runtime linked in from C, so it only exists for native linux, and is rejected rather than ignored elsewhere
+    if build_opts.gc {
+        match arch {
+            Arch::LinuxX86 if build_opts.link && build_opts.std => {},
+            _ => {
+                eprintln!("--gc is only supported for linux-elf-x86_64 with --link and --std");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Collect the options cfg attributes can test
+    let mut config = irgen::BuildConfig::new(arch.short_name(), arch.pointer_width());
+    for define in &build_opts.define {
+        match define.split_once('=') {
+            Some((name, value)) => config.define(name, Some(value.to_string())),
+            None => config.define(define, None)
+        }
+    }
+    if test_opts.is_some() {
+        config.set_testing();
+    }
+
+    if build_opts.ir_object && run {
+        eprintln!("An IR object cannot be run");
+        std::process::exit(1);
+    }
+
+    // Parse and build the IR Unit, then merge in any whole units, such as those written by --emit-ir or --ir-object
+    let (unit_paths, source_paths): (Vec<String>, Vec<String>) = build_opts.path.iter().cloned().partition(|path| is_unit_path(path));
+    let mut ctx = BuildContext::new(&source_paths, config, &build_opts.include, build_opts.emit_ast);
     if build_opts.std {
         ctx.append_linked_path(PathBuf::from(env_search_dir_with("std.nl").expect("No NL_ROOT")));
     }
 
-    let ir_unit = ctx.build();
+    let mut ir_unit = ctx.build();
+    for path in &unit_paths {
+        merge_unit(&mut ir_unit, Path::new(path));
+    }
+    if let Some(test_opts) = test_opts {
+        ctx.append_test_runner(&mut ir_unit, test_opts.filter.as_deref());
+    }
 
     if build_opts.emit_ir {
-        for (idx, func) in ir_unit.functions().iter().enumerate() {
-            print!("func {}:{:?}", idx, func.name());
-            if func.attr_count() != 0 {
-                for attr in func.attrs() {
-                    print!(" ");
-                    match attr {
-                        ir::FunctionAttr::Entry => print!("@entry"),
-                        ir::FunctionAttr::Alloc => print!("@alloc"),
-                        ir::FunctionAttr::AllocSlice => print!("@alloc_slice"),
-                        ir::FunctionAttr::Free => print!("@free"),
-                        ir::FunctionAttr::FreeSlice => print!("@free_slice"),
-                        ir::FunctionAttr::ExternLocation(location) => print!("@extern({:?})", location),
-                    }
-                }
+        print!("{}", ir_unit);
+    }
+
+    // Does not *strictly* need to be here, but good for debugging
+    match ir_unit.validate() {
+        Ok(warnings) => for w in warnings {
+            match w.location() {
+                ir::ValidationLocation::Function(idx, _) => eprintln!("ValidationWarning in {}: {}", ir_unit.get_function(*idx).unwrap().name(), w),
+                ir::ValidationLocation::Global(_) => eprintln!("ValidationWarning: {}", w)
+            }
+        },
+        Err(e) => {
+            match e.location() {
+                ir::ValidationLocation::Function(idx, _) => eprintln!("ValidationError in {}: {}", ir_unit.get_function(*idx).unwrap().name(), e),
+                ir::ValidationLocation::Global(_) => eprintln!("ValidationError: {}", e)
             }
-            println!(" {}", func);
+            std::process::exit(1);
         }
     }
 
-    // Does not *strictly* need to be here, but good for debugging
-    ir_unit.validate().expect("Could not validate IR");
+    ir::opt::PassManager::for_level(build_opts.opt_level, arch.pointer_width() / 8).run(&mut ir_unit);
+
+    if build_opts.ir_object {
+        if let Err(e) = std::fs::write(&build_opts.output, ir_unit.to_binary()) {
+            eprintln!("Could not write {} - {}", build_opts.output, e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     match arch.encode(&ir_unit, &build_opts) {
         Ok(_) => (),
@@ -499,7 +711,9 @@ fn build_and_run(build_opts: &BuildOpts, run: bool) {
     }
 
     if run {
-        match arch.run(&build_opts) {
+        match arch.run(&ir_unit, &build_opts) {
+            // A failing test run must fail the command too, so it can be used from scripts
+            Ok(false) if test_opts.is_some() => std::process::exit(1),
             Ok(_) => (),
             Err(e) => {
                 eprintln!("RunError: {}", e);
@@ -513,7 +727,8 @@ fn main() {
     let opts = Opts::parse();
 
     match opts.cmd {
-        SubCommand::Build(build_opts) => build_and_run(&build_opts, false),
-        SubCommand::Run(build_opts) => build_and_run(&build_opts, true),
+        SubCommand::Build(build_opts) => build_and_run(&build_opts, false, None),
+        SubCommand::Run(build_opts) => build_and_run(&build_opts, true, None),
+        SubCommand::Test(test_opts) => build_and_run(&test_opts.build, true, Some(&test_opts)),
     }
 }
@@ -0,0 +1,151 @@
+use syntax::MatchResult;
+
+use crate::lexer::*;
+use crate::ast::*;
+use crate::irgen::{BuildConfig, IrGenError, SourceFile};
+use crate::types::Declarations;
+
+/// Parses the source and generates IR for it, as the only file in a build for linux
+fn build(source: &str) -> Result<ir::TranslationUnit, IrGenError> {
+    let mut tokenstream = TokenStream::new(source, Box::new(Matcher));
+    tokenstream.step();
+
+    let unit = match TranslationUnit::parse(&mut tokenstream) {
+        MatchResult::Ok(unit) => unit,
+        _ => panic!("Did not parse")
+    };
+
+    let mut ir_unit = ir::TranslationUnit::new();
+    unit.to_ir_on(&mut ir_unit, &mut Declarations::new(), &BuildConfig::new("linux-x86", 64), &SourceFile::new("test.nl", source))?;
+    Ok(ir_unit)
+}
+
+/// The message of the error building the source gives, which must fail
+fn error_of(source: &str) -> String {
+    match build(source) {
+        Ok(_) => panic!("Built without an error"),
+        Err(e) => e.message()
+    }
+}
+
+#[test]
+fn break_continue() {
+    let unit = build("
+func count(): i32 {
+    var n = 0;
+    for {
+        defer n++;
+        if n > 10 {
+            break;
+        }
+        continue;
+    }
+    return n;
+}
+    ").unwrap();
+    assert!(unit.validate().is_ok());
+
+    // The deferred increment runs on the way out of each iteration, including the one which breaks
+    let mut host = ir::StdHost::new(Vec::new());
+    let mut interp = ir::Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(ir::FunctionIndex::new(0), &[]), Ok(ir::CallResult::Return(vec![ 12 ])));
+
+    assert_eq!(error_of("func f() { break; }"), "Can only break or continue inside of a loop");
+    assert_eq!(error_of("func f() { if true { continue; } }"), "Can only break or continue inside of a loop");
+}
+
+#[test]
+fn compound_assign_literal_range() {
+    let unit = build("
+func add(): u8 {
+    var b: u8 = 1;
+    b += 254;
+    b -= 5;
+    return b;
+}
+    ").unwrap();
+
+    let mut host = ir::StdHost::new(Vec::new());
+    let mut interp = ir::Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(ir::FunctionIndex::new(0), &[]), Ok(ir::CallResult::Return(vec![ 250 ])));
+
+    // The literal must fit in the type being assigned to, as it would in a plain assignment
+    assert_eq!(error_of("func f() { var b: u8 = 1; b += 300; }"), "Invalid integer");
+    assert_eq!(error_of("func f() { var b: i8 = 1; b -= 128; }"), "Invalid integer");
+}
+
+#[test]
+fn comparison_resultant_type() {
+    // The type of a comparison is bool whatever the type of its operands, including where it is inferred
+    let unit = build("
+func less(a: i32, b: i32): bool {
+    var result = a < b;
+    return result;
+}
+
+func same(a: u8, b: u8): bool {
+    var result = a == b && a != 0 as u8;
+    return result;
+}
+    ").unwrap();
+    assert!(unit.validate().is_ok());
+
+    let mut host = ir::StdHost::new(Vec::new());
+    let mut interp = ir::Interpreter::new(&unit, &mut host);
+    assert_eq!(interp.call(ir::FunctionIndex::new(0), &[ 1, 2 ]), Ok(ir::CallResult::Return(vec![ 1 ])));
+    assert_eq!(interp.call(ir::FunctionIndex::new(0), &[ 2, 1 ]), Ok(ir::CallResult::Return(vec![ 0 ])));
+    assert_eq!(interp.call(ir::FunctionIndex::new(1), &[ 3, 3 ]), Ok(ir::CallResult::Return(vec![ 1 ])));
+    assert_eq!(interp.call(ir::FunctionIndex::new(1), &[ 0, 0 ]), Ok(ir::CallResult::Return(vec![ 0 ])));
+}
+
+#[test]
+fn distinct_type() {
+    // A distinct type is only its underlying type in the IR, there is nothing left of it to convert
+    let unit = build("
+type UserId = distinct u64;
+
+func next(id: UserId): UserId {
+    return id + 1 as UserId;
+}
+
+func run(): u64 {
+    return next(41) as u64;
+}
+    ").unwrap();
+    assert_eq!(unit.get_function(ir::FunctionIndex::new(0)).unwrap().signature().params(), &vec![ ir::ValueType::U64 ]);
+
+    // But the frontend does not mix it with its underlying type without an as
+    let decl = "type UserId = distinct u64;";
+    assert_eq!(error_of(&format!("{decl} func f(id: UserId, n: u64): bool {{ return id == n; }}")), "Type mismatch in binary operation, lhs is UserId, rhs is u64");
+    assert_eq!(error_of(&format!("{decl} func f(id: UserId) {{ var n: u64 = id; }}")), "Type mismatch in assignment, found UserId, expected u64");
+    assert_eq!(error_of(&format!("{decl} func g(id: UserId) {{}} func f(n: u64) {{ g(n); }}")), "Type mismatch in arg, found u64, expected UserId");
+
+    // A name can only be declared as one type
+    assert_eq!(error_of(&format!("{decl} type UserId = u32;")), "Type 'UserId' is already declared");
+    assert_eq!(error_of("type Id = u32; type Id = distinct u32;"), "Type 'Id' is already declared");
+    assert_eq!(error_of("struct Point { x: i32 } type Point = i32;"), "Type 'Point' is already declared");
+}
+
+#[test]
+fn export_annotation() {
+    let mut unit = build("
+func [export] callback(x: i32): i32 {
+    return x + 1;
+}
+
+func unused(): i32 {
+    return 0;
+}
+
+func [entry] main(): i32 {
+    return 0;
+}
+    ").unwrap();
+
+    // Only the entry point and the function C code may call survive removing dead code
+    ir::opt::PassManager::for_level(1, 8).run(&mut unit);
+    assert!(unit.validate().is_ok());
+    assert!(unit.find_function("callback").unwrap().is_export());
+    assert!(unit.find_function("unused").is_none());
+    assert!(unit.find_function("main").is_some());
+}
@@ -1 +1,2 @@
-mod parsing;
\ No newline at end of file
+mod parsing;
+mod irgen;
@@ -98,4 +98,56 @@ func a.b.c.something_else(self) {}
     assert_eq!(func.params.len(), 0);
     assert_eq!(func.return_types.len(), 0);
     assert!(!func.is_static);
-}
\ No newline at end of file
+}
+
+#[test]
+fn invalid_string_escapes() {
+    let error_of = |string: &str| {
+        let mut tokenstream = TokenStream::new(string, Box::new(Matcher));
+        tokenstream.step();
+
+        match TranslationUnit::parse(&mut tokenstream) {
+            MatchResult::Err(error) => error.message().to_string(),
+            _ => panic!("Parsed an invalid string")
+        }
+    };
+
+    assert_eq!(error_of("func f() { var s = \"\\u{41\"; var t = \"}\"; }"), "Expected '{' and '}' around unicode escape");
+    assert_eq!(error_of("func f() { var s = \"\\u41}\"; }"), "Expected '{' and '}' around unicode escape");
+    assert_eq!(error_of("func f() { var s = \"\\u{}\"; }"), "Unicode escape must have between 1 and 6 digits");
+    assert_eq!(error_of("func f() { var s = \"\\u{+41}\"; }"), "Invalid unicode escape");
+    assert_eq!(error_of("func f() { var s = \"\\q\"; }"), "Unknown escape");
+    assert_eq!(error_of("func f() { var s = \"\\u{41}"), "Expected '\"' to end string");
+}
+
+#[test]
+fn invalid_struct_align() {
+    let error_of = |string: &str| {
+        let mut tokenstream = TokenStream::new(string, Box::new(Matcher));
+        tokenstream.step();
+
+        match TranslationUnit::parse(&mut tokenstream) {
+            MatchResult::Err(error) => error.message().to_string(),
+            _ => panic!("Parsed an invalid alignment")
+        }
+    };
+
+    assert_eq!(error_of("struct [align=16] S { a: u8 }"), "Alignment must be no more than 8");
+    assert_eq!(error_of("struct [align=6] S { a: u8 }"), "Alignment must be a power of two");
+}
+
+#[test]
+fn unexpected_character() {
+    let error_of = |string: &str| {
+        let mut tokenstream = TokenStream::new(string, Box::new(Matcher));
+        tokenstream.step();
+
+        match TranslationUnit::parse(&mut tokenstream) {
+            MatchResult::Err(error) => error.message().to_string(),
+            _ => panic!("Parsed an unexpected character")
+        }
+    };
+
+    assert_eq!(error_of("func f() { var x = 1 ~ 2; }"), "Unexpected character '~'");
+    assert_eq!(error_of("func f() { var x = 1 \u{e9} 2; }"), "Unexpected character '\u{e9}'");
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A type of value as NL sees it. These are the value types of the IR, which code is generated with through `to_ir`, along
+/// with distinct types, which only exist in NL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    U8, I8,
+    U16, I16,
+    U32, I32,
+    U64, I64,
+    UPtr, IPtr,
+    Bool,
+    Ref(Box<StorableType>),
+    Index(Box<StorableType>),
+    Distinct(DistinctTypeRef)
+}
+
+impl ValueType {
+    pub fn is_num(&self) -> bool {
+        !matches!(self.underlying(), ValueType::Ref(_) | ValueType::Index(_))
+    }
+
+    /// The type this is stored and operated on as, which is itself unless this is a distinct type
+    pub fn underlying(&self) -> &ValueType {
+        match self {
+            ValueType::Distinct(distinct) => &distinct.underlying,
+            _ => self
+        }
+    }
+
+    pub fn to_ir(&self) -> ir::ValueType {
+        match self {
+            ValueType::U8 => ir::ValueType::U8,
+            ValueType::I8 => ir::ValueType::I8,
+            ValueType::U16 => ir::ValueType::U16,
+            ValueType::I16 => ir::ValueType::I16,
+            ValueType::U32 => ir::ValueType::U32,
+            ValueType::I32 => ir::ValueType::I32,
+            ValueType::U64 => ir::ValueType::U64,
+            ValueType::I64 => ir::ValueType::I64,
+            ValueType::UPtr => ir::ValueType::UPtr,
+            ValueType::IPtr => ir::ValueType::IPtr,
+            ValueType::Bool => ir::ValueType::Bool,
+            ValueType::Ref(st) => ir::ValueType::Ref(Box::new(st.to_ir())),
+            ValueType::Index(st) => ir::ValueType::Index(Box::new(st.to_ir())),
+            ValueType::Distinct(distinct) => distinct.underlying.to_ir()
+        }
+    }
+}
+
+/// Types which come from the IR, rather than being declared in NL, are never distinct
+impl From<&ir::ValueType> for ValueType {
+    fn from(vt: &ir::ValueType) -> ValueType {
+        match vt {
+            ir::ValueType::U8 => ValueType::U8,
+            ir::ValueType::I8 => ValueType::I8,
+            ir::ValueType::U16 => ValueType::U16,
+            ir::ValueType::I16 => ValueType::I16,
+            ir::ValueType::U32 => ValueType::U32,
+            ir::ValueType::I32 => ValueType::I32,
+            ir::ValueType::U64 => ValueType::U64,
+            ir::ValueType::I64 => ValueType::I64,
+            ir::ValueType::UPtr => ValueType::UPtr,
+            ir::ValueType::IPtr => ValueType::IPtr,
+            ir::ValueType::Bool => ValueType::Bool,
+            ir::ValueType::Ref(st) => ValueType::Ref(Box::new(st.as_ref().into())),
+            ir::ValueType::Index(st) => ValueType::Index(Box::new(st.as_ref().into()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorableType {
+    Compound(ir::CompoundTypeRef),
+    Value(ValueType),
+    Slice(Box<StorableType>)
+}
+
+impl StorableType {
+    pub fn to_ir(&self) -> ir::StorableType {
+        match self {
+            StorableType::Compound(ct) => ir::StorableType::Compound(ct.clone()),
+            StorableType::Value(vt) => ir::StorableType::Value(vt.to_ir()),
+            StorableType::Slice(st) => ir::StorableType::Slice(Box::new(st.to_ir()))
+        }
+    }
+}
+
+impl From<&ir::StorableType> for StorableType {
+    fn from(st: &ir::StorableType) -> StorableType {
+        match st {
+            ir::StorableType::Compound(ct) => StorableType::Compound(ct.clone()),
+            ir::StorableType::Value(vt) => StorableType::Value(vt.into()),
+            ir::StorableType::Slice(st) => StorableType::Slice(Box::new(st.as_ref().into())),
+            ir::StorableType::SliceData(_) => unreachable!() // Only globals hold slice data, which NL never reads the type of
+        }
+    }
+}
+
+/// A number or bool type which is stored exactly like its underlying type, but is only equal to itself
+#[derive(Debug)]
+pub struct DistinctType {
+    name: String,
+    underlying: ValueType
+}
+
+impl DistinctType {
+    /// The underlying type must not itself be distinct
+    pub fn new<T: Into<String>>(name: T, underlying: ValueType) -> DistinctTypeRef {
+        Rc::new(DistinctType {
+            name: name.into(),
+            underlying
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for DistinctType {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+pub type DistinctTypeRef = Rc<DistinctType>;
+
+/// The params and returns of a function as NL sees them. A fallible function also returns its error, and a bool which is
+/// set on failure.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    params: Vec<ValueType>,
+    returns: Vec<ValueType>
+}
+
+impl Signature {
+    pub fn new(params: Vec<ValueType>, returns: Vec<ValueType>) -> Signature {
+        Signature {
+            params, returns
+        }
+    }
+
+    pub fn params(&self) -> &Vec<ValueType> {
+        &self.params
+    }
+
+    pub fn returns(&self) -> &Vec<ValueType> {
+        &self.returns
+    }
+
+    pub fn to_ir(&self) -> ir::Signature {
+        ir::Signature::new(
+            self.params.iter().map(ValueType::to_ir).collect(),
+            self.returns.iter().map(ValueType::to_ir).collect()
+        )
+    }
+}
+
+impl From<&ir::Signature> for Signature {
+    fn from(signature: &ir::Signature) -> Signature {
+        Signature::new(
+            signature.params().iter().map(ValueType::from).collect(),
+            signature.returns().iter().map(ValueType::from).collect()
+        )
+    }
+}
+
+/// What NL knows about the types in a unit on top of the IR, which is kept across every file built into it. Struct
+/// properties and function signatures are recorded as NL declares them, anything else (such as a function merged from an
+/// IR object, or a derived method) is seen as the types it has in the IR.
+#[derive(Default)]
+pub struct Declarations {
+    aliases: Vec<(String, StorableType)>,
+    distinct_types: Vec<DistinctTypeRef>,
+    /// The type of each property of a struct, by the name of the struct
+    props: HashMap<String, Vec<StorableType>>,
+    /// By the index of the function
+    signatures: HashMap<usize, Signature>
+}
+
+impl Declarations {
+    pub fn new() -> Declarations {
+        Declarations::default()
+    }
+
+    pub fn add_alias<T: Into<String>>(&mut self, name: T, target: StorableType) {
+        self.aliases.push((name.into(), target));
+    }
+
+    pub fn find_alias(&self, name: &str) -> Option<&StorableType> {
+        self.aliases.iter().find(|(alias, _)| alias == name).map(|(_, target)| target)
+    }
+
+    pub fn add_distinct_type(&mut self, distinct_type: DistinctTypeRef) {
+        self.distinct_types.push(distinct_type);
+    }
+
+    pub fn find_distinct_type(&self, name: &str) -> Option<DistinctTypeRef> {
+        self.distinct_types.iter().find(|dt| dt.name() == name).cloned()
+    }
+
+    pub fn set_props(&mut self, ct: &ir::CompoundTypeRef, props: Vec<StorableType>) {
+        self.props.insert(ct.name().to_string(), props);
+    }
+
+    /// The type of an existing property of the struct
+    pub fn prop_type(&self, ct: &ir::CompoundTypeRef, idx: ir::PropertyIndex) -> StorableType {
+        if let Some(props) = self.props.get(ct.name()) {
+            return props[idx.idx()].clone();
+        }
+
+        match ct.content() {
+            ir::CompoundContent::Struct(s) => s.prop(idx).unwrap().prop_type().into()
+        }
+    }
+
+    pub fn set_signature(&mut self, idx: ir::FunctionIndex, signature: Signature) {
+        self.signatures.insert(idx.idx(), signature);
+    }
+
+    /// The signature of a function which exists in the unit
+    pub fn signature(&self, ir_unit: &ir::TranslationUnit, idx: ir::FunctionIndex) -> Signature {
+        match self.signatures.get(&idx.idx()) {
+            Some(signature) => signature.clone(),
+            None => ir_unit.get_function(idx).unwrap().signature().into()
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// Mark-sweep garbage collector for linux-elf-x86_64, linked in place of std.c when building with --gc.
+//
+// Roots are found by walking the frame pointer chain kept by every function ir2x86 generates, and conservatively scanning
+// each frame along with the data and bss sections. ir2x86 spills all values on the IR stack to the native stack before a
+// call, so every live reference is somewhere in those frames. Heap objects are traced precisely, using the layout
+// descriptor ir2triple emits for each allocated type, which gives the offset of every reference within the type.
+
+#include <stdlib.h>
+#include <stdint.h>
+#include <setjmp.h>
+
+#define NL_GC_MIN_THRESHOLD (1 << 20)
+
+struct layout_t {
+	size_t size;
+	size_t ref_count;
+	size_t ref_offsets[];
+};
+
+struct __attribute__((packed)) slice_t {
+	void* data;
+	size_t len;
+};
+
+struct header_t {
+	struct header_t* prev;
+	struct header_t* next;
+	const struct layout_t* layout;
+	size_t count; // Element count for slices, the elements follow the slice_t in the payload
+	size_t size; // Size of the payload in bytes
+	int is_slice;
+	int marked;
+};
+
+extern void* __libc_stack_end;
+extern char __data_start[];
+extern char _end[];
+
+static struct header_t* objects = NULL;
+static size_t object_count = 0;
+static size_t allocated = 0;
+static size_t threshold = NL_GC_MIN_THRESHOLD;
+
+// Only valid during a collection
+static struct header_t** sorted = NULL;
+static struct header_t** mark_stack = NULL;
+static size_t mark_stack_len = 0;
+
+static char* payload_of(struct header_t* header) {
+	return (char*) (header + 1);
+}
+
+static int compare_headers(const void* a, const void* b) {
+	uintptr_t x = (uintptr_t) *(struct header_t* const*) a;
+	uintptr_t y = (uintptr_t) *(struct header_t* const*) b;
+	return (x > y) - (x < y);
+}
+
+// Finds the object containing the given address, interior pointers are allowed since the data pointer of a slice points
+// into the middle of its allocation
+static struct header_t* find_object(uintptr_t addr) {
+	size_t low = 0;
+	size_t high = object_count;
+
+	while (low < high) {
+		size_t middle = (low + high) / 2;
+		uintptr_t start = (uintptr_t) payload_of(sorted[middle]);
+
+		if (addr < start) {
+			high = middle;
+		} else if (addr >= start + sorted[middle]->size && !(addr == start && sorted[middle]->size == 0)) {
+			low = middle + 1;
+		} else {
+			return sorted[middle];
+		}
+	}
+
+	return NULL;
+}
+
+static void mark_candidate(uintptr_t addr) {
+	struct header_t* header = find_object(addr);
+
+	if (header != NULL && !header->marked) {
+		header->marked = 1;
+		mark_stack[mark_stack_len++] = header;
+	}
+}
+
+static void scan_range(char* start, char* end) {
+	uintptr_t word = ((uintptr_t) start + sizeof(void*) - 1) & ~(sizeof(void*) - 1);
+
+	for (; word + sizeof(void*) <= (uintptr_t) end; word += sizeof(void*)) {
+		mark_candidate(*(uintptr_t*) word);
+	}
+}
+
+static void scan_layout(const struct layout_t* layout, char* base) {
+	for (size_t i = 0; i < layout->ref_count; i++) {
+		mark_candidate(*(uintptr_t*) (base + layout->ref_offsets[i]));
+	}
+}
+
+static void trace(struct header_t* header) {
+	if (header->is_slice) {
+		char* data = payload_of(header) + sizeof(struct slice_t);
+
+		for (size_t i = 0; i < header->count; i++) {
+			scan_layout(header->layout, data + i * header->layout->size);
+		}
+	} else {
+		scan_layout(header->layout, payload_of(header));
+	}
+}
+
+static void __attribute__((noinline)) scan_stack(void) {
+	// Spill callee saved registers into this frame, so they are scanned with it
+	jmp_buf registers;
+	setjmp(registers);
+
+	char* start = (char*) &registers;
+	void** frame = __builtin_frame_address(0);
+
+	// Each frame runs from the end of the previous one up to its saved frame pointer, plus the return address above it.
+	// Callee saved registers are stored inside the frame, below the saved frame pointer. The chain ends once it leaves
+	// the stack, which happens when the entry function returns to the C runtime.
+	while ((char*) frame >= start && (char*) frame < (char*) __libc_stack_end) {
+		char* end = (char*) (frame + 2);
+		scan_range(start, end);
+		start = end;
+
+		void** next = *frame;
+		if (next <= frame) break;
+		frame = next;
+	}
+}
+
+void nl_gc_collect(void) {
+	if (object_count == 0) return;
+
+	sorted = malloc(object_count * sizeof(struct header_t*));
+	mark_stack = malloc(object_count * sizeof(struct header_t*));
+	mark_stack_len = 0;
+
+	size_t i = 0;
+	for (struct header_t* header = objects; header != NULL; header = header->next) {
+		header->marked = 0;
+		sorted[i++] = header;
+	}
+	qsort(sorted, object_count, sizeof(struct header_t*), compare_headers);
+
+	scan_stack();
+	scan_range(__data_start, _end);
+
+	while (mark_stack_len > 0) {
+		trace(mark_stack[--mark_stack_len]);
+	}
+
+	free(sorted);
+	free(mark_stack);
+	sorted = NULL;
+	mark_stack = NULL;
+
+	allocated = 0;
+	struct header_t* header = objects;
+	while (header != NULL) {
+		struct header_t* next = header->next;
+
+		if (header->marked) {
+			allocated += header->size;
+		} else {
+			if (header->prev != NULL) header->prev->next = header->next;
+			else objects = header->next;
+			if (header->next != NULL) header->next->prev = header->prev;
+
+			object_count--;
+			free(header);
+		}
+
+		header = next;
+	}
+
+	threshold = allocated * 2 > NL_GC_MIN_THRESHOLD ? allocated * 2 : NL_GC_MIN_THRESHOLD;
+}
+
+static struct header_t* allocate(const struct layout_t* layout, size_t size, size_t count, int is_slice) {
+	if (allocated + size > threshold) {
+		nl_gc_collect();
+	}
+
+	struct header_t* header = calloc(1, sizeof(struct header_t) + size);
+	if (header == NULL) abort();
+
+	header->layout = layout;
+	header->count = count;
+	header->size = size;
+	header->is_slice = is_slice;
+	header->marked = 0;
+
+	header->prev = NULL;
+	header->next = objects;
+	if (objects != NULL) objects->prev = header;
+	objects = header;
+
+	object_count++;
+	allocated += size;
+
+	return header;
+}
+
+static void release(void* payload) {
+	if (payload == NULL) return;
+
+	struct header_t* header = (struct header_t*) payload - 1;
+
+	if (header->prev != NULL) header->prev->next = header->next;
+	else objects = header->next;
+	if (header->next != NULL) header->next->prev = header->prev;
+
+	object_count--;
+	allocated -= header->size;
+	free(header);
+}
+
+void* nl_new_object(const struct layout_t* layout) {
+	return payload_of(allocate(layout, layout->size, 0, 0));
+}
+
+struct slice_t* nl_new_slice(size_t length, const struct layout_t* element) {
+	struct header_t* header = allocate(element, sizeof(struct slice_t) + length * element->size, length, 1);
+
+	struct slice_t* slice = (struct slice_t*) payload_of(header);
+	slice->data = payload_of(header) + sizeof(struct slice_t);
+	slice->len = length;
+	return slice;
+}
+
+// Explicit drops are still allowed, and free the object immediately
+
+void nl_drop_object(void* object, size_t size) {
+	release(object);
+}
+
+void nl_drop_slice(struct slice_t* slice, size_t element_size) {
+	release(slice);
+}
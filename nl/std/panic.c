@@ -0,0 +1,154 @@
+// Panic hook for native targets, linked alongside std.c or gc.c when building with --std.
+//
+// Outside of --release, NL_BACKTRACE is defined and on linux a backtrace is printed after the message. It is found by
+// walking the frame pointer chain kept by every function ir2x86 generates, and each return address is named using the
+// symbol table of the executable, which holds a symbol for every function ir2triple emits.
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdint.h>
+
+#define NL_PANIC_EXIT_CODE 101
+
+struct __attribute__((packed)) slice_t {
+	void* data;
+	size_t len;
+};
+
+struct __attribute__((packed)) string_t {
+	struct slice_t* data;
+};
+
+struct __attribute__((packed)) location_t {
+	struct string_t* file;
+	uint32_t line;
+};
+
+void nl_panic(struct string_t* message, struct location_t* location);
+
+#if defined(NL_BACKTRACE) && defined(__linux__)
+#include <elf.h>
+#include <string.h>
+
+extern void* __libc_stack_end;
+
+struct symbols_t {
+	const Elf64_Sym* symbols;
+	size_t count;
+	const char* names;
+};
+
+// The image is never freed, as the process is about to exit
+static char* read_executable(void) {
+	FILE* file = fopen("/proc/self/exe", "rb");
+	if (file == NULL) return NULL;
+
+	char* image = NULL;
+	if (fseek(file, 0, SEEK_END) == 0) {
+		long size = ftell(file);
+		image = size > 0 ? malloc(size) : NULL;
+
+		if (image != NULL && (fseek(file, 0, SEEK_SET) != 0 || fread(image, 1, size, file) != (size_t) size)) {
+			free(image);
+			image = NULL;
+		}
+	}
+
+	fclose(file);
+	return image;
+}
+
+static int load_symbols(struct symbols_t* symbols) {
+	char* image = read_executable();
+	if (image == NULL) return 0;
+
+	const Elf64_Ehdr* header = (const Elf64_Ehdr*) image;
+	const Elf64_Shdr* sections = (const Elf64_Shdr*) (image + header->e_shoff);
+
+	for (size_t i = 0; i < header->e_shnum; i++) {
+		if (sections[i].sh_type == SHT_SYMTAB) {
+			symbols->symbols = (const Elf64_Sym*) (image + sections[i].sh_offset);
+			symbols->count = sections[i].sh_size / sizeof(Elf64_Sym);
+			symbols->names = image + sections[sections[i].sh_link].sh_offset;
+			return 1;
+		}
+	}
+
+	return 0;
+}
+
+static const Elf64_Sym* find_symbol(const struct symbols_t* symbols, const char* name) {
+	for (size_t i = 0; i < symbols->count; i++) {
+		if (strcmp(symbols->names + symbols->symbols[i].st_name, name) == 0) {
+			return &symbols->symbols[i];
+		}
+	}
+
+	return NULL;
+}
+
+static const char* function_containing(const struct symbols_t* symbols, uintptr_t addr) {
+	for (size_t i = 0; i < symbols->count; i++) {
+		const Elf64_Sym* symbol = &symbols->symbols[i];
+
+		if (ELF64_ST_TYPE(symbol->st_info) == STT_FUNC && addr >= symbol->st_value && addr < symbol->st_value + symbol->st_size) {
+			return symbols->names + symbol->st_name;
+		}
+	}
+
+	return NULL;
+}
+
+static void print_backtrace(void) {
+	struct symbols_t symbols;
+	const Elf64_Sym* self;
+	if (!load_symbols(&symbols) || (self = find_symbol(&symbols, "nl_panic")) == NULL) {
+		fprintf(stderr, "no backtrace available, could not read the symbol table\n");
+		return;
+	}
+
+	// Position independent executables are loaded away from the addresses in the symbol table, so find how far by
+	// comparing against the address of this hook
+	uintptr_t bias = (uintptr_t) &nl_panic - self->st_value;
+
+	fprintf(stderr, "backtrace:\n");
+
+	// Frames up to and including the hook itself are skipped, so the backtrace starts where the panic was raised
+	uintptr_t* frame = __builtin_frame_address(0);
+	int in_hook = 1;
+	size_t depth = 0;
+	while (frame != NULL && (void*) frame < __libc_stack_end) {
+		// The return address points after the call, which may be the very end of the function
+		const char* name = function_containing(&symbols, frame[1] - bias - 1);
+		if (name == NULL) break;
+
+		if (in_hook) {
+			in_hook = strcmp(name, "nl_panic") != 0;
+		} else {
+			fprintf(stderr, "%4zu: %s\n", depth++, name);
+			if (strcmp(name, "main") == 0) break;
+		}
+
+		uintptr_t* next = (uintptr_t*) frame[0];
+		if (next <= frame) break;
+		frame = next;
+	}
+}
+#endif
+
+void nl_panic(struct string_t* message, struct location_t* location) {
+	// Anything the program has already printed should appear before the panic
+	fflush(stdout);
+
+	fprintf(stderr, "panic at %.*s:%u: %.*s\n",
+		(int) location->file->data->len, (const char*) location->file->data->data,
+		location->line,
+		(int) message->data->len, (const char*) message->data->data
+	);
+
+#if defined(NL_BACKTRACE) && defined(__linux__)
+	print_backtrace();
+#endif
+
+	exit(NL_PANIC_EXIT_CODE);
+}
@@ -1,23 +1,10 @@
-func [arch="linux-x86"] exit(code: i32) extern
-func [arch="linux-x86"] putchar(chr: u32) extern
-func [arch="linux-x86", alloc] nl_new_object(size: uptr): uptr extern
-func [arch="linux-x86", alloc_slice] nl_new_slice(count: uptr, size: uptr): uptr extern
-func [arch="linux-x86", free] nl_drop_object(object: uptr, size: uptr) extern
-func [arch="linux-x86", free_slice] nl_drop_slice(slice: uptr, element_size: uptr) extern
-
-func [arch="macos-x86"] exit(code: i32) extern
-func [arch="macos-x86"] putchar(chr: u32) extern
-func [arch="macos-x86", alloc] nl_new_object(size: uptr): uptr extern
-func [arch="macos-x86", alloc_slice] nl_new_slice(count: uptr, size: uptr): uptr extern
-func [arch="macos-x86", free] nl_drop_object(object: uptr, size: uptr) extern
-func [arch="macos-x86", free_slice] nl_drop_slice(slice: uptr, element_size: uptr) extern
-
-func [arch="macos-arm64"] exit(code: i32) extern
-func [arch="macos-arm64"] putchar(chr: u32) extern
-func [arch="macos-arm64", alloc] nl_new_object(size: uptr): uptr extern
-func [arch="macos-arm64", alloc_slice] nl_new_slice(count: uptr, size: uptr): uptr extern
-func [arch="macos-arm64", free] nl_drop_object(object: uptr, size: uptr) extern
-func [arch="macos-arm64", free_slice] nl_drop_slice(slice: uptr, element_size: uptr) extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64"))] exit(code: i32) extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64"))] putchar(chr: u32) extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64")), alloc] nl_new_object(size: uptr): uptr extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64")), alloc_slice] nl_new_slice(count: uptr, size: uptr): uptr extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64")), free] nl_drop_object(object: uptr, size: uptr) extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64")), free_slice] nl_drop_slice(slice: uptr, element_size: uptr) extern
+func [cfg(any(target="linux-x86", target="macos-x86", target="macos-arm64")), panic] nl_panic(message: String, location: PanicLocation) extern
 
 func [arch="wasm", location="core"] exit(code: i32) extern
 func [arch="wasm", location="core"] putchar(chr: u32) extern
@@ -25,14 +12,33 @@ func [arch="wasm", location="core", alloc] new_object(size: uptr): uptr extern
 func [arch="wasm", location="core", alloc_slice] new_slice(count: uptr, size: uptr): uptr extern
 func [arch="wasm", location="core", free] drop_object(object: uptr, size: uptr) extern
 func [arch="wasm", location="core", free_slice] drop_slice(slice: uptr, element_size: uptr) extern
+func [arch="wasm", location="core", panic] report_panic(message: String, location: PanicLocation) extern
 
 func [arch="java", location="nl/std/Std"] exit(code: i32) extern
 func [arch="java", location="nl/std/Std"] putchar(b: u32) extern
 
+func [arch="interp"] exit(code: i32) extern
+func [arch="interp"] putchar(chr: u32) extern
+
 struct String {
 	data: u8[]
 }
 
+struct PanicLocation {
+	file: String,
+	line: u32
+}
+
+func [arch="java,interp", panic] nl_panic(message: String, location: PanicLocation) {
+	print("panic at ");
+	print(location.file);
+	print(":");
+	printi(location.line as i32);
+	print(": ");
+	println(message);
+	exit(101);
+}
+
 func String.empty(): String {
 	var string: String = new String;
 	string.data = new u8[0];
@@ -48,6 +54,148 @@ func String.at(self, idx: uptr): u8 {
 	return self.data[idx];
 }
 
+func String.op_eq(self, other: String): bool {
+	if self.len() != other.len() {
+		return false;
+	}
+
+	for var i: uptr = 0; i < self.len(); i++ {
+		if self.data[i] != other.data[i] {
+			return false;
+		}
+	}
+
+	return true;
+}
+
+func String.op_ne(self, other: String): bool {
+	return self.op_eq(other) == false;
+}
+
+func String.hash(self): u64 {
+	var hash: u64 = 17;
+	for var i: uptr = 0; i < self.len(); i++ {
+		hash = hash * 31 + self.data[i] as u64;
+	}
+
+	return hash;
+}
+
+func String.print(self) {
+	print(self);
+}
+
+func String.is_continuation_at(self, offset: uptr): bool {
+	if offset < self.len() {
+		return self.data[offset] >= 128 && self.data[offset] < 192;
+	}
+
+	return false;
+}
+
+func String.char_width_at(self, offset: uptr): uptr {
+	var lead = self.data[offset];
+	var width: uptr = 1;
+	if lead >= 240 {
+		width = 4;
+	} else if lead >= 224 {
+		width = 3;
+	} else if lead >= 192 {
+		width = 2;
+	}
+
+	for var i: uptr = 1; i < width; i = i + 1 {
+		if self.is_continuation_at(offset + i) == false {
+			return 1 as uptr;
+		}
+	}
+
+	return width;
+}
+
+func String.char_at(self, offset: uptr): char {
+	var width = self.char_width_at(offset);
+	var lead = self.data[offset] as u32;
+	if width == 1 {
+		if lead >= 128 {
+			return 65533 as char;
+		}
+		return lead;
+	}
+
+	var c: char = 0;
+	if width == 2 {
+		c = lead - 192;
+	} else if width == 3 {
+		c = lead - 224;
+	} else {
+		c = lead - 240;
+	}
+
+	for var i: uptr = 1; i < width; i = i + 1 {
+		c = c * 64 + (self.data[offset + i] as u32 - 128);
+	}
+
+	return c;
+}
+
+func String.char_count(self): uptr {
+	var count: uptr = 0;
+	for var offset: uptr = 0; offset < self.len(); offset = offset + self.char_width_at(offset) {
+		count = count + 1;
+	}
+
+	return count;
+}
+
+struct Chars {
+	string: String,
+	offset: uptr
+}
+
+func String.chars(self): Chars {
+	var chars = new Chars;
+	chars.string = self;
+	chars.offset = 0;
+
+	return chars;
+}
+
+func Chars.has_next(self): bool {
+	return self.offset < self.string.len();
+}
+
+func Chars.next(self): char {
+	var c = self.string.char_at(self.offset);
+	self.offset = self.offset + self.string.char_width_at(self.offset);
+
+	return c;
+}
+
+func utf8_continuation(c: char, divisor: u32): u32 {
+	var part = c / divisor;
+	var byte: u32 = part - (part / 64) * 64 + 128;
+	return byte;
+}
+
+func print_char(c: char) {
+	if c < 128 {
+		putchar(c);
+	} else if c < 2048 {
+		putchar(c / 64 + 192);
+		putchar(utf8_continuation(c, 1));
+	} else if c < 65536 {
+		putchar(c / 4096 + 224);
+		putchar(utf8_continuation(c, 64));
+		putchar(utf8_continuation(c, 1));
+	} else {
+		putchar(c / 262144 + 240);
+		putchar(utf8_continuation(c, 4096));
+		putchar(utf8_continuation(c, 64));
+		putchar(utf8_continuation(c, 1));
+	}
+}
+
 func print(string: String) {
 	for var i: uptr = 0; i < string.len(); i = i + 1 {
 		putchar(string.at(i) as u32);
@@ -84,6 +232,21 @@ func printi(i: i32) {
 	}
 }
 
+func printi64(i: i64) {
+	var rest = i / 10;
+	var digit = i - rest * 10;
+	if i < 0 {
+		print("-");
+		rest = -rest;
+		digit = -digit;
+	}
+
+	if rest > 0 {
+		printi64(rest);
+	}
+	putchar(48 + digit as u32);
+}
+
 func printiln(i: i32) {
 	printi(i);
 	putchar(10);
@@ -139,8 +302,6 @@ func binary_search(sorted_slice: i32[], target: i32): uptr {
 
 		if end < start { return sorted_slice.length; }
 	}
-
-	return 0 as uptr;
 }
 
 func bubble_sort(slice: i32[]) {
@@ -3,7 +3,9 @@ const primaryPath = process.argv[2];
 const fs = require("fs");
 
 const ALLOC_SIZE_OFF = 0;
-const ALLOC_HEADER_SIZE = 4;
+// Allocations are 8 byte aligned, the largest alignment a type can ask for, so the 4 byte size header is padded
+const ALLOC_ALIGN = 8;
+const ALLOC_HEADER_SIZE = ALLOC_ALIGN;
 const FREE_FD_OFF = ALLOC_HEADER_SIZE;
 const FREE_FD_NONE = 0xffffffff;
 const FREE_SELF_SIZE = 4;
@@ -13,28 +15,29 @@ class NLProcessMemory {
         this.memory = memory;
         this.u8mem = new Uint8Array(memory.buffer);
 
-        // 8 byte align the heap
-        this.heap_start = heap_start & 7 == 0? heap_start:(heap_start + 16 - (heap_start & 7));
+        // Align the heap, blocks then stay aligned as their headers and sizes are multiples of the alignment
+        this.heap_start = (heap_start & (ALLOC_ALIGN - 1)) == 0? heap_start:(heap_start + ALLOC_ALIGN - (heap_start & (ALLOC_ALIGN - 1)));
         this.heap_end = this.heap_start;
 
-        // Freelist buckets, of 4, 8, 12, 16, 20, 24, 28 and 32 byte blocks - the most common sizes for structures
+        // Freelist buckets, of 8, 16, 24, 32, 40, 48, 56 and 64 byte blocks - the most common sizes for structures
         // Addresses refer to the beginning of the block, not the FD pointer as in GLIBC
         this.firstfree = {
-            4: null,
             8: null,
-            12: null,
             16: null,
-            20: null,
             24: null,
-            28: null,
             32: null,
+            40: null,
+            48: null,
+            56: null,
+            64: null,
         };
         this.any_firstfree = null;
     }
 
     read_u32(addr) {
         // It would be nice to align addresses and thus use a Uint32Array, but we can't every guarantee alignment
-        return this.u8mem[addr] | (this.u8mem[addr + 1] << 8) | (this.u8mem[addr + 2] << 16) | (this.u8mem[addr + 3] << 24);
+        // >>> 0 makes the result unsigned, otherwise FREE_FD_NONE would be read back as -1
+        return (this.u8mem[addr] | (this.u8mem[addr + 1] << 8) | (this.u8mem[addr + 2] << 16) | (this.u8mem[addr + 3] << 24)) >>> 0;
     }
 
     write_u32(addr, value) {
@@ -44,6 +47,15 @@ class NLProcessMemory {
         this.u8mem[addr + 3] = (value >> 24) & 0xff;
     }
 
+    // Reads the contents of a String struct, which holds a reference to a slice of its bytes
+    read_string(addr) {
+        const slice = this.read_u32(addr);
+        const data = this.read_u32(slice);
+        const length = this.read_u32(slice + 4);
+
+        return Buffer.from(this.u8mem.subarray(data, data + length)).toString();
+    }
+
     // Size is already assumed to be aligned
     allocate_from_wilderness(size) {
         const addr = this.heap_end;
@@ -51,13 +63,13 @@ class NLProcessMemory {
 
         this.write_u32(addr + ALLOC_SIZE_OFF, size);
 
-        // + 4 to get the usable region, addr only points to the start of the block
+        // Skip the header to get the usable region, addr only points to the start of the block
         return addr + ALLOC_HEADER_SIZE;
     }
 
     heap_allocate(size) {
-        if (size & 3) size += 4 - (size & 3);
-        if (size < 4) size = FREE_SELF_SIZE; // make sure the free data can fit
+        if (size < FREE_SELF_SIZE) size = FREE_SELF_SIZE; // make sure the free data can fit
+        if (size & (ALLOC_ALIGN - 1)) size += ALLOC_ALIGN - (size & (ALLOC_ALIGN - 1));
         
         if (size in this.firstfree) {
             // This is probably sub-optimal - we should likely look at other buckets, bigger than ours first.
@@ -69,7 +81,7 @@ class NLProcessMemory {
             
             let addr = this.firstfree[size];
             let fd = this.read_u32(addr + FREE_FD_OFF);
-            this.firstfree[addr] = fd == FREE_FD_NONE? null:fd;
+            this.firstfree[size] = fd == FREE_FD_NONE? null:fd;
 
             // Alloc block should already be set up
             return addr + ALLOC_HEADER_SIZE;
@@ -150,12 +162,13 @@ class NLProcessMemory {
 
     const core = {
         exit: (code) => process.exit(code),
-        putchar: (char) => process.stdout.write(String.fromCharCode(char)),
+        // Called once per byte, so the bytes of a UTF-8 string must be written as they are rather than as characters
+        putchar: (byte) => process.stdout.write(Uint8Array.of(byte)),
         
         new_object: (size) => mem_ctx.heap_allocate(size),
         new_slice: (length, size) => {
             let addr = mem_ctx.heap_allocate(8);
-            let data = mem_ctx.heap_free(length * size);
+            let data = mem_ctx.heap_allocate(length * size);
             
             mem_ctx.write_u32(addr, data);
             mem_ctx.write_u32(addr + 4, length);
@@ -170,6 +183,15 @@ class NLProcessMemory {
 
             mem_ctx.heap_free(data_addr, slice_length * size);
             mem_ctx.heap_free(slice, 8);
+        },
+
+        // location is a PanicLocation, holding the file (a String) and then the line
+        report_panic: (message, location) => {
+            const file = mem_ctx.read_string(mem_ctx.read_u32(location));
+            const line = mem_ctx.read_u32(location + 4);
+
+            console.error(`panic at ${file}:${line}: ${mem_ctx.read_string(message)}`);
+            process.exit(101);
         }
     };
     
@@ -1,18 +1,15 @@
 import std
+import [cfg(target="no-such-target")] no.such.file
 
-func test_new() {
+func [test] test_new(): bool {
 	var string = new String;
 	var data = new u8[10];
 	string.data = data;
 
-	if string.len() == 10 {
-		test_pass("test_new");
-	} else {
-		test_fail("test_new");
-	}
+	return string.len() == 10;
 }
 
-func test_int_slice_index() {
+func [test] test_int_slice_index(): bool {
 	var u8_data = new u8[10];
 	u8_data[0] = 5;
 	u8_data[1] = 6;
@@ -24,78 +21,72 @@ func test_int_slice_index() {
 
 	if u8_data[0] == 5 {
 		if i32_data[3] == 9 {
-			test_pass("test_int_slice_index");
-			return;
+			return true;
 		}
 	}
 	
-	test_fail("test_int_slice_index");
+	return false;
 }
 
-func test_if() {
+func [test] test_if(): bool {
 	if 1 < 2 {
 		if 2 > 1 {
-			test_pass("test_if");
-			return;
+			return true;
 		}
 	}
-	test_fail("test_if");
+	return false;
 }
 
-func test_if_else() {
+func [test] test_if_else(): bool {
 	if 1 < 2 {
 		if 2 < 1 {
 
 		} else {
-			test_pass("test_if_else");
-			return;
+			return true;
 		}
 	} else {
 
 	}
-	test_fail("test_if_else");
+	return false;
 }
 
-func test_bool_expr() {
+func [test] test_bool_expr(): bool {
 	if 1 + 2 == 3 && 1 == 1 {
 		if 1 + 2 == 1 || 1 == 1 {
 			if 1 + 2 == 1 || 2 == 1 {
 
 			} else {
-				test_pass("test_bool_expr");
-				return;
+				return true;
 			}
 		}
 	}
-	test_fail("test_bool_expr");
+	return false;
 }
 
-func test_math() {
+func [test] test_math(): bool {
 	if 1 + 2 == 3 {
 		if 3 * 4 == 12 {
 			if 6 / 3 == 2 {
 				if 9 - 8 == 1 {
-					test_pass("test_math");
-					return;
+					return true;
 				}
 			}
 		}
 	}
 
-	test_fail("test_math");
+	return false;
 }
 
-func test_div() {
+func [test] test_div(): bool {
 	if (4 / 2) == 2 {
 		if (9 + 3) / 4 == 3 {
 			if 3 == 1 + (4 / (1 + 1)) {
-				test_pass("test_div");
-				return;
+				return true;
 			}
 		}
 	}
 
-	test_fail("test_div");
+	return false;
 }
 
 func a(): i32 {
@@ -106,15 +97,11 @@ func b(): i32 {
 	return 1 - 2;
 }
 
-func test_call_expr() {
-	if (a() + b()) == 2 {
-		test_pass("test_call_expr");
-	} else {
-		test_fail("test_call_expr");
-	}
+func [test] test_call_expr(): bool {
+	return (a() + b()) == 2;
 }
 
-func test_for() {
+func [test] test_for(): bool {
 	var test = 0;
 
 	for var i = 1; i <= 100; i = i + 1 {
@@ -122,55 +109,38 @@ func test_for() {
 	}
 
 	var expected = 100 * (100 + 1) / 2;
-	if test == expected {
-		test_pass("test_for");
-	} else {
-		test_fail("test_for");
-	}
+	return test == expected;
 }
 
-func test_op_order() {
+func [test] test_op_order(): bool {
 	if 3*2 + 1 == 7 {
 		if 9/3 + 2 == 5 {
 			if 9/3 + 6*2 == 15 {
-				test_pass("test_op_order");
-				return;
+				return true;
 			}
 		}
 	}
 
-	test_fail("test_op_order");
+	return false;
 }
 
-func test_static() {
+func [test] test_static(): bool {
 	var x = String.empty();
 
-	if x.len() == 0 {
-		test_pass("test_static");
-	} else {
-		test_fail("test_static");
-	}
+	return x.len() == 0;
 }
 
-func test_slice_literal() {
+func [test] test_slice_literal(): bool {
 	var x = [1, 2, 3, 4];
 
-	if x.length == 4 && x[0] == 1 && x[2] == 3 {
-		test_pass("test_slice_literal");
-	} else {
-		test_fail("test_slice_literal");
-	}
+	return x.length == 4 && x[0] == 1 && x[2] == 3;
 }
 
-func test_binary_search() {
-	if binary_search([1, 2, 3, 4, 5, 6, 7], 4) == 3 && binary_search([1, 2, 3, 4, 5, 6, 7], 1) == 0 {
-		test_pass("test_binary_search");
-	} else {
-		test_fail("test_binary_search");
-	}
+func [test] test_binary_search(): bool {
+	return binary_search([1, 2, 3, 4, 5, 6, 7], 4) == 3 && binary_search([1, 2, 3, 4, 5, 6, 7], 1) == 0;
 }
 
-func test_bubble_sort() {
+func [test] test_bubble_sort(): bool {
 	var a = [6, 4, 3, 2, 1, 5];
 	var b = [1, 2, 3, 4, 5, 6];
 	
@@ -183,48 +153,816 @@ func test_bubble_sort() {
 		}
 	}
 
-	if is_ok {
-		test_pass("test_bubble_sort");
-	} else {
-		test_fail("test_bubble_sort");
-	}
+	return is_ok;
 }
 
-func test_drop() {
+func [test] test_drop(): bool {
 	var x = new String;
 	drop x;
 
 	var y = new u8[7];
 	drop y;
 
-	test_pass("test_drop");
+	return true;
+}
+
+func [test] test_neg(): bool {
+	return (-(-4) == 4) && (-4 + 4 == 0) && (-3 * 2 == -6);
+}
+
+struct Counter {
+	value: i32
+}
+
+func defer_digits(counter: Counter, early: i32): i32 {
+	defer counter.value = counter.value * 10 + 1;
+
+	if early == 1 {
+		defer counter.value = counter.value * 10 + 2;
+		return counter.value;
+	}
+
+	for var i = 0; i < 2; i = i + 1 {
+		defer counter.value = counter.value * 10 + 3;
+		counter.value = counter.value * 10 + 4;
+	}
+
+	return counter.value;
+}
+
+func [test] test_defer(): bool {
+	var early = new Counter;
+	early.value = 0;
+	var early_result = defer_digits(early, 1);
+
+	var late = new Counter;
+	late.value = 0;
+	var late_result = defer_digits(late, 0);
+
+	var passed = early_result == 0 && early.value == 21 && late_result == 4343 && late.value == 43431;
+
+	drop early;
+	drop late;
+
+	return passed;
+}
+
+func [test] test_reuse_after_drop(): bool {
+	var a = new Counter;
+	var b = new Counter;
+	drop a;
+	drop b;
+
+	var c = new Counter;
+	var d = new Counter;
+	c.value = 1;
+	d.value = 2;
+
+	var first = new i32[2];
+	var second = new i32[2];
+	first[1] = 3;
+	second[1] = 4;
+
+	var passed = c.value == 1 && d.value == 2 && first[1] == 3 && second[1] == 4;
+
+	drop c;
+	drop d;
+	drop first;
+	drop second;
+
+	return passed;
+}
+
+struct Septet {
+	a: i32, b: i32, c: i32, d: i32, e: i32, f: i32, g: i32
+}
+
+func [test] test_reuse_last_free(): bool {
+	var only = new Septet;
+	drop only;
+
+	var reused = new Septet;
+	var fresh = new Septet;
+	reused.g = 5;
+	fresh.g = 6;
+
+	var passed = reused.g == 5 && fresh.g == 6;
+
+	drop reused;
+	drop fresh;
+
+	return passed;
+}
+
+func defer_loop_digits(counter: Counter): i32 {
+	for var i = 0; i < 5; i++ {
+		defer counter.value = counter.value * 10 + 1;
+
+		if i == 1 {
+			continue;
+		}
+		if i == 3 {
+			defer counter.value = counter.value * 10 + 2;
+			break;
+		}
+
+		counter.value = counter.value * 10 + 3;
+	}
+
+	return counter.value;
+}
+
+func [test] test_break_continue(): bool {
+	var counter = new Counter;
+	counter.value = 0;
+	var digits = defer_loop_digits(counter);
+	drop counter;
+
+	var n = 0;
+	var total = 0;
+	for {
+		n++;
+		switch n {
+			2 => continue;
+			5 => break;
+		}
+		total += n;
+	}
+
+	var pairs = 0;
+	for var a = 0; a < 3; a++ {
+		for var b = 0; b < 3; b++ {
+			if b > a {
+				break;
+			}
+			pairs++;
+		}
+	}
+
+	return digits == 3113121 && total == 8 && pairs == 6;
+}
+
+func checked_div(a: i32, b: i32): i32 ! i32 {
+	if b == 0 {
+		fail 7;
+	}
+
+	return a / b;
+}
+
+func sum_of_quotients(a: i32, b: i32, divisor: i32): i32 ! i32 {
+	return try checked_div(a, divisor) + try checked_div(b, divisor);
+}
+
+func count_quotients(counter: Counter, divisor: i32): ! i32 {
+	defer counter.value = counter.value + 1;
+
+	try sum_of_quotients(6, 4, divisor);
+	counter.value = counter.value + 10;
+}
+
+func [test] test_try(): bool {
+	var ok = sum_of_quotients(6, 4, 2) catch -1;
+	var failed = sum_of_quotients(6, 4, 0) catch (error) error * 100;
+
+	var counter = new Counter;
+	counter.value = 0;
+	count_quotients(counter, 0) catch 0;
+	var after_failure = counter.value;
+	count_quotients(counter, 2) catch 0;
+
+	var passed = ok == 5 && failed == 700 && after_failure == 1 && counter.value == 12;
+
+	drop counter;
+
+	return passed;
+}
+
+func record_digit(counter: Counter, digit: i32): i32 {
+	counter.value = counter.value * 10 + digit;
+	return digit;
+}
+
+func checked_record_digit(counter: Counter, digit: i32): i32 ! i32 {
+	counter.value = counter.value * 10 + digit;
+	return digit;
+}
+
+func digit_pair(a: i32, b: i32): i32 {
+	return a * 10 + b;
+}
+
+func record_in_order(counter: Counter, data: i32[]): i32 ! i32 {
+	var sum = record_digit(counter, 1) + try checked_record_digit(counter, 2);
+	data[record_digit(counter, 3) as uptr] = try checked_record_digit(counter, 4);
+	data[record_digit(counter, 2) as uptr] += try checked_record_digit(counter, 5);
+	var pair = digit_pair(record_digit(counter, 6), try checked_record_digit(counter, 7));
+	var caught = record_digit(counter, 8) + (checked_record_digit(counter, 9) catch 0);
+
+	return sum + data[3] + data[2] + pair + caught;
+}
+
+func [test] test_try_order(): bool {
+	var counter = new Counter;
+	counter.value = 0;
+	var data = new i32[4];
+	data[2] = 1;
+
+	var total = record_in_order(counter, data) catch -1;
+	var passed = counter.value == 1234256789 && total == 3 + 4 + 6 + 67 + 17;
+
+	drop counter;
+	drop data;
+
+	return passed;
+}
+
+func [test] test_unicode(): bool {
+	var name = "Zoë \u{1F600}";
+	var escaped = "\u{eb}";
+
+	var sum: char = 0;
+	var chars = name.chars();
+	for chars.has_next() {
+		sum = sum + chars.next();
+	}
+	drop chars;
+
+	return name.len() == 9 && name.char_count() == 5 && sum == 128980 && escaped.char_at(0) == name.char_at(2);
+}
+
+func [test] test_truncated_utf8(): bool {
+	var truncated = new String;
+	truncated.data = new u8[3];
+	truncated.data[0] = 97;
+	truncated.data[1] = 226;
+	truncated.data[2] = 130;
+
+	var passed = truncated.char_count() == 3 && truncated.char_at(1) == 65533 as char;
+
+	drop truncated.data;
+	drop truncated;
+
+	return passed;
+}
+
+func [test] test_string_equality(): bool {
+	var built = new String;
+	built.data = new u8[3];
+	built.data[0] = 97;
+	built.data[1] = 98;
+	built.data[2] = 99;
+
+	var literal = "abc";
+	var same = built == literal && literal == built && built.hash() == literal.hash();
+	var different = built != "abd" && built != "ab" && built != "abcd" && "" != built;
+
+	drop built.data;
+	drop built;
+
+	return same && different && String.empty() == "";
+}
+
+func [test] test_unsigned_arithmetic(): bool {
+	var big: u32 = 4000000000;
+	var small: u8 = 200;
+	var wide: u64 = 18000000000000000000;
+	var third: u32 = big / 3;
+	var small_third: u8 = small / 3;
+	var wide_seventh: u64 = wide / 7;
+	var negative: i8 = -100;
+	var negative_third: i8 = negative / 3;
+	var product: u32 = 70000;
+	product = product * 70000;
+
+	return third == 1333333333 && small_third == 66 && wide_seventh == 2571428571428571428 && negative_third == -33 && product == 605032704;
+}
+
+func [test] test_unsigned_widening(): bool {
+	var byte: u8 = 200;
+	var short: u16 = 60000;
+	var int: u32 = 4000000000;
+
+	var bytes = byte as i32 == 200 && byte as u16 == 200 as u16 && byte as i64 == 200 as i64;
+	var shorts = short as i32 == 60000 && short as u64 == 60000 as u64;
+	var ints = int as i64 == 4000000000 as i64 && int as u64 == 4000000000 as u64;
+
+	return bytes && shorts && ints;
+}
+
+func [test] test_long_comparison(): bool {
+	var big: i64 = 5000000000 as i64;
+	var small: i64 = -3000000000 as i64;
+	var huge: u64 = 9000000000 as u64;
+	var count = 0;
+
+	for var i = 0; i < 3; i = i + 1 {
+		if big > small && small < big && big >= big && small <= small {
+			count = count + 1;
+		}
+	}
+
+	var signed = big != small && big == 5000000000 as i64 && (big < small) == false && (small >= big) == false;
+	var unsigned = huge > 8999999999 as u64 && huge <= 9000000000 as u64 && huge != 1 as u64 && (huge == 0 as u64) == false;
+
+	return count == 3 && signed && unsigned;
+}
+
+struct Vec2 {
+	x: i32,
+	y: i32
+}
+
+func Vec2.op_add(self, other: Vec2): Vec2 {
+	var sum = new Vec2;
+	sum.x = self.x + other.x;
+	sum.y = self.y + other.y;
+	return sum;
+}
+
+func Vec2.op_eq(self, other: Vec2): bool {
+	return self.x == other.x && self.y == other.y;
+}
+
+func Vec2.op_lt(self, other: Vec2): bool {
+	return self.x * self.x + self.y * self.y < other.x * other.x + other.y * other.y;
+}
+
+func [test] test_operators(): bool {
+	var a = new Vec2;
+	a.x = 1;
+	a.y = 2;
+	var b = new Vec2;
+	b.x = 3;
+	b.y = 4;
+
+	var sum = a + b;
+	var reversed = b + a;
+
+	var passed = sum.x == 4 && sum.y == 6 && sum == reversed && a < b && sum < b == false;
+
+	drop a;
+	drop b;
+	drop sum;
+	drop reversed;
+
+	return passed;
+}
+
+type Handle = u32;
+type UserId = distinct u64;
+
+func next_user(id: UserId): UserId {
+	return id + 1 as UserId;
+}
+
+func [test] test_type_decl(): bool {
+	var handle: Handle = 7;
+	var raw: u32 = handle;
+
+	var id: UserId = 41;
+	id = next_user(id);
+	var back = id as u64;
+	var forth = (back + 1) as UserId;
+
+	return raw == 7 && id == 42 && back == 42 && forth == 43 && id < forth;
+}
+
+struct Platform {
+	[cfg(pointer_width="64")] wide: bool,
+	[cfg(pointer_width="32")] narrow: bool
+}
+
+func [cfg(target="no-such-target")] cfg_choice(): i32 {
+	return 0 as i32;
 }
 
-func test_neg() {
-	if (-(-4) != 4) || (-4 + 4 != 0) || (-3 * 2 != -6) {
-		test_fail("test_neg");
+func [cfg(not(target="no-such-target"))] cfg_choice(): i32 {
+	return 1 as i32;
+}
+
+func [test] test_cfg(): bool {
+	var platform = new Platform;
+	var count = 0;
+
+	[cfg(pointer_width="64")] {
+		platform.wide = true;
+		count = count + 1;
+	}
+
+	[cfg(pointer_width="32")] {
+		platform.narrow = true;
+		count = count + 1;
+	}
+
+	[cfg(all(not(no_such_flag), any(target="linux-x86", target="macos-x86", target="macos-arm64", target="wasm", target="java", target="interp")))] {
+		count = count + 10;
+	}
+
+	drop platform;
+
+	return count == 11 && cfg_choice() == 1;
+}
+
+
+func Counter.next_index(self): uptr {
+	self.value++;
+	return 1 as uptr;
+}
+
+func [test] test_compound_assign(): bool {
+	var x = 10;
+	x += 5;
+	x -= 3;
+	x *= 4;
+	x /= 6;
+	x++;
+	x--;
+	x++;
+
+	var y = 2;
+	y += x;
+
+	var big: i64 = 1;
+	big += 300;
+	big -= 1;
+
+	var counter = new Counter;
+	counter.value = 0;
+	var data = new u8[4];
+	data[1] = 0;
+	data[counter.next_index()] += 7;
+	data[counter.next_index()] *= 2;
+	data[counter.next_index()] -= 4;
+
+	var total = 0;
+	for var i = 0; i < 4; i++ {
+		total += i;
+	}
+
+	var passed = x == 9 && y == 11 && big == 300 && data[1] == 10 && counter.value == 3 && total == 6;
+
+	drop counter;
+	drop data;
+
+	return passed;
+}
+
+func square(x: i32): i32 {
+	return x * x;
+}
+
+func pick_max(a: i32, b: i32): i32 {
+	return if a > b { a } else { b };
+}
+
+func [test] test_if_expr(): bool {
+	var a = 3;
+	var b = 7;
+	var max = if a > b { a } else { b };
+	var sign = if a < 5 { -1 } else if a == 5 { 0 } else { 1 };
+	var sum = 1 + if b > 5 { square(b) } else { 0 };
+	var big: i64 = if max == 7 { 40 } else { 2 };
+	var flag = if sum > 10 { true } else { false };
+	var chosen = if flag { a + b } else { a * b };
+
+	return max == 7 && sign == -1 && sum == 50 && big == 40 && flag && chosen == 10 && pick_max(2, 9) == 9 && pick_max(9, 2) == 9;
+}
+
+func classify(x: i32): i32 {
+	switch x {
+		0 => return 10 as i32;
+		1, 2 => return 20 as i32;
+		3 => {
+			var y = x * 10;
+			return y;
+		}
+		4 => return 40 as i32;
+		else => {}
+	}
+
+	return 0 as i32;
+}
+
+func classify_sparse(x: i64): i32 {
+	var result = 0;
+	switch x {
+		-5 => result = 1;
+		100 => result = 2;
+		10000, 5000000000 => result = 3;
+		else => result = 4;
+	}
+
+	return result;
+}
+
+func classify_few(x: i32): i32 {
+	switch x {
+		-100 => return 1 as i32;
+		7, 900 => return 2 as i32;
+	}
+
+	return 0 as i32;
+}
+
+func classify_near(x: i64): i32 {
+	switch x {
+		-2 => return 1 as i32;
+		0, 1 => return 2 as i32;
+		3 => return 3 as i32;
+	}
+
+	return 0 as i32;
+}
+
+func classify_wide(x: i64): i32 {
+	switch x {
+		5000000000 => return 1 as i32;
+		5000000001, 5000000003 => return 2 as i32;
+	}
+
+	return 0 as i32;
+}
+
+func classify_byte(x: u8): i32 {
+	switch x {
+		0 => return 1 as i32;
+		100 => return 2 as i32;
+		255 => return 3 as i32;
+	}
+
+	return 0 as i32;
+}
+
+func classify_signed_byte(x: i8): i32 {
+	switch x {
+		-1 => return 1 as i32;
+		-100, 5 => return 2 as i32;
+	}
+
+	return 0 as i32;
+}
+
+func [test] test_switch_edges(): bool {
+	var near = classify_near(-2 as i64) == 1 && classify_near(0 as i64) == 2 && classify_near(1 as i64) == 2 && classify_near(3 as i64) == 3;
+	var near_outside = classify_near(-3 as i64) == 0 && classify_near(-1 as i64) == 0 && classify_near(2 as i64) == 0 && classify_near(4 as i64) == 0;
+	var wide = classify_wide(5000000000 as i64) == 1 && classify_wide(5000000003 as i64) == 2 && classify_wide(5000000002 as i64) == 0 && classify_wide(4999999999 as i64) == 0;
+	var byte = classify_byte(0 as u8) == 1 && classify_byte(100 as u8) == 2 && classify_byte(255 as u8) == 3 && classify_byte(254 as u8) == 0;
+	var signed_byte = classify_signed_byte(-1 as i8) == 1 && classify_signed_byte(-100 as i8) == 2 && classify_signed_byte(5 as i8) == 2 && classify_signed_byte(127 as i8) == 0;
+
+	return near && near_outside && wide && byte && signed_byte;
+}
+
+func [test] test_switch(): bool {
+	var dense = classify(0) == 10 && classify(1) == 20 && classify(2) == 20 && classify(3) == 30 && classify(4) == 40;
+	var outside = classify(-1) == 0 && classify(5) == 0 && classify(1000) == 0;
+	var sparse = classify_sparse(-5 as i64) == 1 && classify_sparse(100 as i64) == 2 && classify_sparse(10000 as i64) == 3 && classify_sparse(5000000000 as i64) == 3 && classify_sparse(7 as i64) == 4;
+	var few = classify_few(-100) == 1 && classify_few(7) == 2 && classify_few(900) == 2 && classify_few(8) == 0;
+
+	var small: u8 = 199;
+	var hits = 0;
+	for var i = 0; i < 3; i++ {
+		switch small {
+			199, 200, 201 => hits += 1;
+		}
+		small++;
+	}
+
+	switch hits {
+		else => hits *= 2;
+	}
+
+	return dense && outside && sparse && few && hits == 6;
+}
+
+struct [derive(eq, hash, print)] Tile {
+	x: i32,
+	y: i64,
+	solid: bool
+}
+
+struct [derive(eq, hash, print)] Room {
+	name: String,
+	corner: Tile,
+	size: u8
+}
+
+func new_room(name: String, x: i32, solid: bool): Room {
+	var corner = new Tile;
+	corner.x = x;
+	corner.y = -5000000000;
+	corner.solid = solid;
+
+	var room = new Room;
+	room.name = name;
+	room.corner = corner;
+	room.size = 200;
+	return room;
+}
+
+func [test] test_derive(): bool {
+	var a = new_room("hall", 3, true);
+	var b = new_room("hall", 3, true);
+	var other_name = new_room("kitchen", 3, true);
+	var other_corner = new_room("hall", 4, true);
+	var other_solid = new_room("hall", 3, false);
+
+	var equal = a == b && a.corner == b.corner && a.hash() == b.hash() && a.name.hash() == b.name.hash();
+	var different = a != other_name && a != other_corner && a != other_solid && a.corner != other_corner.corner;
+	var hashes = a.hash() != other_name.hash() && a.hash() != other_corner.hash() && a.hash() != other_solid.hash();
+
+	return equal && different && hashes && "hall" == a.name && "hall" != "hal";
+}
+
+func [test] test_asm(): bool {
+	var x: i64 = 7;
+	var sum: i64 = 0;
+	var square: i64 = 0;
+	var pid: i64 = 0;
+
+	[cfg(any(target="linux-x86", target="macos-x86"))] {
+		asm x86_64 in(rax = x) out(rax = sum, rbx = square) {
+			add rax, 5;
+			mov rbx, rax;
+			imul rbx, rax;
+			push rbx;
+			pop rbx;
+		}
+	}
+
+	[cfg(target="linux-x86")] {
+		asm x86_64 out(rax = pid) {
+			mov rax, 39;
+			syscall;
+		}
+	}
+
+	[cfg(target="macos-x86")] {
+		asm x86_64 out(rax = pid) {
+			mov rax, 33554452;
+			syscall;
+		}
+	}
+
+	[cfg(target="macos-arm64")] {
+		asm arm64 in(x0 = x) out(x0 = sum, x19 = square) {
+			add x0, x0, #5;
+			mul x19, x0, x0;
+		}
+
+		asm arm64 out(x0 = pid) {
+			mov x16, #20;
+			svc #128;
+		}
+	}
+
+	[cfg(any(target="wasm", target="java", target="interp"))] {
+		sum = x + 5;
+		square = sum * sum;
+		pid = 1;
+	}
+
+	return sum == 12 && square == 144 && pid > 0;
+}
+
+struct [repr="c"] CHeader {
+	tag: u8,
+	size: u32,
+	next: u64
+}
+
+struct [packed] PackedHeader {
+	tag: u8,
+	size: u32,
+	next: u64
+}
+
+func [test] test_struct_layout(): bool {
+	var c = new CHeader;
+	c.tag = 1;
+	c.size = 2;
+	c.next = 3;
+
+	var p = new PackedHeader;
+	p.tag = 4;
+	p.size = 5;
+	p.next = 6;
+
+	var c_next: u64 = 0;
+	var p_next: u64 = 0;
+
+	[cfg(any(target="linux-x86", target="macos-x86"))] {
+		asm x86_64 in(rax = c, rbx = p) out(rcx = c_next, rdx = p_next) {
+			mov rcx, [rax + 8];
+			mov rdx, [rbx + 5];
+		}
+	}
+
+	[cfg(target="macos-arm64")] {
+		asm arm64 in(x0 = c, x1 = p) out(x2 = c_next, x3 = p_next) {
+			ldur x2, [x0, #8];
+			ldur x3, [x1, #5];
+		}
+	}
+
+	[cfg(any(target="wasm", target="java", target="interp"))] {
+		c_next = c.next;
+		p_next = p.next;
+	}
+
+	return c_next == 3 && p_next == 6 && c.tag == 1 && c.size == 2 && p.tag == 4 && p.size == 5;
+}
+
+struct [align=8] AlignedByte {
+	value: u8
+}
+
+func [test] test_struct_align(): bool {
+	var i: u32 = 0;
+	for i < 4 {
+		var p = new PackedHeader;
+		var a = new AlignedByte;
+		a.value = 7;
+
+		var addr: uptr = 0;
+
+		[cfg(any(target="linux-x86", target="macos-x86"))] {
+			asm x86_64 in(rax = a) out(rcx = addr) {
+				mov rcx, rax;
+			}
+		}
+
+		[cfg(target="macos-arm64")] {
+			asm arm64 in(x0 = a) out(x1 = addr) {
+				mov x1, x0;
+			}
+		}
+
+		if addr / 8 as uptr * 8 as uptr != addr || a.value != 7 || p.tag != 0 {
+			return false;
+		}
+		i++;
+	}
+
+	return true;
+}
+
+func first_multiple(of: i32, above: i32): i32 {
+	var i = above;
+	for {
+		i++;
+		if i / of * of == i {
+			return i;
+		}
+	}
+}
+
+func sign(x: i32): i32 {
+	if x < 0 {
+		return -1;
+	} else if x == 0 {
+		return 0;
 	} else {
-		test_pass("test_neg");
-	}
-}
-
-func [entry] main(): i32 {
-	test_if();
-	test_if_else();
-	test_bool_expr();
-	test_new();
-	test_int_slice_index();
-	test_math();
-	test_call_expr();
-	test_for();
-	test_div();
-	test_op_order();
-	test_static();
-	test_slice_literal();
-	test_binary_search();
-	test_bubble_sort();
-	test_drop();
-	test_neg();
-
-	return 0;
-}
\ No newline at end of file
+		return 1;
+	}
+}
+
+func [test] test_returns(): bool {
+	return first_multiple(7, 30) == 35 && sign(-4) == -1 && sign(0) == 0 && sign(9) == 1;
+}
+func [inline] index_of_digit(s: String, from: uptr): uptr {
+	var i = from;
+	for i < s.len() {
+		if s.at(i) >= 48 && s.at(i) <= 57 {
+			return i;
+		}
+		i++;
+	}
+	return s.len();
+}
+
+func [test] test_inline(): bool {
+	var s = "ab3d5";
+	return index_of_digit(s, 0) == 2 && index_of_digit(s, 3) == 4 && index_of_digit("abc", 0) == 3;
+}
+
+struct Rect {
+	x: i32,
+	y: i32,
+	w: i32,
+	h: i32
+}
+
+func [test] test_stack_objects(): bool {
+	var area = 0;
+	for var i = 1; i <= 4; i = i + 1 {
+		var r = new Rect;
+		r.x = i;
+		r.y = 1;
+		r.w = i * 2;
+		r.h = 4;
+		area = area + (r.w - r.x) * (r.h - r.y);
+		drop r;
+	}
+	return area == 30;
+}
@@ -0,0 +1,31 @@
+import std
+
+struct [derive(print)] Tile {
+	x: i32,
+	y: i64,
+	solid: bool
+}
+
+struct [derive(print)] Room {
+	name: String,
+	corner: Tile,
+	size: u8
+}
+
+func [entry] main(): i32 {
+	println("Zoë \u{1F600}");
+
+	var corner = new Tile;
+	corner.x = -3;
+	corner.y = 5000000000;
+	corner.solid = true;
+
+	var room = new Room;
+	room.name = "hall";
+	room.corner = corner;
+	room.size = 200;
+	room.print();
+	println("");
+
+	return 0;
+}
@@ -0,0 +1,17 @@
+import std
+
+func deep(a: i32, b: i32, c: i32, d: i32): i32 {
+	var sum = a + (b + (c + d));
+	if sum > 0 {
+		panic("deep");
+	}
+	return sum;
+}
+
+func mid(n: i32): i32 {
+	return deep(n, n, n, n) + 1;
+}
+
+func [entry] main(): i32 {
+	return mid(1);
+}
@@ -230,6 +230,10 @@ impl SectionHeader {
         self.file_offset = offset;
     }
 
+    pub fn set_addralign(&mut self, addralign: u64) {
+        self.addralign = addralign;
+    }
+
     pub fn size<I: EncodableInt>() -> usize {
         if I::size() == 4 { 40 } else { 64 }
     }
@@ -183,6 +183,8 @@ impl StaticELF {
                 };
                 let data_sh_idx = elf.push_section_header(SectionHeader::new_progbits(shstrtab.push(".data.rel.local"), *vaddr, 0, data.len() as u64));
                 elf.section_header_mut(data_sh_idx).set_flags(true, true, false);
+                // Globals are placed at offsets aligned for their type, up to 8 bytes
+                elf.section_header_mut(data_sh_idx).set_addralign(8);
 
                 Some((data_ph_idx, data_sh_idx))
             },
@@ -196,8 +198,12 @@ impl StaticELF {
                 } else {
                     None
                 };
-                let rodata_sh_idx = elf.push_section_header(SectionHeader::new_progbits(shstrtab.push(".rodata"), *vaddr, 0, rodata.len() as u64));
-                elf.section_header_mut(rodata_sh_idx).set_flags(false, true, false);
+                // Read only data that needs relocating must be writable until the dynamic linker has done so, the linker
+                // will then make it read only again
+                let relocated = matches!(&self.rodata_relocations, Some(relocs) if !relocs.is_empty());
+                let rodata_sh_idx = elf.push_section_header(SectionHeader::new_progbits(shstrtab.push(if relocated { ".data.rel.ro" } else { ".rodata" }), *vaddr, 0, rodata.len() as u64));
+                elf.section_header_mut(rodata_sh_idx).set_flags(relocated, true, false);
+                elf.section_header_mut(rodata_sh_idx).set_addralign(8);
 
                 Some((rodata_ph_idx, rodata_sh_idx))
             },
@@ -242,7 +248,7 @@ impl StaticELF {
             if relocs.len() == 0 {
                 None
             } else {
-                let rela_rodata_idx = shstrtab.push(".rela.rodata");
+                let rela_rodata_idx = shstrtab.push(".rela.data.rel.ro");
                 Some(elf.push_section_header(SectionHeader::new_relas::<I>(rela_rodata_idx, 0, relocs.len() as u64, symtab_idx as u32, rodata_idx.expect("Cannot do rodata relocation without rodata section").1 as u32)))
             }
         } else {
@@ -1,5 +1,6 @@
 use crate::Span;
 
+#[derive(Debug)]
 pub struct SyntaxError {
     span: Span,
     message: String
@@ -14,6 +14,11 @@ impl Span {
 
 pub trait TokenKind {
     fn is_whitespace(&self) -> bool;
+
+    /// Why the token could not be lexed, if it is one which could not be, such as a string with an invalid escape
+    fn lexer_error(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -94,11 +99,17 @@ impl<'a, T: std::fmt::Debug + TokenKind> TokenStream<'a, T> {
         self.offset == self.string.len()
     }
 
+    /// An error at the current token. If the token could not be lexed, that is the cause of the error, so its message is given instead
     pub fn error<U: Into<String>>(&self, msg: U) -> SyntaxError {
+        let msg = match self.token_kind().and_then(T::lexer_error) {
+            Some(lexer_error) => lexer_error,
+            None => msg.into()
+        };
+
         SyntaxError::new(match &self.token {
             None => Span::new(self.offset, self.offset),
             Some(t) => t.span.clone()
-        }, msg.into())
+        }, msg)
     }
 
     pub fn tell(&self) -> usize {
@@ -136,39 +147,106 @@ macro_rules! ident {
     };
 }
 
-/// Create a generic cstring parser, parsing /"[^"]+"/, handling escapes
+/// Encodes a code point as UTF-8, without checking it is a valid char. Surrogates and values above U+10FFFF are encoded
+/// the same way as any other value, producing bytes which are not valid UTF-8.
+pub fn push_code_point(bytes: &mut Vec<u8>, code_point: u32) {
+    if code_point < 0x80 {
+        bytes.push(code_point as u8);
+    } else if code_point < 0x800 {
+        bytes.push(0xc0 | (code_point >> 6) as u8);
+        bytes.push(0x80 | (code_point & 0x3f) as u8);
+    } else if code_point < 0x10000 {
+        bytes.push(0xe0 | (code_point >> 12) as u8);
+        bytes.push(0x80 | ((code_point >> 6) & 0x3f) as u8);
+        bytes.push(0x80 | (code_point & 0x3f) as u8);
+    } else {
+        bytes.push(0xf0 | ((code_point >> 18) & 0x07) as u8);
+        bytes.push(0x80 | ((code_point >> 12) & 0x3f) as u8);
+        bytes.push(0x80 | ((code_point >> 6) & 0x3f) as u8);
+        bytes.push(0x80 | (code_point & 0x3f) as u8);
+    }
+}
+
+/// Create a generic cstring parser, parsing /"[^"]+"/, handling escapes. The value is given as bytes, which are only valid
+/// UTF-8 if any \u{...} escapes are valid chars. A literal which cannot be lexed is given as the error kind, with why.
 #[macro_export]
 macro_rules! cstring {
-    ( $string:expr , $offset:expr , $( $i:ident )::* ) => {
+    ( $string:expr , $offset:expr , $( $i:ident )::* , $( $e:ident )::* ) => {
         if $string.starts_with('"') {
-            let mut len = 1;
-            let mut escaped = false;
-            let mut value = String::new();
-            loop {
-                let char = *$string.as_bytes().get(len).expect("Early string termination") as char; // TODO: Pass a lexer error
-                if escaped {
-                    match char {
-                        '\\' => value.push('\\'),
-                        '"' => value.push('"'),
-                        'n' => value.push('\n'),
-                        't' => value.push('\t'),
-                        _ => panic!("Unknown escape")
-                    }
-                    escaped = false;
-                } else {
-                    match char {
-                        '\\' => escaped = true,
-                        '"' => break,
-                        _ => value.push(char)
-                    }
-                }
-                len += 1;
-            }
-            return Some((len + 1, Token::new($( $i )::* (value), ::syntax::Span::new($offset, $offset + len))));
+            let (len, value) = ::syntax::lex_cstring($string);
+            let span = ::syntax::Span::new($offset, $offset + len);
+            return Some((len, match value {
+                Ok(value) => Token::new($( $i )::* (value), span),
+                Err(error) => Token::new($( $e )::* (error), span)
+            }));
         }
     };
 }
 
+/// Lex the string literal at the start of the string, which starts with a quote. Gives the length of the literal including its
+/// quotes, and either its value or why it is invalid.
+pub fn lex_cstring(string: &str) -> (usize, Result<Vec<u8>, String>) {
+    // Find the closing quote first, so an invalid escape cannot run on past the end of the literal
+    let bytes = string.as_bytes();
+    let mut end = 1;
+    let mut escaped = false;
+    while let Some(&byte) = bytes.get(end) {
+        match byte {
+            b'"' if !escaped => break,
+            b'\\' => escaped = !escaped,
+            _ => escaped = false
+        }
+        end += 1;
+    }
+
+    if end == bytes.len() {
+        return (end, Err("Expected '\"' to end string".to_string()));
+    }
+
+    (end + 1, unescape_cstring(&string[1..end]))
+}
+
+/// Replace the escapes in the contents of a string literal, which cannot end in an unescaped backslash
+fn unescape_cstring(literal: &str) -> Result<Vec<u8>, String> {
+    let bytes = literal.as_bytes();
+    let mut value = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            value.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        match bytes[i + 1] {
+            b'\\' => value.push(b'\\'),
+            b'"' => value.push(b'"'),
+            b'n' => value.push(b'\n'),
+            b't' => value.push(b'\t'),
+            b'u' => {
+                // \u{...} with up to six hex digits
+                let digits = match literal[i + 2..].strip_prefix('{').and_then(|rest| rest.find('}').map(|end| &rest[..end])) {
+                    Some(digits) => digits,
+                    None => return Err("Expected '{' and '}' around unicode escape".to_string())
+                };
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err("Unicode escape must have between 1 and 6 digits".to_string());
+                }
+                if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+                    return Err("Invalid unicode escape".to_string());
+                }
+
+                push_code_point(&mut value, u32::from_str_radix(digits, 16).unwrap());
+                i += digits.len() + 2;
+            },
+            _ => return Err("Unknown escape".to_string())
+        }
+        i += 2;
+    }
+
+    Ok(value)
+}
+
 /// Create a generic whitespace parser, parsing /\s+/
 #[macro_export]
 macro_rules! whitespace {
@@ -225,6 +303,8 @@ macro_rules! exact_long {
 #[macro_export]
 macro_rules! char {
     ( $string:expr , $offset:expr , $( $i:ident )::* ) => {
-        return Some((1, Token::new($( $i )::* ($string.as_bytes()[0] as char), ::syntax::Span::new($offset, $offset + 1))));
+        // The whole character is taken, even if it is more than one byte, so the next token starts on a character boundary
+        let c = $string.chars().next().unwrap();
+        return Some((c.len_utf8(), Token::new($( $i )::* (c), ::syntax::Span::new($offset, $offset + c.len_utf8()))));
     };
 }
\ No newline at end of file
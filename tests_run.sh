@@ -1,13 +1,53 @@
 #!/bin/bash
 
+export NL_ROOT=nl/std
+NL=target/debug/nl
+status=0
+
 echo "========================== java =========================="
-java BasicSuiteJava
+$NL test nl/tests/basic_suite.nl nl/std/std.nl -o BasicSuiteJava -t java -I nl/std || status=1
 
 echo "========================== wasm =========================="
-node nl/examples/wasm_exec.js basic_suite_wasm.wasm
+$NL test nl/tests/basic_suite.nl nl/std/std.nl -o basic_suite_wasm.wasm -t wasm -c -I nl/std || status=1
+
+echo "========================== interp =========================="
+$NL test nl/tests/basic_suite.nl -t interp --std || status=1
+
+echo "========================== interp -O1 =========================="
+$NL test nl/tests/basic_suite.nl -t interp --std -O 1 || status=1
+
+echo "========================== interp -O2 =========================="
+$NL test nl/tests/basic_suite.nl -t interp --std -O 2 || status=1
 
 # Doesn't check if x86-64
 if [[ "$OSTYPE" == "linux-gnu"* ]]; then
 	echo "========================== x86 =========================="
-	./basic_suite_x86
-fi
\ No newline at end of file
+	$NL test nl/tests/basic_suite.nl -o basic_suite_x86 -t linux-elf-x86_64 -c --link --std || status=1
+fi
+
+# Compares what a program prints with nl/tests/output.txt
+check_output() {
+	echo "========================== output ($1) =========================="
+	shift
+	"$@" | cmp - nl/tests/output.txt || status=1
+}
+
+check_output java $NL run nl/tests/output.nl nl/std/std.nl -o OutputJava -t java -I nl/std
+check_output wasm $NL run nl/tests/output.nl nl/std/std.nl -o output_wasm.wasm -t wasm -c -I nl/std
+check_output interp $NL run nl/tests/output.nl -t interp --std
+
+if [[ "$OSTYPE" == "linux-gnu"* ]]; then
+	check_output x86 $NL run nl/tests/output.nl -o output_x86 -t linux-elf-x86_64 -c --link --std
+
+	# The backtrace has to walk through frames which save callee saved registers
+	echo "========================== backtrace (x86) =========================="
+	$NL build nl/tests/panic.nl -o panic_x86 -t linux-elf-x86_64 -c --link --std || status=1
+	./panic_x86 2> panic_x86.txt
+	if [[ $? -ne 101 ]] || ! grep -Pzq "0: deep\n.*1: mid\n.*2: main\n" panic_x86.txt; then
+		cat panic_x86.txt
+		status=1
+	fi
+	rm -f panic_x86.txt
+fi
+
+exit $status
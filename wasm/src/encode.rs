@@ -36,7 +36,7 @@ impl WasmEncodable for u32 {
 
 impl WasmEncodable for i64 {
     fn wasm_encode(&self, data: &mut Vec<u8>) {
-        let mut value = *self as i32;
+        let mut value = *self;
         loop {
             let byte = (value & 0x7f) as u8;
             value >>= 7;
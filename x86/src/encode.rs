@@ -289,7 +289,8 @@ impl Encoder {
         if a.class().is_rn() { self.prefix = Some(self.prefix_or_new().r()) }
         if b.class().is_rn() { self.prefix = Some(self.prefix_or_new().b()) }
         
-        if matches!(a.size(), Size::Byte) && a.class().byte_forces_rex() { self.prefix = Some(self.prefix_or_new()); }
+        // Either register being sil, dil, spl or bpl needs a prefix, as without one they are ah, bh, ch and dh
+        if matches!(a.size(), Size::Byte) && (a.class().byte_forces_rex() || b.class().byte_forces_rex()) { self.prefix = Some(self.prefix_or_new()); }
         if matches!(a.size(), Size::Quad) { self.prefix = Some(self.prefix_or_new().w()) }
         if matches!(a.size(), Size::Word) { self.operand_size_override = true; }
 
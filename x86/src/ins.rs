@@ -8,7 +8,9 @@ pub enum Condition {
     Less,
     LessOrEqual,
     Greater,
-    GreaterOrEqual
+    GreaterOrEqual,
+    Below,
+    AboveOrEqual
 }
 
 impl Condition {
@@ -20,6 +22,8 @@ impl Condition {
             Condition::LessOrEqual => 0xe,
             Condition::Greater => 0xf,
             Condition::GreaterOrEqual => 0xd,
+            Condition::Below => 0x2,
+            Condition::AboveOrEqual => 0x3,
         }
     }
 
@@ -31,6 +35,8 @@ impl Condition {
             Condition::GreaterOrEqual => Condition::Less,
             Condition::Greater => Condition::LessOrEqual,
             Condition::LessOrEqual => Condition::Greater,
+            Condition::Below => Condition::AboveOrEqual,
+            Condition::AboveOrEqual => Condition::Below,
         }
     }
 }
@@ -102,11 +108,17 @@ pub enum Ins {
     JumpLocalSymbol(LocalSymbolID),
     /// If Condition Then Jump A
     JumpConditionalLocalSymbol(Condition, LocalSymbolID),
+    /// Jump *A
+    JumpReg(Reg),
+    /// Not an instruction, but the 32 bit offset of B from A, as placed in a jump table starting at A
+    JumpTableEntry(LocalSymbolID, LocalSymbolID),
 
     /// A <- &B
     LeaRegMem(Reg, Mem),
     /// A <- &B
     LeaRegGlobalSymbol(Reg, GlobalSymbolID),
+    /// A <- &B
+    LeaRegLocalSymbol(Reg, LocalSymbolID),
 
     /// A <- B
     MovRegReg(Reg, Reg),
@@ -171,6 +183,9 @@ pub enum Ins {
     /// A <- A - B
     SubMemImm(Size, Mem, u64),
 
+    /// Syscall
+    Syscall,
+
     /// Test A, B
     TestRegReg(Reg, Reg),
     /// Test A, B
@@ -229,8 +244,8 @@ impl Ins {
             // https://www.felixcloutier.com/x86/cwd:cdq:cqo
             Ins::Cdq(s) => match s {
                 Size::Byte => panic!("Cannot CDQ byte"),
-                Size::Word => Encoder::new(0x99).to(data),
-                Size::Double => Encoder::new(0x99).opsize_override().to(data),
+                Size::Word => Encoder::new(0x99).opsize_override().to(data),
+                Size::Double => Encoder::new(0x99).to(data),
                 Size::Quad => Encoder::new(0x99).long().to(data),
             },
 
@@ -270,6 +285,13 @@ impl Ins {
                 Encoder::new_long([0x0f, 0x80 + c.base()]).imm32(0).to(data);
                 unfilled_local_symbols.push(Relocation::new_local_branch(id, data.len() - 4, -4));
             },
+            // Always 64 bit in long mode, so never needs REX.W
+            Ins::JumpReg(r) => Encoder::new(0xff).rn(r.class().u32(), 4).to(data),
+            Ins::JumpTableEntry(table, target) => {
+                let table_addr = *local_symbols.get(&table).expect("Jump table must be placed before its entries");
+                data.extend(&[0; 4]);
+                unfilled_local_symbols.push(Relocation::new_local_branch(target, data.len() - 4, (data.len() - 4 - table_addr) as i64));
+            },
 
             // https://www.felixcloutier.com/x86/lea
             Ins::LeaRegMem(r, ref m) => Encoder::new(0x8d).rm(r, m).to(data),
@@ -277,6 +299,10 @@ impl Ins {
                 Encoder::new(0x8d).rm(r, &Mem::new().base(RegClass::Eip).disp(0)).to(data);
                 unfilled_local_symbols.push(Relocation::new_global_relative(idx, data.len() - 4, -4));
             },
+            Ins::LeaRegLocalSymbol(r, id) => {
+                Encoder::new(0x8d).rm(r, &Mem::new().base(RegClass::Eip).disp(0)).to(data);
+                unfilled_local_symbols.push(Relocation::new_local_branch(id, data.len() - 4, -4));
+            },
 
             // https://www.felixcloutier.com/x86/mov
             Ins::MovRegReg(a, b) => Encoder::new(if a.size() == Size::Byte { 0x88 } else { 0x89 }).rr(b, a).to(data),
@@ -287,7 +313,8 @@ impl Ins {
                     Size::Byte => Encoder::new(0xb0).offset(r).imm8(i as u8).to(data),
                     Size::Word => Encoder::new(0xb8).offset(r).imm16(i as u16).to(data),
                     Size::Double => Encoder::new(0xb8).offset(r).imm32(i as u32).to(data),
-                    Size::Quad if (i as i64) <= (i32::MAX as i64) && (i as i64) >= (i32::MIN as i64) => Encoder::new(0xb8 + r.class().id()).imm32(i as u32).to(data),
+                    // Writing the 32 bit register zero extends into the whole register, which is shorter when the value fits
+                    Size::Quad if i <= u32::MAX as u64 => Encoder::new(0xb8).offset(r.class().u32()).imm32(i as u32).to(data),
                     Size::Quad => Encoder::new(0xb8).offset(r).imm64(i as u64).to(data),
                 }
             },
@@ -357,6 +384,9 @@ impl Ins {
             Ins::SubRegImm(r, i) => Encoder::new(if r.size() == Size::Byte { 0x80 } else { 0x81 }).rn(r, 5).immn(i as u32, r.size()).to(data),
             Ins::SubMemImm(s, ref m, i) => Encoder::new(if s == Size::Byte { 0x80 } else { 0x81 }).mn(s, m, 5).immn(i as u32, s).to(data),
 
+            // https://www.felixcloutier.com/x86/syscall
+            Ins::Syscall => Encoder::new_long([0x0f, 0x05]).to(data),
+
             // https://www.felixcloutier.com/x86/test
             Ins::TestRegReg(a, b) => Encoder::new(if a.size() == Size::Byte { 0x84 } else { 0x85 }).rr(b, a).to(data),
             Ins::TestMemReg(ref m, r) => Encoder::new(if r.size() == Size::Byte { 0x84 } else { 0x85 }).mr(m, r).to(data),
@@ -35,7 +35,8 @@ pub fn collapse_sequential_symbols(insns: &mut Vec<Ins>) {
 
     for ins in insns.iter_mut() {
         match ins {
-            Ins::JumpLocalSymbol(jump_target) | Ins::JumpConditionalLocalSymbol(_, jump_target) => {
+            Ins::JumpLocalSymbol(jump_target) | Ins::JumpConditionalLocalSymbol(_, jump_target) |
+            Ins::JumpTableEntry(_, jump_target) | Ins::LeaRegLocalSymbol(_, jump_target) => {
                 if let Some(new) = symbol_translation.get(jump_target) {
                     *jump_target = *new;
                 }
@@ -11,7 +11,9 @@ instruction_opcodes:
     and rax, rcx ; AndRegReg
     and [rdi], rcx ; AndMemReg
 
-    cwd ; Cdq(Size::Quad)
+    cdq ; Cdq(Size::Double)
+    cwd ; Cdq(Size::Word)
+    cqo ; Cdq(Size::Quad)
 
     cmove rax, rcx ; CMovRegReg
     cmovl rax, [rdi] ; CMovRegMem
@@ -47,4 +47,8 @@ registers:
     mov rax, [r8+rbx]
     mov rax, [rbx+r8]
     mov rax, [r9+r8]
-    mov r10, [r9+r8]
\ No newline at end of file
+    mov r10, [r9+r8]
+
+    mov r10d, 2 ; MovRegImm(R10, 2), zero extended
+    mov r10, 0xffffffffffffffff ; MovRegImm(R10, u64::MAX)
+    mov eax, 0x80000000 ; MovRegImm(Rax, 0x80000000), too large to sign extend
\ No newline at end of file
@@ -18,6 +18,8 @@ fn instruction_opcodes() {
         Ins::AndMemReg(Mem::new().base(RegClass::Edi), Reg::Rcx),
 
         Ins::Cdq(Size::Double),
+        Ins::Cdq(Size::Word),
+        Ins::Cdq(Size::Quad),
 
         Ins::CMovRegReg(Condition::Zero, Reg::Rax, Reg::Rcx),
         Ins::CMovRegMem(Condition::Less, Reg::Rax, Mem::new().base(RegClass::Edi)),
@@ -137,6 +139,14 @@ fn registers() {
         Ins::MovRegMem(Reg::Rax, Mem::new().base(RegClass::Ebx).index(RegClass::R8)),
         Ins::MovRegMem(Reg::Rax, Mem::new().base(RegClass::R9).index(RegClass::R8)),
         Ins::MovRegMem(Reg::R10, Mem::new().base(RegClass::R9).index(RegClass::R8)),
+
+        Ins::MovRegImm(Reg::R10, 2),
+        Ins::MovRegImm(Reg::R10, u64::MAX),
+        Ins::MovRegImm(Reg::Rax, 0x80000000),
+
+        Ins::MovRegReg(Reg::Dil, Reg::Al),
+        Ins::MovRegReg(Reg::Al, Reg::Sil),
+        Ins::MovRegReg(Reg::Bpl, Reg::Spl),
     ];
 
     let mut data = Vec::new();
@@ -196,4 +206,54 @@ fn relocations() {
     assert_eq!(data, &[
         0xe9, 0x04, 0x00, 0x00, 0x00, 0x48, 0x83, 0xc0, 0x03, 0x48, 0x83, 0xc1, 0x04, 0x0f, 0x85, 0xf6, 0xff, 0xff, 0xff
     ]);
-}
\ No newline at end of file
+}
+/// Test that jump table entries are filled in with the offset of their target from the start of the table
+#[test]
+fn jump_table_relocations() {
+    let mut ctx = EncodeContext::new();
+
+    let table = LocalSymbolID::new(0);
+    let a = LocalSymbolID::new(1);
+    let b = LocalSymbolID::new(2);
+    ctx.append_function(&vec![
+        Ins::LeaRegLocalSymbol(Reg::Rcx, table),
+        Ins::JumpReg(Reg::Rcx),
+        Ins::LocalSymbol(table),
+        Ins::JumpTableEntry(table, a),
+        Ins::JumpTableEntry(table, b),
+        Ins::LocalSymbol(a),
+        Ins::Ret,
+        Ins::LocalSymbol(b),
+        Ins::Ret,
+    ]);
+
+    let (data, relocs) = ctx.take();
+
+    assert_eq!(relocs.len(), 0);
+    // lea rcx, [rip+2]; jmp rcx; dd 8, 9; ret; ret
+    assert_eq!(data, &[
+        0x48, 0x8d, 0x0d, 0x02, 0x00, 0x00, 0x00, 0xff, 0xe1, 0x08, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0xc3, 0xc3
+    ]);
+}
+
+/// Test instructions which are only emitted from inline assembly
+#[test]
+fn inline_assembly() {
+    let mut local_symbols = HashMap::new();
+    let mut unfilled_local_symbols = Vec::new();
+
+    let insns = vec![
+        Ins::MovRegImm(Reg::Rax, 60),
+        Ins::Syscall,
+    ];
+
+    let mut data = Vec::new();
+    for ins in insns {
+        ins.encode(&mut data, &mut local_symbols, &mut unfilled_local_symbols);
+    }
+
+    // mov eax, 60; syscall
+    assert_eq!(data, &[
+        0xb8, 0x3c, 0x00, 0x00, 0x00, 0x0f, 0x05
+    ]);
+}